@@ -0,0 +1,151 @@
+//! Cold storage for old conversations: a second database file that takes the conversations
+//! nobody has touched in a long time, so the live one stays small and fast to search.
+//!
+//! The archive has the live database's `conversations`, `messages` and `messages_fts`, created
+//! from the live definitions, and rows keep their ids. Reads open the archive and attach the live
+//! database behind it: unqualified names find the archived tables first and everything else
+//! (tags, projects, languages) in the live file, so searches and conversation lookups run on it
+//! unchanged.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::db::{self, encryption};
+use crate::erase;
+use crate::models::ArchiveConversationsResponse;
+
+/// Tables the archive holds, in the order they are created.
+const ARCHIVED_TABLES: &[&str] = &["conversations", "messages", "messages_fts"];
+
+/// Archive next to the database at `db_path`: `conversations.db` archives to
+/// `conversations.archive.db`.
+pub fn default_path(db_path: &str) -> PathBuf {
+    let path = Path::new(db_path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map_or(String::new(), |e| format!(".{}", e.to_string_lossy()));
+    path.with_file_name(format!("{}.archive{}", stem, extension))
+}
+
+/// Move the conversations whose last message (or creation, without messages) is older than
+/// `before` from `conn` to the archive at `archive_path`, created when missing, in a single
+/// transaction. Pinned conversations stay.
+pub fn archive_conversations(conn: &Connection, archive_path: &Path, before: i64) -> Result<ArchiveConversationsResponse> {
+    create_schema(conn, archive_path)?;
+    attach(conn, archive_path, "archive")?;
+    let moved = move_conversations(conn, before);
+    conn.execute("DETACH DATABASE archive", []).context("Failed to detach archive")?;
+    let (conversations_archived, messages_archived, pinned_skipped) = moved?;
+    Ok(ArchiveConversationsResponse {
+        success: true,
+        archive_path: archive_path.display().to_string(),
+        conversations_archived,
+        messages_archived,
+        pinned_skipped,
+        error: None,
+    })
+}
+
+/// The archive at `archive_path` with the live database at `db_path` attached behind it, for
+/// reading; `None` when nothing was ever archived.
+pub fn open(archive_path: &Path, db_path: &str) -> Result<Option<Connection>> {
+    if !archive_path.exists() {
+        return Ok(None);
+    }
+    let conn = db::open_read_only(&archive_path.to_string_lossy())?;
+    attach(&conn, Path::new(db_path), "live")?;
+    Ok(Some(conn))
+}
+
+/// Create the archived tables the archive at `path` is missing, as `conn`'s database defines them.
+fn create_schema(conn: &Connection, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let archive = Connection::open(path).with_context(|| format!("Failed to open archive {}", path.display()))?;
+    encryption::unlock(&archive)?;
+    for table in ARCHIVED_TABLES {
+        if db::has_index(&archive, table) {
+            continue;
+        }
+        let sql: String = conn
+            .query_row("SELECT sql FROM main.sqlite_master WHERE name = ?", [table], |row| row.get(0))
+            .optional()?
+            .with_context(|| format!("The database has no {} table to archive", table))?;
+        archive.execute(&sql, []).with_context(|| format!("Failed to create {} in the archive", table))?;
+    }
+    Ok(())
+}
+
+fn attach(conn: &Connection, path: &Path, name: &str) -> Result<()> {
+    let file = path.to_string_lossy();
+    match encryption::attach_key(path) {
+        Some(key) => conn.execute(&format!("ATTACH DATABASE ?1 AS {} KEY ?2", name), params![file, key]),
+        None => conn.execute(&format!("ATTACH DATABASE ?1 AS {}", name), [&file]),
+    }
+    .with_context(|| format!("Failed to attach {}", path.display()))?;
+    Ok(())
+}
+
+/// Copy the old conversations into the attached `archive` and delete them from `main`; returns
+/// how many conversations and messages moved and how many pinned ones stayed.
+fn move_conversations(conn: &Connection, before: i64) -> Result<(usize, usize, usize)> {
+    // An in-memory database attaches an empty in-memory namesake instead of the file
+    let attached: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM archive.sqlite_master WHERE name = 'messages_fts')", [], |row| {
+        row.get(0)
+    })?;
+    anyhow::ensure!(attached, "This database can't attach the archive file; in-memory databases can't be archived");
+    let tx = conn.unchecked_transaction().context("Failed to start archive transaction")?;
+    let candidates: Vec<(String, bool)> = {
+        let mut stmt = tx.prepare(
+            "SELECT c.id, c.id IN (SELECT conversation_id FROM main.pinned_conversations)
+             FROM main.conversations c LEFT JOIN main.messages m ON m.conversation_id = c.id
+             GROUP BY c.id
+             HAVING COALESCE(MAX(m.created_at), c.created_at) < ?1",
+        )?;
+        let rows = stmt.query_map([before], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<rusqlite::Result<_>>()?;
+        rows
+    };
+    let conversation_columns = shared_columns(&tx, "conversations")?;
+    let message_columns = shared_columns(&tx, "messages")?;
+    let (mut conversations, mut messages, mut pinned) = (0, 0, 0);
+    for (conversation_id, is_pinned) in candidates {
+        if is_pinned {
+            pinned += 1;
+            continue;
+        }
+        tx.execute(
+            &format!("INSERT INTO archive.conversations ({0}) SELECT {0} FROM main.conversations WHERE id = ?1", conversation_columns),
+            [&conversation_id],
+        )
+        .with_context(|| format!("Failed to archive conversation '{}'", conversation_id))?;
+        tx.execute(
+            &format!("INSERT INTO archive.messages ({0}) SELECT {0} FROM main.messages WHERE conversation_id = ?1", message_columns),
+            [&conversation_id],
+        )
+        .with_context(|| format!("Failed to archive the messages of '{}'", conversation_id))?;
+        // The archive has no triggers; index its copies here
+        tx.execute(
+            "INSERT INTO archive.messages_fts (rowid, content) SELECT id, content FROM main.messages WHERE conversation_id = ?1",
+            [&conversation_id],
+        )
+        .context("Failed to index archived messages")?;
+        messages += erase::remove_rows(&tx, &conversation_id)?;
+        conversations += 1;
+    }
+    tx.commit().context("Failed to commit archive")?;
+    Ok((conversations, messages, pinned))
+}
+
+/// Columns `table` has both in `main` and in `archive`, quoted and comma-separated.
+fn shared_columns(conn: &Connection, table: &str) -> Result<String> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM pragma_table_info(?1, 'main') WHERE name IN (SELECT name FROM pragma_table_info(?1, 'archive')) ORDER BY cid",
+    )?;
+    let columns = stmt
+        .query_map([table], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(|| format!("Failed to read the columns of {}", table))?;
+    Ok(columns.iter().map(|c| format!("\"{}\"", c.replace('"', "\"\""))).collect::<Vec<_>>().join(", "))
+}
@@ -0,0 +1,231 @@
+//! Attachments: files kept alongside memories and messages, such as a diagram or a config file the
+//! user wants remembered with a fact.
+//!
+//! Stored content is addressed by its SHA-256: `attachment_blobs` holds each distinct content once,
+//! however many attachments share it. A file reference only records the file's path, size and hash;
+//! its content is read from disk when the attachment is, and reported as changed when the hash no
+//! longer matches. Referenced files must lie inside the configured attachments directory, checked
+//! after resolving symlinks both when the reference is stored and whenever it is read.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ring::digest::{digest, SHA256};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::dates::Timezone;
+use crate::db::unix_now;
+use crate::models::{AttachmentInfo, MemoryEntry};
+use crate::resources;
+
+/// Largest attachment stored or read, in bytes.
+pub const MAX_BYTES: usize = 10 * 1024 * 1024;
+
+const COLUMNS: &str = "id, name, mime_type, size_bytes, sha256, file_path, memory_id, message_id, created_at";
+
+/// Where an attachment's content comes from.
+pub enum Content {
+    /// Bytes to keep in the database
+    Stored(Vec<u8>),
+    /// A file in the attachments directory, read whenever the attachment is
+    File(PathBuf),
+}
+
+/// An attachment to store.
+pub struct NewAttachment {
+    pub name: String,
+    /// Guessed from the name's extension when absent
+    pub mime_type: Option<String>,
+    pub content: Content,
+    pub memory_id: Option<i64>,
+    pub message_id: Option<i64>,
+}
+
+fn from_row(row: &Row, timezone: Timezone) -> rusqlite::Result<AttachmentInfo> {
+    let id = row.get(0)?;
+    let created_at = row.get(8)?;
+    Ok(AttachmentInfo {
+        id,
+        uri: resources::attachment_uri(id),
+        name: row.get(1)?,
+        mime_type: row.get(2)?,
+        size_bytes: row.get(3)?,
+        sha256: row.get(4)?,
+        file_path: row.get(5)?,
+        memory_id: row.get(6)?,
+        message_id: row.get(7)?,
+        created_at,
+        created_at_iso: timezone.iso(created_at),
+    })
+}
+
+/// Store `attachment` and link it to its memory and message, which must exist. A file reference
+/// must resolve inside `files_dir`.
+pub fn store(conn: &Connection, attachment: NewAttachment, files_dir: Option<&Path>, timezone: Timezone) -> Result<AttachmentInfo> {
+    let name = attachment.name.trim();
+    anyhow::ensure!(!name.is_empty(), "Attachment name must not be empty");
+    if let Some(memory_id) = attachment.memory_id {
+        anyhow::ensure!(exists(conn, "memory", memory_id)?, "Memory entry {} not found", memory_id);
+    }
+    if let Some(message_id) = attachment.message_id {
+        anyhow::ensure!(exists(conn, "messages", message_id)?, "Message {} not found", message_id);
+    }
+
+    let (bytes, file_path) = match attachment.content {
+        Content::Stored(bytes) => (bytes, None),
+        Content::File(path) => {
+            let path = confined(&path, files_dir)?;
+            (read_file(&path)?, Some(path.to_string_lossy().into_owned()))
+        }
+    };
+    anyhow::ensure!(bytes.len() <= MAX_BYTES, "Attachment is {} bytes; the limit is {}", bytes.len(), MAX_BYTES);
+    let sha256 = sha256_hex(&bytes);
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    if file_path.is_none() {
+        tx.execute("INSERT OR IGNORE INTO attachment_blobs (sha256, content) VALUES (?1, ?2)", params![sha256, bytes])
+            .context("Failed to store attachment content")?;
+    }
+    let mime_type = attachment
+        .mime_type
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| guess_mime_type(name, &bytes).to_string());
+    tx.execute(
+        "INSERT INTO attachments (name, mime_type, size_bytes, sha256, file_path, memory_id, message_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![name, mime_type, bytes.len() as i64, sha256, file_path, attachment.memory_id, attachment.message_id, unix_now()],
+    )
+    .context("Failed to store attachment")?;
+    let id = tx.last_insert_rowid();
+    tx.commit().context("Failed to commit attachment")?;
+    get(conn, id, timezone)?.context("Attachment disappeared")
+}
+
+/// The attachment with id `attachment_id`, if any.
+pub fn get(conn: &Connection, attachment_id: i64, timezone: Timezone) -> Result<Option<AttachmentInfo>> {
+    conn.query_row(&format!("SELECT {} FROM attachments WHERE id = ?", COLUMNS), [attachment_id], |row| {
+        from_row(row, timezone)
+    })
+    .optional()
+    .context("Failed to read attachment")
+}
+
+/// Content of `attachment`, and whether a referenced file changed since it was attached. A
+/// referenced file is only read while it resolves inside `files_dir`.
+pub fn content(conn: &Connection, attachment: &AttachmentInfo, files_dir: Option<&Path>) -> Result<(Vec<u8>, bool)> {
+    match &attachment.file_path {
+        Some(path) => {
+            let bytes = read_file(&confined(Path::new(path), files_dir)?)?;
+            let changed = sha256_hex(&bytes) != attachment.sha256;
+            Ok((bytes, changed))
+        }
+        None => {
+            let bytes = conn
+                .query_row("SELECT content FROM attachment_blobs WHERE sha256 = ?", [&attachment.sha256], |row| row.get(0))
+                .optional()
+                .context("Failed to read attachment content")?
+                .context("Attachment content is missing")?;
+            Ok((bytes, false))
+        }
+    }
+}
+
+/// The newest `limit` attachments, newest first.
+pub fn recent(conn: &Connection, limit: u32, timezone: Timezone) -> Result<Vec<AttachmentInfo>> {
+    let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM attachments ORDER BY id DESC LIMIT ?", COLUMNS))?;
+    let rows = stmt.query_map([limit], |row| from_row(row, timezone))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to list attachments")
+}
+
+/// Fill in the attachments of each of `entries`.
+pub fn link(conn: &Connection, entries: &mut [MemoryEntry], timezone: Timezone) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM attachments WHERE memory_id = ? ORDER BY id", COLUMNS))?;
+    for entry in entries {
+        entry.attachments = stmt.query_map([entry.id], |row| from_row(row, timezone))?.collect::<rusqlite::Result<_>>()?;
+    }
+    Ok(())
+}
+
+/// Delete the attachments of memory `memory_id`, and stored content nothing refers to anymore.
+pub fn delete_for_memory(conn: &Connection, memory_id: i64) -> rusqlite::Result<usize> {
+    let deleted = conn.prepare_cached("DELETE FROM attachments WHERE memory_id = ?")?.execute([memory_id])?;
+    delete_orphan_blobs(conn)?;
+    Ok(deleted)
+}
+
+/// Delete the attachments of each of `message_ids`, and stored content nothing refers to anymore.
+pub fn delete_for_messages(conn: &Connection, message_ids: &[i64]) -> rusqlite::Result<usize> {
+    let mut stmt = conn.prepare_cached("DELETE FROM attachments WHERE message_id = ?")?;
+    let mut deleted = 0;
+    for id in message_ids {
+        deleted += stmt.execute([id])?;
+    }
+    delete_orphan_blobs(conn)?;
+    Ok(deleted)
+}
+
+/// Delete stored content no attachment refers to anymore.
+pub fn delete_orphan_blobs(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.prepare_cached("DELETE FROM attachment_blobs WHERE sha256 NOT IN (SELECT sha256 FROM attachments)")?.execute([])
+}
+
+fn exists(conn: &Connection, table: &str, id: i64) -> Result<bool> {
+    conn.query_row(&format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?)", table), [id], |row| row.get(0))
+        .with_context(|| format!("Failed to look up {}", table))
+}
+
+/// `path` with symlinks resolved, when that lies inside `files_dir`.
+fn confined(path: &Path, files_dir: Option<&Path>) -> Result<PathBuf> {
+    let files_dir = files_dir.context("File references are refused: MCP_LUNA_ATTACHMENTS_DIR is not set")?;
+    let files_dir =
+        files_dir.canonicalize().with_context(|| format!("Failed to find attachments directory {}", files_dir.display()))?;
+    let path = path.canonicalize().with_context(|| format!("Failed to find {}", path.display()))?;
+    anyhow::ensure!(path.starts_with(&files_dir), "{} is outside MCP_LUNA_ATTACHMENTS_DIR", path.display());
+    Ok(path)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    let metadata = std::fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    anyhow::ensure!(metadata.is_file(), "{} is not a file", path.display());
+    anyhow::ensure!(
+        metadata.len() <= MAX_BYTES as u64,
+        "{} is {} bytes; the limit is {}",
+        path.display(),
+        metadata.len(),
+        MAX_BYTES
+    );
+    std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    digest(&SHA256, bytes).as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// MIME type for a file called `name`: by its extension, else plain text for UTF-8 content.
+fn guess_mime_type(name: &str, bytes: &[u8]) -> &'static str {
+    let extension = Path::new(name).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "xml" => "application/xml",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "md" | "markdown" => "text/markdown",
+        "mmd" | "mermaid" => "text/vnd.mermaid",
+        _ if std::str::from_utf8(bytes).is_ok() => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Whether content of `mime_type` is text, to return as such rather than base64.
+pub fn is_text(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || matches!(mime_type, "application/json" | "application/yaml" | "application/toml" | "application/xml" | "image/svg+xml")
+}
@@ -0,0 +1,140 @@
+//! Database snapshots via SQLite's online backup API, with daily/weekly retention.
+//!
+//! Backups are written as `luna-backup-YYYYMMDD-HHMMSS.db` (UTC) so the retention pass can date
+//! them from the file name alone.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDateTime, Utc};
+use rusqlite::{Connection, MAIN_DB};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::env_parse;
+use crate::db::{self, encryption};
+use crate::models::BackupReport;
+
+const FILE_PREFIX: &str = "luna-backup-";
+const FILE_SUFFIX: &str = ".db";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(Debug, Clone, Default)]
+pub struct BackupConfig {
+    /// Default target directory (`MCP_LUNA_BACKUP_DIR`)
+    pub dir: Option<PathBuf>,
+    /// Take a backup this often while the server runs (`MCP_LUNA_BACKUP_INTERVAL_HOURS`)
+    pub interval: Option<Duration>,
+    pub retention: RetentionPolicy,
+}
+
+/// Keep the newest backup of each of the last `daily` days and of each of the last `weekly` ISO weeks.
+/// The newest backup is always kept.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self { daily: 7, weekly: 4 }
+    }
+}
+
+impl BackupConfig {
+    pub fn from_env() -> Self {
+        let defaults = RetentionPolicy::default();
+        Self {
+            dir: std::env::var_os("MCP_LUNA_BACKUP_DIR").filter(|d| !d.is_empty()).map(PathBuf::from),
+            interval: env_parse::<u64>("MCP_LUNA_BACKUP_INTERVAL_HOURS")
+                .filter(|h| *h > 0)
+                .map(|h| Duration::from_secs(h * 60 * 60)),
+            retention: RetentionPolicy {
+                daily: env_parse("MCP_LUNA_BACKUP_KEEP_DAILY").unwrap_or(defaults.daily),
+                weekly: env_parse("MCP_LUNA_BACKUP_KEEP_WEEKLY").unwrap_or(defaults.weekly),
+            },
+        }
+    }
+}
+
+/// Snapshot the main database of `conn` into `dir`, then prune old snapshots per `retention`.
+pub fn create_backup(conn: &Connection, dir: &Path, retention: RetentionPolicy) -> Result<BackupReport> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create backup directory {}", dir.display()))?;
+    let now = Utc::now();
+    let path = dir.join(format!("{}{}{}", FILE_PREFIX, now.format(TIMESTAMP_FORMAT), FILE_SUFFIX));
+    if encryption::key().is_some() {
+        // SQLCipher refuses the backup API; VACUUM INTO writes a copy encrypted with the same key
+        conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])
+            .with_context(|| format!("Backup to {} failed", path.display()))?;
+    } else {
+        conn.backup(MAIN_DB, &path, None)
+            .with_context(|| format!("Backup to {} failed", path.display()))?;
+    }
+
+    let size_bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let removed = apply_retention(dir, retention)?;
+    Ok(BackupReport {
+        path: path.display().to_string(),
+        size_bytes,
+        created_at: now.timestamp(),
+        removed: removed.iter().map(|p| p.display().to_string()).collect(),
+        error: None,
+    })
+}
+
+/// Delete backups in `dir` that fall outside the retention policy. Returns the removed paths.
+pub fn apply_retention(dir: &Path, retention: RetentionPolicy) -> Result<Vec<PathBuf>> {
+    let mut backups: Vec<(NaiveDateTime, PathBuf)> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to list {}", dir.display()))?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            let stamp = name.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_SUFFIX)?;
+            let taken_at = NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok()?;
+            Some((taken_at, path))
+        })
+        .collect();
+    backups.sort_by_key(|(taken_at, _)| std::cmp::Reverse(*taken_at));
+
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut removed = Vec::new();
+    for (index, (taken_at, path)) in backups.into_iter().enumerate() {
+        let date = taken_at.date();
+        let week = (date.iso_week().year(), date.iso_week().week());
+        // Newest-first, so the first backup seen for a day/week is the one to keep
+        let keep_daily = days.len() < retention.daily && days.insert(date);
+        let keep_weekly = weeks.len() < retention.weekly && weeks.insert(week);
+        if index == 0 || keep_daily || keep_weekly {
+            continue;
+        }
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+/// Take a backup every `config.interval` until the process exits. Uses its own read-only
+/// connection so snapshots never wait on the tool connection.
+pub async fn run_schedule(db_path: String, config: BackupConfig) {
+    let (Some(interval), Some(dir)) = (config.interval, config.dir.clone()) else {
+        return;
+    };
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        ticker.tick().await;
+        let db_path = db_path.clone();
+        let dir = dir.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = db::open_read_only(&db_path)?;
+            create_backup(&conn, &dir, config.retention)
+        })
+        .await;
+        match result {
+            Ok(Ok(report)) => eprintln!("Scheduled backup written to {}", report.path),
+            Ok(Err(e)) => eprintln!("Scheduled backup failed: {:#}", e),
+            Err(e) => eprintln!("Scheduled backup task failed: {}", e),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Bookmarks: links worth keeping, with the user's note, tags and the page's own title and
+//! description, indexed for full-text search. Saving a URL again updates its bookmark.
+//!
+//! Page metadata is read from the `<title>` and the description and Open Graph `<meta>` tags of
+//! the first [`MAX_PAGE_BYTES`] of the page. Unless allowed, pages on loopback, link-local and
+//! private addresses are not fetched, so a model can't probe the server's own network.
+
+use std::io;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, Row};
+use ureq::config::Config as HttpConfig;
+use ureq::http::Uri;
+use ureq::unversioned::resolver::{DefaultResolver, ResolvedSocketAddrs, Resolver};
+use ureq::unversioned::transport::{DefaultConnector, NextTimeout};
+
+use crate::dates::Timezone;
+use crate::db::unix_now;
+use crate::fts;
+use crate::models::Bookmark;
+
+/// Bytes of a page read for its metadata; the head comes first.
+const MAX_PAGE_BYTES: u64 = 512 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest title or description kept from a page, in characters.
+const MAX_METADATA_CHARS: usize = 500;
+
+const COLUMNS: &str = "b.id, b.url, b.title, b.description, b.note, b.tags, b.created_at, b.updated_at, b.fetched_at";
+
+fn from_row(row: &Row, timezone: Timezone) -> rusqlite::Result<Bookmark> {
+    let created_at = row.get(6)?;
+    Ok(Bookmark {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        description: row.get(3)?,
+        note: row.get(4)?,
+        tags: row.get::<_, String>(5).ok().and_then(|tags| serde_json::from_str(&tags).ok()).unwrap_or_default(),
+        created_at,
+        created_at_iso: timezone.iso(created_at),
+        updated_at: row.get(7)?,
+        fetched_at: row.get(8)?,
+    })
+}
+
+/// A bookmark to save. Fields left out keep their value when the URL is already bookmarked.
+#[derive(Debug, Default)]
+pub struct BookmarkUpdate {
+    pub url: String,
+    pub note: Option<String>,
+    /// Added to the bookmark's tags
+    pub tags: Vec<String>,
+    pub metadata: Option<PageMetadata>,
+}
+
+/// What a page says about itself.
+#[derive(Debug, Default, Clone)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+/// `url` trimmed, checked to be an http(s) URL.
+pub fn normalize_url(url: &str) -> Result<String> {
+    let url = url.trim();
+    let scheme = url.split_once("://").map(|(scheme, _)| scheme.to_ascii_lowercase());
+    anyhow::ensure!(
+        matches!(scheme.as_deref(), Some("http" | "https")) && url.len() > "https://".len(),
+        "'{}' is not an http(s) URL",
+        url
+    );
+    Ok(url.to_string())
+}
+
+/// Save `update`, creating the bookmark or updating the one with the same URL.
+pub fn save(conn: &Connection, update: BookmarkUpdate, timezone: Timezone) -> Result<Bookmark> {
+    let url = normalize_url(&update.url)?;
+    let existing = find(conn, &url, timezone)?;
+    let mut tags = existing.as_ref().map(|b| b.tags.clone()).unwrap_or_default();
+    for tag in update.tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|seen| seen.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    let note = update.note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    let metadata = update.metadata.unwrap_or_default();
+    let fetched_at = (metadata.title.is_some() || metadata.description.is_some()).then(unix_now);
+    let now = unix_now();
+    conn.execute(
+        r#"
+        INSERT INTO bookmarks (url, title, description, note, tags, created_at, updated_at, fetched_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7)
+        ON CONFLICT(url) DO UPDATE SET
+            title = COALESCE(excluded.title, bookmarks.title),
+            description = COALESCE(excluded.description, bookmarks.description),
+            note = COALESCE(excluded.note, bookmarks.note),
+            tags = excluded.tags,
+            updated_at = excluded.updated_at,
+            fetched_at = COALESCE(excluded.fetched_at, bookmarks.fetched_at)
+        "#,
+        rusqlite::params![url, metadata.title, metadata.description, note, serde_json::to_string(&tags)?, now, fetched_at],
+    )
+    .context("Failed to save bookmark")?;
+    find(conn, &url, timezone)?.context("Bookmark disappeared")
+}
+
+/// The bookmark of `url`, if any.
+pub fn find(conn: &Connection, url: &str, timezone: Timezone) -> Result<Option<Bookmark>> {
+    let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM bookmarks b WHERE b.url = ?", COLUMNS))?;
+    let mut rows = stmt.query_map([url.trim()], |row| from_row(row, timezone))?;
+    rows.next().transpose().context("Failed to read bookmark")
+}
+
+/// Up to `limit` bookmarks matching any of `keywords` in their URL, title, description, note or
+/// tags, best match first, and carrying every one of `tags`. Without keywords, the newest first.
+pub fn search(conn: &Connection, keywords: &[String], tags: &[String], limit: u32, timezone: Timezone) -> Result<Vec<Bookmark>> {
+    let fts_query = fts::any_of(&fts::clean_keywords(keywords), true);
+    let mut conditions = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    if let Some(query) = &fts_query {
+        conditions.push("bookmarks_fts MATCH ?".to_string());
+        params.push(query.clone());
+    }
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        conditions.push("EXISTS(SELECT 1 FROM json_each(b.tags) WHERE value = ? COLLATE NOCASE)".to_string());
+        params.push(tag.to_string());
+    }
+    let (join, order) = match fts_query {
+        Some(_) => ("JOIN bookmarks_fts ON b.id = bookmarks_fts.rowid", "bookmarks_fts.rank"),
+        None => ("", "b.updated_at DESC, b.id DESC"),
+    };
+    let filter = if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) };
+    params.push(limit.to_string());
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM bookmarks b {} {} ORDER BY {} LIMIT ?", COLUMNS, join, filter, order))
+        .context("Failed to search bookmarks")?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| from_row(row, timezone))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read bookmarks")
+}
+
+/// Title and description of the page at `url`. Hosts with a loopback, link-local or private
+/// address are refused, redirects included, unless `allow_private`.
+pub fn fetch_metadata(url: &str, allow_private: bool) -> Result<PageMetadata> {
+    let config = ureq::Agent::config_builder().timeout_global(Some(FETCH_TIMEOUT)).build();
+    let agent = if allow_private {
+        ureq::Agent::new_with_config(config)
+    } else {
+        ureq::Agent::with_parts(config, DefaultConnector::default(), PublicResolver::default())
+    };
+    let mut response = agent.get(url).call().with_context(|| format!("Failed to fetch {}", url))?;
+    let html = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_PAGE_BYTES)
+        .lossy_utf8(true)
+        .read_to_string()
+        .with_context(|| format!("Failed to read {}", url))?;
+    Ok(page_metadata(&html))
+}
+
+/// The default resolver, failing for hosts with any address that isn't public. Checking the
+/// addresses connected to, rather than the URL, covers redirects and names that resolve inward.
+#[derive(Debug, Default)]
+struct PublicResolver(DefaultResolver);
+
+impl Resolver for PublicResolver {
+    fn resolve(&self, uri: &Uri, config: &HttpConfig, timeout: NextTimeout) -> Result<ResolvedSocketAddrs, ureq::Error> {
+        let addrs = self.0.resolve(uri, config, timeout)?;
+        match addrs.iter().find(|addr| !is_public(addr.ip())) {
+            Some(addr) => Err(ureq::Error::Io(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} resolves to the non-public address {}; set MCP_LUNA_FETCH_PRIVATE_URLS to allow it",
+                    uri.host().unwrap_or_default(),
+                    addr.ip()
+                ),
+            ))),
+            None => Ok(addrs),
+        }
+    }
+}
+
+/// Whether `ip` is outside the loopback, link-local, private, shared and unspecified ranges.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => !(ip.is_loopback() || ip.is_unspecified() || ip.is_unique_local() || ip.is_unicast_link_local()),
+        },
+    }
+}
+
+/// Title and description declared by an HTML document, preferring Open Graph tags.
+fn page_metadata(html: &str) -> PageMetadata {
+    let mut metadata = PageMetadata::default();
+    let mut title = None;
+    let mut description = None;
+    let lower = html.to_ascii_lowercase();
+    let mut rest = 0;
+    while let Some(start) = lower[rest..].find("<meta").map(|i| rest + i) {
+        let end = lower[start..].find('>').map_or(lower.len(), |i| start + i);
+        let tag = &html[start..end];
+        rest = end;
+        let key = attribute(tag, "property").or_else(|| attribute(tag, "name")).map(|k| k.to_ascii_lowercase());
+        let Some(content) = attribute(tag, "content") else {
+            continue;
+        };
+        match key.as_deref() {
+            Some("og:title") => metadata.title = Some(content),
+            Some("og:description") => metadata.description = Some(content),
+            Some("description") => description = Some(content),
+            _ => {}
+        }
+    }
+    if let Some(start) = lower.find("<title") {
+        let open = lower[start..].find('>').map(|i| start + i + 1);
+        if let Some(open) = open {
+            let close = lower[open..].find("</title").map_or(lower.len(), |i| open + i);
+            title = Some(html[open..close].to_string());
+        }
+    }
+    metadata.title = metadata.title.or(title).map(|t| cleaned(&t)).filter(|t| !t.is_empty());
+    metadata.description = metadata.description.or(description).map(|d| cleaned(&d)).filter(|d| !d.is_empty());
+    metadata
+}
+
+/// Value of attribute `name` in the HTML tag `tag`, quoted or not.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(name).map(|i| from + i) {
+        from = found + name.len();
+        let preceded = lower[..found].chars().next_back().is_some_and(char::is_whitespace);
+        let after = lower[from..].trim_start();
+        if !preceded || !after.starts_with('=') {
+            continue;
+        }
+        let value = after[1..].trim_start();
+        let value_start = tag.len() - value.len();
+        let value = &tag[value_start..];
+        return Some(match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default().to_string(),
+            _ => value.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or_default().to_string(),
+        });
+    }
+    None
+}
+
+/// `text` with HTML entities decoded and whitespace collapsed, cut to [`MAX_METADATA_CHARS`].
+fn cleaned(text: &str) -> String {
+    let decoded = decode_entities(text);
+    let collapsed = decoded.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(MAX_METADATA_CHARS) {
+        Some((cut, _)) => format!("{}…", &collapsed[..cut]),
+        None => collapsed,
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest.find(';').filter(|end| *end <= 10).map(|end| (&rest[1..end], end));
+        let character = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => name
+                .strip_prefix("#x")
+                .or_else(|| name.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| name.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        });
+        match (character, entity) {
+            (Some(character), Some((_, end))) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
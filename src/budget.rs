@@ -0,0 +1,140 @@
+//! Per-session limits on tool calls, so a runaway agent can't pull the whole history back to back:
+//! calls per minute, bytes per result and bytes per session. Each MCP session gets its own
+//! [`Budget`]; all limits are off unless configured. A call over a limit gets a structured error
+//! naming the limit instead of its result.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rmcp::model::CallToolResult;
+use serde_json::json;
+
+use crate::config::env_parse;
+use crate::models::BudgetExceeded;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default)]
+pub struct BudgetConfig {
+    /// Tool calls allowed in any 60 seconds (`MCP_LUNA_MAX_CALLS_PER_MINUTE`)
+    pub calls_per_minute: Option<u64>,
+    /// Largest result a single call may return, in bytes of JSON (`MCP_LUNA_MAX_RESULT_BYTES`)
+    pub max_result_bytes: Option<u64>,
+    /// Bytes all results of a session may add up to (`MCP_LUNA_MAX_SESSION_BYTES`)
+    pub max_session_bytes: Option<u64>,
+}
+
+impl BudgetConfig {
+    pub fn from_env() -> Self {
+        let limit = |name| env_parse::<u64>(name).filter(|n| *n > 0);
+        Self {
+            calls_per_minute: limit("MCP_LUNA_MAX_CALLS_PER_MINUTE"),
+            max_result_bytes: limit("MCP_LUNA_MAX_RESULT_BYTES"),
+            max_session_bytes: limit("MCP_LUNA_MAX_SESSION_BYTES"),
+        }
+    }
+}
+
+/// What a session has used so far.
+#[derive(Debug, Default)]
+pub struct Budget {
+    config: BudgetConfig,
+    usage: Mutex<Usage>,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    /// Start times of the calls within the last minute, oldest first
+    calls: VecDeque<Instant>,
+    /// Bytes returned this session
+    bytes: u64,
+}
+
+impl Budget {
+    pub fn new(config: BudgetConfig) -> Self {
+        Self { config, usage: Mutex::default() }
+    }
+
+    /// Count a call about to start, or refuse it when the session is over its call rate or has
+    /// used up its bytes.
+    pub fn admit(&self) -> Result<(), BudgetExceeded> {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(max) = self.config.max_session_bytes.filter(|max| usage.bytes >= *max) {
+            return Err(exceeded("max_session_bytes", max, usage.bytes, None));
+        }
+        if let Some(max) = self.config.calls_per_minute {
+            let now = Instant::now();
+            while usage.calls.front().is_some_and(|started| now.duration_since(*started) >= RATE_WINDOW) {
+                usage.calls.pop_front();
+            }
+            if usage.calls.len() as u64 >= max {
+                let retry_after = usage.calls.front().map(|oldest| RATE_WINDOW.saturating_sub(now.duration_since(*oldest)));
+                return Err(exceeded("calls_per_minute", max, usage.calls.len() as u64, retry_after));
+            }
+            usage.calls.push_back(now);
+        }
+        Ok(())
+    }
+
+    /// Charge `result` to the session, or refuse it when it is larger than one call or the rest of
+    /// the session may return. Refused results and error results aren't charged.
+    pub fn charge(&self, result: &CallToolResult) -> Result<(), BudgetExceeded> {
+        let limited = self.config.max_result_bytes.is_some() || self.config.max_session_bytes.is_some();
+        if !limited || result.is_error == Some(true) {
+            return Ok(());
+        }
+        let size = result_bytes(result);
+        if let Some(max) = self.config.max_result_bytes.filter(|max| size > *max) {
+            return Err(exceeded("max_result_bytes", max, size, None));
+        }
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(max) = self.config.max_session_bytes.filter(|max| usage.bytes + size > *max) {
+            return Err(exceeded("max_session_bytes", max, usage.bytes + size, None));
+        }
+        usage.bytes += size;
+        Ok(())
+    }
+}
+
+fn exceeded(limit: &str, max: u64, used: u64, retry_after: Option<Duration>) -> BudgetExceeded {
+    BudgetExceeded {
+        limit: limit.to_string(),
+        max,
+        used,
+        // Round up so a client that waits this long is let through
+        retry_after_secs: retry_after.map(|wait| wait.as_secs() + u64::from(wait.subsec_nanos() > 0)),
+    }
+}
+
+/// Size of a result as sent: its text, which is the serialized JSON or a Markdown rendering, or
+/// its structured content when it has no text.
+fn result_bytes(result: &CallToolResult) -> u64 {
+    let text: u64 = result.content.iter().filter_map(|content| content.as_text()).map(|text| text.text.len() as u64).sum();
+    match &result.structured_content {
+        Some(value) if text == 0 => value.to_string().len() as u64,
+        _ => text,
+    }
+}
+
+impl BudgetExceeded {
+    /// The error result returned in place of a call over budget.
+    pub fn into_result(self) -> CallToolResult {
+        let error = match self.limit.as_str() {
+            "calls_per_minute" => format!(
+                "Budget exceeded: more than {} tool calls in a minute; wait {} seconds",
+                self.max,
+                self.retry_after_secs.unwrap_or(1)
+            ),
+            "max_result_bytes" => format!(
+                "Budget exceeded: the result is {} bytes, more than the {} allowed per call; ask for fewer or shorter items",
+                self.used, self.max
+            ),
+            _ => format!(
+                "Budget exceeded: this session may return {} bytes in all, and this call would bring it to {}",
+                self.max, self.used
+            ),
+        };
+        CallToolResult::structured_error(json!({ "error": error, "budget_exceeded": self }))
+    }
+}
@@ -0,0 +1,207 @@
+//! Request cancellation. When the client cancels a tool call (`notifications/cancelled`), the
+//! SQLite statement it is running on the shared connection is interrupted, so one pathological
+//! query doesn't hold the connection for every other request.
+//!
+//! Calls are numbered and the number travels with the call as a task-local, so a cancellation
+//! only interrupts the connection while that same call holds it, never the request after it.
+//!
+//! With a query timeout, a progress handler on the shared connection also interrupts whoever
+//! holds it for longer than the timeout, and the call gets a [`QueryTimeout`] error instead of
+//! whatever its tool made of the failed query.
+//!
+//! Pooled read-only connections running part of a call's queries are registered with the call
+//! while in use, so cancelling it interrupts them too, and get a progress handler enforcing the
+//! same timeout.
+
+use rmcp::model::CallToolResult;
+use rusqlite::{Connection, InterruptHandle};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::models::QueryTimeout;
+
+/// SQLite virtual machine instructions between deadline checks; a few microseconds of work.
+const PROGRESS_OPS: i32 = 10_000;
+
+tokio::task_local! {
+    static CALL: u64;
+}
+
+#[derive(Default)]
+pub struct Interrupter {
+    next_call: AtomicU64,
+    /// Interrupt handle of the shared connection, once it has been opened
+    handle: Mutex<Option<InterruptHandle>>,
+    /// Call currently using the shared connection
+    running: Mutex<Option<u64>>,
+    /// Longest one holder may keep the shared connection busy; `None` for no limit
+    timeout: Option<Duration>,
+    /// When the current holder of the shared connection runs out of time
+    deadline: Mutex<Option<Instant>>,
+    /// Calls interrupted for running out of time, until `run` reports them
+    timed_out: Mutex<HashSet<u64>>,
+    next_pooled: AtomicU64,
+    /// Interrupt handles of the pooled connections in use, with the call using each
+    pooled: Mutex<HashMap<u64, (Option<u64>, InterruptHandle)>>,
+}
+
+impl Interrupter {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self { timeout, ..Default::default() }
+    }
+
+    /// Remember the shared connection's interrupt handle and, with a timeout, watch its queries
+    /// for running out of time. Called when the connection is opened.
+    pub fn attach(self: &Arc<Self>, conn: &Connection) -> rusqlite::Result<()> {
+        self.set_handle(conn.get_interrupt_handle());
+        if self.timeout.is_some() {
+            let interrupter = Arc::clone(self);
+            conn.progress_handler(PROGRESS_OPS, Some(move || interrupter.expired()))?;
+        }
+        Ok(())
+    }
+
+    /// Remember the shared connection's interrupt handle. Called when the connection is opened.
+    fn set_handle(&self, handle: InterruptHandle) {
+        *self.handle.lock().unwrap_or_else(|e| e.into_inner()) = Some(handle);
+    }
+
+    /// Run `call`, interrupting its query if `cancelled` completes first. A call whose queries
+    /// ran out of time is a [`QueryTimeout`], whatever it returned.
+    pub async fn run<C, F>(self: &Arc<Self>, cancelled: C, call: F) -> Result<F::Output, QueryTimeout>
+    where
+        C: Future<Output = ()> + Send + 'static,
+        F: Future,
+    {
+        let id = self.next_call.fetch_add(1, Ordering::Relaxed);
+        let interrupter = Arc::clone(self);
+        let watcher = tokio::spawn(async move {
+            cancelled.await;
+            interrupter.interrupt(id);
+        });
+        let output = run_blocking(CALL.scope(id, call)).await;
+        watcher.abort();
+        match (self.timeout, self.timed_out.lock().unwrap_or_else(|e| e.into_inner()).remove(&id)) {
+            (Some(timeout), true) => Err(QueryTimeout { timeout_secs: timeout.as_secs_f64() }),
+            _ => Ok(output),
+        }
+    }
+
+    /// Mark the shared connection as used by the current call until the guard drops. Outside
+    /// [`run`](Self::run) (e.g. prompts and resources) there is no call to cancel, and nothing is marked.
+    pub fn enter(&self) -> RunningGuard<'_> {
+        let id = CALL.try_with(|id| *id).ok();
+        *self.running.lock().unwrap_or_else(|e| e.into_inner()) = id;
+        *self.deadline.lock().unwrap_or_else(|e| e.into_inner()) = self.timeout.map(|timeout| Instant::now() + timeout);
+        RunningGuard { interrupter: self }
+    }
+
+    /// The call running on this task, if any, for handing to [`enter_pooled`](Self::enter_pooled)
+    /// on another thread.
+    pub fn current_call() -> Option<u64> {
+        CALL.try_with(|id| *id).ok()
+    }
+
+    /// Mark pooled connection `conn` as used by `call` until the guard drops, so cancelling the
+    /// call interrupts it, and with a timeout, interrupt its queries once they run out of time.
+    pub fn enter_pooled<'a>(self: &Arc<Self>, call: Option<u64>, conn: &'a Connection) -> rusqlite::Result<PooledGuard<'a>> {
+        if let Some(timeout) = self.timeout {
+            let interrupter = Arc::clone(self);
+            let deadline = Instant::now() + timeout;
+            conn.progress_handler(
+                PROGRESS_OPS,
+                Some(move || {
+                    if Instant::now() < deadline {
+                        return false;
+                    }
+                    if let Some(id) = call {
+                        interrupter.timed_out.lock().unwrap_or_else(|e| e.into_inner()).insert(id);
+                    }
+                    true
+                }),
+            )?;
+        }
+        let slot = self.next_pooled.fetch_add(1, Ordering::Relaxed);
+        self.pooled.lock().unwrap_or_else(|e| e.into_inner()).insert(slot, (call, conn.get_interrupt_handle()));
+        Ok(PooledGuard { interrupter: Arc::clone(self), slot, conn })
+    }
+
+    /// Whether the holder of the shared connection is out of time, remembering the call if so.
+    fn expired(&self) -> bool {
+        let deadline = *self.deadline.lock().unwrap_or_else(|e| e.into_inner());
+        if deadline.is_none_or(|deadline| Instant::now() < deadline) {
+            return false;
+        }
+        if let Some(id) = *self.running.lock().unwrap_or_else(|e| e.into_inner()) {
+            self.timed_out.lock().unwrap_or_else(|e| e.into_inner()).insert(id);
+        }
+        true
+    }
+
+    fn interrupt(&self, id: u64) {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        if *running == Some(id) {
+            if let Some(handle) = self.handle.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+                handle.interrupt();
+            }
+        }
+        drop(running);
+        let pooled = self.pooled.lock().unwrap_or_else(|e| e.into_inner());
+        for (_, handle) in pooled.values().filter(|(call, _)| *call == Some(id)) {
+            handle.interrupt();
+        }
+    }
+}
+
+/// Tools run their queries synchronously. On the multi-threaded runtime, hand this worker's other
+/// tasks to another thread first so the transport keeps reading messages, cancellations included,
+/// while the query runs.
+async fn run_blocking<F: Future>(call: F) -> F::Output {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| handle.block_on(call))
+        }
+        _ => call.await,
+    }
+}
+
+pub struct RunningGuard<'a> {
+    interrupter: &'a Interrupter,
+}
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        *self.interrupter.running.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        *self.interrupter.deadline.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+pub struct PooledGuard<'a> {
+    interrupter: Arc<Interrupter>,
+    slot: u64,
+    conn: &'a Connection,
+}
+
+impl Drop for PooledGuard<'_> {
+    fn drop(&mut self) {
+        self.interrupter.pooled.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.slot);
+        // The next user of the connection gets a deadline of its own
+        let _ = self.conn.progress_handler(0, None::<fn() -> bool>);
+    }
+}
+
+impl QueryTimeout {
+    /// The error result returned in place of a call whose queries ran out of time.
+    pub fn into_result(self) -> CallToolResult {
+        let error = format!(
+            "Query timed out after {} seconds; narrow it down (fewer or longer keywords, a date range, a conversation) and try again",
+            self.timeout_secs
+        );
+        CallToolResult::structured_error(json!({ "error": error, "query_timeout": self }))
+    }
+}
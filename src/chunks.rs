@@ -0,0 +1,44 @@
+//! Paging through conversations too large to return at once. `get_conversation` hands back the
+//! first chunk of messages with a token for the next; each chunk holds as many messages as fit in
+//! the configured number of bytes of JSON, and at least one. Tokens only record where the next
+//! chunk starts, so they don't expire and messages added meanwhile show up in later chunks.
+
+use crate::models::Message;
+
+/// Conversations whose messages serialize to more than this are returned in chunks, unless
+/// `MCP_LUNA_CHUNK_BYTES` says otherwise.
+pub const DEFAULT_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Token for the chunk of `conversation_id` starting at message `start`.
+pub fn token(conversation_id: &str, start: usize) -> String {
+    format!("{}:{}", start, conversation_id)
+}
+
+/// The conversation and first message index a token points at.
+pub fn parse_token(token: &str) -> Option<(&str, usize)> {
+    let (start, conversation_id) = token.trim().split_once(':')?;
+    Some((conversation_id, start.parse().ok()?)).filter(|(id, _)| !id.is_empty())
+}
+
+fn json_len(message: &Message) -> usize {
+    serde_json::to_string(message).map_or(0, |json| json.len())
+}
+
+/// Whether `messages` are too large to return in one piece.
+pub fn too_large(messages: &[Message], max_bytes: usize) -> bool {
+    max_bytes > 0 && messages.iter().map(json_len).sum::<usize>() > max_bytes
+}
+
+/// End (exclusive) of the chunk starting at `start`.
+pub fn chunk_end(messages: &[Message], start: usize, max_bytes: usize) -> usize {
+    let mut end = start;
+    let mut bytes = 0;
+    for message in messages.iter().skip(start) {
+        bytes += json_len(message);
+        if end > start && bytes > max_bytes {
+            break;
+        }
+        end += 1;
+    }
+    end
+}
@@ -0,0 +1,170 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use mcp_luna_history::import;
+use std::path::PathBuf;
+
+/// MCP server for Luna AI conversation history and long-term memory.
+/// Runs the MCP server, over stdio or with --http over HTTP, when no subcommand is given.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Database file (default: COSMIC_LLM_DB_PATH, then the Cosmic LLM data directory)
+    #[arg(long, global = true)]
+    pub db: Option<PathBuf>,
+    /// Serve without any tool that writes, on a read-only connection (also MCP_LUNA_READONLY=1)
+    #[arg(long)]
+    pub read_only: bool,
+    /// Serve MCP over streamable HTTP on this address instead of stdio, e.g. 127.0.0.1:8765
+    /// (also MCP_LUNA_HTTP_ADDR)
+    #[arg(long, value_name = "ADDR")]
+    pub http: Option<std::net::SocketAddr>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Export all conversations and memories for backup or analysis
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Jsonl)]
+        format: ExportFormat,
+        /// Directory to write the export files into
+        #[arg(long)]
+        output: PathBuf,
+        /// Mask email addresses, phone numbers and street addresses in the exported records
+        #[arg(long)]
+        mask_pii: bool,
+    },
+    /// Import conversations from another assistant's data export
+    Import {
+        /// Export format of the file
+        #[arg(value_enum)]
+        format: ImportFormat,
+        /// Path to the export file (ChatGPT or Claude conversations.json)
+        file: PathBuf,
+    },
+    /// Snapshot the database and prune old snapshots
+    Backup {
+        /// Directory to write the snapshot into (default: MCP_LUNA_BACKUP_DIR)
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Keep the newest snapshot of each of this many recent days
+        #[arg(long)]
+        keep_daily: Option<usize>,
+        /// Keep the newest snapshot of each of this many recent weeks
+        #[arg(long)]
+        keep_weekly: Option<usize>,
+    },
+    /// Check integrity, optimize full-text indexes and refresh planner statistics
+    Maintenance {
+        /// Also VACUUM the database to reclaim free space
+        #[arg(long)]
+        vacuum: bool,
+    },
+    /// Replace large tool results of old tool calls with short summaries, keeping the originals
+    /// compressed unless told otherwise
+    PruneToolResults {
+        /// Prune tool results larger than this many kilobytes
+        #[arg(long, default_value_t = 16)]
+        min_kb: u32,
+        /// Only prune results of tool calls older than this many days
+        #[arg(long, default_value_t = 90)]
+        older_than_days: u32,
+        /// Drop the originals instead of keeping them compressed
+        #[arg(long)]
+        discard_original: bool,
+        /// Only report what would be pruned
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Move conversations untouched since a date to the archive database, searchable with
+    /// include_archive
+    Archive {
+        /// Archive conversations whose last message is older than this: an ISO date, unix seconds
+        /// or a phrase like "last year"
+        #[arg(long)]
+        before: String,
+        /// Archive database (default: MCP_LUNA_ARCHIVE_PATH, then next to the database)
+        #[arg(long)]
+        archive: Option<PathBuf>,
+    },
+    /// Rebuild full-text indexes with the configured tokenizer (MCP_LUNA_FTS_KEEP_DIACRITICS, MCP_LUNA_FTS_PORTER)
+    Reindex {
+        /// Indexes to rebuild (messages_fts, memory_fts, titles_fts); default: all
+        tables: Vec<String>,
+    },
+    /// Group conversations into topics and print a summary of each
+    ClusterTopics {
+        /// Number of topics (default: about the square root of half the number of conversations)
+        #[arg(long)]
+        topics: Option<usize>,
+    },
+    /// Embed past messages with the configured embeddings endpoint (MCP_LUNA_EMBEDDING_URL),
+    /// resuming from the last checkpoint
+    IndexEmbeddings {
+        /// Messages sent to the endpoint per request
+        #[arg(long, default_value_t = 64)]
+        batch_size: usize,
+        /// Stop after embedding this many messages
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Drop the model's embeddings and checkpoint and start over from the first message
+        #[arg(long)]
+        restart: bool,
+    },
+    /// Merge another conversation/memory database into this one (e.g. from a second machine)
+    MergeDb {
+        /// Path to the database to merge in; it is only read
+        source: PathBuf,
+    },
+    /// Fill a new scratch database with synthetic conversations for benchmarking searches; the
+    /// same counts and seed always give the same data. Refuses a database that holds conversations
+    Seed {
+        /// Conversations to create
+        #[arg(long, default_value_t = 1000)]
+        conversations: usize,
+        /// Messages to spread over them, at least one per conversation
+        #[arg(long, default_value_t = 50_000)]
+        messages: usize,
+        /// Random seed; change it for different data of the same size
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+    },
+    /// Check that the database opens and has the tables the server needs; prints the report as
+    /// JSON and exits non-zero when unhealthy (for container health checks)
+    Health,
+    /// Encrypt a plaintext database with the configured key (MCP_LUNA_DB_KEY or the keyring);
+    /// needs a build with the sqlcipher feature
+    MigrateToEncrypted {
+        /// Write the encrypted copy here and leave the database alone (default: replace the
+        /// database, keeping the plaintext file next to it with a .plaintext suffix)
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    /// Detect the format from the file contents
+    Auto,
+    /// OpenAI ChatGPT data export
+    Chatgpt,
+    /// Anthropic Claude data export
+    Claude,
+}
+
+impl ImportFormat {
+    /// The format the user asked for, or `None` to detect it from the file.
+    pub fn explicit(self) -> Option<import::Format> {
+        match self {
+            ImportFormat::Auto => None,
+            ImportFormat::Chatgpt => Some(import::Format::ChatGpt),
+            ImportFormat::Claude => Some(import::Format::Claude),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Compact JSON for `format: "compact"`: the result serialized without null fields and with the
+//! most common long keys shortened, which about halves large result sets. Works on any tool's
+//! result, after it was built, so tools don't know about it. A `_keys` object at the top maps the
+//! short keys that occur back to their names.
+
+use std::collections::BTreeMap;
+
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+/// Long keys and their short forms. The short forms are not the names of any other field.
+const SHORT_KEYS: &[(&str, &str)] = &[
+    ("conversation_id", "cid"),
+    ("conversation_title", "ctitle"),
+    ("message_id", "mid"),
+    ("memory_id", "mem"),
+    ("content", "c"),
+    ("content_preview", "pv"),
+    ("reasoning_content", "rc"),
+    ("reasoning_preview", "rp"),
+    ("created_at", "t"),
+    ("created_at_iso", "at"),
+    ("updated_at", "ut"),
+    ("expires_at", "exp"),
+    ("due_at", "dat"),
+    ("last_used_at", "lua"),
+    ("estimated_tokens", "tok"),
+    ("matched_keywords", "mk"),
+    ("keyword_hits", "kh"),
+    ("message_count", "mc"),
+    ("total_messages", "tm"),
+    ("profile_name", "pn"),
+    ("title_generated", "tg"),
+    ("tool_name", "tn"),
+    ("tool_status", "tst"),
+    ("tool_call_id", "tci"),
+    ("tool_calls", "tcs"),
+    ("tool_params_json", "tpj"),
+    ("tool_result_json", "trj"),
+    ("similarity", "sim"),
+    ("importance", "imp"),
+    ("category", "cat"),
+    ("relationship", "rel"),
+    ("description", "desc"),
+];
+
+/// Key of the legend added to compact results.
+const LEGEND_KEY: &str = "_keys";
+
+fn short_key(key: &str) -> &str {
+    SHORT_KEYS.iter().find(|(long, _)| *long == key).map_or(key, |(_, short)| short)
+}
+
+/// `value` serialized compactly, with a `_keys` legend when it is an object and had keys shortened.
+pub fn to_string(value: &Value) -> String {
+    let mut legend = BTreeMap::new();
+    collect_legend(value, &mut legend);
+    let result = match value {
+        Value::Object(_) if !legend.is_empty() => serde_json::to_string(&WithLegend { legend, value }),
+        _ => serde_json::to_string(&Compact(value)),
+    };
+    result.unwrap_or_else(|_| value.to_string())
+}
+
+/// Short keys that occur in non-null fields of `value`, with their long names.
+fn collect_legend(value: &Value, legend: &mut BTreeMap<&'static str, &'static str>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter().filter(|(_, v)| !v.is_null()) {
+                if let Some((long, short)) = SHORT_KEYS.iter().find(|(long, _)| long == key) {
+                    legend.insert(short, long);
+                }
+                collect_legend(value, legend);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_legend(item, legend)),
+        _ => {}
+    }
+}
+
+/// Serializes the wrapped value with null fields skipped and keys shortened.
+struct Compact<'a>(&'a Value);
+
+impl Serialize for Compact<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Object(map) => {
+                let mut out = serializer.serialize_map(None)?;
+                for (key, value) in map.iter().filter(|(_, v)| !v.is_null()) {
+                    out.serialize_entry(short_key(key), &Compact(value))?;
+                }
+                out.end()
+            }
+            Value::Array(items) => {
+                let mut out = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    out.serialize_element(&Compact(item))?;
+                }
+                out.end()
+            }
+            other => other.serialize(serializer),
+        }
+    }
+}
+
+/// A compact object with the legend as its first key.
+struct WithLegend<'a> {
+    legend: BTreeMap<&'static str, &'static str>,
+    value: &'a Value,
+}
+
+impl Serialize for WithLegend<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut out = serializer.serialize_map(None)?;
+        out.serialize_entry(LEGEND_KEY, &self.legend)?;
+        if let Value::Object(map) = self.value {
+            for (key, value) in map.iter().filter(|(_, v)| !v.is_null()) {
+                out.serialize_entry(short_key(key), &Compact(value))?;
+            }
+        }
+        out.end()
+    }
+}
@@ -0,0 +1,158 @@
+//! Server configuration, read from `MCP_LUNA_*` environment variables.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rmcp::model::LoggingLevel;
+
+use crate::backup::BackupConfig;
+use crate::dates::Timezone;
+use crate::budget::BudgetConfig;
+use crate::chunks::DEFAULT_CHUNK_BYTES;
+use crate::conversation_cache;
+use crate::embeddings::EmbeddingConfig;
+use crate::fts::Tokenizer;
+use crate::http::HttpConfig;
+use crate::logging;
+use crate::pii::PiiConfig;
+use crate::redact::RedactionConfig;
+
+const DEFAULT_READ_POOL_SIZE: usize = 4;
+const DEFAULT_WATCH_INTERVAL_SECS: u64 = 2;
+const DEFAULT_INDEX_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub backup: BackupConfig,
+    pub budget: BudgetConfig,
+    pub embedding: EmbeddingConfig,
+    pub http: HttpConfig,
+    pub redaction: RedactionConfig,
+    pub pii: PiiConfig,
+    /// Expose admin-only tools such as `run_maintenance` (`MCP_LUNA_ADMIN_TOOLS`)
+    pub admin_tools: bool,
+    /// Expose tools that change the conversation history itself, such as `update_title`
+    /// (`MCP_LUNA_HISTORY_WRITES`)
+    pub history_writes: bool,
+    /// Database file `archive_conversations` moves old conversations to; next to the database,
+    /// with `.archive` before its extension, when unset (`MCP_LUNA_ARCHIVE_PATH`)
+    pub archive_path: Option<PathBuf>,
+    /// Let `save_bookmark` fetch pages on loopback, link-local and private addresses
+    /// (`MCP_LUNA_FETCH_PRIVATE_URLS`)
+    pub fetch_private_urls: bool,
+    /// Directory `export_all` writes into; the tool refuses to export when unset (`MCP_LUNA_EXPORT_DIR`)
+    pub export_dir: Option<PathBuf>,
+    /// Directory `store_attachment` may reference files in; file references are refused when unset
+    /// (`MCP_LUNA_ATTACHMENTS_DIR`)
+    pub attachments_dir: Option<PathBuf>,
+    /// Reject destructive calls without a token from the matching `preview_*` tool (`MCP_LUNA_REQUIRE_CONFIRMATION`)
+    pub require_confirmation: bool,
+    /// Reject destructive calls from clients that can't ask the user to confirm them through
+    /// elicitation (`MCP_LUNA_REQUIRE_ELICITATION`)
+    pub require_elicitation: bool,
+    /// Conversations whose messages serialize to more than this many bytes are returned in chunks;
+    /// 0 returns them whole (`MCP_LUNA_CHUNK_BYTES`)
+    pub chunk_bytes: usize,
+    /// Conversations each session keeps loaded for repeated reads; 0 turns the cache off
+    /// (`MCP_LUNA_CONVERSATION_CACHE_SIZE`)
+    pub conversation_cache_size: usize,
+    /// Read-only connections for running a report's independent queries in parallel; 0 runs them
+    /// one after another on the main connection (`MCP_LUNA_READ_POOL_SIZE`)
+    pub read_pool_size: usize,
+    /// Longest a tool call's queries may run on the shared connection before they are interrupted;
+    /// `None` for no limit (`MCP_LUNA_QUERY_TIMEOUT_SECS`, 0 for none)
+    pub query_timeout: Option<Duration>,
+    /// Open the database read-only and leave out every tool that writes (`MCP_LUNA_READONLY`, `--read-only`)
+    pub read_only: bool,
+    /// Tool groups to register; `None` registers all (`MCP_LUNA_TOOL_GROUPS`)
+    pub tool_groups: Option<Vec<String>>,
+    /// Individual tools to register on top of the groups (`MCP_LUNA_TOOLS`)
+    pub enabled_tools: Vec<String>,
+    /// Tools to leave out even when their group is enabled (`MCP_LUNA_DISABLED_TOOLS`)
+    pub disabled_tools: Vec<String>,
+    /// How often subscribed conversations are checked for new messages (`MCP_LUNA_WATCH_INTERVAL_SECS`)
+    pub watch_interval: Duration,
+    /// How often the background indexer looks for new messages; `None` turns it off
+    /// (`MCP_LUNA_INDEX_INTERVAL_SECS`, 0 for off)
+    pub index_interval: Option<Duration>,
+    /// Lowest level sent as log notifications until the client calls `logging/setLevel`;
+    /// `None` means warnings and above (`MCP_LUNA_LOG_LEVEL`)
+    pub log_level: Option<LoggingLevel>,
+    /// Tokenizer for full-text indexes the server creates or rebuilds with `reindex`
+    pub fts_tokenizer: Tokenizer,
+    /// Languages message language detection chooses from, as ISO 639-3 codes or English names;
+    /// every language it knows when empty (`MCP_LUNA_LANGUAGES`)
+    pub languages: Vec<String>,
+    /// Timezone of `created_at_iso` fields and of dates like "yesterday" in filters; the system's
+    /// own when unset or malformed (`MCP_LUNA_TIMEZONE`)
+    pub timezone: Timezone,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Self {
+            backup: BackupConfig::from_env(),
+            budget: BudgetConfig::from_env(),
+            embedding: EmbeddingConfig::from_env(),
+            http: HttpConfig::from_env(),
+            redaction: RedactionConfig::from_env(),
+            pii: PiiConfig::from_env(),
+            admin_tools: env_flag("MCP_LUNA_ADMIN_TOOLS"),
+            history_writes: env_flag("MCP_LUNA_HISTORY_WRITES"),
+            archive_path: std::env::var_os("MCP_LUNA_ARCHIVE_PATH").filter(|p| !p.is_empty()).map(PathBuf::from),
+            fetch_private_urls: env_flag("MCP_LUNA_FETCH_PRIVATE_URLS"),
+            export_dir: std::env::var_os("MCP_LUNA_EXPORT_DIR").filter(|p| !p.is_empty()).map(PathBuf::from),
+            attachments_dir: std::env::var_os("MCP_LUNA_ATTACHMENTS_DIR").filter(|p| !p.is_empty()).map(PathBuf::from),
+            require_confirmation: env_flag("MCP_LUNA_REQUIRE_CONFIRMATION"),
+            require_elicitation: env_flag("MCP_LUNA_REQUIRE_ELICITATION"),
+            chunk_bytes: env_parse("MCP_LUNA_CHUNK_BYTES").unwrap_or(DEFAULT_CHUNK_BYTES),
+            conversation_cache_size: env_parse("MCP_LUNA_CONVERSATION_CACHE_SIZE")
+                .unwrap_or(conversation_cache::DEFAULT_CAPACITY),
+            read_pool_size: env_parse("MCP_LUNA_READ_POOL_SIZE").unwrap_or(DEFAULT_READ_POOL_SIZE),
+            query_timeout: env_parse::<f64>("MCP_LUNA_QUERY_TIMEOUT_SECS")
+                .filter(|s| s.is_finite() && *s > 0.0)
+                .map(Duration::from_secs_f64),
+            read_only: env_flag("MCP_LUNA_READONLY"),
+            tool_groups: env_list("MCP_LUNA_TOOL_GROUPS"),
+            enabled_tools: env_list("MCP_LUNA_TOOLS").unwrap_or_default(),
+            disabled_tools: env_list("MCP_LUNA_DISABLED_TOOLS").unwrap_or_default(),
+            watch_interval: Duration::from_secs(
+                env_parse::<u64>("MCP_LUNA_WATCH_INTERVAL_SECS")
+                    .filter(|s| *s > 0)
+                    .unwrap_or(DEFAULT_WATCH_INTERVAL_SECS),
+            ),
+            index_interval: Some(env_parse::<u64>("MCP_LUNA_INDEX_INTERVAL_SECS").unwrap_or(DEFAULT_INDEX_INTERVAL_SECS))
+                .filter(|s| *s > 0)
+                .map(Duration::from_secs),
+            log_level: std::env::var("MCP_LUNA_LOG_LEVEL").ok().and_then(|v| logging::parse_level(&v)),
+            fts_tokenizer: Tokenizer::from_env(),
+            languages: env_list("MCP_LUNA_LANGUAGES").unwrap_or_default(),
+            timezone: std::env::var("MCP_LUNA_TIMEZONE").ok().and_then(|v| Timezone::parse(&v)).unwrap_or_default(),
+        }
+    }
+}
+
+/// Parse an environment variable, ignoring it when unset, empty or malformed.
+pub(crate) fn env_parse<T: FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok().and_then(|v| v.trim().parse().ok())
+}
+
+/// Read a boolean switch: `1`, `true`, `yes` or `on` (any case) enable it.
+pub(crate) fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+/// Read a comma-separated list, dropping blank entries. `None` when the variable is unset or empty.
+pub(crate) fn env_list(name: &str) -> Option<Vec<String>> {
+    let value = std::env::var(name).ok().filter(|v| !v.trim().is_empty())?;
+    Some(
+        value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+    )
+}
@@ -0,0 +1,108 @@
+//! Two-phase confirmation for destructive tools.
+//!
+//! A `preview_*` tool issues a short-lived, single-use token bound to the exact action it
+//! previewed (e.g. `delete_memory:42`). The destructive tool then only proceeds when handed a
+//! token for that same action, so one mis-parameterized call cannot destroy data.
+//!
+//! Tokens only prove the agent looked first. Where the client supports MCP elicitation, the user
+//! is also asked directly, so no memory is deleted without a human saying yes.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rmcp::service::{ElicitationError, Peer, RoleServer};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::db;
+
+/// How long a preview's token stays valid.
+pub const TOKEN_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How long the user has to answer a confirmation request before the call gives up.
+const ELICITATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Confirmation of a destructive action, asked of the user.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UserConfirmation {
+    #[schemars(description = "Go ahead")]
+    pub confirm: bool,
+}
+
+rmcp::elicit_safe!(UserConfirmation);
+
+#[derive(Debug, Default)]
+pub struct ConfirmationTokens {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    action: String,
+    expires: Instant,
+}
+
+impl ConfirmationTokens {
+    /// Issue a token for `action`. Returns the token and its expiry as unix seconds.
+    pub fn issue(&self, action: &str) -> (String, i64) {
+        let token = new_token();
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        pending.retain(|_, p| p.expires > now);
+        pending.insert(
+            token.clone(),
+            Pending {
+                action: action.to_string(),
+                expires: now + TOKEN_TTL,
+            },
+        );
+        (token, db::unix_now() + TOKEN_TTL.as_secs() as i64)
+    }
+
+    /// Consume `token` if it was issued for `action` and hasn't expired. A token presented for a
+    /// different action stays valid for its own.
+    pub fn redeem(&self, token: &str, action: &str) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.get(token) {
+            Some(p) if p.action == action && p.expires > Instant::now() => {
+                pending.remove(token);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Ask the user through MCP elicitation to confirm `question`. Clients without elicitation pass
+/// unless `required`, in which case nothing destructive can run through them.
+pub async fn ask_user(peer: &Peer<RoleServer>, question: &str, required: bool) -> Result<(), String> {
+    if !peer.supports_elicitation() {
+        return match required {
+            true => Err("Confirmation by the user is required, but the client can't ask them (no elicitation support)".to_string()),
+            false => Ok(()),
+        };
+    }
+    match peer.elicit_with_timeout::<UserConfirmation>(question, Some(ELICITATION_TIMEOUT)).await {
+        Ok(Some(UserConfirmation { confirm: true })) => Ok(()),
+        Ok(_) | Err(ElicitationError::UserDeclined) => Err("The user declined".to_string()),
+        Err(ElicitationError::UserCancelled) => Err("The user cancelled".to_string()),
+        Err(e) => Err(format!("Couldn't ask the user to confirm: {}", e)),
+    }
+}
+
+/// Unguessable enough to stop an agent from inventing one; not a security boundary.
+fn new_token() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    let high = hasher.finish();
+    hasher.write_u64(high);
+    format!("{:016x}{:016x}", high, hasher.finish())
+}
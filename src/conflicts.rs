@@ -0,0 +1,229 @@
+//! Conflicts: a memory or fact contradicting an earlier one about the same thing, recorded when it
+//! is stored so the superseded value can be retired instead of recalled next to the new one.
+//!
+//! Memories conflict when stored with the same key and different content; facts when they share
+//! subject and predicate but not object. Values compare as [`facts::key`] does. Each pair is
+//! recorded once, and stays open until resolved by keeping one side, which deletes the other, or
+//! both.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::attachments;
+use crate::dates::Timezone;
+use crate::db::unix_now;
+use crate::facts;
+use crate::models::{Conflict, ConflictKind, ConflictSide, Fact, MemoryEntry};
+use crate::queries::{memories, QueryError};
+
+const COLUMNS: &str =
+    "c.id, c.kind, c.key, c.existing_id, c.existing_value, c.new_id, c.new_value, c.detected_at, c.kept, c.resolved_at";
+
+/// SQL predicate for conflicts whose memories or facts both still exist.
+const SIDES_EXIST_SQL: &str = "CASE c.kind
+    WHEN 'fact' THEN (SELECT COUNT(*) FROM facts WHERE id IN (c.existing_id, c.new_id))
+    ELSE (SELECT COUNT(*) FROM memory WHERE id IN (c.existing_id, c.new_id))
+END = 2";
+
+fn kind_name(kind: ConflictKind) -> &'static str {
+    match kind {
+        ConflictKind::Memory => "memory",
+        ConflictKind::Fact => "fact",
+    }
+}
+
+fn side_name(side: ConflictSide) -> &'static str {
+    match side {
+        ConflictSide::New => "new",
+        ConflictSide::Existing => "existing",
+        ConflictSide::Both => "both",
+    }
+}
+
+fn from_row(row: &Row, timezone: Timezone) -> rusqlite::Result<Conflict> {
+    let detected_at = row.get(7)?;
+    let kept: Option<String> = row.get(8)?;
+    Ok(Conflict {
+        id: row.get(0)?,
+        kind: match row.get::<_, String>(1)?.as_str() {
+            "fact" => ConflictKind::Fact,
+            _ => ConflictKind::Memory,
+        },
+        key: row.get(2)?,
+        existing_id: row.get(3)?,
+        existing_value: row.get(4)?,
+        new_id: row.get(5)?,
+        new_value: row.get(6)?,
+        detected_at,
+        detected_at_iso: timezone.iso(detected_at),
+        kept: kept.map(|kept| match kept.as_str() {
+            "new" => ConflictSide::New,
+            "existing" => ConflictSide::Existing,
+            _ => ConflictSide::Both,
+        }),
+        resolved_at: row.get(9)?,
+    })
+}
+
+/// Record the conflicts of `memory`, just stored, with unexpired memories of the same key, and
+/// return those still open.
+pub fn for_memory(conn: &Connection, memory: &MemoryEntry, timezone: Timezone) -> Result<Vec<Conflict>> {
+    let Some(key) = memory.key.as_deref() else {
+        return Ok(Vec::new());
+    };
+    let content = facts::key(&memory.content);
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, content FROM memory
+         WHERE key = ?1 AND id != ?2 AND (expires_at IS NULL OR expires_at > unixepoch())
+         ORDER BY id",
+    )?;
+    let earlier: Vec<(i64, String)> = stmt
+        .query_map(params![key, memory.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(i64, String)>>>()
+        .context("Failed to look up memories with the same key")?
+        .into_iter()
+        .filter(|(_, earlier)| facts::key(earlier) != content)
+        .collect();
+    record(conn, ConflictKind::Memory, key, earlier, (memory.id, &memory.content), timezone)
+}
+
+/// Record the conflicts of `fact`, just asserted, with facts of the same subject and predicate,
+/// and return those still open.
+pub fn for_fact(conn: &Connection, fact: &Fact, timezone: Timezone) -> Result<Vec<Conflict>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, object FROM facts
+         WHERE subject_key = ?1 AND predicate_key = ?2 AND id != ?3
+         ORDER BY id",
+    )?;
+    let earlier = stmt
+        .query_map(params![facts::key(&fact.subject), facts::key(&fact.predicate), fact.id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<_>>()
+        .context("Failed to look up facts with the same subject and predicate")?;
+    let key = format!("{} {}", fact.subject, fact.predicate);
+    record(conn, ConflictKind::Fact, &key, earlier, (fact.id, &fact.object), timezone)
+}
+
+/// Record a conflict between `new` and each of `earlier`, unless the pair already has one.
+fn record(
+    conn: &Connection,
+    kind: ConflictKind,
+    key: &str,
+    earlier: Vec<(i64, String)>,
+    (new_id, new_value): (i64, &str),
+    timezone: Timezone,
+) -> Result<Vec<Conflict>> {
+    let mut open = Vec::new();
+    for (existing_id, existing_value) in earlier {
+        let known: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM conflicts
+                 WHERE kind = ?1 AND ((existing_id = ?2 AND new_id = ?3) OR (existing_id = ?3 AND new_id = ?2))",
+                params![kind_name(kind), existing_id, new_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to look up conflict")?;
+        let id = match known {
+            Some(id) => id,
+            None => {
+                conn.execute(
+                    "INSERT INTO conflicts (kind, key, existing_id, existing_value, new_id, new_value, detected_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![kind_name(kind), key, existing_id, existing_value, new_id, new_value, unix_now()],
+                )
+                .context("Failed to record conflict")?;
+                conn.last_insert_rowid()
+            }
+        };
+        open.extend(get(conn, id, timezone)?.filter(|conflict| conflict.resolved_at.is_none()));
+    }
+    Ok(open)
+}
+
+/// The conflict with id `conflict_id`, if any.
+pub fn get(conn: &Connection, conflict_id: i64, timezone: Timezone) -> Result<Option<Conflict>> {
+    conn.query_row(&format!("SELECT {} FROM conflicts c WHERE c.id = ?", COLUMNS), [conflict_id], |row| {
+        from_row(row, timezone)
+    })
+    .optional()
+    .context("Failed to read conflict")
+}
+
+/// Up to `limit` open conflicts whose sides both still exist, newest first, followed by resolved
+/// ones when `include_resolved`.
+pub fn list(conn: &Connection, include_resolved: bool, limit: u32, timezone: Timezone) -> Result<Vec<Conflict>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM conflicts c
+             WHERE (c.resolved_at IS NULL AND {}) OR (?1 AND c.resolved_at IS NOT NULL)
+             ORDER BY c.resolved_at IS NOT NULL, c.detected_at DESC, c.id DESC
+             LIMIT ?2",
+            COLUMNS, SIDES_EXIST_SQL
+        ))
+        .context("Failed to list conflicts")?;
+    let rows = stmt.query_map(params![include_resolved, limit], |row| from_row(row, timezone))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read conflicts")
+}
+
+/// The open conflict with id `conflict_id`; an error when there is none.
+pub fn get_open(conn: &Connection, conflict_id: i64, timezone: Timezone) -> Result<Conflict> {
+    let conflict = get(conn, conflict_id, timezone)?.with_context(|| format!("Conflict {} not found", conflict_id))?;
+    anyhow::ensure!(conflict.resolved_at.is_none(), "Conflict {} is already resolved", conflict_id);
+    Ok(conflict)
+}
+
+/// Id and value of the memory or fact that keeping `keep` deletes, if any.
+pub fn retired(conflict: &Conflict, keep: ConflictSide) -> Option<(i64, &str)> {
+    match keep {
+        ConflictSide::New => Some((conflict.existing_id, &conflict.existing_value)),
+        ConflictSide::Existing => Some((conflict.new_id, &conflict.new_value)),
+        ConflictSide::Both => None,
+    }
+}
+
+/// Resolve conflict `conflict_id` by keeping `keep` and deleting the other side, in one
+/// transaction. Returns the resolved conflict and the id deleted, if any.
+pub fn resolve(conn: &Connection, conflict_id: i64, keep: ConflictSide, timezone: Timezone) -> Result<(Conflict, Option<i64>)> {
+    let conflict = get_open(conn, conflict_id, timezone)?;
+    let retired = retired(&conflict, keep).map(|(id, _)| id);
+    let now = unix_now();
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    let deleted = match retired {
+        Some(id) => delete_side(&tx, conflict.kind, id)?.then_some(id),
+        None => None,
+    };
+    tx.execute(
+        "UPDATE conflicts SET kept = ?1, resolved_at = ?2 WHERE id = ?3",
+        params![side_name(keep), now, conflict_id],
+    )
+    .context("Failed to resolve conflict")?;
+    if let Some(id) = retired {
+        // Other conflicts over the retired side are settled in favor of what remains
+        tx.execute(
+            "UPDATE conflicts SET kept = CASE WHEN existing_id = ?1 THEN 'new' ELSE 'existing' END, resolved_at = ?2
+             WHERE kind = ?3 AND resolved_at IS NULL AND ?1 IN (existing_id, new_id)",
+            params![id, now, kind_name(conflict.kind)],
+        )
+        .context("Failed to resolve related conflicts")?;
+    }
+    tx.commit().context("Failed to commit conflict resolution")?;
+    let conflict = get(conn, conflict_id, timezone)?.context("Conflict disappeared")?;
+    Ok((conflict, deleted))
+}
+
+/// Delete memory or fact `id`, with a memory's attachments; false when it was already gone.
+fn delete_side(conn: &Connection, kind: ConflictKind, id: i64) -> Result<bool> {
+    match kind {
+        ConflictKind::Fact => facts::delete(conn, id),
+        ConflictKind::Memory => match memories::delete(conn, id) {
+            Ok(()) => {
+                attachments::delete_for_memory(conn, id).context("Failed to delete attachments")?;
+                Ok(true)
+            }
+            Err(QueryError::NotFound(_)) => Ok(false),
+            Err(e) => Err(anyhow::Error::new(e).context("Failed to delete memory")),
+        },
+    }
+}
@@ -0,0 +1,75 @@
+//! Conversations this session loaded recently, so an agent re-reading the same thread doesn't
+//! re-read its messages and re-mark its key messages every time.
+//!
+//! Entries are valid for one state of the database, identified by `PRAGMA data_version`, which
+//! changes whenever another connection (Cosmic LLM, the indexer) commits, together with the
+//! shared connection's own change count, which changes when a tool writes through it. Any change
+//! empties the cache.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::Connection;
+
+use crate::models::Conversation;
+
+/// Conversations kept per session unless `MCP_LUNA_CONVERSATION_CACHE_SIZE` says otherwise.
+pub const DEFAULT_CAPACITY: usize = 16;
+
+/// State of the database as seen from one connection.
+pub type Version = (i64, u64);
+
+/// The state of the database `db` sees.
+pub fn version(db: &Connection) -> rusqlite::Result<Version> {
+    let data_version = db.query_row("PRAGMA data_version", [], |row| row.get(0))?;
+    Ok((data_version, db.total_changes()))
+}
+
+/// The most recently used conversations, up to a fixed number; none with capacity 0.
+#[derive(Debug, Default)]
+pub struct ConversationCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Database state the entries were loaded at
+    version: Option<Version>,
+    /// Most recently used first
+    entries: VecDeque<Arc<Conversation>>,
+}
+
+impl ConversationCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, inner: Mutex::default() }
+    }
+
+    /// Conversation `conversation_id` if it was loaded while the database was at `version`.
+    pub fn get(&self, conversation_id: &str, version: Version) -> Option<Arc<Conversation>> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.version != Some(version) {
+            inner.entries.clear();
+            return None;
+        }
+        let index = inner.entries.iter().position(|c| c.id == conversation_id)?;
+        let conversation = inner.entries.remove(index)?;
+        inner.entries.push_front(Arc::clone(&conversation));
+        Some(conversation)
+    }
+
+    /// Keep `conversation`, loaded at `version`, evicting the least recently used one when full.
+    pub fn insert(&self, conversation: Arc<Conversation>, version: Version) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.version != Some(version) {
+            inner.entries.clear();
+            inner.version = Some(version);
+        }
+        inner.entries.retain(|c| c.id != conversation.id);
+        inner.entries.push_front(conversation);
+        inner.entries.truncate(self.capacity);
+    }
+}
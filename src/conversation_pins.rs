@@ -0,0 +1,30 @@
+//! Pinned conversations: reference threads the user returns to, kept in a side table so the
+//! Cosmic `conversations` schema stays untouched.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db::unix_now;
+
+/// Pin the conversation `conversation_id`. Pinning it again keeps the original pin time.
+pub fn pin(conn: &Connection, conversation_id: &str) -> Result<()> {
+    let exists = conn
+        .query_row("SELECT 1 FROM conversations WHERE id = ?", [conversation_id], |_| Ok(()))
+        .optional()
+        .context("Failed to look up conversation")?
+        .is_some();
+    anyhow::ensure!(exists, "No conversation with ID '{}'", conversation_id);
+    conn.execute(
+        "INSERT OR IGNORE INTO pinned_conversations (conversation_id, pinned_at) VALUES (?1, ?2)",
+        rusqlite::params![conversation_id, unix_now()],
+    )
+    .context("Failed to pin conversation")?;
+    Ok(())
+}
+
+/// Unpin the conversation `conversation_id`; unpinning one that isn't pinned changes nothing.
+pub fn unpin(conn: &Connection, conversation_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM pinned_conversations WHERE conversation_id = ?", [conversation_id])
+        .context("Failed to unpin conversation")?;
+    Ok(())
+}
@@ -0,0 +1,403 @@
+//! Dates in search filters. Models convert "last week" into unix seconds unreliably, so filters
+//! take the phrase itself and the server resolves it against the current time.
+//!
+//! Every input names a span of time: "yesterday" is a whole day, "March 2024" a whole month and
+//! "3 days ago" or a timestamp a single instant. `since` filters use the start of the span and
+//! `until` filters its end, so `since: "March 2024", until: "March 2024"` covers all of March.
+//!
+//! Responses carry timestamps as unix seconds and, for the model, as RFC 3339 in the configured
+//! [`Timezone`]; relative phrases are resolved in that timezone too.
+
+use anyhow::{bail, Result};
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, SecondsFormat, TimeDelta,
+    TimeZone, Utc, Weekday,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::db;
+
+/// A date filter: unix seconds, or text such as "yesterday", "last week", "3 days ago",
+/// "March 2024" or "2024-03-15".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum DateInput {
+    Timestamp(i64),
+    Text(String),
+}
+
+impl From<i64> for DateInput {
+    fn from(timestamp: i64) -> Self {
+        DateInput::Timestamp(timestamp)
+    }
+}
+
+impl From<&str> for DateInput {
+    fn from(text: &str) -> Self {
+        DateInput::Text(text.to_string())
+    }
+}
+
+/// Unix seconds `[start, end)` an input stands for; `start == end` for instants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Span {
+    fn instant(timestamp: i64) -> Self {
+        Span { start: timestamp, end: timestamp }
+    }
+}
+
+/// Timezone dates are shown and resolved in (`MCP_LUNA_TIMEZONE`): the system's own, or a fixed
+/// offset from UTC.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timezone {
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    /// `local`, `UTC` (or `Z`), or an offset such as `+02:00`, `-0530` or `+2`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "local" => return Some(Timezone::Local),
+            "utc" | "z" | "gmt" => return Some(Timezone::Fixed(FixedOffset::east_opt(0)?)),
+            _ => {}
+        }
+        let (sign, offset) = match name.as_bytes().first()? {
+            b'+' => (1, &name[1..]),
+            b'-' => (-1, &name[1..]),
+            _ => return None,
+        };
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let hours: i32 = hours.parse().ok()?;
+        let minutes: i32 = minutes.parse().ok()?;
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).map(Timezone::Fixed)
+    }
+
+    /// `timestamp` as RFC 3339, e.g. `2024-03-15T10:30:00+01:00`.
+    pub fn iso(&self, timestamp: i64) -> Option<String> {
+        let utc = DateTime::from_timestamp(timestamp, 0)?;
+        Some(match self {
+            Timezone::Local => utc.with_timezone(&Local).to_rfc3339_opts(SecondsFormat::Secs, true),
+            Timezone::Fixed(offset) => utc.with_timezone(offset).to_rfc3339_opts(SecondsFormat::Secs, true),
+        })
+    }
+
+    /// Calendar day of `timestamp`, e.g. `2024-03-15`.
+    pub fn date(&self, timestamp: i64) -> Option<String> {
+        let utc = DateTime::from_timestamp(timestamp, 0)?;
+        Some(match self {
+            Timezone::Local => utc.with_timezone(&Local).format("%Y-%m-%d").to_string(),
+            Timezone::Fixed(offset) => utc.with_timezone(offset).format("%Y-%m-%d").to_string(),
+        })
+    }
+
+    /// Start of `input`'s span, for `since` filters.
+    pub fn since(&self, input: &DateInput) -> Result<i64> {
+        Ok(self.resolve(input)?.start)
+    }
+
+    /// End of `input`'s span, for `until` filters.
+    pub fn until(&self, input: &DateInput) -> Result<i64> {
+        Ok(self.resolve(input)?.end)
+    }
+
+    fn resolve(&self, input: &DateInput) -> Result<Span> {
+        match self {
+            Timezone::Local => resolve(input, &Local::now()),
+            Timezone::Fixed(offset) => resolve(input, &Utc::now().with_timezone(offset)),
+        }
+    }
+}
+
+/// How long ago `timestamp` was, in the largest whole unit: "just now", "5 minutes ago",
+/// "3 days ago", "2 years ago", or "in 2 hours" for times ahead of the clock.
+pub fn age(timestamp: i64) -> String {
+    age_at(timestamp, db::unix_now())
+}
+
+fn age_at(timestamp: i64, now: i64) -> String {
+    const UNITS: [(i64, &str); 6] = [
+        (365 * 24 * 3600, "year"),
+        (30 * 24 * 3600, "month"),
+        (7 * 24 * 3600, "week"),
+        (24 * 3600, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+    ];
+    let seconds = now.saturating_sub(timestamp);
+    let Some((count, unit)) = UNITS
+        .iter()
+        .map(|(length, unit)| (seconds.saturating_abs() / length, unit))
+        .find(|(count, _)| *count > 0)
+    else {
+        return "just now".to_string();
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    if seconds < 0 {
+        format!("in {} {}{}", count, unit, plural)
+    } else {
+        format!("{} {}{} ago", count, unit, plural)
+    }
+}
+
+/// Span of `input`, with relative phrases counted from `now` in its timezone.
+pub fn resolve<Tz: TimeZone>(input: &DateInput, now: &DateTime<Tz>) -> Result<Span> {
+    match input {
+        DateInput::Timestamp(timestamp) => Ok(Span::instant(*timestamp)),
+        DateInput::Text(text) => match parse(text, now) {
+            Some(span) => Ok(span),
+            None => bail!(
+                "Unrecognized date '{}': use unix seconds, an ISO date like 2024-03-15, or a phrase like 'yesterday', 'last week', '3 days ago' or 'March 2024'",
+                text.trim()
+            ),
+        },
+    }
+}
+
+fn parse<Tz: TimeZone>(text: &str, now: &DateTime<Tz>) -> Option<Span> {
+    let text = text.trim();
+    if let Ok(instant) = DateTime::parse_from_rfc3339(text) {
+        return Some(Span::instant(instant.timestamp()));
+    }
+    let lower = text.to_lowercase().replace(',', " ");
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let tz = now.timezone();
+    let today = now.date_naive();
+
+    match words.as_slice() {
+        ["now"] => Some(Span::instant(now.timestamp())),
+        ["today"] => Some(days(&tz, today, 1)),
+        ["yesterday"] => Some(days(&tz, today.checked_sub_days(Days::new(1))?, 1)),
+        ["tomorrow"] => Some(days(&tz, today.checked_add_days(Days::new(1))?, 1)),
+        [which @ ("this" | "last" | "next"), unit] => {
+            let offset = match *which {
+                "this" => 0,
+                "last" => -1,
+                _ => 1,
+            };
+            if let Some(weekday) = weekday(unit) {
+                return match offset {
+                    -1 => Some(days(&tz, previous(today, weekday, false)?, 1)),
+                    1 => Some(days(&tz, following(today, weekday)?, 1)),
+                    _ => None,
+                };
+            }
+            calendar_unit(&tz, today, unit, offset)
+        }
+        ["past", unit] => Some(Span { start: ago(now, 1, unit)?, end: now.timestamp() }),
+        ["last" | "past", count, unit] => {
+            let since = ago(now, count_of(count)?, unit)?;
+            Some(Span { start: since, end: now.timestamp() })
+        }
+        [count, unit, "ago"] => Some(Span::instant(ago(now, count_of(count)?, unit)?)),
+        ["in", count, unit] => Some(Span::instant(ahead(now, count_of(count)?, unit)?)),
+        [name] if weekday(name).is_some() => Some(days(&tz, previous(today, weekday(name)?, true)?, 1)),
+        [name] if month(name).is_some() => {
+            let month = month(name)?;
+            // The most recent one that has begun: in February, "march" is last March
+            let year = if month > today.month() { today.year() - 1 } else { today.year() };
+            months(&tz, NaiveDate::from_ymd_opt(year, month, 1)?, 1)
+        }
+        [name, year] if month(name).is_some() && year.len() == 4 => {
+            months(&tz, NaiveDate::from_ymd_opt(year.parse().ok()?, month(name)?, 1)?, 1)
+        }
+        [name, day] if month(name).is_some() => {
+            let date = NaiveDate::from_ymd_opt(today.year(), month(name)?, day_of(day)?)?;
+            let date = if date > today { date.with_year(today.year() - 1)? } else { date };
+            Some(days(&tz, date, 1))
+        }
+        [name, day, year] if month(name).is_some() => {
+            Some(days(&tz, NaiveDate::from_ymd_opt(year.parse().ok()?, month(name)?, day_of(day)?)?, 1))
+        }
+        [day, name, year] if month(name).is_some() => {
+            Some(days(&tz, NaiveDate::from_ymd_opt(year.parse().ok()?, month(name)?, day_of(day)?)?, 1))
+        }
+        [single] => numeric(&tz, single),
+        [date, time] => {
+            let naive = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S")
+                .or_else(|_| NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M"))
+                .ok()?;
+            Some(Span::instant(local_timestamp(&tz, naive)))
+        }
+        _ => None,
+    }
+}
+
+/// `2024` (a year), `2024-03` (a month), `2024-03-15` (a day), `2024-03-15T10:00` (local time) or
+/// unix seconds.
+fn numeric<Tz: TimeZone>(tz: &Tz, text: &str) -> Option<Span> {
+    if text.len() == 4 {
+        let year = text.parse().ok()?;
+        return years(tz, NaiveDate::from_ymd_opt(year, 1, 1)?, 1);
+    }
+    if let Ok(timestamp) = text.parse::<i64>() {
+        return Some(Span::instant(timestamp));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Some(days(tz, date, 1));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&format!("{}-01", text), "%Y-%m-%d") {
+        return months(tz, date, 1);
+    }
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M"))
+        .ok()?;
+    Some(Span::instant(local_timestamp(tz, naive)))
+}
+
+/// The week (from Monday), month or year containing `today`, shifted by `offset` of them.
+fn calendar_unit<Tz: TimeZone>(tz: &Tz, today: NaiveDate, unit: &str, offset: i32) -> Option<Span> {
+    match unit {
+        "week" => {
+            let monday = today.checked_sub_days(Days::new(today.weekday().num_days_from_monday() as u64))?;
+            let first = if offset < 0 {
+                monday.checked_sub_days(Days::new(7))?
+            } else {
+                monday.checked_add_days(Days::new(7 * offset as u64))?
+            };
+            Some(days(tz, first, 7))
+        }
+        "month" => {
+            let first = today.with_day(1)?;
+            months(tz, shift_months(first, offset)?, 1)
+        }
+        "year" => years(tz, NaiveDate::from_ymd_opt(today.year() + offset, 1, 1)?, 1),
+        _ => None,
+    }
+}
+
+/// Timestamp `count` `unit`s before `now`; months and years go by the calendar.
+fn ago<Tz: TimeZone>(now: &DateTime<Tz>, count: u32, unit: &str) -> Option<i64> {
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    let then = match unit {
+        "minute" | "min" => now.clone() - TimeDelta::try_minutes(count.into())?,
+        "hour" | "hr" => now.clone() - TimeDelta::try_hours(count.into())?,
+        "day" => now.clone().checked_sub_days(Days::new(count.into()))?,
+        "week" => now.clone().checked_sub_days(Days::new(7 * u64::from(count)))?,
+        "month" => now.clone().checked_sub_months(Months::new(count))?,
+        "year" => now.clone().checked_sub_months(Months::new(count.checked_mul(12)?))?,
+        _ => return None,
+    };
+    Some(then.timestamp())
+}
+
+/// Timestamp `count` `unit`s after `now`; months and years go by the calendar.
+fn ahead<Tz: TimeZone>(now: &DateTime<Tz>, count: u32, unit: &str) -> Option<i64> {
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    let then = match unit {
+        "minute" | "min" => now.clone() + TimeDelta::try_minutes(count.into())?,
+        "hour" | "hr" => now.clone() + TimeDelta::try_hours(count.into())?,
+        "day" => now.clone().checked_add_days(Days::new(count.into()))?,
+        "week" => now.clone().checked_add_days(Days::new(7 * u64::from(count)))?,
+        "month" => now.clone().checked_add_months(Months::new(count))?,
+        "year" => now.clone().checked_add_months(Months::new(count.checked_mul(12)?))?,
+        _ => return None,
+    };
+    Some(then.timestamp())
+}
+
+/// First `weekday` after `today`.
+fn following(today: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let ahead = match (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7 {
+        0 => 7,
+        days => days,
+    };
+    today.checked_add_days(Days::new(ahead.into()))
+}
+
+/// Most recent `weekday` before `today`, or on it with `include_today`.
+fn previous(today: NaiveDate, weekday: Weekday, include_today: bool) -> Option<NaiveDate> {
+    let mut back = (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+    if back == 0 && !include_today {
+        back = 7;
+    }
+    today.checked_sub_days(Days::new(back.into()))
+}
+
+fn shift_months(date: NaiveDate, offset: i32) -> Option<NaiveDate> {
+    if offset < 0 {
+        date.checked_sub_months(Months::new(offset.unsigned_abs()))
+    } else {
+        date.checked_add_months(Months::new(offset as u32))
+    }
+}
+
+fn days<Tz: TimeZone>(tz: &Tz, first: NaiveDate, count: u64) -> Span {
+    let end = first.checked_add_days(Days::new(count)).unwrap_or(NaiveDate::MAX);
+    Span { start: midnight(tz, first), end: midnight(tz, end) }
+}
+
+fn months<Tz: TimeZone>(tz: &Tz, first: NaiveDate, count: u32) -> Option<Span> {
+    let end = first.checked_add_months(Months::new(count))?;
+    Some(Span { start: midnight(tz, first), end: midnight(tz, end) })
+}
+
+fn years<Tz: TimeZone>(tz: &Tz, first: NaiveDate, count: u32) -> Option<Span> {
+    months(tz, first, count.checked_mul(12)?)
+}
+
+fn midnight<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> i64 {
+    local_timestamp(tz, date.and_time(chrono::NaiveTime::MIN))
+}
+
+/// Unix seconds of a wall-clock time in `tz`. Times skipped by a DST change count as UTC.
+fn local_timestamp<Tz: TimeZone>(tz: &Tz, naive: NaiveDateTime) -> i64 {
+    match tz.from_local_datetime(&naive).earliest() {
+        Some(time) => time.timestamp(),
+        None => naive.and_utc().timestamp(),
+    }
+}
+
+fn count_of(word: &str) -> Option<u32> {
+    match word {
+        "a" | "an" | "one" => Some(1),
+        "two" => Some(2),
+        "three" => Some(3),
+        _ => word.parse().ok(),
+    }
+}
+
+fn day_of(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    digits.parse().ok()
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "monday" | "mon" => Some(Weekday::Mon),
+        "tuesday" | "tue" | "tues" => Some(Weekday::Tue),
+        "wednesday" | "wed" => Some(Weekday::Wed),
+        "thursday" | "thu" | "thurs" => Some(Weekday::Thu),
+        "friday" | "fri" => Some(Weekday::Fri),
+        "saturday" | "sat" => Some(Weekday::Sat),
+        "sunday" | "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january", "february", "march", "april", "may", "june", "july", "august", "september", "october",
+        "november", "december",
+    ];
+    let word = word.strip_suffix('.').unwrap_or(word);
+    if word.len() < 3 {
+        return None;
+    }
+    MONTHS.iter().position(|name| name.starts_with(word)).map(|i| i as u32 + 1)
+}
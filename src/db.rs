@@ -1,63 +1,356 @@
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{Connection, OpenFlags};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 
-/// Initialize the memory module database schema.
-/// Creates the memory table, FTS5 virtual table, and triggers for auto-syncing.
-pub fn init_memory_schema(conn: &Connection) -> Result<()> {
-    // Create memory table
+use crate::fts::{self, Tokenizer};
+use crate::queries;
+
+pub mod encryption;
+pub mod migrations;
+pub mod pool;
+
+/// Name of the case- and diacritic-insensitive collation registered on every connection.
+pub const FOLDED_COLLATION: &str = "LUNA_FOLDED";
+
+/// SQL function `LUNA_RECENCY_DECAY(age_seconds, half_life_seconds)`, see [`recency_decay`].
+pub const RECENCY_DECAY_FUNCTION: &str = "LUNA_RECENCY_DECAY";
+
+/// Open the database at `path` and migrate the memory module schema to the latest version.
+/// Full-text indexes created along the way use `tokenizer`.
+pub fn open(path: &str, tokenizer: &Tokenizer) -> Result<Connection> {
+    let conn = Connection::open(path).context("Failed to open database connection")?;
+    encryption::unlock(&conn)?;
+    register_functions(&conn)?;
+    conn.set_prepared_statement_cache_capacity(queries::STATEMENT_CACHE_CAPACITY);
+    migrations::migrate(&conn, tokenizer)?;
+    init_title_index(&conn)?;
+    init_tool_activity_index(&conn, tokenizer)?;
+    init_reasoning_index(&conn, tokenizer)?;
+    Ok(conn)
+}
+
+/// Open the database at `path` for reading only. Nothing is created or migrated, so memory tools
+/// find no memories in a database the server has never written to.
+pub fn open_read_only(path: &str) -> Result<Connection> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI)
+        .context("Failed to open database read-only")?;
+    encryption::unlock(&conn)?;
+    register_functions(&conn)?;
+    conn.set_prepared_statement_cache_capacity(queries::STATEMENT_CACHE_CAPACITY);
+    Ok(conn)
+}
+
+/// Register custom collations and SQL functions. Both live on the connection, so this must run
+/// for every connection that uses them.
+pub fn register_functions(conn: &Connection) -> Result<()> {
+    conn.create_collation(FOLDED_COLLATION, |a, b| {
+        fold_for_sorting(a)
+            .cmp(&fold_for_sorting(b))
+            .then_with(|| a.cmp(b))
+    })
+    .context("Failed to register folded collation")?;
+
+    // The bundled SQLite is built without math functions, so exponential decay is done here
+    conn.create_scalar_function(
+        RECENCY_DECAY_FUNCTION,
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let age_seconds: f64 = ctx.get(0)?;
+            let half_life_seconds: f64 = ctx.get(1)?;
+            Ok(recency_decay(age_seconds, half_life_seconds))
+        },
+    )
+    .context("Failed to register recency decay function")
+}
+
+/// Weight of something `age_seconds` old when weights halve every `half_life_seconds`: 1.0 now,
+/// 0.5 one half-life ago. Future timestamps count as now.
+pub fn recency_decay(age_seconds: f64, half_life_seconds: f64) -> f64 {
+    if half_life_seconds <= 0.0 {
+        return 1.0;
+    }
+    0.5_f64.powf(age_seconds.max(0.0) / half_life_seconds)
+}
+
+/// Sort key that ignores case and diacritics: "Ćwiczenia" sorts with "cwiczenia", "Łódź" with "lodz".
+pub fn fold_for_sorting(s: &str) -> String {
+    let mut folded = String::with_capacity(s.len());
+    for c in s.nfd().filter(|c| !is_combining_mark(*c)) {
+        // Letters with strokes don't decompose under NFD
+        match c {
+            'ł' | 'Ł' => folded.push('l'),
+            'ø' | 'Ø' => folded.push('o'),
+            'đ' | 'Đ' => folded.push('d'),
+            'ß' => folded.push_str("ss"),
+            'æ' | 'Æ' => folded.push_str("ae"),
+            'œ' | 'Œ' => folded.push_str("oe"),
+            c => folded.extend(c.to_lowercase()),
+        }
+    }
+    folded
+}
+
+/// Current time as unix seconds, the timestamp format used throughout the database.
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Add a column to an existing table unless it is already there (SQLite has no `ADD COLUMN IF NOT EXISTS`).
+pub fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+            [table, column],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("Failed to inspect table {}", table))?;
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])
+            .with_context(|| format!("Failed to add {}.{}", table, column))?;
+    }
+    Ok(())
+}
+
+/// Create the Cosmic LLM conversation tables when they don't exist yet (e.g. importing into a fresh file).
+/// Existing Cosmic databases are left untouched; FTS sync triggers are only added alongside a
+/// `messages_fts` table created here, never on top of the app's own indexing. An index created here
+/// also keeps 2- and 3-character prefix indexes, so short prefix searches don't scan every term.
+pub fn init_conversation_schema(conn: &Connection) -> Result<()> {
     conn.execute(
         r#"
-        CREATE TABLE IF NOT EXISTS memory (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            content TEXT NOT NULL,
-            category TEXT,
-            importance INTEGER DEFAULT 5,
-            created_at INTEGER
+        CREATE TABLE IF NOT EXISTS conversations (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            title_generated INTEGER DEFAULT 0,
+            profile_name TEXT
         )
         "#,
         [],
     )
-    .context("Failed to create memory table")?;
+    .context("Failed to create conversations table")?;
 
-    // Create FTS5 virtual table for full-text search
     conn.execute(
         r#"
-        CREATE VIRTUAL TABLE IF NOT EXISTS memory_fts USING fts5(
-            content,
-            content='memory',
-            content_rowid='id'
+        CREATE TABLE IF NOT EXISTS messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conversation_id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            tool_calls TEXT,
+            tool_call_id TEXT,
+            tool_name TEXT,
+            tool_status TEXT,
+            tool_params_json TEXT,
+            tool_result_json TEXT,
+            reasoning_content TEXT
         )
         "#,
         [],
     )
-    .context("Failed to create memory_fts virtual table")?;
+    .context("Failed to create messages table")?;
 
-    // Create trigger for auto-syncing FTS index on insert
-    conn.execute(
+    let has_fts: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'messages_fts')",
+            [],
+            |row| row.get(0),
+        )
+        .context("Failed to inspect schema")?;
+    if has_fts {
+        return init_title_index(conn);
+    }
+
+    conn.execute_batch(
+        r#"
+        CREATE VIRTUAL TABLE messages_fts USING fts5(
+            content,
+            content='messages',
+            content_rowid='id',
+            prefix='2 3'
+        );
+        CREATE TRIGGER messages_ai AFTER INSERT ON messages BEGIN
+            INSERT INTO messages_fts(rowid, content) VALUES (new.id, new.content);
+        END;
+        CREATE TRIGGER messages_ad AFTER DELETE ON messages BEGIN
+            INSERT INTO messages_fts(messages_fts, rowid, content) VALUES('delete', old.id, old.content);
+        END;
+        CREATE TRIGGER messages_au AFTER UPDATE OF content ON messages BEGIN
+            INSERT INTO messages_fts(messages_fts, rowid, content) VALUES('delete', old.id, old.content);
+            INSERT INTO messages_fts(rowid, content) VALUES (new.id, new.content);
+        END;
+        INSERT INTO messages_fts(messages_fts) VALUES('rebuild');
+        "#,
+    )
+    .context("Failed to create messages_fts index")?;
+
+    init_title_index(conn)
+}
+
+/// Keep `titles_fts`, a full-text index over conversation titles, next to the Cosmic `conversations`
+/// table. Triggers keep it current, and it is rebuilt on every open because writes that bypass
+/// delete triggers (`INSERT OR REPLACE`) would otherwise leave it stale. Titles are short, so the
+/// rebuild takes milliseconds even for tens of thousands of conversations. Without a
+/// `conversations` table there is nothing to index.
+pub fn init_title_index(conn: &Connection) -> Result<()> {
+    let has_conversations: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'conversations')",
+            [],
+            |row| row.get(0),
+        )
+        .context("Failed to inspect schema")?;
+    if !has_conversations {
+        return Ok(());
+    }
+
+    conn.execute_batch(
         r#"
-        CREATE TRIGGER IF NOT EXISTS memory_ai AFTER INSERT ON memory BEGIN
-            INSERT INTO memory_fts(rowid, content) VALUES (new.id, new.content);
-        END
+        CREATE VIRTUAL TABLE IF NOT EXISTS titles_fts USING fts5(
+            title,
+            content='conversations',
+            content_rowid='rowid',
+            tokenize='unicode61 remove_diacritics 2',
+            prefix='2 3'
+        );
+        CREATE TRIGGER IF NOT EXISTS titles_fts_ai AFTER INSERT ON conversations BEGIN
+            INSERT INTO titles_fts(rowid, title) VALUES (new.rowid, new.title);
+        END;
+        CREATE TRIGGER IF NOT EXISTS titles_fts_ad AFTER DELETE ON conversations BEGIN
+            INSERT INTO titles_fts(titles_fts, rowid, title) VALUES('delete', old.rowid, old.title);
+        END;
+        CREATE TRIGGER IF NOT EXISTS titles_fts_au AFTER UPDATE OF title ON conversations BEGIN
+            INSERT INTO titles_fts(titles_fts, rowid, title) VALUES('delete', old.rowid, old.title);
+            INSERT INTO titles_fts(rowid, title) VALUES (new.rowid, new.title);
+        END;
+        INSERT INTO titles_fts(titles_fts) VALUES('rebuild');
         "#,
-        [],
     )
-    .context("Failed to create memory_ai trigger")?;
+    .context("Failed to build titles_fts index")
+}
 
-    // Create trigger for auto-syncing FTS index on delete
-    conn.execute(
+/// Keep `tool_activity_fts`, a full-text index over tool names, parameters and results of Cosmic
+/// `messages`, so commands run and files touched can be searched like message text.
+pub fn init_tool_activity_index(conn: &Connection, tokenizer: &Tokenizer) -> Result<()> {
+    init_messages_index(conn, "tool_activity_fts", &["tool_name", "tool_params_json", "tool_result_json"], tokenizer)
+}
+
+/// Keep `reasoning_fts`, a full-text index over the model's reasoning traces in Cosmic `messages`.
+pub fn init_reasoning_index(conn: &Connection, tokenizer: &Tokenizer) -> Result<()> {
+    init_messages_index(conn, "reasoning_fts", &["reasoning_content"], tokenizer)
+}
+
+/// Create the external-content index `index` over `columns` of `messages`, with triggers keeping it
+/// current. Unlike `titles_fts` it is only built when created: rebuilding means reading every
+/// message. Rows without data in `columns` are indexed as empty documents so the row count
+/// matches `messages`. Nothing is created when `messages` or one of the columns is missing.
+fn init_messages_index(conn: &Connection, index: &str, columns: &[&str], tokenizer: &Tokenizer) -> Result<()> {
+    let has_index: bool = conn
+        .query_row("SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = ?)", [index], |row| row.get(0))
+        .context("Failed to inspect schema")?;
+    if has_index || !columns.iter().all(|column| has_column(conn, "messages", column)) {
+        return Ok(());
+    }
+
+    let names = columns.join(", ");
+    let values = |prefix: &str| columns.iter().map(|c| format!("{}.{}", prefix, c)).collect::<Vec<_>>().join(", ");
+    conn.execute_batch(&format!(
         r#"
-        CREATE TRIGGER IF NOT EXISTS memory_ad AFTER DELETE ON memory BEGIN
-            INSERT INTO memory_fts(memory_fts, rowid, content) VALUES('delete', old.id, old.content);
-        END
+        CREATE VIRTUAL TABLE {index} USING fts5(
+            {names},
+            content='messages',
+            content_rowid='id',
+            tokenize='{tokenize}'
+        );
+        CREATE TRIGGER {index}_ai AFTER INSERT ON messages BEGIN
+            INSERT INTO {index}(rowid, {names}) VALUES (new.id, {new});
+        END;
+        CREATE TRIGGER {index}_ad AFTER DELETE ON messages BEGIN
+            INSERT INTO {index}({index}, rowid, {names}) VALUES ('delete', old.id, {old});
+        END;
+        CREATE TRIGGER {index}_au AFTER UPDATE OF {names} ON messages BEGIN
+            INSERT INTO {index}({index}, rowid, {names}) VALUES ('delete', old.id, {old});
+            INSERT INTO {index}(rowid, {names}) VALUES (new.id, {new});
+        END;
+        INSERT INTO {index}({index}) VALUES('rebuild');
         "#,
+        tokenize = tokenizer.spec(),
+        new = values("new"),
+        old = values("old"),
+    ))
+    .with_context(|| format!("Failed to build {} index", index))
+}
+
+/// Whether `table` has a column `column`; false when the table doesn't exist.
+pub fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        [table, column],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// Whether the full-text index `index` exists. Read-only connections may find a database that
+/// never got the indexes the server creates.
+pub fn has_index(conn: &Connection, index: &str) -> bool {
+    conn.query_row("SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = ?)", [index], |row| row.get(0))
+        .unwrap_or(false)
+}
+
+/// Whether `titles_fts` exists; read-only connections may find a database that never got one.
+pub fn has_title_index(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'titles_fts')",
         [],
+        |row| row.get(0),
     )
-    .context("Failed to create memory_ad trigger")?;
+    .unwrap_or(false)
+}
 
-    // Rebuild FTS index from content table (syncs pre-existing rows not covered by triggers)
-    conn.execute("INSERT INTO memory_fts(memory_fts) VALUES('rebuild')", [])
-        .context("Failed to rebuild memory_fts index")?;
+/// Add message `id` with text `content` to `messages_fts`, for writers to a database whose index
+/// has no insert trigger (see [`messages_fts_has_triggers`]).
+pub fn index_message(conn: &Connection, id: i64, content: &str) -> Result<()> {
+    conn.execute("INSERT INTO messages_fts (rowid, content) VALUES (?1, ?2)", rusqlite::params![id, content])
+        .context("Failed to index message")?;
+    Ok(())
+}
 
+/// Drop message `id`, whose indexed text is `content`, from `messages_fts` before it is deleted or
+/// changed, for writers to a database whose index has no trigger for that (see [`messages_fts_synced_on`]).
+pub fn unindex_message(conn: &Connection, id: i64, content: &str) -> Result<()> {
+    if fts::needs_delete_command(conn, "messages_fts")? {
+        conn.execute(
+            "INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', ?1, ?2)",
+            rusqlite::params![id, content],
+        )
+    } else {
+        conn.execute("DELETE FROM messages_fts WHERE rowid = ?1", [id])
+    }
+    .context("Failed to remove message from the full-text index")?;
     Ok(())
 }
 
+/// Whether inserts into `messages` are indexed into `messages_fts` by a trigger.
+/// When they aren't, writers must keep the index in sync themselves.
+pub fn messages_fts_has_triggers(conn: &Connection) -> Result<bool> {
+    messages_fts_synced_on(conn, "INSERT")
+}
+
+/// Whether a trigger updates `messages_fts` after `event` ("INSERT", "DELETE" or "UPDATE") on
+/// `messages`. Apps keeping their own index may have some of these triggers and not others.
+pub fn messages_fts_synced_on(conn: &Connection, event: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND tbl_name = 'messages' AND sql LIKE '%messages_fts%' AND sql LIKE ?1)",
+        [format!("%AFTER {} %", event)],
+        |row| row.get(0),
+    )
+    .context("Failed to inspect messages triggers")
+}
@@ -0,0 +1,122 @@
+//! Encryption at rest with SQLCipher (`sqlcipher` feature). The key comes from `MCP_LUNA_DB_KEY`
+//! or, when that is unset, from the desktop keyring through `secret-tool` (Secret Service, as
+//! provided by GNOME Keyring or KWallet). Every connection is unlocked with it before its first
+//! statement; without a key the database is plain SQLite, as before.
+
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+use crate::info;
+
+const KEY_VAR: &str = "MCP_LUNA_DB_KEY";
+
+/// First bytes of every unencrypted SQLite file; SQLCipher encrypts them along with the rest.
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Attributes the key is stored under in the keyring, for `secret-tool lookup` and `store`.
+pub const KEYRING_ATTRIBUTES: &[&str] = &["service", "mcp_luna_memory", "key", "database"];
+
+static KEY: OnceLock<Option<String>> = OnceLock::new();
+
+/// The configured database key, looked up once per process.
+pub fn key() -> Option<&'static str> {
+    KEY.get_or_init(|| {
+        std::env::var(KEY_VAR)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .or_else(keyring_key)
+    })
+    .as_deref()
+}
+
+/// Key stored in the keyring; `None` when `secret-tool` is missing or has no such secret.
+/// Builds without SQLCipher never ask.
+fn keyring_key() -> Option<String> {
+    if !cfg!(feature = "sqlcipher") {
+        return None;
+    }
+    let output = std::process::Command::new("secret-tool")
+        .arg("lookup")
+        .args(KEYRING_ATTRIBUTES)
+        .output()
+        .ok()?;
+    let key = String::from_utf8(output.stdout).ok()?;
+    let key = key.trim_end_matches(['\r', '\n']);
+    (output.status.success() && !key.is_empty()).then(|| key.to_string())
+}
+
+/// Unlock `conn` with the configured key. Must run before anything else touches the database;
+/// a wrong key, or a key for a file that isn't encrypted, fails here rather than on first use.
+pub fn unlock(conn: &Connection) -> Result<()> {
+    let Some(key) = key() else {
+        return Ok(());
+    };
+    anyhow::ensure!(
+        cfg!(feature = "sqlcipher"),
+        "{} is set, but this build can't open encrypted databases; rebuild with --features sqlcipher",
+        KEY_VAR
+    );
+    // SQLCipher logs failed decryption to stderr; the error below says the same
+    let _ = conn.pragma_update(None, "cipher_log_level", "NONE");
+    conn.pragma_update(None, "key", key).context("Failed to set database key")?;
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        .context("Cannot read the database with the configured key: wrong key, or the file isn't encrypted")?;
+    Ok(())
+}
+
+/// Key to attach the database at `path` with when a key is configured: the same key, or an empty
+/// one for a plaintext file, so an encrypted database can still merge from a plain one.
+pub fn attach_key(path: &Path) -> Option<&'static str> {
+    let key = key()?;
+    let mut header = [0u8; 16];
+    let plaintext = std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|()| &header == PLAINTEXT_HEADER);
+    Some(if plaintext { "" } else { key })
+}
+
+/// Write an encrypted copy of the plaintext database at `source` to `output`, keyed with `key`,
+/// and check that the copy opens with the key and has the same rows. Returns the number of tables
+/// copied. `source` is only read; its write-ahead log is checkpointed first so the copy is complete.
+pub fn encrypt_copy(source: &Path, output: &Path, key: &str) -> Result<usize> {
+    anyhow::ensure!(
+        cfg!(feature = "sqlcipher"),
+        "This build can't write encrypted databases; rebuild with --features sqlcipher"
+    );
+    anyhow::ensure!(source.is_file(), "No database at {}", source.display());
+    anyhow::ensure!(!output.exists(), "{} already exists", output.display());
+    // Attached databases are opened with the main connection's flags, so it needs to create files
+    let conn = Connection::open(source).with_context(|| format!("Failed to open {}", source.display()))?;
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        .with_context(|| format!("{} is not a plaintext SQLite database; is it encrypted already?", source.display()))?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+        .context("Failed to checkpoint the write-ahead log")?;
+    let user_version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    conn.execute("ATTACH DATABASE ?1 AS encrypted KEY ?2", rusqlite::params![output.to_string_lossy(), key])
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    let exported = conn
+        .query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+        .context("Failed to copy the database")
+        .and_then(|()| {
+            conn.pragma_update(Some("encrypted"), "user_version", user_version)
+                .context("Failed to copy user_version")
+        });
+    conn.execute("DETACH DATABASE encrypted", []).context("Failed to close the encrypted copy")?;
+    exported?;
+
+    let copy = Connection::open(output).with_context(|| format!("Failed to open {}", output.display()))?;
+    copy.pragma_update(None, "key", key).context("Failed to set database key")?;
+    let expected = info::table_counts(&conn)?;
+    let copied = info::table_counts(&copy).context("The encrypted copy doesn't open with the key")?;
+    anyhow::ensure!(
+        copied == expected,
+        "The encrypted copy at {} doesn't match the original; leaving the original alone",
+        output.display()
+    );
+    Ok(copied.len())
+}
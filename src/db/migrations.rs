@@ -0,0 +1,816 @@
+//! Versioned upgrades for the tables this server owns (the memory module).
+//!
+//! Applied versions are recorded in `schema_version`. Each migration runs once, in order, inside
+//! its own transaction. Migrations must also be idempotent: databases created before versioning
+//! already have some of these objects, and start from version 0 like a fresh file.
+//!
+//! To change the schema, append a migration with the next version number. Never edit or reorder
+//! a migration that has shipped.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+
+use super::{add_column_if_missing, unix_now};
+use crate::fts::{self, Tokenizer};
+
+struct Migration {
+    version: i64,
+    description: &'static str,
+    /// Applies the migration; indexes it creates use the configured tokenizer
+    up: fn(&Connection, &Tokenizer) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "memory table with full-text index",
+        up: memory_table,
+    },
+    Migration {
+        version: 2,
+        description: "memory retrieval tracking",
+        up: memory_access_tracking,
+    },
+    Migration {
+        version: 3,
+        description: "memory index with configurable tokenizer",
+        up: memory_tokenizer,
+    },
+    Migration {
+        version: 4,
+        description: "saved searches",
+        up: saved_searches_table,
+    },
+    Migration {
+        version: 5,
+        description: "search history",
+        up: search_history_table,
+    },
+    Migration {
+        version: 6,
+        description: "memory expiration",
+        up: memory_expiration,
+    },
+    Migration {
+        version: 7,
+        description: "pinned memories",
+        up: memory_pinning,
+    },
+    Migration {
+        version: 8,
+        description: "preferences",
+        up: preferences_table,
+    },
+    Migration {
+        version: 9,
+        description: "scratchpads",
+        up: scratchpad_table,
+    },
+    Migration {
+        version: 10,
+        description: "reminders",
+        up: reminders_table,
+    },
+    Migration {
+        version: 11,
+        description: "journal with full-text index",
+        up: journal_table,
+    },
+    Migration {
+        version: 12,
+        description: "people",
+        up: people_table,
+    },
+    Migration {
+        version: 13,
+        description: "projects",
+        up: projects_table,
+    },
+    Migration {
+        version: 14,
+        description: "project rules and conversation projects",
+        up: project_rules_table,
+    },
+    Migration {
+        version: 15,
+        description: "conversation tags",
+        up: conversation_tags_table,
+    },
+    Migration {
+        version: 16,
+        description: "pinned conversations",
+        up: pinned_conversations_table,
+    },
+    Migration {
+        version: 17,
+        description: "topic clusters",
+        up: topic_tables,
+    },
+    Migration {
+        version: 18,
+        description: "conversation keyphrases",
+        up: conversation_keywords_table,
+    },
+    Migration {
+        version: 19,
+        description: "message languages",
+        up: message_languages_table,
+    },
+    Migration {
+        version: 20,
+        description: "message embeddings",
+        up: embedding_tables,
+    },
+    Migration {
+        version: 21,
+        description: "search synonyms",
+        up: synonyms_table,
+    },
+    Migration {
+        version: 22,
+        description: "conversation trash",
+        up: conversation_trash_table,
+    },
+    Migration {
+        version: 23,
+        description: "pruned tool results",
+        up: pruned_tool_results_table,
+    },
+    Migration {
+        version: 24,
+        description: "attachments",
+        up: attachment_tables,
+    },
+    Migration {
+        version: 25,
+        description: "bookmarks with full-text index",
+        up: bookmarks_table,
+    },
+    Migration {
+        version: 26,
+        description: "code snippets with full-text index",
+        up: snippets_table,
+    },
+    Migration {
+        version: 27,
+        description: "facts",
+        up: facts_table,
+    },
+    Migration {
+        version: 28,
+        description: "memory keys and conflicts",
+        up: conflicts_table,
+    },
+    Migration {
+        version: 29,
+        description: "memory history",
+        up: memory_history_table,
+    },
+];
+
+/// Newest schema version this build knows about.
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Version recorded in the database; 0 when no migration has run.
+pub fn current_version(conn: &Connection) -> Result<i64> {
+    let has_table: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version')",
+            [],
+            |row| row.get(0),
+        )
+        .context("Failed to inspect schema")?;
+    if !has_table {
+        return Ok(0);
+    }
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+        .context("Failed to read schema version")
+}
+
+/// Bring the database up to `latest_version()`. Returns the versions applied.
+pub fn migrate(conn: &Connection, tokenizer: &Tokenizer) -> Result<Vec<i64>> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )
+        "#,
+        [],
+    )
+    .context("Failed to create schema_version table")?;
+
+    let current = current_version(conn)?;
+    anyhow::ensure!(
+        current <= latest_version(),
+        "Database schema version {} is newer than this build supports ({}); upgrade mcp_luna_history",
+        current,
+        latest_version()
+    );
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        // Another connection may be migrating at the same time: wait for its write lock, then skip
+        // what it already applied
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate).context("Failed to start migration")?;
+        if current_version(&tx)? >= migration.version {
+            continue;
+        }
+        (migration.up)(&tx, tokenizer)
+            .with_context(|| format!("Migration {} ({}) failed", migration.version, migration.description))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![migration.version, migration.description, unix_now()],
+        )
+        .context("Failed to record schema version")?;
+        tx.commit()
+            .with_context(|| format!("Failed to commit migration {}", migration.version))?;
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+/// Memory table, FTS5 index and the triggers keeping it in sync.
+fn memory_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS memory (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            content TEXT NOT NULL,
+            category TEXT,
+            importance INTEGER DEFAULT 5,
+            created_at INTEGER
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS memory_fts USING fts5(
+            content,
+            content='memory',
+            content_rowid='id'
+        );
+        CREATE TRIGGER IF NOT EXISTS memory_ai AFTER INSERT ON memory BEGIN
+            INSERT INTO memory_fts(rowid, content) VALUES (new.id, new.content);
+        END;
+        CREATE TRIGGER IF NOT EXISTS memory_ad AFTER DELETE ON memory BEGIN
+            INSERT INTO memory_fts(memory_fts, rowid, content) VALUES('delete', old.id, old.content);
+        END;
+        "#,
+    )
+    .context("Failed to create memory schema")?;
+
+    // Index rows written before the triggers existed
+    conn.execute("INSERT INTO memory_fts(memory_fts) VALUES('rebuild')", [])
+        .context("Failed to rebuild memory_fts index")?;
+    Ok(())
+}
+
+/// How often and how recently searches returned each memory, for the hygiene report.
+fn memory_access_tracking(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    add_column_if_missing(conn, "memory", "access_count", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "memory", "last_accessed_at", "INTEGER")?;
+    Ok(())
+}
+
+/// Rebuild `memory_fts` with the configured tokenizer, by default one that ignores diacritics so
+/// "café" and "cafe" find each other. Later tokenizer changes go through `reindex`.
+fn memory_tokenizer(conn: &Connection, tokenizer: &Tokenizer) -> Result<()> {
+    fts::retokenize(conn, "memory_fts", tokenizer)?;
+    Ok(())
+}
+
+/// Named `search_conversations` parameters, rerun by name, with stats of the last run.
+fn saved_searches_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS saved_searches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            params_json TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            run_count INTEGER NOT NULL DEFAULT 0,
+            last_run_at INTEGER,
+            last_result_count INTEGER
+        );
+        "#,
+    )
+    .context("Failed to create saved_searches table")
+}
+
+/// When a memory stops being returned (unix seconds); NULL keeps it for good.
+fn memory_expiration(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    add_column_if_missing(conn, "memory", "expires_at", "INTEGER")?;
+    conn.execute("CREATE INDEX IF NOT EXISTS memory_expires_at ON memory(expires_at)", [])
+        .context("Failed to index memory expiration")?;
+    Ok(())
+}
+
+/// Memories returned by every `search_memory` call and by `get_pinned_memories`.
+fn memory_pinning(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    add_column_if_missing(conn, "memory", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+/// Exact key-value settings, separate from free-text memory.
+fn preferences_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS preferences (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .context("Failed to create preferences table")
+}
+
+/// Short-lived working notes by session or topic name, deleted once `expires_at` passes.
+fn scratchpad_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS scratchpad (
+            name TEXT PRIMARY KEY,
+            content TEXT NOT NULL,
+            updated_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .context("Failed to create scratchpad table")
+}
+
+/// Reminders with a due time; `completed_at` stays NULL until `complete_reminder`.
+fn reminders_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS reminders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            content TEXT NOT NULL,
+            due_at INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            completed_at INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS reminders_open_due_at ON reminders(due_at) WHERE completed_at IS NULL;
+        "#,
+    )
+    .context("Failed to create reminders table")
+}
+
+/// Daily journal entries, their FTS5 index and the triggers keeping it in sync.
+fn journal_table(conn: &Connection, tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date TEXT NOT NULL,
+            text TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS journal_date ON journal(date);
+        CREATE VIRTUAL TABLE IF NOT EXISTS journal_fts USING fts5(
+            text,
+            content='journal',
+            content_rowid='id',
+            tokenize='{}'
+        );
+        CREATE TRIGGER IF NOT EXISTS journal_ai AFTER INSERT ON journal BEGIN
+            INSERT INTO journal_fts(rowid, text) VALUES (new.id, new.text);
+        END;
+        CREATE TRIGGER IF NOT EXISTS journal_ad AFTER DELETE ON journal BEGIN
+            INSERT INTO journal_fts(journal_fts, rowid, text) VALUES('delete', old.id, old.text);
+        END;
+        "#,
+        tokenizer.spec()
+    ))
+    .context("Failed to create journal schema")
+}
+
+/// People the user mentions; `aliases` is a JSON array of other names they go by.
+fn people_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS people (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            aliases TEXT NOT NULL DEFAULT '[]',
+            relationship TEXT,
+            notes TEXT,
+            last_mentioned_message_id INTEGER,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .context("Failed to create people table")
+}
+
+/// Projects scoping recall, and the project each memory is assigned to. `keywords` is a JSON
+/// array of terms identifying the project's conversations.
+fn projects_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS projects (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            description TEXT,
+            keywords TEXT NOT NULL DEFAULT '[]',
+            created_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .context("Failed to create projects table")?;
+    add_column_if_missing(conn, "memory", "project_id", "INTEGER REFERENCES projects(id)")?;
+    conn.execute("CREATE INDEX IF NOT EXISTS memory_project_id ON memory(project_id)", [])
+        .context("Failed to index memory projects")?;
+    Ok(())
+}
+
+/// Patterns tagging conversations with a project, and the conversations they tagged.
+fn project_rules_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS project_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_id INTEGER NOT NULL REFERENCES projects(id),
+            pattern TEXT NOT NULL,
+            scope TEXT NOT NULL DEFAULT 'any',
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS conversation_projects (
+            conversation_id TEXT NOT NULL,
+            project_id INTEGER NOT NULL REFERENCES projects(id),
+            rule_id INTEGER,
+            classified_at INTEGER NOT NULL,
+            PRIMARY KEY (conversation_id, project_id)
+        );
+        CREATE INDEX IF NOT EXISTS conversation_projects_project_id ON conversation_projects(project_id);
+        "#,
+    )
+    .context("Failed to create project rule tables")
+}
+
+/// Free-form labels on conversations, compared ignoring case.
+fn conversation_tags_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS conversation_tags (
+            conversation_id TEXT NOT NULL,
+            tag TEXT NOT NULL COLLATE NOCASE,
+            created_at INTEGER NOT NULL,
+            PRIMARY KEY (conversation_id, tag)
+        );
+        CREATE INDEX IF NOT EXISTS conversation_tags_tag ON conversation_tags(tag);
+        "#,
+    )
+    .context("Failed to create conversation_tags table")
+}
+
+/// Conversations the user pinned as favorites.
+fn pinned_conversations_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS pinned_conversations (
+            conversation_id TEXT PRIMARY KEY,
+            pinned_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .context("Failed to create pinned_conversations table")
+}
+
+/// The latest topic clustering: each topic's label and terms, and the topic of every clustered
+/// conversation.
+fn topic_tables(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS topics (
+            id INTEGER PRIMARY KEY,
+            label TEXT NOT NULL,
+            terms TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS conversation_topics (
+            conversation_id TEXT PRIMARY KEY,
+            topic_id INTEGER NOT NULL,
+            similarity REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS conversation_topics_topic_id ON conversation_topics(topic_id);
+        "#,
+    )
+    .context("Failed to create topic tables")
+}
+
+/// Keyphrases cached by `extract_keywords`.
+fn conversation_keywords_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS conversation_keywords (
+            conversation_id TEXT NOT NULL,
+            phrase TEXT NOT NULL,
+            score REAL NOT NULL,
+            created_at INTEGER NOT NULL,
+            PRIMARY KEY (conversation_id, phrase)
+        );
+        "#,
+    )
+    .context("Failed to create conversation_keywords table")
+}
+
+/// Detected language of each user and assistant message; NULL when it couldn't be told.
+fn message_languages_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS message_languages (
+            message_id INTEGER PRIMARY KEY,
+            language TEXT
+        );
+        CREATE INDEX IF NOT EXISTS message_languages_language ON message_languages(language);
+        "#,
+    )
+    .context("Failed to create message_languages table")
+}
+
+/// Message embeddings, one set per model, and each model's backfill checkpoint.
+fn embedding_tables(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS message_embeddings (
+            message_id INTEGER NOT NULL,
+            model TEXT NOT NULL,
+            dimensions INTEGER NOT NULL,
+            vector BLOB NOT NULL,
+            PRIMARY KEY (model, message_id)
+        );
+        CREATE TABLE IF NOT EXISTS embedding_state (
+            model TEXT PRIMARY KEY,
+            last_message_id INTEGER NOT NULL,
+            embedded INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .context("Failed to create embedding tables")
+}
+
+/// Synonym links for query expansion.
+fn synonyms_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS synonyms (
+            term TEXT NOT NULL,
+            synonym TEXT NOT NULL,
+            source TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            PRIMARY KEY (term, synonym)
+        );
+        CREATE INDEX IF NOT EXISTS synonyms_synonym ON synonyms(synonym);
+        "#,
+    )
+    .context("Failed to create synonyms table")
+}
+
+/// Conversations deleted with `delete_conversation`, kept as JSON copies of their rows until they
+/// are restored or their retention runs out.
+fn conversation_trash_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS conversation_trash (
+            conversation_id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            message_count INTEGER NOT NULL,
+            deleted_at INTEGER NOT NULL,
+            conversation_json TEXT NOT NULL,
+            messages_json TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS conversation_trash_deleted_at ON conversation_trash(deleted_at);
+        "#,
+    )
+    .context("Failed to create conversation_trash table")
+}
+
+/// Tool results replaced by a summary with `prune_tool_results`, with the compressed original when
+/// it was kept.
+fn pruned_tool_results_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS pruned_tool_results (
+            message_id INTEGER PRIMARY KEY,
+            original_bytes INTEGER NOT NULL,
+            original BLOB,
+            pruned_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .context("Failed to create pruned_tool_results table")
+}
+
+/// Files stored with `store_attachment`: their content once per SHA-256, and each attachment with
+/// the memory or message it belongs to. File references keep only their path.
+fn attachment_tables(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS attachment_blobs (
+            sha256 TEXT PRIMARY KEY,
+            content BLOB NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS attachments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            sha256 TEXT NOT NULL,
+            file_path TEXT,
+            memory_id INTEGER,
+            message_id INTEGER,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS attachments_memory_id ON attachments(memory_id);
+        CREATE INDEX IF NOT EXISTS attachments_message_id ON attachments(message_id);
+        "#,
+    )
+    .context("Failed to create attachment tables")
+}
+
+/// Links saved with `save_bookmark`, one per URL; `tags` is a JSON array. The index covers the
+/// URL, the page's title and description, the note and the tags.
+fn bookmarks_table(conn: &Connection, tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS bookmarks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            url TEXT NOT NULL UNIQUE,
+            title TEXT,
+            description TEXT,
+            note TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            fetched_at INTEGER
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS bookmarks_fts USING fts5(
+            url, title, description, note, tags,
+            content='bookmarks',
+            content_rowid='id',
+            tokenize='{}'
+        );
+        CREATE TRIGGER IF NOT EXISTS bookmarks_ai AFTER INSERT ON bookmarks BEGIN
+            INSERT INTO bookmarks_fts(rowid, url, title, description, note, tags)
+            VALUES (new.id, new.url, new.title, new.description, new.note, new.tags);
+        END;
+        CREATE TRIGGER IF NOT EXISTS bookmarks_ad AFTER DELETE ON bookmarks BEGIN
+            INSERT INTO bookmarks_fts(bookmarks_fts, rowid, url, title, description, note, tags)
+            VALUES ('delete', old.id, old.url, old.title, old.description, old.note, old.tags);
+        END;
+        CREATE TRIGGER IF NOT EXISTS bookmarks_au AFTER UPDATE ON bookmarks BEGIN
+            INSERT INTO bookmarks_fts(bookmarks_fts, rowid, url, title, description, note, tags)
+            VALUES ('delete', old.id, old.url, old.title, old.description, old.note, old.tags);
+            INSERT INTO bookmarks_fts(rowid, url, title, description, note, tags)
+            VALUES (new.id, new.url, new.title, new.description, new.note, new.tags);
+        END;
+        "#,
+        tokenizer.spec()
+    ))
+    .context("Failed to create bookmarks schema")
+}
+
+/// Snippets saved with `store_snippet`. The index keeps its own text: besides the description,
+/// code and language it holds the words of the code's compound identifiers, which the server
+/// derives when it stores a snippet.
+fn snippets_table(conn: &Connection, tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS snippets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            language TEXT NOT NULL,
+            description TEXT NOT NULL,
+            code TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS snippets_language ON snippets(language);
+        CREATE VIRTUAL TABLE IF NOT EXISTS snippets_fts USING fts5(
+            description, code, language, identifiers,
+            tokenize='{}'
+        );
+        "#,
+        tokenizer.spec()
+    ))
+    .context("Failed to create snippets schema")
+}
+
+/// Subject–predicate–object facts from `assert_fact`. The `_key` columns hold each part as
+/// queries compare it: trimmed, lowercased, whitespace collapsed.
+fn facts_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS facts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            subject TEXT NOT NULL,
+            predicate TEXT NOT NULL,
+            object TEXT NOT NULL,
+            subject_key TEXT NOT NULL,
+            predicate_key TEXT NOT NULL,
+            object_key TEXT NOT NULL,
+            confidence REAL NOT NULL DEFAULT 1.0,
+            source TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            UNIQUE(subject_key, predicate_key, object_key)
+        );
+        CREATE INDEX IF NOT EXISTS facts_predicate ON facts(predicate_key);
+        CREATE INDEX IF NOT EXISTS facts_object ON facts(object_key);
+        "#,
+    )
+    .context("Failed to create facts table")
+}
+
+/// What a memory states a value of, and pairs of memories or facts with differing values for the
+/// same thing, until one side is kept.
+fn conflicts_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    add_column_if_missing(conn, "memory", "key", "TEXT")?;
+    conn.execute_batch(
+        r#"
+        CREATE INDEX IF NOT EXISTS memory_key ON memory(key);
+        CREATE TABLE IF NOT EXISTS conflicts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            key TEXT NOT NULL,
+            existing_id INTEGER NOT NULL,
+            existing_value TEXT NOT NULL,
+            new_id INTEGER NOT NULL,
+            new_value TEXT NOT NULL,
+            detected_at INTEGER NOT NULL,
+            kept TEXT,
+            resolved_at INTEGER,
+            UNIQUE(kind, existing_id, new_id)
+        );
+        CREATE INDEX IF NOT EXISTS conflicts_open ON conflicts(resolved_at);
+        "#,
+    )
+    .context("Failed to create conflicts table")
+}
+
+/// Every version of every memory: as created, after each change and as last seen before its
+/// deletion, written by triggers so every path that changes memories is covered. Memories stored
+/// before the history existed start with their current state.
+fn memory_history_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS memory_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            memory_id INTEGER NOT NULL,
+            change TEXT NOT NULL,
+            content TEXT NOT NULL,
+            category TEXT,
+            importance INTEGER,
+            pinned INTEGER NOT NULL,
+            expires_at INTEGER,
+            key TEXT,
+            changed_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS memory_history_memory_id ON memory_history(memory_id);
+        CREATE INDEX IF NOT EXISTS memory_history_key ON memory_history(key);
+        CREATE INDEX IF NOT EXISTS memory_history_changed_at ON memory_history(changed_at);
+
+        INSERT INTO memory_history (memory_id, change, content, category, importance, pinned, expires_at, key, changed_at)
+        SELECT id, 'created', content, category, importance, pinned, expires_at, key, created_at FROM memory
+        WHERE id NOT IN (SELECT memory_id FROM memory_history);
+
+        CREATE TRIGGER IF NOT EXISTS memory_history_ai AFTER INSERT ON memory BEGIN
+            INSERT INTO memory_history (memory_id, change, content, category, importance, pinned, expires_at, key, changed_at)
+            VALUES (new.id, 'created', new.content, new.category, new.importance, new.pinned, new.expires_at, new.key, new.created_at);
+        END;
+        CREATE TRIGGER IF NOT EXISTS memory_history_au AFTER UPDATE OF content, category, importance, pinned, expires_at, key ON memory
+        WHEN old.content IS NOT new.content OR old.category IS NOT new.category OR old.importance IS NOT new.importance
+            OR old.pinned IS NOT new.pinned OR old.expires_at IS NOT new.expires_at OR old.key IS NOT new.key
+        BEGIN
+            INSERT INTO memory_history (memory_id, change, content, category, importance, pinned, expires_at, key, changed_at)
+            VALUES (new.id, 'updated', new.content, new.category, new.importance, new.pinned, new.expires_at, new.key, unixepoch());
+        END;
+        CREATE TRIGGER IF NOT EXISTS memory_history_ad AFTER DELETE ON memory BEGIN
+            INSERT INTO memory_history (memory_id, change, content, category, importance, pinned, expires_at, key, changed_at)
+            VALUES (old.id, 'deleted', old.content, old.category, old.importance, old.pinned, old.expires_at, old.key, unixepoch());
+        END;
+        "#,
+    )
+    .context("Failed to create memory_history table")
+}
+
+/// Every search a tool ran and how many results it found, for history and query suggestions.
+fn search_history_table(conn: &Connection, _tokenizer: &Tokenizer) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS search_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tool TEXT NOT NULL,
+            query TEXT NOT NULL,
+            hit_count INTEGER NOT NULL,
+            searched_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS search_history_searched_at ON search_history(searched_at);
+        "#,
+    )
+    .context("Failed to create search_history table")
+}
@@ -0,0 +1,104 @@
+//! Read-only connections for running a compound tool's independent queries side by side.
+//!
+//! The service's main connection is behind a mutex, so queries on it run one after another. On
+//! large databases the aggregate scans behind reports dominate latency; running them on separate
+//! connections lets SQLite scan in parallel.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use super::{encryption, register_functions};
+use crate::cancel::Interrupter;
+use crate::queries;
+
+pub struct ReadPool {
+    path: String,
+    /// Most idle connections kept open for reuse
+    size: usize,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl ReadPool {
+    /// Pool over the database at `path`, keeping up to `size` idle connections. Connections are
+    /// opened on demand, so creating a pool never touches the file.
+    pub fn new(path: &str, size: usize) -> Self {
+        Self {
+            path: path.to_string(),
+            size,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Take an idle connection or open a new one. It returns to the pool when dropped.
+    pub fn get(&self) -> Result<PooledConnection<'_>> {
+        let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => {
+                let conn = Connection::open_with_flags(
+                    &self.path,
+                    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+                )
+                .context("Failed to open read-only connection")?;
+                encryption::unlock(&conn)?;
+                register_functions(&conn)?;
+                conn.set_prepared_statement_cache_capacity(queries::STATEMENT_CACHE_CAPACITY);
+                conn
+            }
+        };
+        Ok(PooledConnection { pool: self, conn: Some(conn) })
+    }
+
+    /// Run `a` and `b` concurrently, each on its own connection, and return both results. The
+    /// connections are registered with `interrupter` under the current call while they run.
+    pub fn join<A, B, RA, RB>(&self, interrupter: &Arc<Interrupter>, a: A, b: B) -> Result<(RA, RB)>
+    where
+        A: FnOnce(&Connection) -> RA + Send,
+        B: FnOnce(&Connection) -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        let call = Interrupter::current_call();
+        std::thread::scope(|scope| {
+            let first = scope.spawn(|| {
+                let conn = self.get()?;
+                let _running = interrupter.enter_pooled(call, &conn)?;
+                Ok::<_, anyhow::Error>(a(&conn))
+            });
+            let second = self.get().and_then(|conn| {
+                let _running = interrupter.enter_pooled(call, &conn)?;
+                Ok(b(&conn))
+            });
+            let first = first
+                .join()
+                .map_err(|_| anyhow::anyhow!("Parallel query panicked"))?;
+            Ok((first?, second?))
+        })
+    }
+}
+
+pub struct PooledConnection<'a> {
+    pool: &'a ReadPool,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection taken before drop")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            let mut idle = self.pool.idle.lock().unwrap_or_else(|e| e.into_inner());
+            if idle.len() < self.pool.size {
+                idle.push(conn);
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Message embeddings from an OpenAI-compatible `/v1/embeddings` endpoint (OpenAI, Ollama,
+//! llama.cpp, LM Studio). Vectors are stored normalized in `message_embeddings`, one set per
+//! model, and `embedding_state` checkpoints how far each model's backfill got, so an interrupted
+//! run picks up where it stopped instead of starting over.
+
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::db::unix_now;
+
+const DEFAULT_MODEL: &str = "nomic-embed-text";
+/// Longer messages are cut; embedding models only read the first few thousand tokens anyway.
+const MAX_INPUT_CHARS: usize = 8000;
+/// Attempts per batch before giving up; the endpoint of a long backfill will hiccup now and then.
+const MAX_ATTEMPTS: u32 = 3;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddingConfig {
+    /// Embeddings endpoint, e.g. `http://localhost:11434/v1/embeddings` (`MCP_LUNA_EMBEDDING_URL`)
+    pub url: Option<String>,
+    /// Model name sent to the endpoint (`MCP_LUNA_EMBEDDING_MODEL`)
+    pub model: String,
+    /// Bearer token, for hosted endpoints (`MCP_LUNA_EMBEDDING_API_KEY`)
+    pub api_key: Option<String>,
+}
+
+impl EmbeddingConfig {
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        Self {
+            url: var("MCP_LUNA_EMBEDDING_URL"),
+            model: var("MCP_LUNA_EMBEDDING_MODEL").unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            api_key: var("MCP_LUNA_EMBEDDING_API_KEY"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Client for the configured embeddings endpoint.
+pub struct Embedder {
+    agent: ureq::Agent,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl Embedder {
+    pub fn new(config: &EmbeddingConfig) -> Result<Self> {
+        let url = config
+            .url
+            .clone()
+            .context("No embeddings endpoint; set MCP_LUNA_EMBEDDING_URL, e.g. http://localhost:11434/v1/embeddings")?;
+        let agent = ureq::Agent::config_builder().timeout_global(Some(REQUEST_TIMEOUT)).build().into();
+        Ok(Self { agent, url, model: config.model.clone(), api_key: config.api_key.clone() })
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Normalized embeddings of `texts`, in order.
+    pub fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut request = self.agent.post(&self.url);
+        if let Some(key) = &self.api_key {
+            request = request.header("Authorization", &format!("Bearer {}", key));
+        }
+        let mut response: EmbeddingResponse = request
+            .send_json(EmbeddingRequest { model: &self.model, input: texts })
+            .with_context(|| format!("Embeddings request to {} failed", self.url))?
+            .body_mut()
+            .read_json()
+            .context("Malformed embeddings response")?;
+        anyhow::ensure!(
+            response.data.len() == texts.len(),
+            "Embeddings endpoint returned {} vectors for {} inputs",
+            response.data.len(),
+            texts.len()
+        );
+        response.data.sort_by_key(|data| data.index);
+        Ok(response.data.into_iter().map(|data| normalize(data.embedding)).collect())
+    }
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// Little-endian `f32`s, as stored in `message_embeddings.vector`.
+pub fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+}
+
+/// How far a backfill has got, reported after every batch.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BackfillProgress {
+    /// Messages embedded by this run
+    pub embedded: usize,
+    /// Messages left after this batch
+    pub remaining: usize,
+    /// Highest message ID embedded so far, by this or earlier runs
+    pub checkpoint: i64,
+}
+
+/// Checkpoint of `model`'s backfill: the highest message ID it has embedded.
+pub fn checkpoint(conn: &Connection, model: &str) -> Result<i64> {
+    Ok(conn
+        .query_row("SELECT last_message_id FROM embedding_state WHERE model = ?", [model], |row| row.get(0))
+        .optional()
+        .context("Failed to read embedding state")?
+        .unwrap_or(0))
+}
+
+/// Forget `model`'s embeddings and checkpoint, so the next backfill starts from the first message.
+pub fn reset(conn: &Connection, model: &str) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM message_embeddings WHERE model = ?", [model]).context("Failed to clear embeddings")?;
+    tx.execute("DELETE FROM embedding_state WHERE model = ?", [model]).context("Failed to clear embedding state")?;
+    tx.commit().context("Failed to clear embeddings")
+}
+
+/// User and assistant messages after `after` that have text to embed.
+fn pending_count(conn: &Connection, after: i64) -> Result<usize> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM messages WHERE id > ? AND role IN ('user', 'assistant') AND TRIM(content) != ''",
+        [after],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n as usize)
+    .context("Failed to count messages to embed")
+}
+
+/// Embed the user and assistant messages after `embedder`'s checkpoint in batches of
+/// `batch_size`, stopping after `limit` messages when given. Each batch is stored together with
+/// the new checkpoint in one transaction, so an interrupted run loses at most the batch in flight.
+pub fn backfill(
+    conn: &Connection,
+    embedder: &Embedder,
+    batch_size: usize,
+    limit: Option<usize>,
+    mut on_batch: impl FnMut(&BackfillProgress),
+) -> Result<BackfillProgress> {
+    let model = embedder.model();
+    let batch_size = batch_size.max(1);
+    let mut progress = BackfillProgress { checkpoint: checkpoint(conn, model)?, ..Default::default() };
+    progress.remaining = pending_count(conn, progress.checkpoint)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, content FROM messages WHERE id > ? AND role IN ('user', 'assistant') AND TRIM(content) != ''
+             ORDER BY id LIMIT ?",
+        )
+        .context("Failed to read messages")?;
+    loop {
+        let wanted = limit.map_or(batch_size, |limit| batch_size.min(limit - progress.embedded));
+        if wanted == 0 {
+            break;
+        }
+        let batch = stmt
+            .query_map(rusqlite::params![progress.checkpoint, wanted as i64], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read messages")?;
+        let Some(&(last_id, _)) = batch.last() else {
+            break;
+        };
+        let texts: Vec<&str> = batch.iter().map(|(_, content)| truncate(content)).collect();
+        let vectors = embed_with_retries(embedder, &texts)?;
+
+        let tx = conn.unchecked_transaction()?;
+        for ((id, _), vector) in batch.iter().zip(&vectors) {
+            tx.execute(
+                "INSERT OR REPLACE INTO message_embeddings (message_id, model, dimensions, vector) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![id, model, vector.len() as i64, to_blob(vector)],
+            )
+            .context("Failed to store embeddings")?;
+        }
+        tx.execute(
+            "INSERT INTO embedding_state (model, last_message_id, embedded, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(model) DO UPDATE SET last_message_id = excluded.last_message_id,
+                 embedded = embedded + excluded.embedded, updated_at = excluded.updated_at",
+            rusqlite::params![model, last_id, batch.len() as i64, unix_now()],
+        )
+        .context("Failed to store embedding state")?;
+        tx.commit().context("Failed to store embeddings")?;
+
+        progress.embedded += batch.len();
+        progress.remaining = progress.remaining.saturating_sub(batch.len());
+        progress.checkpoint = last_id;
+        on_batch(&progress);
+    }
+    Ok(progress)
+}
+
+fn truncate(text: &str) -> &str {
+    match text.char_indices().nth(MAX_INPUT_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+fn embed_with_retries(embedder: &Embedder, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+    let mut attempt = 1;
+    loop {
+        match embedder.embed(texts) {
+            Ok(vectors) => return Ok(vectors),
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                eprintln!("{:#}; retrying", e);
+                thread::sleep(Duration::from_secs(1 << attempt));
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,431 @@
+//! Taking content out of the conversation history: whole conversations moved to a trash, where
+//! they stay restorable for a while, and messages redacted for good.
+//!
+//! The trash holds JSON copies of the Cosmic rows, taken column by column, so a conversation comes
+//! back exactly as it was, ids included. `messages_fts` follows every change: through its triggers
+//! when the server created it, by hand when the app maintains it.
+
+use anyhow::{ensure, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::attachments;
+use crate::db::{self, unix_now};
+use crate::models::{
+    DeleteConversationPreview, DeleteConversationResponse, RedactMessagePreview, RedactMessageResponse, RestoreConversationResponse,
+};
+
+/// Days a deleted conversation stays in the trash before maintenance removes it for good.
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// What redacted text is replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+/// Message columns a redaction rewrites, the indexed text first.
+const REDACTED_COLUMNS: &[&str] = &["content", "tool_params_json", "tool_result_json", "reasoning_content"];
+
+/// Server tables keyed by conversation: annotations and caches that go when a conversation is
+/// deleted for good.
+const CONVERSATION_TABLES: &[&str] = &[
+    "conversation_tags",
+    "pinned_conversations",
+    "conversation_projects",
+    "conversation_topics",
+    "conversation_keywords",
+];
+
+/// Server tables keyed by message, derived from its text or holding a copy of it. Attachments
+/// linked to a message go with it too, through [`attachments::delete_for_messages`].
+const MESSAGE_TABLES: &[&str] = &["message_languages", "message_embeddings", "pruned_tool_results"];
+
+/// Columns of `table`, in order.
+fn columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?;
+    let columns = stmt
+        .query_map([table], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()
+        .with_context(|| format!("Failed to read the columns of {}", table))?;
+    Ok(columns)
+}
+
+fn quoted(column: &str) -> String {
+    format!("\"{}\"", column.replace('"', "\"\""))
+}
+
+/// SQL expression turning a row with `columns` into a JSON object.
+fn json_object_sql(columns: &[String]) -> String {
+    let pairs: Vec<String> = columns.iter().map(|c| format!("'{}', {}", c.replace('\'', "''"), quoted(c))).collect();
+    format!("json_object({})", pairs.join(", "))
+}
+
+/// Move conversation `conversation_id` and its messages to the trash.
+pub fn trash(conn: &Connection, conversation_id: &str) -> Result<DeleteConversationResponse> {
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    let response = move_to_trash(&tx, conversation_id)?;
+    tx.commit().context("Failed to commit deletion")?;
+    Ok(response)
+}
+
+/// [`trash`] within a transaction the caller holds.
+pub(crate) fn move_to_trash(tx: &Connection, conversation_id: &str) -> Result<DeleteConversationResponse> {
+    let title: String = tx
+        .query_row("SELECT title FROM conversations WHERE id = ?", [conversation_id], |row| row.get(0))
+        .optional()
+        .context("Failed to look up conversation")?
+        .with_context(|| format!("No conversation with ID '{}'", conversation_id))?;
+    let conversation_json: String = tx.query_row(
+        &format!("SELECT {} FROM conversations WHERE id = ?", json_object_sql(&columns(tx, "conversations")?)),
+        [conversation_id],
+        |row| row.get(0),
+    )?;
+    let (messages_json, message_count): (String, usize) = tx.query_row(
+        &format!(
+            "SELECT json_group_array(json(message)), COUNT(*) FROM (SELECT {} AS message FROM messages WHERE conversation_id = ? ORDER BY id)",
+            json_object_sql(&columns(tx, "messages")?)
+        ),
+        [conversation_id],
+        |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)),
+    )?;
+    let deleted_at = unix_now();
+    tx.execute(
+        "INSERT OR REPLACE INTO conversation_trash (conversation_id, title, message_count, deleted_at, conversation_json, messages_json)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![conversation_id, title, message_count as i64, deleted_at, conversation_json, messages_json],
+    )
+    .context("Failed to move conversation to the trash")?;
+    remove_rows(tx, conversation_id)?;
+    Ok(DeleteConversationResponse {
+        success: true,
+        conversation_id: conversation_id.to_string(),
+        title: Some(title),
+        messages_deleted: message_count,
+        permanent: false,
+        restorable_until: Some(deleted_at + TRASH_RETENTION_DAYS * 86400),
+        error: None,
+    })
+}
+
+/// What deleting conversation `conversation_id` for good would remove, from the history or the trash.
+pub fn preview_delete(conn: &Connection, conversation_id: &str) -> Result<DeleteConversationPreview> {
+    let live: Option<(String, usize)> = conn
+        .query_row(
+            "SELECT title, (SELECT COUNT(*) FROM messages WHERE conversation_id = c.id) FROM conversations c WHERE id = ?",
+            [conversation_id],
+            |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)),
+        )
+        .optional()
+        .context("Failed to look up conversation")?;
+    let trashed = || {
+        conn.query_row(
+            "SELECT title, message_count FROM conversation_trash WHERE conversation_id = ?",
+            [conversation_id],
+            |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)),
+        )
+        .optional()
+        .context("Failed to look up the trash")
+    };
+    let (title, message_count, in_trash) = match live {
+        Some((title, message_count)) => (title, message_count, false),
+        None => match trashed()? {
+            Some((title, message_count)) => (title, message_count, true),
+            None => anyhow::bail!("No conversation with ID '{}'", conversation_id),
+        },
+    };
+    Ok(DeleteConversationPreview {
+        conversation_id: conversation_id.to_string(),
+        title: Some(title),
+        message_count,
+        in_trash,
+        ..Default::default()
+    })
+}
+
+/// Delete conversation `conversation_id` for good, whether it is in the history or the trash,
+/// along with everything the server keeps about it.
+pub fn delete_permanently(conn: &Connection, conversation_id: &str) -> Result<DeleteConversationResponse> {
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    let live_title: Option<String> = tx
+        .query_row("SELECT title FROM conversations WHERE id = ?", [conversation_id], |row| row.get(0))
+        .optional()
+        .context("Failed to look up conversation")?;
+    let trashed: Option<(String, usize)> = tx
+        .query_row(
+            "SELECT title, message_count FROM conversation_trash WHERE conversation_id = ?",
+            [conversation_id],
+            |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)),
+        )
+        .optional()
+        .context("Failed to look up the trash")?;
+    let (title, messages_deleted) = match (live_title, trashed) {
+        (Some(title), _) => {
+            let message_ids = live_message_ids(&tx, conversation_id)?;
+            forget(&tx, conversation_id, &message_ids)?;
+            (title, remove_rows(&tx, conversation_id)?)
+        }
+        (None, Some((title, message_count))) => {
+            forget(&tx, conversation_id, &trashed_message_ids(&tx, conversation_id)?)?;
+            (title, message_count)
+        }
+        (None, None) => anyhow::bail!("No conversation with ID '{}'", conversation_id),
+    };
+    tx.execute("DELETE FROM conversation_trash WHERE conversation_id = ?", [conversation_id])
+        .context("Failed to empty the conversation from the trash")?;
+    tx.commit().context("Failed to commit deletion")?;
+    Ok(DeleteConversationResponse {
+        success: true,
+        conversation_id: conversation_id.to_string(),
+        title: Some(title),
+        messages_deleted,
+        permanent: true,
+        restorable_until: None,
+        error: None,
+    })
+}
+
+/// Put conversation `conversation_id` back from the trash, with its original message ids.
+pub fn restore(conn: &Connection, conversation_id: &str) -> Result<RestoreConversationResponse> {
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    let (title, conversation_json, messages_json): (String, String, String) = tx
+        .query_row(
+            "SELECT title, conversation_json, messages_json FROM conversation_trash WHERE conversation_id = ?",
+            [conversation_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .context("Failed to look up the trash")?
+        .with_context(|| format!("No deleted conversation with ID '{}' in the trash", conversation_id))?;
+    let exists: bool = tx.query_row("SELECT EXISTS(SELECT 1 FROM conversations WHERE id = ?)", [conversation_id], |row| row.get(0))?;
+    ensure!(!exists, "A conversation with ID '{}' exists again; delete it before restoring the old one", conversation_id);
+
+    // Columns the table has now and the copy has, so later schema changes don't break restores
+    let conversation_columns = stored_columns(&tx, "conversations", &conversation_json)?;
+    tx.execute(
+        &format!(
+            "INSERT INTO conversations ({}) SELECT {}",
+            conversation_columns.iter().map(|c| quoted(c)).collect::<Vec<_>>().join(", "),
+            json_extract_sql(&conversation_columns, "?1")
+        ),
+        [&conversation_json],
+    )
+    .context("Failed to restore conversation")?;
+    let message_columns = match tx
+        .query_row("SELECT value FROM json_each(?1) LIMIT 1", [&messages_json], |row| row.get::<_, String>(0))
+        .optional()?
+    {
+        Some(first) => stored_columns(&tx, "messages", &first)?,
+        None => Vec::new(),
+    };
+    let mut messages_restored = 0;
+    if !message_columns.is_empty() {
+        messages_restored = tx
+            .execute(
+                &format!(
+                    "INSERT INTO messages ({}) SELECT {} FROM json_each(?1)",
+                    message_columns.iter().map(|c| quoted(c)).collect::<Vec<_>>().join(", "),
+                    json_extract_sql(&message_columns, "value")
+                ),
+                [&messages_json],
+            )
+            .context("Failed to restore messages")?;
+        if !db::messages_fts_has_triggers(&tx)? {
+            let mut stmt = tx.prepare("SELECT id, content FROM messages WHERE conversation_id = ?")?;
+            let rows = stmt
+                .query_map([conversation_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for (id, content) in rows {
+                db::index_message(&tx, id, &content)?;
+            }
+        }
+    }
+    tx.execute("DELETE FROM conversation_trash WHERE conversation_id = ?", [conversation_id])
+        .context("Failed to take the conversation out of the trash")?;
+    tx.commit().context("Failed to commit restore")?;
+    Ok(RestoreConversationResponse {
+        success: true,
+        conversation_id: conversation_id.to_string(),
+        title: Some(title),
+        messages_restored,
+        error: None,
+    })
+}
+
+/// Delete for good the conversations that have been in the trash longer than
+/// [`TRASH_RETENTION_DAYS`].
+pub fn purge_trash(conn: &Connection) -> Result<usize> {
+    if !db::has_column(conn, "conversation_trash", "deleted_at") {
+        return Ok(0);
+    }
+    let cutoff = unix_now() - TRASH_RETENTION_DAYS * 86400;
+    let expired: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT conversation_id FROM conversation_trash WHERE deleted_at <= ?")?;
+        let ids = stmt.query_map([cutoff], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        ids
+    };
+    for conversation_id in &expired {
+        delete_permanently(conn, conversation_id)?;
+    }
+    Ok(expired.len())
+}
+
+/// Replace `text` in message `message_id` with [`REDACTED`], or blank the message when `text` is
+/// `None`: its content becomes [`REDACTED`] and its tool parameters, result and reasoning are
+/// cleared. What the server derived from the old text (language, embedding, keyphrases) goes too.
+pub fn redact(conn: &Connection, message_id: i64, text: Option<&str>) -> Result<RedactMessageResponse> {
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    let Redaction { conversation_id, old, new, fields_redacted } = redaction(&tx, message_id, text)?;
+
+    let old_content = old[0].clone().unwrap_or_default();
+    let new_content = new[0].clone().unwrap_or_default();
+    let sync_fts = old_content != new_content && !db::messages_fts_synced_on(&tx, "UPDATE")?;
+    if sync_fts {
+        db::unindex_message(&tx, message_id, &old_content)?;
+    }
+    tx.execute(
+        &format!(
+            "UPDATE messages SET {} WHERE id = ?{}",
+            REDACTED_COLUMNS.iter().enumerate().map(|(i, c)| format!("{} = ?{}", c, i + 1)).collect::<Vec<_>>().join(", "),
+            REDACTED_COLUMNS.len() + 1
+        ),
+        params![new[0], new[1], new[2], new[3], message_id],
+    )
+    .context("Failed to redact message")?;
+    if sync_fts {
+        db::index_message(&tx, message_id, &new_content)?;
+    }
+    forget_messages(&tx, &[message_id])?;
+    tx.execute("DELETE FROM conversation_keywords WHERE conversation_id = ?", [&conversation_id])
+        .context("Failed to clear cached keyphrases")?;
+    tx.commit().context("Failed to commit redaction")?;
+    Ok(RedactMessageResponse {
+        success: true,
+        message_id,
+        conversation_id: Some(conversation_id),
+        fields_redacted,
+        error: None,
+    })
+}
+
+/// What [`redact`] would change in message `message_id`, without changing it.
+pub fn preview_redact(conn: &Connection, message_id: i64, text: Option<&str>) -> Result<RedactMessagePreview> {
+    let Redaction { conversation_id, new, fields_redacted, .. } = redaction(conn, message_id, text)?;
+    Ok(RedactMessagePreview {
+        message_id,
+        conversation_id: Some(conversation_id),
+        fields_redacted,
+        redacted_content: new[0].clone(),
+        ..Default::default()
+    })
+}
+
+/// A message's [`REDACTED_COLUMNS`] before and after a redaction.
+struct Redaction {
+    conversation_id: String,
+    old: Vec<Option<String>>,
+    new: Vec<Option<String>>,
+    fields_redacted: Vec<String>,
+}
+
+/// Redaction of `text` in message `message_id`, or of the whole message.
+fn redaction(conn: &Connection, message_id: i64, text: Option<&str>) -> Result<Redaction> {
+    if let Some(text) = text {
+        ensure!(!text.is_empty(), "The text to redact is empty");
+    }
+    let (conversation_id, old): (String, Vec<Option<String>>) = conn
+        .query_row(
+            &format!("SELECT conversation_id, {} FROM messages WHERE id = ?", REDACTED_COLUMNS.join(", ")),
+            [message_id],
+            |row| Ok((row.get(0)?, (1..=REDACTED_COLUMNS.len()).map(|i| row.get(i)).collect::<rusqlite::Result<_>>()?)),
+        )
+        .optional()
+        .context("Failed to look up message")?
+        .with_context(|| format!("No message with ID {}", message_id))?;
+
+    let new: Vec<Option<String>> = REDACTED_COLUMNS
+        .iter()
+        .zip(&old)
+        .map(|(column, value)| match text {
+            Some(text) => value.as_ref().map(|value| value.replace(text, REDACTED)),
+            None if *column == "content" => Some(REDACTED.to_string()),
+            None => None,
+        })
+        .collect();
+    let fields_redacted: Vec<String> = REDACTED_COLUMNS
+        .iter()
+        .zip(old.iter().zip(&new))
+        .filter(|(_, (old, new))| old != new)
+        .map(|(column, _)| column.to_string())
+        .collect();
+    if text.is_some() {
+        ensure!(!fields_redacted.is_empty(), "Message {} doesn't contain the text to redact", message_id);
+    }
+    Ok(Redaction { conversation_id, old, new, fields_redacted })
+}
+
+/// Delete the conversation's messages, out of `messages_fts` too, and its row; returns how many
+/// messages went.
+pub(crate) fn remove_rows(conn: &Connection, conversation_id: &str) -> Result<usize> {
+    if !db::messages_fts_synced_on(conn, "DELETE")? {
+        let mut stmt = conn.prepare("SELECT id, content FROM messages WHERE conversation_id = ?")?;
+        let rows = stmt
+            .query_map([conversation_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, content) in rows {
+            db::unindex_message(conn, id, &content)?;
+        }
+    }
+    let deleted = conn
+        .execute("DELETE FROM messages WHERE conversation_id = ?", [conversation_id])
+        .context("Failed to delete messages")?;
+    conn.execute("DELETE FROM conversations WHERE id = ?", [conversation_id])
+        .context("Failed to delete conversation")?;
+    Ok(deleted)
+}
+
+fn live_message_ids(conn: &Connection, conversation_id: &str) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM messages WHERE conversation_id = ?")?;
+    let ids = stmt.query_map([conversation_id], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(ids)
+}
+
+fn trashed_message_ids(conn: &Connection, conversation_id: &str) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT json_extract(m.value, '$.id') FROM conversation_trash t, json_each(t.messages_json) m WHERE t.conversation_id = ?",
+    )?;
+    let ids = stmt.query_map([conversation_id], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(ids)
+}
+
+/// Drop what the server keeps about a conversation and its messages.
+fn forget(conn: &Connection, conversation_id: &str, message_ids: &[i64]) -> Result<()> {
+    for table in CONVERSATION_TABLES {
+        conn.execute(&format!("DELETE FROM {} WHERE conversation_id = ?", table), [conversation_id])
+            .with_context(|| format!("Failed to clear {}", table))?;
+    }
+    forget_messages(conn, message_ids)
+}
+
+fn forget_messages(conn: &Connection, message_ids: &[i64]) -> Result<()> {
+    for table in MESSAGE_TABLES {
+        let mut stmt = conn.prepare(&format!("DELETE FROM {} WHERE message_id = ?", table))?;
+        for id in message_ids {
+            stmt.execute([id]).with_context(|| format!("Failed to clear {}", table))?;
+        }
+    }
+    attachments::delete_for_messages(conn, message_ids).context("Failed to delete attachments")?;
+    Ok(())
+}
+
+/// Columns of `table` that the JSON object `object` has a key for.
+fn stored_columns(conn: &Connection, table: &str, object: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT key FROM json_each(?1)")?;
+    let keys: Vec<String> = stmt.query_map([object], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(columns(conn, table)?.into_iter().filter(|c| keys.contains(c)).collect())
+}
+
+/// SQL reading `columns` out of the JSON object `object`.
+fn json_extract_sql(columns: &[String], object: &str) -> String {
+    columns
+        .iter()
+        .map(|c| format!("json_extract({}, '$.\"{}\"')", object, c.replace('\'', "''").replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
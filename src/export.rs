@@ -0,0 +1,266 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::dates::Timezone;
+use crate::db;
+use crate::models::{Conversation, ExportReport, MemoryEntry, Message};
+use crate::pii::PiiConfig;
+use crate::redact::Masker;
+use crate::tokens;
+
+/// Version of the JSONL export layout. Bump when a record shape changes incompatibly.
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+const CONVERSATIONS_FILE: &str = "conversations.jsonl";
+const MEMORIES_FILE: &str = "memories.jsonl";
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    schema_version: u32,
+    exported_at: i64,
+    conversations: i64,
+    messages: i64,
+    memories: i64,
+    estimated_tokens: u64,
+    pii_masked: bool,
+    files: &'a [String],
+}
+
+/// Stream every conversation (with its messages) and every memory entry to JSONL files in `output_dir`.
+/// One conversation or memory per line; a `manifest.json` records the schema version and counts.
+/// With `pii`, personal data in every record is masked before it is written.
+pub fn export_all(conn: &Connection, output_dir: &Path, pii: Option<&PiiConfig>) -> Result<ExportReport> {
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create export directory {}", output_dir.display()))?;
+
+    let (conversations, messages, conversation_tokens) =
+        export_conversations(conn, &output_dir.join(CONVERSATIONS_FILE), pii)?;
+    let (memories, memory_tokens) = export_memories(conn, &output_dir.join(MEMORIES_FILE), pii)?;
+    let estimated_tokens = conversation_tokens + memory_tokens;
+
+    let files = vec![
+        CONVERSATIONS_FILE.to_string(),
+        MEMORIES_FILE.to_string(),
+        MANIFEST_FILE.to_string(),
+    ];
+    let manifest = Manifest {
+        schema_version: EXPORT_SCHEMA_VERSION,
+        exported_at: db::unix_now(),
+        conversations,
+        messages,
+        memories,
+        estimated_tokens,
+        pii_masked: pii.is_some(),
+        files: &files,
+    };
+    let manifest_file = File::create(output_dir.join(MANIFEST_FILE)).context("Failed to create manifest")?;
+    serde_json::to_writer_pretty(manifest_file, &manifest).context("Failed to write manifest")?;
+
+    Ok(ExportReport {
+        schema_version: EXPORT_SCHEMA_VERSION,
+        output_dir: output_dir.display().to_string(),
+        conversations,
+        messages,
+        memories,
+        files,
+        estimated_tokens,
+        pii_masked: pii.is_some(),
+        error: None,
+    })
+}
+
+/// Returns the numbers of conversations and messages exported and their estimated tokens.
+fn export_conversations(conn: &Connection, path: &Path, pii: Option<&PiiConfig>) -> Result<(i64, i64, u64)> {
+    let mut out = BufWriter::new(File::create(path).context("Failed to create conversations export")?);
+    let mut conv_stmt = conn
+        .prepare("SELECT id, title, created_at, title_generated, profile_name FROM conversations ORDER BY created_at ASC")
+        .context("Failed to query conversations")?;
+    let mut msg_stmt = conn
+        .prepare(
+            r#"
+            SELECT
+                id, conversation_id, role, content, created_at,
+                tool_calls, tool_call_id, tool_name, tool_status,
+                tool_params_json, tool_result_json, reasoning_content
+            FROM messages
+            WHERE conversation_id = ?
+            ORDER BY created_at ASC
+            "#,
+        )
+        .context("Failed to query messages")?;
+
+    let mut conversations = 0;
+    let mut messages = 0;
+    let mut estimated_tokens = 0;
+    let mut rows = conv_stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let mut conversation = Conversation {
+            id: row.get(0)?,
+            title: row.get(1).unwrap_or_default(),
+            created_at: row.get(2).unwrap_or(0),
+            title_generated: row.get(3).unwrap_or(0),
+            profile_name: row.get(4).ok(),
+            messages: Vec::new(),
+            ..Default::default()
+        };
+        conversation.messages = msg_stmt
+            .query_map([conversation.id.as_str()], |row| {
+                Ok(Message {
+                    id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    role: row.get(2).unwrap_or_default(),
+                    content: row.get(3).unwrap_or_default(),
+                    created_at: row.get(4).unwrap_or(0),
+                    created_at_iso: None,
+                    tool_calls: row.get(5).ok(),
+                    tool_call_id: row.get(6).ok(),
+                    tool_name: row.get(7).ok(),
+                    tool_status: row.get(8).ok(),
+                    tool_params_json: row.get(9).ok(),
+                    tool_result_json: row.get(10).ok(),
+                    reasoning_content: row.get(11).ok(),
+                    key: false,
+                    estimated_tokens: 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for message in &mut conversation.messages {
+            message.estimated_tokens = tokens::message(message);
+        }
+        conversation.estimated_tokens = tokens::conversation(&conversation);
+
+        conversations += 1;
+        messages += conversation.messages.len() as i64;
+        estimated_tokens += conversation.estimated_tokens;
+        write_record(&mut out, &conversation, pii)?;
+    }
+    out.flush()?;
+    Ok((conversations, messages, estimated_tokens))
+}
+
+/// Returns the number of memories exported and their estimated tokens.
+fn export_memories(conn: &Connection, path: &Path, pii: Option<&PiiConfig>) -> Result<(i64, u64)> {
+    let mut out = BufWriter::new(File::create(path).context("Failed to create memories export")?);
+    let mut stmt = conn
+        .prepare("SELECT id, content, category, importance, created_at FROM memory ORDER BY id ASC")
+        .context("Failed to query memory")?;
+
+    let mut memories = 0;
+    let mut estimated_tokens = 0;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let entry = MemoryEntry {
+            id: row.get(0)?,
+            content: row.get(1).unwrap_or_default(),
+            category: row.get(2).ok(),
+            importance: row.get(3).unwrap_or(5),
+            created_at: row.get(4).unwrap_or(0),
+            created_at_iso: None,
+            score: None,
+            expires_at: None,
+            pinned: false,
+            key: None,
+            attachments: Vec::new(),
+            conflicts: Vec::new(),
+        };
+        memories += 1;
+        estimated_tokens += tokens::estimate(&entry.content);
+        write_record(&mut out, &entry, pii)?;
+    }
+    out.flush()?;
+    Ok((memories, estimated_tokens))
+}
+
+/// Write `record` as one JSONL line, with its personal data masked when `pii` is given.
+fn write_record(out: &mut impl Write, record: &impl Serialize, pii: Option<&PiiConfig>) -> Result<()> {
+    match pii {
+        Some(pii) => {
+            let mut value = serde_json::to_value(record)?;
+            pii.redact_json(&mut value);
+            serde_json::to_writer(&mut *out, &value)?;
+        }
+        None => serde_json::to_writer(&mut *out, record)?,
+    }
+    out.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Render `conversation` as a Markdown document: a title and metadata list, then one section per
+/// message with its time in `timezone`. Tool parameters and results become JSON code blocks and
+/// reasoning is folded into a `<details>` block, so the thread reads like the chat did.
+pub fn conversation_markdown(conversation: &Conversation, timezone: Timezone) -> String {
+    let mut out = format!("# {}\n\n", conversation.title.trim());
+    out.push_str(&format!("- Conversation: `{}`\n", conversation.id));
+    if let Some(started) = timezone.iso(conversation.created_at) {
+        out.push_str(&format!("- Started: {}\n", started));
+    }
+    if let Some(profile) = conversation.profile_name.as_deref().filter(|p| !p.is_empty()) {
+        out.push_str(&format!("- Profile: {}\n", profile));
+    }
+    out.push_str(&format!("- Messages: {}\n\n", conversation.messages.len()));
+    out.push_str(&messages_markdown(&conversation.messages, timezone));
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+/// One Markdown section per message, as in [`conversation_markdown`].
+pub fn messages_markdown(messages: &[Message], timezone: Timezone) -> String {
+    let mut out = String::new();
+    for message in messages {
+        let mut heading = match message.role.as_str() {
+            "user" => "User".to_string(),
+            "assistant" => "Assistant".to_string(),
+            "system" => "System".to_string(),
+            "tool" => match &message.tool_name {
+                Some(name) => format!("Tool `{}`", name),
+                None => "Tool".to_string(),
+            },
+            other => other.to_string(),
+        };
+        if let Some(status) = message.tool_status.as_deref().filter(|s| !s.is_empty()) {
+            heading.push_str(&format!(" ({})", status));
+        }
+        if let Some(time) = timezone.iso(message.created_at) {
+            heading.push_str(&format!(" · {}", time));
+        }
+        out.push_str(&format!("## {}\n\n", heading));
+
+        if let Some(reasoning) = message.reasoning_content.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+            out.push_str(&format!("<details>\n<summary>Reasoning</summary>\n\n{}\n\n</details>\n\n", reasoning));
+        }
+        let content = message.content.trim();
+        if !content.is_empty() {
+            out.push_str(content);
+            out.push_str("\n\n");
+        }
+        for (label, json) in [("Parameters", &message.tool_params_json), ("Result", &message.tool_result_json)] {
+            if let Some(json) = json.as_deref().map(str::trim).filter(|j| !j.is_empty()) {
+                out.push_str(&format!("{}:\n\n{}\n\n", label, code_block(&pretty_json(json), "json")));
+            }
+        }
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+/// `json` pretty-printed, or unchanged when it doesn't parse.
+fn pretty_json(json: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(json)
+        .ok()
+        .and_then(|value| serde_json::to_string_pretty(&value).ok())
+        .unwrap_or_else(|| json.to_string())
+}
+
+/// Fenced code block around `text`, with a fence longer than any backtick run inside it.
+fn code_block(text: &str, language: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{text}\n{fence}")
+}
@@ -0,0 +1,145 @@
+//! Facts: structured statements as subject–predicate–object triples ("user's dog" – "name" –
+//! "Rex"), each with a confidence and where it came from, for exact questions keyword search
+//! answers badly.
+//!
+//! Parts compare by key: trimmed, lowercased and with inner whitespace collapsed, so "User's  Dog"
+//! and "user's dog" are the same subject. The text as given is what facts return.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::dates::Timezone;
+use crate::db::unix_now;
+use crate::models::Fact;
+
+const COLUMNS: &str = "f.id, f.subject, f.predicate, f.object, f.confidence, f.source, f.created_at, f.updated_at";
+
+fn from_row(row: &Row, timezone: Timezone) -> rusqlite::Result<Fact> {
+    let created_at = row.get(6)?;
+    Ok(Fact {
+        id: row.get(0)?,
+        subject: row.get(1)?,
+        predicate: row.get(2)?,
+        object: row.get(3)?,
+        confidence: row.get(4)?,
+        source: row.get(5)?,
+        created_at,
+        created_at_iso: timezone.iso(created_at),
+        updated_at: row.get(7)?,
+    })
+}
+
+/// `text` as facts compare it: trimmed, lowercased, inner whitespace collapsed.
+pub fn key(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// A fact to assert.
+#[derive(Debug, Default)]
+pub struct NewFact {
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+    /// From 0.0 to 1.0
+    pub confidence: f64,
+    pub source: Option<String>,
+}
+
+/// Record `fact`. Asserting a triple that is already known updates its confidence and source
+/// instead; the second value tells which happened.
+pub fn assert(conn: &Connection, fact: NewFact, timezone: Timezone) -> Result<(Fact, bool)> {
+    let parts = [&fact.subject, &fact.predicate, &fact.object];
+    anyhow::ensure!(parts.iter().all(|part| !part.trim().is_empty()), "Subject, predicate and object must not be empty");
+    anyhow::ensure!((0.0..=1.0).contains(&fact.confidence), "Confidence must be between 0.0 and 1.0");
+    let (subject_key, predicate_key, object_key) = (key(&fact.subject), key(&fact.predicate), key(&fact.object));
+    let source = fact.source.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let now = unix_now();
+
+    let known: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM facts WHERE subject_key = ?1 AND predicate_key = ?2 AND object_key = ?3",
+            rusqlite::params![subject_key, predicate_key, object_key],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to look up fact")?;
+    let id = match known {
+        Some(id) => {
+            conn.execute(
+                "UPDATE facts SET confidence = ?1, source = COALESCE(?2, source), updated_at = ?3 WHERE id = ?4",
+                rusqlite::params![fact.confidence, source, now, id],
+            )
+            .context("Failed to update fact")?;
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO facts (subject, predicate, object, subject_key, predicate_key, object_key, confidence, source, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+                rusqlite::params![
+                    fact.subject.trim(),
+                    fact.predicate.trim(),
+                    fact.object.trim(),
+                    subject_key,
+                    predicate_key,
+                    object_key,
+                    fact.confidence,
+                    source,
+                    now
+                ],
+            )
+            .context("Failed to store fact")?;
+            conn.last_insert_rowid()
+        }
+    };
+    Ok((get(conn, id, timezone)?.context("Fact disappeared")?, known.is_some()))
+}
+
+/// The fact with id `fact_id`, if any.
+pub fn get(conn: &Connection, fact_id: i64, timezone: Timezone) -> Result<Option<Fact>> {
+    conn.query_row(&format!("SELECT {} FROM facts f WHERE f.id = ?", COLUMNS), [fact_id], |row| from_row(row, timezone))
+        .optional()
+        .context("Failed to read fact")
+}
+
+/// Delete the fact with id `fact_id`; false when there was none.
+pub fn delete(conn: &Connection, fact_id: i64) -> Result<bool> {
+    let deleted = conn.execute("DELETE FROM facts WHERE id = ?", [fact_id]).context("Failed to delete fact")?;
+    Ok(deleted > 0)
+}
+
+/// What to look up: facts matching every part given.
+#[derive(Debug, Default)]
+pub struct FactQuery<'a> {
+    pub subject: Option<&'a str>,
+    pub predicate: Option<&'a str>,
+    pub object: Option<&'a str>,
+    pub min_confidence: Option<f64>,
+}
+
+/// Up to `limit` facts matching `query`, most confident first, then most recently asserted.
+pub fn query(conn: &Connection, query: &FactQuery, limit: u32, timezone: Timezone) -> Result<Vec<Fact>> {
+    let mut conditions = Vec::new();
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    let parts = [("subject_key", query.subject), ("predicate_key", query.predicate), ("object_key", query.object)];
+    for (column, part) in parts {
+        if let Some(part) = part.map(key).filter(|p| !p.is_empty()) {
+            conditions.push(format!("f.{} = ?", column));
+            params.push(part.into());
+        }
+    }
+    if let Some(min_confidence) = query.min_confidence {
+        conditions.push("f.confidence >= ?".to_string());
+        params.push(min_confidence.into());
+    }
+    let filter = if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) };
+    params.push(i64::from(limit).into());
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM facts f {} ORDER BY f.confidence DESC, f.updated_at DESC, f.id DESC LIMIT ?",
+            COLUMNS, filter
+        ))
+        .context("Failed to query facts")?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| from_row(row, timezone))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read facts")
+}
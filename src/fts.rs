@@ -0,0 +1,258 @@
+//! FTS5 helpers: building `MATCH` expressions from user input, and the tokenizer indexes are built
+//! with. Input is always quoted as FTS5 strings, so characters like `-`, `:`, `*` or `"` search as
+//! text instead of failing as query syntax.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::config::env_flag;
+use crate::models::ReindexedTable;
+
+/// FTS5's own default for `NEAR` when no distance is given.
+pub const DEFAULT_NEAR_DISTANCE: u32 = 10;
+
+/// Quote `term` as an FTS5 string. A term of several words matches them as a phrase.
+pub fn quote(term: &str) -> String {
+    format!("\"{}\"", term.replace('"', "\"\""))
+}
+
+/// Keywords with surrounding whitespace, blanks and repeats removed, in their original order.
+pub fn clean_keywords(keywords: &[String]) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for keyword in keywords.iter().map(|k| k.trim()).filter(|k| !k.is_empty()) {
+        if !cleaned.iter().any(|seen| seen == keyword) {
+            cleaned.push(keyword.to_string());
+        }
+    }
+    cleaned
+}
+
+/// Tokenizer for full-text indexes the server creates or rebuilds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tokenizer {
+    /// Keep "café" and "cafe" apart instead of matching either for both (`MCP_LUNA_FTS_KEEP_DIACRITICS`)
+    pub keep_diacritics: bool,
+    /// Match English word forms: "running" finds "run" (`MCP_LUNA_FTS_PORTER`)
+    pub porter: bool,
+    /// Index character trigrams instead of words, for text without spaces between words such as
+    /// Chinese and Japanese (`MCP_LUNA_FTS_TRIGRAM`). Takes precedence over `porter`.
+    pub trigram: bool,
+}
+
+impl Tokenizer {
+    pub fn from_env() -> Self {
+        Self {
+            keep_diacritics: env_flag("MCP_LUNA_FTS_KEEP_DIACRITICS"),
+            porter: env_flag("MCP_LUNA_FTS_PORTER"),
+            trigram: env_flag("MCP_LUNA_FTS_TRIGRAM"),
+        }
+    }
+
+    /// Value of the FTS5 `tokenize` option, e.g. `porter unicode61 remove_diacritics 2`.
+    pub fn spec(&self) -> String {
+        if self.trigram {
+            return if self.keep_diacritics { "trigram" } else { "trigram remove_diacritics 1" }.to_string();
+        }
+        let unicode61 = format!("unicode61 remove_diacritics {}", if self.keep_diacritics { 0 } else { 2 });
+        if self.porter {
+            format!("porter {}", unicode61)
+        } else {
+            unicode61
+        }
+    }
+}
+
+/// Shortest term a trigram index can match. Shorter ones (most Chinese words have two characters)
+/// must be matched with `LIKE` instead.
+pub const TRIGRAM_MIN_CHARS: usize = 3;
+
+/// Whether the FTS5 table `table` indexes trigrams, going by the tokenizer it was created with.
+pub fn uses_trigram(conn: &Connection, table: &str) -> bool {
+    matches!(table_tokenizer(conn, table), Ok(Some(tokenizer)) if tokenizer.starts_with("trigram"))
+}
+
+/// `LIKE` pattern matching `text` anywhere, for use with `ESCAPE '\'`.
+pub fn like_pattern(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// `term` quoted, and with `prefix` also matching longer words: `"kube"*` matches "kubernetes".
+/// In a multi-word term only the last word is a prefix.
+pub fn term(text: &str, prefix: bool) -> String {
+    if prefix {
+        format!("{}*", quote(text))
+    } else {
+        quote(text)
+    }
+}
+
+/// `"kw1" OR "kw2" OR …`; `None` when there are no keywords.
+pub fn any_of(keywords: &[String], prefix: bool) -> Option<String> {
+    (!keywords.is_empty()).then(|| keywords.iter().map(|k| term(k, prefix)).collect::<Vec<_>>().join(" OR "))
+}
+
+/// `"words in order"`; `None` when `text` is blank.
+pub fn phrase(text: &str, prefix: bool) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| term(text, prefix))
+}
+
+/// `NEAR("a" "b", distance)`: all terms within `distance` words of each other. `None` without terms.
+pub fn near(terms: &[String], distance: Option<u32>, prefix: bool) -> Option<String> {
+    let terms = clean_keywords(terms);
+    (!terms.is_empty()).then(|| {
+        format!(
+            "NEAR({}, {})",
+            terms.iter().map(|t| term(t, prefix)).collect::<Vec<_>>().join(" "),
+            distance.unwrap_or(DEFAULT_NEAR_DISTANCE)
+        )
+    })
+}
+
+/// Expressions that must all match; `None` when there are none.
+pub fn all_of(expressions: impl IntoIterator<Item = String>) -> Option<String> {
+    let expressions: Vec<String> = expressions.into_iter().map(|e| format!("({})", e)).collect();
+    (!expressions.is_empty()).then(|| expressions.join(" AND "))
+}
+
+/// Recreate the FTS5 table `table` with `tokenizer`, keeping its columns and other options, and index
+/// its rows again. Tables that already use `tokenizer` are left alone. `None` when `table` doesn't
+/// exist. Drops and recreates the table, so run it inside a transaction.
+pub fn retokenize(conn: &Connection, table: &str, tokenizer: &Tokenizer) -> Result<Option<ReindexedTable>> {
+    let Some(args) = table_arguments(conn, table)? else {
+        return Ok(None);
+    };
+    let previous_tokenizer = tokenizer_of(&args);
+    let spec = tokenizer.spec();
+    let rebuilt = previous_tokenizer != spec;
+
+    if rebuilt {
+        let content = option_value(&args, "content");
+        anyhow::ensure!(content.as_deref() != Some(""), "{} is contentless and can't be rebuilt", table);
+        let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+        let columns: Vec<String> = stmt
+            .query_map([table], |row| row.get::<_, String>(0))?
+            .map(|name| name.map(|name| format!("\"{}\"", name.replace('"', "\"\""))))
+            .collect::<rusqlite::Result<_>>()
+            .with_context(|| format!("Failed to read columns of {}", table))?;
+        let mut new_args: Vec<String> = args.iter().filter(|a| option_name(a) != "tokenize").cloned().collect();
+        new_args.push(format!("tokenize='{}'", spec));
+
+        // An index with its own copy of the text loses it on DROP; keep the rows across the rebuild.
+        // External-content indexes re-read their content table instead.
+        let own_content = content.is_none();
+        if own_content {
+            conn.execute_batch(&format!(
+                "CREATE TEMP TABLE retokenize_rows AS SELECT rowid AS retokenize_rowid, {} FROM {}",
+                columns.join(", "),
+                table
+            ))
+            .with_context(|| format!("Failed to copy {}", table))?;
+        }
+        conn.execute_batch(&format!(
+            "DROP TABLE {0}; CREATE VIRTUAL TABLE {0} USING fts5({1});",
+            table,
+            new_args.join(", ")
+        ))
+        .with_context(|| format!("Failed to recreate {}", table))?;
+        if own_content {
+            conn.execute_batch(&format!(
+                "INSERT INTO {0}(rowid, {1}) SELECT retokenize_rowid, {1} FROM temp.retokenize_rows; DROP TABLE temp.retokenize_rows;",
+                table,
+                columns.join(", ")
+            ))
+        } else {
+            conn.execute_batch(&format!("INSERT INTO {0}({0}) VALUES('rebuild')", table))
+        }
+        .with_context(|| format!("Failed to reindex {}", table))?;
+    }
+
+    let rows = conn
+        .query_row(&format!("SELECT COUNT(*) FROM {}_docsize", table), [], |row| row.get(0))
+        .unwrap_or(0);
+    Ok(Some(ReindexedTable {
+        table: table.to_string(),
+        previous_tokenizer,
+        rebuilt,
+        rows,
+    }))
+}
+
+/// Tokenizer the FTS5 table `table` was created with; `None` when it doesn't exist.
+pub fn table_tokenizer(conn: &Connection, table: &str) -> Result<Option<String>> {
+    Ok(table_arguments(conn, table)?.map(|args| tokenizer_of(&args)))
+}
+
+/// Whether rows leave the FTS5 table `table` through its `'delete'` command, given the indexed
+/// values: true for external-content and contentless tables, which keep no copy of the text.
+pub fn needs_delete_command(conn: &Connection, table: &str) -> Result<bool> {
+    Ok(table_arguments(conn, table)?.is_some_and(|args| option_value(&args, "content").is_some()))
+}
+
+fn table_arguments(conn: &Connection, table: &str) -> Result<Option<Vec<String>>> {
+    let sql: Option<String> = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to inspect schema")?;
+    sql.map(|sql| fts5_arguments(&sql).with_context(|| format!("{} is not an FTS5 table", table)))
+        .transpose()
+}
+
+/// Normalized `tokenize` option; FTS5 defaults to `unicode61`.
+fn tokenizer_of(args: &[String]) -> String {
+    option_value(args, "tokenize")
+        .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_else(|| "unicode61".to_string())
+}
+
+/// Arguments of a `CREATE VIRTUAL TABLE … USING fts5(…)` statement: columns and `name=value` options.
+fn fts5_arguments(sql: &str) -> Option<Vec<String>> {
+    let module = sql.to_ascii_lowercase().find("using fts5")?;
+    let open = module + sql[module..].find('(')?;
+    let close = sql.rfind(')')?;
+    let inner = sql.get(open + 1..close)?;
+
+    // Split on commas outside quotes
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in inner.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '[') => quote = Some(']'),
+            (None, ',') => {
+                args.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    args.push(current.trim().to_string());
+    Some(args.into_iter().filter(|a| !a.is_empty()).collect())
+}
+
+/// Option name of a `name=value` argument, lowercased; empty for column arguments.
+fn option_name(arg: &str) -> String {
+    arg.split_once('=').map(|(name, _)| name.trim().to_ascii_lowercase()).unwrap_or_default()
+}
+
+/// Unquoted value of option `name`.
+fn option_value(args: &[String], name: &str) -> Option<String> {
+    let arg = args.iter().find(|a| option_name(a) == name)?;
+    let value = arg.split_once('=')?.1.trim();
+    let unquoted = match value.chars().next() {
+        Some(q @ ('\'' | '"')) if value.len() >= 2 && value.ends_with(q) => {
+            value[1..value.len() - 1].replace(&format!("{0}{0}", q), &q.to_string())
+        }
+        _ => value.to_string(),
+    };
+    Some(unquoted)
+}
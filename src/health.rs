@@ -0,0 +1,102 @@
+//! Health check for supervisors and container orchestrators: the database opens, the tables the
+//! server needs exist and a query on them succeeds. Unlike `get_database_info` it counts nothing,
+//! so it stays cheap enough to poll on large databases.
+
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+use crate::db::{self, migrations};
+use crate::models::{HealthCheck, HealthReport};
+
+/// Cosmic LLM tables every conversation tool reads.
+const CONVERSATION_TABLES: &[&str] = &["conversations", "messages"];
+
+/// Tables the server creates on startup; a read-only server never creates them.
+const MEMORY_TABLES: &[&str] = &["schema_version", "memory", "memory_fts", "messages_fts"];
+
+/// Check the database at `path` on a connection of its own, so a wedged service connection
+/// doesn't hide a database that opens fine, nor the other way round. `read_only` servers don't
+/// need the memory tables or an up-to-date schema.
+pub fn check(path: &str, read_only: bool) -> HealthReport {
+    let started = Instant::now();
+    let mut checks = Vec::new();
+    match db::open_read_only(path).and_then(|conn| {
+        // Opening is lazy; reading the schema is what touches the file
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+            .context("Failed to read database schema")?;
+        Ok(conn)
+    }) {
+        Ok(conn) => {
+            checks.push(passed("open", None));
+            checks.push(tables_check(&conn, read_only));
+            checks.push(query_check(&conn));
+            checks.push(schema_check(&conn, read_only));
+        }
+        Err(e) => checks.push(failed("open", format!("{:#}", e))),
+    }
+    let healthy = checks.iter().all(|check| check.ok);
+    HealthReport {
+        status: if healthy { "ok" } else { "unhealthy" }.to_string(),
+        healthy,
+        path: path.to_string(),
+        checks,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn passed(name: &str, detail: Option<String>) -> HealthCheck {
+    HealthCheck { name: name.to_string(), ok: true, detail }
+}
+
+fn failed(name: &str, detail: String) -> HealthCheck {
+    HealthCheck { name: name.to_string(), ok: false, detail: Some(detail) }
+}
+
+fn tables_check(conn: &Connection, read_only: bool) -> HealthCheck {
+    let required = CONVERSATION_TABLES.iter().chain(if read_only { &[][..] } else { MEMORY_TABLES });
+    let mut missing = Vec::new();
+    for table in required {
+        match conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [table],
+            |row| row.get::<_, bool>(0),
+        ) {
+            Ok(true) => {}
+            Ok(false) => missing.push(*table),
+            Err(e) => return failed("tables", format!("Failed to inspect schema: {}", e)),
+        }
+    }
+    if missing.is_empty() {
+        passed("tables", None)
+    } else {
+        failed("tables", format!("Missing tables: {}", missing.join(", ")))
+    }
+}
+
+fn query_check(conn: &Connection) -> HealthCheck {
+    let newest: Result<Option<i64>> = conn
+        .query_row("SELECT MAX(id) FROM messages", [], |row| row.get(0))
+        .context("Failed to query messages");
+    match newest {
+        Ok(Some(id)) => passed("query", Some(format!("Newest message: {}", id))),
+        Ok(None) => passed("query", Some("No messages yet".to_string())),
+        Err(e) => failed("query", format!("{:#}", e)),
+    }
+}
+
+fn schema_check(conn: &Connection, read_only: bool) -> HealthCheck {
+    let latest = migrations::latest_version();
+    match migrations::current_version(conn) {
+        Ok(version) if version > latest => failed(
+            "schema",
+            format!("Schema version {} is newer than this build's {}", version, latest),
+        ),
+        Ok(version) if version < latest && !read_only => {
+            failed("schema", format!("Schema version {} is behind {}", version, latest))
+        }
+        Ok(version) => passed("schema", Some(format!("Schema version {}", version))),
+        Err(e) => failed("schema", format!("{:#}", e)),
+    }
+}
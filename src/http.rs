@@ -0,0 +1,235 @@
+//! Streamable HTTP transport, for several MCP clients served by one process at once.
+//!
+//! Each session opened with an `initialize` request gets its own [`ConversationService`], kept in
+//! the session map under its `Mcp-Session-Id`, so everything the service holds per session –
+//! confirmation tokens, the call budget, cached conversations, resource subscriptions, the log
+//! level and the scope set with `set_session_scope` – stays with the client that opened it. A
+//! session ends when the client deletes it or after [`HttpConfig::session_idle`] without a
+//! message. Sessions share the database through SQLite, like several stdio servers would.
+//!
+//! With bearer tokens configured, every request must carry one in its `Authorization` header,
+//! and each token has its own session map, so a session only answers to the token that opened
+//! it. Read-only tokens get sessions in [read-only mode](Config::read_only). Without tokens the
+//! server only listens on loopback addresses, and refuses requests whose `Host` isn't one, which
+//! a web page could otherwise send through DNS rebinding.
+//!
+//! [`HEALTH_PATH`] answers without a token, with the report of [`health::check`], for load
+//! balancers and orchestrators to poll.
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::extract::{Request, State};
+use axum::http::{header, uri::Authority, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use ring::digest::{digest, Digest, SHA256};
+use rmcp::transport::streamable_http_server::session::local::{LocalSessionManager, SessionConfig};
+use rmcp::transport::{StreamableHttpServerConfig, StreamableHttpService};
+use tokio::net::TcpListener;
+
+use crate::config::{env_parse, Config};
+use crate::health;
+use crate::service::ConversationService;
+
+/// Path the MCP endpoint is served on.
+pub const MCP_PATH: &str = "/mcp";
+
+/// Path of the health check, served without authentication.
+pub const HEALTH_PATH: &str = "/healthz";
+
+const DEFAULT_SESSION_IDLE_SECS: u64 = 30 * 60;
+
+/// Shortest bearer token accepted, so a guessable one can't be configured by accident.
+const MIN_TOKEN_LEN: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// Serve over streamable HTTP on this address instead of stdio (`MCP_LUNA_HTTP_ADDR`, `--http`)
+    pub addr: Option<SocketAddr>,
+    /// How long a session may go without a message before it is closed
+    /// (`MCP_LUNA_HTTP_SESSION_IDLE_SECS`)
+    pub session_idle: Duration,
+    /// Bearer token with read-write access (`MCP_LUNA_HTTP_TOKEN`)
+    pub token: Option<String>,
+    /// File of further tokens, one `read-only <token>` or `read-write <token>` per line
+    /// (`MCP_LUNA_HTTP_TOKENS_FILE`)
+    pub tokens_file: Option<PathBuf>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            addr: None,
+            session_idle: Duration::from_secs(DEFAULT_SESSION_IDLE_SECS),
+            token: None,
+            tokens_file: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn from_env() -> Self {
+        Self {
+            addr: env_parse("MCP_LUNA_HTTP_ADDR"),
+            session_idle: Duration::from_secs(
+                env_parse::<u64>("MCP_LUNA_HTTP_SESSION_IDLE_SECS")
+                    .filter(|s| *s > 0)
+                    .unwrap_or(DEFAULT_SESSION_IDLE_SECS),
+            ),
+            token: std::env::var("MCP_LUNA_HTTP_TOKEN").ok().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()),
+            tokens_file: std::env::var_os("MCP_LUNA_HTTP_TOKENS_FILE").filter(|p| !p.is_empty()).map(PathBuf::from),
+        }
+    }
+}
+
+/// What a bearer token lets its sessions do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Sessions in read-only mode: the tools that write aren't listed
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Bearer tokens from the configured token and tokens file, with their access.
+pub fn load_tokens(config: &HttpConfig) -> Result<Vec<(String, Access)>> {
+    let mut tokens: Vec<(String, Access)> = config.token.iter().map(|token| (token.clone(), Access::ReadWrite)).collect();
+    if let Some(path) = &config.tokens_file {
+        tokens.extend(read_tokens_file(path)?);
+    }
+    for (token, _) in &tokens {
+        anyhow::ensure!(token.len() >= MIN_TOKEN_LEN, "HTTP bearer tokens must be at least {} characters long", MIN_TOKEN_LEN);
+    }
+    Ok(tokens)
+}
+
+/// Tokens listed in `path`; blank lines and lines starting with `#` are skipped.
+fn read_tokens_file(path: &Path) -> Result<Vec<(String, Access)>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut tokens = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let access = match line.split_once(char::is_whitespace) {
+            Some(("read-only", token)) => (token.trim().to_string(), Access::ReadOnly),
+            Some(("read-write", token)) => (token.trim().to_string(), Access::ReadWrite),
+            _ => anyhow::bail!("{} line {}: expected 'read-only <token>' or 'read-write <token>'", path.display(), n + 1),
+        };
+        tokens.push(access);
+    }
+    Ok(tokens)
+}
+
+/// A bearer token's digest, and the sessions opened with it.
+struct Credential {
+    digest: Digest,
+    service: StreamableHttpService<ConversationService>,
+}
+
+/// Serve MCP sessions on `listener` at [`MCP_PATH`], and the health check at [`HEALTH_PATH`],
+/// until the listener fails. Every session opens its own service on `db_path` with `config`.
+pub async fn serve(listener: TcpListener, db_path: &str, config: Config) -> Result<()> {
+    let addr = listener.local_addr().context("Failed to read the listening address")?;
+    let tokens = load_tokens(&config.http)?;
+    let health = {
+        let (db_path, read_only) = (db_path.to_string(), config.read_only);
+        axum::routing::get(move || healthz(db_path.clone(), read_only))
+    };
+    let router = if tokens.is_empty() {
+        anyhow::ensure!(
+            addr.ip().is_loopback(),
+            "Refusing to serve HTTP without authentication on {}; listen on a loopback address or set MCP_LUNA_HTTP_TOKEN",
+            addr
+        );
+        axum::Router::new()
+            .route_service(MCP_PATH, sessions(db_path, config))
+            .route(HEALTH_PATH, health)
+            .layer(middleware::from_fn(loopback_host))
+    } else {
+        let credentials: Vec<Credential> = tokens
+            .into_iter()
+            .map(|(token, access)| {
+                let config = Config { read_only: config.read_only || access == Access::ReadOnly, ..config.clone() };
+                Credential { digest: digest(&SHA256, token.as_bytes()), service: sessions(db_path, config) }
+            })
+            .collect();
+        axum::Router::new()
+            .route(MCP_PATH, axum::routing::any(authorized))
+            .with_state(Arc::new(credentials))
+            .route(HEALTH_PATH, health)
+    };
+    eprintln!("Serving MCP on http://{}{}", addr, MCP_PATH);
+    axum::serve(listener, router).await.context("HTTP server failed")
+}
+
+/// Sessions with services on `db_path` and `config`, in a session map of their own.
+fn sessions(db_path: &str, config: Config) -> StreamableHttpService<ConversationService> {
+    let sessions = LocalSessionManager {
+        session_config: SessionConfig { keep_alive: Some(config.http.session_idle), ..Default::default() },
+        ..Default::default()
+    };
+    let db_path = db_path.to_string();
+    StreamableHttpService::new(
+        move || ConversationService::new(&db_path, config.clone()).map_err(std::io::Error::other),
+        Arc::new(sessions),
+        StreamableHttpServerConfig::default(),
+    )
+}
+
+/// Hand the request to the sessions of the bearer token it carries; without a known one, refuse it.
+async fn authorized(State(credentials): State<Arc<Vec<Credential>>>, request: Request) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim_start().split_once(' '))
+        // Authentication schemes are case-insensitive (RFC 7235)
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+        .map(|(_, token)| digest(&SHA256, token.trim().as_bytes()));
+    // Digests, not the tokens themselves, are compared, so the comparison's timing reveals nothing
+    let credential = presented.and_then(|presented| credentials.iter().find(|c| c.digest.as_ref() == presented.as_ref()));
+    match credential {
+        Some(credential) => credential.service.handle(request).await.map(axum::body::Body::new),
+        None => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Unauthorized: pass a valid bearer token",
+        )
+            .into_response(),
+    }
+}
+
+/// The report of [`health::check`] on `db_path`, with 503 Service Unavailable when it failed.
+async fn healthz(db_path: String, read_only: bool) -> Response {
+    match tokio::task::spawn_blocking(move || health::check(&db_path, read_only)).await {
+        Ok(report) => {
+            let status = if report.healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+            (status, axum::Json(report)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Health check failed: {}", e)).into_response(),
+    }
+}
+
+/// Refuse requests addressed to a host other than this machine.
+async fn loopback_host(request: Request, next: Next) -> Response {
+    let host = request.headers().get(header::HOST).and_then(|host| host.to_str().ok()).unwrap_or_default();
+    if is_loopback_host(host) {
+        next.run(request).await
+    } else {
+        (StatusCode::FORBIDDEN, "Forbidden: Host must be a loopback address").into_response()
+    }
+}
+
+/// Whether a `Host` header names this machine: `localhost` or a loopback address, with any port.
+fn is_loopback_host(host: &str) -> bool {
+    let Ok(authority) = host.parse::<Authority>() else {
+        return false;
+    };
+    let host = authority.host().trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
@@ -0,0 +1,141 @@
+//! Importer for the OpenAI ChatGPT data export (`conversations.json`).
+//!
+//! Each exported conversation stores its messages as a tree (`mapping`) to support edits and
+//! regenerations; we import the branch that ends at `current_node`, i.e. what the user last saw.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::{ImportedConversation, ImportedMessage};
+
+/// Prefix for conversation IDs generated from ChatGPT conversation IDs.
+pub const ID_PREFIX: &str = "chatgpt-";
+
+#[derive(Debug, Deserialize)]
+struct ExportConversation {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    mapping: HashMap<String, Node>,
+    #[serde(default)]
+    current_node: Option<String>,
+    #[serde(default)]
+    conversation_id: Option<String>,
+    #[serde(default)]
+    id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Node {
+    #[serde(default)]
+    message: Option<ExportMessage>,
+    #[serde(default)]
+    parent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportMessage {
+    author: Author,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    content: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Author {
+    role: String,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// Parse a ChatGPT `conversations.json` document.
+pub fn parse(json: &str) -> Result<Vec<ImportedConversation>> {
+    let export: Vec<ExportConversation> =
+        serde_json::from_str(json).context("Not a ChatGPT conversations.json export")?;
+    Ok(export.into_iter().filter_map(convert).collect())
+}
+
+fn convert(conv: ExportConversation) -> Option<ImportedConversation> {
+    let source_id = conv.conversation_id.clone().or_else(|| conv.id.clone())?;
+    let created_at = conv.create_time.map(|t| t as i64).unwrap_or(0);
+
+    // Walk from the current leaf up to the root, then reverse into chronological order
+    let mut branch = Vec::new();
+    let mut cursor = conv.current_node.clone();
+    while let Some(node_id) = cursor {
+        let Some(node) = conv.mapping.get(&node_id) else { break };
+        if let Some(message) = &node.message {
+            branch.push(message);
+        }
+        cursor = node.parent.clone();
+        if branch.len() > conv.mapping.len() {
+            break; // cycle guard for malformed exports
+        }
+    }
+    branch.reverse();
+
+    let mut messages = Vec::new();
+    let mut pending_reasoning: Option<String> = None;
+    for message in branch {
+        let Some(content) = &message.content else { continue };
+        let content_type = content.get("content_type").and_then(Value::as_str).unwrap_or("text");
+        if content_type == "thoughts" || content_type == "reasoning_recap" {
+            // Reasoning is exported as separate nodes; attach it to the next assistant message
+            let text = extract_text(content);
+            if !text.is_empty() {
+                pending_reasoning = Some(match pending_reasoning.take() {
+                    Some(prev) => format!("{}\n\n{}", prev, text),
+                    None => text,
+                });
+            }
+            continue;
+        }
+        let text = extract_text(content);
+        if text.trim().is_empty() || message.author.role == "system" {
+            continue;
+        }
+        let role = message.author.role.clone();
+        let reasoning_content = if role == "assistant" { pending_reasoning.take() } else { None };
+        messages.push(ImportedMessage {
+            tool_name: if role == "tool" { message.author.name.clone() } else { None },
+            role,
+            content: text,
+            created_at: message.create_time.map(|t| t as i64).unwrap_or(created_at),
+            reasoning_content,
+        });
+    }
+
+    Some(ImportedConversation {
+        id: format!("{}{}", ID_PREFIX, source_id),
+        title: conv.title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| "Imported conversation".to_string()),
+        created_at,
+        messages,
+    })
+}
+
+/// Flatten the various ChatGPT content shapes (`parts`, `text`, `thoughts`) into plain text.
+fn extract_text(content: &Value) -> String {
+    if let Some(parts) = content.get("parts").and_then(Value::as_array) {
+        return parts
+            .iter()
+            .filter_map(|p| p.as_str().map(str::to_string).or_else(|| p.get("text").and_then(Value::as_str).map(str::to_string)))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    if let Some(thoughts) = content.get("thoughts").and_then(Value::as_array) {
+        return thoughts
+            .iter()
+            .filter_map(|t| t.get("content").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+    if let Some(text) = content.get("content").and_then(Value::as_str) {
+        return text.to_string();
+    }
+    content.get("text").and_then(Value::as_str).unwrap_or_default().to_string()
+}
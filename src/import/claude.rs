@@ -0,0 +1,116 @@
+//! Importer for the Anthropic Claude data export (`conversations.json`).
+//!
+//! Messages carry both a flattened `text` and structured `content` blocks; we prefer the blocks so
+//! thinking ends up in `reasoning_content` and tool results become their own `tool` messages.
+
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ImportedConversation, ImportedMessage};
+
+/// Prefix for conversation IDs generated from Claude conversation UUIDs.
+pub const ID_PREFIX: &str = "claude-";
+
+#[derive(Debug, Deserialize)]
+struct ExportConversation {
+    uuid: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    chat_messages: Vec<ExportMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportMessage {
+    #[serde(default)]
+    sender: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    content: Vec<Value>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+/// Parse a Claude `conversations.json` document.
+pub fn parse(json: &str) -> Result<Vec<ImportedConversation>> {
+    let export: Vec<ExportConversation> =
+        serde_json::from_str(json).context("Not a Claude conversations.json export")?;
+    Ok(export.into_iter().map(convert).collect())
+}
+
+fn convert(conv: ExportConversation) -> ImportedConversation {
+    let created_at = parse_timestamp(conv.created_at.as_deref()).unwrap_or(0);
+    let mut messages = Vec::new();
+
+    for message in conv.chat_messages {
+        let role = match message.sender.as_str() {
+            "human" => "user",
+            "assistant" => "assistant",
+            _ => continue,
+        };
+        let message_created_at = parse_timestamp(message.created_at.as_deref()).unwrap_or(created_at);
+
+        let mut text = Vec::new();
+        let mut reasoning = Vec::new();
+        let mut tool_results = Vec::new();
+        for block in &message.content {
+            match block.get("type").and_then(Value::as_str) {
+                Some("text") => text.extend(block.get("text").and_then(Value::as_str)),
+                Some("thinking") => reasoning.extend(block.get("thinking").and_then(Value::as_str)),
+                Some("tool_result") => tool_results.push(ImportedMessage {
+                    role: "tool".to_string(),
+                    content: block_text(block.get("content")),
+                    created_at: message_created_at,
+                    tool_name: block.get("name").and_then(Value::as_str).map(str::to_string),
+                    reasoning_content: None,
+                }),
+                _ => {}
+            }
+        }
+        let content = if text.is_empty() {
+            message.text.unwrap_or_default()
+        } else {
+            text.join("\n")
+        };
+
+        if !content.trim().is_empty() {
+            messages.push(ImportedMessage {
+                role: role.to_string(),
+                content,
+                created_at: message_created_at,
+                tool_name: None,
+                reasoning_content: (!reasoning.is_empty()).then(|| reasoning.join("\n\n")),
+            });
+        }
+        messages.extend(tool_results.into_iter().filter(|m| !m.content.trim().is_empty()));
+    }
+
+    ImportedConversation {
+        id: format!("{}{}", ID_PREFIX, conv.uuid),
+        title: conv.name.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| "Imported conversation".to_string()),
+        created_at,
+        messages,
+    }
+}
+
+/// Tool result content is either a string or a list of `{type: "text", text}` blocks.
+fn block_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn parse_timestamp(value: Option<&str>) -> Option<i64> {
+    DateTime::parse_from_rfc3339(value?).ok().map(|t| t.timestamp())
+}
@@ -0,0 +1,145 @@
+//! Importers that map other assistants' export formats onto the Cosmic conversations/messages schema.
+
+pub mod chatgpt;
+pub mod claude;
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::db;
+use crate::models::ImportReport;
+
+/// A conversation parsed from an external export, ready to be written to the database.
+#[derive(Debug)]
+pub struct ImportedConversation {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub messages: Vec<ImportedMessage>,
+}
+
+#[derive(Debug)]
+pub struct ImportedMessage {
+    pub role: String,
+    pub content: String,
+    pub created_at: i64,
+    pub tool_name: Option<String>,
+    pub reasoning_content: Option<String>,
+}
+
+/// Supported export formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    ChatGpt,
+    Claude,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::ChatGpt => "chatgpt",
+            Format::Claude => "claude",
+        }
+    }
+}
+
+/// Guess the export format from the shape of the first conversation in the document.
+pub fn detect_format(json: &str) -> Result<Format> {
+    let doc: Value = serde_json::from_str(json).context("Import file is not valid JSON")?;
+    let first = doc
+        .as_array()
+        .and_then(|items| items.first())
+        .ok_or_else(|| anyhow!("Import file must be a non-empty array of conversations"))?;
+    if first.get("mapping").is_some() {
+        Ok(Format::ChatGpt)
+    } else if first.get("chat_messages").is_some() {
+        Ok(Format::Claude)
+    } else {
+        Err(anyhow!("Unrecognized export format (expected ChatGPT or Claude conversations.json)"))
+    }
+}
+
+/// Parse `json` as the given export format.
+pub fn parse(format: Format, json: &str) -> Result<Vec<ImportedConversation>> {
+    match format {
+        Format::ChatGpt => chatgpt::parse(json),
+        Format::Claude => claude::parse(json),
+    }
+}
+
+/// Write imported conversations in a single transaction.
+/// Conversation IDs are derived from the source's own IDs, so re-importing a newer export of the
+/// same history only appends messages newer than what is already stored; identical conversations
+/// are skipped.
+pub fn write_conversations(
+    conn: &mut Connection,
+    source: &str,
+    conversations: Vec<ImportedConversation>,
+) -> Result<ImportReport> {
+    db::init_conversation_schema(conn)?;
+    let sync_fts = !db::messages_fts_has_triggers(conn)?;
+
+    let tx = conn.transaction().context("Failed to start import transaction")?;
+    let mut report = ImportReport {
+        source: source.to_string(),
+        conversations_imported: 0,
+        messages_imported: 0,
+        conversations_updated: 0,
+        conversations_skipped: 0,
+    };
+    for conversation in conversations {
+        // Latest stored message timestamp, or None when the conversation is new
+        let stored_until: Option<i64> = tx
+            .query_row(
+                "SELECT COALESCE((SELECT MAX(created_at) FROM messages WHERE conversation_id = c.id), c.created_at) FROM conversations c WHERE c.id = ?",
+                [&conversation.id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let new_messages: Vec<&ImportedMessage> = conversation
+            .messages
+            .iter()
+            .filter(|m| stored_until.is_none_or(|until| m.created_at > until))
+            .collect();
+        if new_messages.is_empty() {
+            report.conversations_skipped += 1;
+            continue;
+        }
+
+        if stored_until.is_some() {
+            report.conversations_updated += 1;
+        } else {
+            tx.execute(
+                "INSERT INTO conversations (id, title, created_at, title_generated, profile_name) VALUES (?1, ?2, ?3, 1, NULL)",
+                params![conversation.id, conversation.title, conversation.created_at],
+            )
+            .context("Failed to insert conversation")?;
+            report.conversations_imported += 1;
+        }
+        for message in new_messages {
+            tx.execute(
+                "INSERT INTO messages (conversation_id, role, content, created_at, tool_name, reasoning_content) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    conversation.id,
+                    message.role,
+                    message.content,
+                    message.created_at,
+                    message.tool_name,
+                    message.reasoning_content
+                ],
+            )
+            .context("Failed to insert message")?;
+            if sync_fts {
+                tx.execute(
+                    "INSERT INTO messages_fts (rowid, content) VALUES (?1, ?2)",
+                    params![tx.last_insert_rowid(), message.content],
+                )
+                .context("Failed to index message")?;
+            }
+            report.messages_imported += 1;
+        }
+    }
+    tx.commit().context("Failed to commit import")?;
+    Ok(report)
+}
@@ -0,0 +1,109 @@
+//! Background indexer keeping derived data current while Cosmic LLM writes new messages. The
+//! full-text indexes follow `messages` through triggers; message languages, embeddings and cached
+//! keyphrases don't, so a task polls the database and brings them up to date.
+//!
+//! Like the resource watcher it compares `PRAGMA data_version`, which changes whenever another
+//! connection commits, and then the newest message id against a watermark, so an idle database
+//! costs one pragma per tick.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use whatlang::Detector;
+
+use crate::config::Config;
+use crate::embeddings::{self, Embedder};
+use crate::fts::Tokenizer;
+use crate::{db, keywords, languages};
+
+/// Messages embedded per tick at most, so a pending backfill (see `index-embeddings`) is caught
+/// up on gradually instead of in one long write.
+const EMBEDDINGS_PER_TICK: usize = 256;
+const EMBEDDING_BATCH_SIZE: usize = 64;
+
+struct Indexer {
+    db_path: String,
+    tokenizer: Tokenizer,
+    conn: Option<Connection>,
+    data_version: Option<i64>,
+    /// Newest message id already indexed
+    watermark: i64,
+    detector: Detector,
+    embedder: Option<Embedder>,
+}
+
+impl Indexer {
+    /// Index new messages if there are any. Errors leave the watermark where it was, so the next
+    /// tick tries again.
+    fn step(&mut self) -> Result<()> {
+        let conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => db::open(&self.db_path, &self.tokenizer)?,
+        };
+        let conn = self.conn.insert(conn);
+        let version: i64 = conn.query_row("PRAGMA data_version", [], |row| row.get(0))?;
+        if self.data_version == Some(version) {
+            return Ok(());
+        }
+        let newest: i64 = conn.query_row("SELECT COALESCE(MAX(id), 0) FROM messages", [], |row| row.get(0))?;
+        if newest > self.watermark {
+            languages::index(conn, &self.detector)?;
+            if let Some(embedder) = &self.embedder {
+                embeddings::backfill(conn, embedder, EMBEDDING_BATCH_SIZE, Some(EMBEDDINGS_PER_TICK), |_| {})?;
+            }
+            for (conversation_id, count) in keywords::stale(conn)? {
+                let keyphrases = keywords::extract(conn, &conversation_id, count)?;
+                keywords::save(conn, &conversation_id, &keyphrases)?;
+            }
+            self.watermark = newest;
+        }
+        self.data_version = Some(version);
+        Ok(())
+    }
+}
+
+/// Index new messages every `config.index_interval` until the process exits. Uses its own
+/// connection; does nothing in read-only mode or when the interval is off.
+pub async fn run(db_path: String, config: Config) {
+    let Some(interval) = config.index_interval.filter(|_| !config.read_only) else {
+        return;
+    };
+    let mut indexer = Some(Indexer {
+        db_path,
+        tokenizer: config.fts_tokenizer.clone(),
+        conn: None,
+        data_version: None,
+        watermark: 0,
+        detector: languages::detector(&config.languages),
+        // Embeddings are only kept current when an endpoint is configured
+        embedder: Embedder::new(&config.embedding).ok(),
+    });
+    // The first pass waits an interval, leaving startup (and migrating a new database) to the server
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        ticker.tick().await;
+        let Some(mut taken) = indexer.take() else {
+            return;
+        };
+        let stepped = tokio::task::spawn_blocking(move || {
+            let result = taken.step();
+            if result.is_err() {
+                // Database briefly unavailable; reopen on the next tick
+                taken.conn = None;
+            }
+            (taken, result)
+        })
+        .await;
+        match stepped {
+            Ok((taken, result)) => {
+                if let Err(e) = result {
+                    eprintln!("Background indexing failed: {:#}", e);
+                }
+                indexer = Some(taken);
+            }
+            Err(e) => {
+                eprintln!("Background indexing task failed: {}", e);
+                return;
+            }
+        }
+    }
+}
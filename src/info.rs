@@ -0,0 +1,152 @@
+//! Database health summary, so a server pointed at the wrong file is easy to spot.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::path::Path;
+
+use crate::db::migrations;
+use crate::models::{DatabaseInfo, FtsIndexStatus, TableCount};
+
+/// Full-text indexes and the content tables they mirror.
+const FTS_INDEXES: &[(&str, &str)] = &[
+    ("messages_fts", "messages"),
+    ("memory_fts", "memory"),
+    ("titles_fts", "conversations"),
+    ("tool_activity_fts", "messages"),
+    ("reasoning_fts", "messages"),
+    ("journal_fts", "journal"),
+    ("bookmarks_fts", "bookmarks"),
+    ("snippets_fts", "snippets"),
+];
+
+/// Collect size, schema and index status for the database at `path` opened as `conn`.
+pub fn database_info(conn: &Connection, path: &Path) -> Result<DatabaseInfo> {
+    let tables = table_counts(conn)?;
+    let fts_indexes = fts_status(conn)?;
+    summarize(conn, path, tables, fts_indexes)
+}
+
+/// Row counts of regular tables, leaving out virtual tables and their shadow tables.
+pub fn table_counts(conn: &Connection) -> Result<Vec<TableCount>> {
+    let virtual_tables = virtual_tables(conn)?;
+    let mut stmt = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")
+        .context("Failed to list tables")?;
+    let names: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+
+    let mut counts = Vec::new();
+    for name in names {
+        // Shadow tables are named after their virtual table, e.g. messages_fts_data
+        if virtual_tables.iter().any(|v| name == *v || name.starts_with(&format!("{}_", v))) {
+            continue;
+        }
+        counts.push(TableCount {
+            rows: count_rows(conn, &name)?,
+            table: name,
+        });
+    }
+    Ok(counts)
+}
+
+/// Existence, coverage and trigger status of each full-text index.
+pub fn fts_status(conn: &Connection) -> Result<Vec<FtsIndexStatus>> {
+    let virtual_tables = virtual_tables(conn)?;
+    let mut statuses = Vec::new();
+    for (index, content) in FTS_INDEXES {
+        let exists = virtual_tables.iter().any(|v| v == index);
+        let content_exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [content],
+                |row| row.get(0),
+            )
+            .context("Failed to inspect schema")?;
+        let content_rows = if content_exists { count_rows(conn, content)? } else { 0 };
+        let indexed_rows = if exists {
+            count_rows(conn, &format!("{}_docsize", index))?
+        } else {
+            0
+        };
+        let has_triggers: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ?1 AND sql LIKE '%' || ?2 || '%')",
+                [content, index],
+                |row| row.get(0),
+            )
+            .context("Failed to inspect triggers")?;
+        statuses.push(FtsIndexStatus {
+            name: index.to_string(),
+            exists,
+            indexed_rows,
+            content_rows,
+            in_sync: exists && indexed_rows == content_rows,
+            has_triggers,
+        });
+    }
+    Ok(statuses)
+}
+
+/// Combine table counts and index status with file-level details, and derive warnings.
+pub fn summarize(
+    conn: &Connection,
+    path: &Path,
+    tables: Vec<TableCount>,
+    fts_indexes: Vec<FtsIndexStatus>,
+) -> Result<DatabaseInfo> {
+    let pragma = |name: &str| -> Result<i64> {
+        conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+            .with_context(|| format!("Failed to read PRAGMA {}", name))
+    };
+    let journal_mode: String = conn
+        .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+        .context("Failed to read journal mode")?;
+
+    let mut warnings = Vec::new();
+    let has_table = |name: &str| tables.iter().any(|t| t.table == name);
+    if !has_table("conversations") || !has_table("messages") {
+        warnings.push("No conversations/messages tables: COSMIC_LLM_DB_PATH may not point at the Cosmic LLM database".to_string());
+    } else if tables.iter().any(|t| t.table == "messages" && t.rows == 0) {
+        warnings.push("The messages table is empty: conversation searches will return nothing".to_string());
+    }
+    for fts in &fts_indexes {
+        if !fts.exists {
+            warnings.push(format!("{} does not exist: full-text search over it is unavailable", fts.name));
+        } else if !fts.in_sync {
+            warnings.push(format!(
+                "{} indexes {} of {} rows: searches miss the rest",
+                fts.name, fts.indexed_rows, fts.content_rows
+            ));
+        }
+    }
+
+    Ok(DatabaseInfo {
+        path: path.display().to_string(),
+        file_size_bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        page_count: pragma("page_count")?,
+        page_size: pragma("page_size")?,
+        freelist_count: pragma("freelist_count")?,
+        journal_mode,
+        schema_version: migrations::current_version(conn)?,
+        tables,
+        fts_indexes,
+        warnings,
+        error: None,
+    })
+}
+
+fn virtual_tables(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND sql LIKE 'CREATE VIRTUAL%'")
+        .context("Failed to list virtual tables")?;
+    let names = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(names)
+}
+
+fn count_rows(conn: &Connection, table: &str) -> Result<i64> {
+    conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\"")), [], |row| row.get(0))
+        .with_context(|| format!("Failed to count rows of {}", table))
+}
@@ -0,0 +1,85 @@
+//! Journal: a running daily log of what was worked on, written by the assistant in its own words
+//! and kept apart from the verbatim conversation logs. Entries are filed under a calendar day in
+//! the configured timezone and indexed for full-text search.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, Row};
+
+use crate::dates::Timezone;
+use crate::db::unix_now;
+use crate::fts;
+use crate::models::JournalEntry;
+
+const COLUMNS: &str = "j.id, j.date, j.text, j.created_at";
+
+fn from_row(row: &Row, timezone: Timezone) -> rusqlite::Result<JournalEntry> {
+    let created_at: i64 = row.get(3)?;
+    Ok(JournalEntry {
+        id: row.get(0)?,
+        date: row.get(1)?,
+        text: row.get(2)?,
+        created_at,
+        created_at_iso: timezone.iso(created_at),
+        error: None,
+    })
+}
+
+/// Add `text` to the journal of `date` (`YYYY-MM-DD`).
+pub fn append(conn: &Connection, date: &str, text: &str, timezone: Timezone) -> Result<JournalEntry> {
+    let text = text.trim();
+    anyhow::ensure!(!text.is_empty(), "Journal text must not be empty");
+    conn.execute(
+        "INSERT INTO journal (date, text, created_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![date, text, unix_now()],
+    )
+    .context("Failed to append to journal")?;
+    conn.query_row(
+        &format!("SELECT {} FROM journal j WHERE j.id = ?", COLUMNS),
+        [conn.last_insert_rowid()],
+        |row| from_row(row, timezone),
+    )
+    .context("Failed to read journal entry")
+}
+
+/// The latest `limit` entries dated from `since` to `until` (`YYYY-MM-DD`, inclusive) that mention
+/// any of `keywords`, in the order they were written.
+pub fn entries(
+    conn: &Connection,
+    since: Option<&str>,
+    until: Option<&str>,
+    keywords: &[String],
+    limit: u32,
+    timezone: Timezone,
+) -> Result<Vec<JournalEntry>> {
+    let mut conditions = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    let fts_query = fts::any_of(&fts::clean_keywords(keywords), false);
+    if let Some(query) = &fts_query {
+        conditions.push("journal_fts MATCH ?");
+        params.push(query.clone());
+    }
+    if let Some(since) = since {
+        conditions.push("j.date >= ?");
+        params.push(since.to_string());
+    }
+    if let Some(until) = until {
+        conditions.push("j.date <= ?");
+        params.push(until.to_string());
+    }
+    let join = if fts_query.is_some() { "JOIN journal_fts ON j.id = journal_fts.rowid" } else { "" };
+    let filter = if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) };
+    params.push(limit.to_string());
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM journal j {} {} ORDER BY j.date DESC, j.id DESC LIMIT ?",
+            COLUMNS, join, filter
+        ))
+        .context("Failed to query journal")?;
+    let mut entries = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| from_row(row, timezone))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read journal")?;
+    entries.reverse();
+    Ok(entries)
+}
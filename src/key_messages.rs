@@ -0,0 +1,79 @@
+//! Heuristic detection of the messages worth reading when skimming a long conversation.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Message;
+
+/// Share of a conversation's assistant answers that count as "long", by length rank.
+const LONG_ANSWER_SHARE: f64 = 0.1;
+/// Answers shorter than this are never "long", however they rank.
+const LONG_ANSWER_MIN_CHARS: usize = 400;
+
+/// Phrases that tend to mark a decision, conclusion or fix (matched case-insensitively).
+const DECISION_KEYWORDS: &[&str] = &[
+    "decided",
+    "decision",
+    "let's go with",
+    "we'll go with",
+    "going with",
+    "agreed",
+    "conclusion",
+    "in summary",
+    "to summarize",
+    "the fix is",
+    "the solution is",
+    "final answer",
+    "root cause",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyReason {
+    /// Among the longest assistant answers in the conversation
+    LongAnswer,
+    /// Assistant reply to a user question
+    AnswersQuestion,
+    /// Contains decision or conclusion wording
+    Decision,
+}
+
+/// Why each message is a key message; an empty list means it isn't one. Parallel to `messages`,
+/// which must be in chronological order.
+pub fn detect(messages: &[Message]) -> Vec<Vec<KeyReason>> {
+    let mut reasons = vec![Vec::new(); messages.len()];
+
+    let mut answers: Vec<(usize, usize)> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.role == "assistant")
+        .map(|(i, m)| (i, m.content.chars().count()))
+        .collect();
+    answers.sort_by_key(|(_, len)| std::cmp::Reverse(*len));
+    let long_count = ((answers.len() as f64 * LONG_ANSWER_SHARE).ceil() as usize).max(1);
+    for (i, len) in answers.into_iter().take(long_count) {
+        if len >= LONG_ANSWER_MIN_CHARS {
+            reasons[i].push(KeyReason::LongAnswer);
+        }
+    }
+
+    let mut pending_question = false;
+    for (i, message) in messages.iter().enumerate() {
+        match message.role.as_str() {
+            "user" => pending_question = message.content.contains('?'),
+            // The final answer follows any tool round-trips, so skip to the first non-empty reply
+            "assistant" if pending_question && !message.content.trim().is_empty() => {
+                reasons[i].push(KeyReason::AnswersQuestion);
+                pending_question = false;
+            }
+            _ => {}
+        }
+
+        let lower = message.content.to_lowercase();
+        if message.role != "tool" && DECISION_KEYWORDS.iter().any(|k| lower.contains(k)) {
+            reasons[i].push(KeyReason::Decision);
+        }
+    }
+
+    reasons
+}
@@ -0,0 +1,142 @@
+//! Keyphrase extraction with RAKE (Rapid Automatic Keyword Extraction): candidate phrases are the
+//! runs of words between stopwords and punctuation, and each phrase scores the sum of its words'
+//! degree-to-frequency ratios, so words that keep appearing inside longer phrases rank high. Every
+//! occurrence counts, so a phrase the conversation keeps coming back to beats a one-off.
+//! Results can be cached in `conversation_keywords`, where they describe conversations whose
+//! auto-generated titles are too vague to find them by.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db::unix_now;
+use crate::models::Keyphrase;
+
+/// Longer runs of content words are rarely meaningful phrases; they are split no further and skipped.
+const MAX_PHRASE_WORDS: usize = 4;
+
+/// Words that end a candidate phrase.
+const STOPWORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "also", "am", "an", "and", "any", "are", "as", "at",
+    "be", "because", "been", "before", "being", "below", "between", "both", "but", "by", "can", "could", "did",
+    "do", "does", "doing", "done", "down", "during", "each", "else", "even", "every", "few", "for", "from",
+    "further", "get", "gets", "got", "had", "has", "have", "having", "he", "her", "here", "hers", "him", "his",
+    "how", "however", "i", "if", "in", "into", "is", "it", "its", "itself", "just", "let", "like", "make", "may",
+    "me", "might", "more", "most", "much", "must", "my", "need", "no", "nor", "not", "now", "of", "off", "ok",
+    "okay", "on", "once", "one", "only", "or", "other", "our", "ours", "out", "over", "own", "please", "same",
+    "see", "she", "should", "so", "some", "such", "sure", "than", "thank", "thanks", "that", "the", "their",
+    "them", "then", "there", "these", "they", "this", "those", "through", "to", "too", "try", "under", "until",
+    "up", "us", "use", "used", "using", "very", "want", "was", "we", "well", "were", "what", "when", "where",
+    "which", "while", "who", "whom", "why", "will", "with", "would", "yes", "yet", "you", "your", "yours",
+];
+
+/// Candidate phrases of `text`: maximal runs of non-stopwords within a sentence or clause.
+/// Fenced code blocks are skipped; identifiers and output don't make keyphrases.
+fn candidates(text: &str) -> Vec<Vec<String>> {
+    let mut phrases = Vec::new();
+    let mut in_code = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        for clause in line.split(|c: char| !(c.is_alphanumeric() || c.is_whitespace() || c == '-' || c == '\'')) {
+            let mut phrase: Vec<String> = Vec::new();
+            for word in clause.split_whitespace().map(|w| w.trim_matches(|c| c == '-' || c == '\'').to_lowercase()) {
+                if word.is_empty() || STOPWORDS.contains(&word.as_str()) || word.chars().all(|c| !c.is_alphabetic()) {
+                    phrases.push(std::mem::take(&mut phrase));
+                } else {
+                    phrase.push(word);
+                }
+            }
+            phrases.push(phrase);
+        }
+    }
+    phrases.retain(|phrase| !phrase.is_empty() && phrase.len() <= MAX_PHRASE_WORDS);
+    phrases
+}
+
+/// The `limit` best keyphrases of `texts`, highest score first.
+pub fn rake<'a>(texts: impl IntoIterator<Item = &'a str>, limit: usize) -> Vec<Keyphrase> {
+    let phrases: Vec<Vec<String>> = texts.into_iter().flat_map(candidates).collect();
+    let mut frequency: HashMap<&str, f64> = HashMap::new();
+    let mut degree: HashMap<&str, f64> = HashMap::new();
+    for phrase in &phrases {
+        for word in phrase {
+            *frequency.entry(word).or_default() += 1.0;
+            *degree.entry(word).or_default() += phrase.len() as f64;
+        }
+    }
+    let mut scored: HashMap<String, Keyphrase> = HashMap::new();
+    for phrase in &phrases {
+        let text = phrase.join(" ");
+        let score: f64 = phrase.iter().map(|word| degree[word.as_str()] / frequency[word.as_str()]).sum();
+        let keyphrase = scored.entry(text.clone()).or_insert(Keyphrase { phrase: text, score: 0.0, occurrences: 0 });
+        keyphrase.score += score;
+        keyphrase.occurrences += 1;
+    }
+    let mut keyphrases: Vec<Keyphrase> = scored.into_values().collect();
+    keyphrases.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.occurrences.cmp(&a.occurrences))
+            .then_with(|| a.phrase.cmp(&b.phrase))
+    });
+    keyphrases.truncate(limit);
+    keyphrases
+}
+
+/// The `limit` best keyphrases of the title and user and assistant messages of conversation
+/// `conversation_id`.
+pub fn extract(conn: &Connection, conversation_id: &str, limit: usize) -> Result<Vec<Keyphrase>> {
+    let title: String = conn
+        .query_row("SELECT title FROM conversations WHERE id = ?", [conversation_id], |row| row.get(0))
+        .optional()
+        .context("Failed to look up conversation")?
+        .with_context(|| format!("No conversation with ID '{}'", conversation_id))?;
+    let mut stmt = conn
+        .prepare("SELECT content FROM messages WHERE conversation_id = ? AND role IN ('user', 'assistant') ORDER BY id")
+        .context("Failed to read conversation")?;
+    let contents = stmt
+        .query_map([conversation_id], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read conversation")?;
+    Ok(rake(std::iter::once(title.as_str()).chain(contents.iter().map(String::as_str)), limit))
+}
+
+/// Replace the cached keyphrases of conversation `conversation_id`.
+pub fn save(conn: &Connection, conversation_id: &str, keyphrases: &[Keyphrase]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM conversation_keywords WHERE conversation_id = ?", [conversation_id])
+        .context("Failed to clear keyphrases")?;
+    let now = unix_now();
+    for keyphrase in keyphrases {
+        tx.execute(
+            "INSERT INTO conversation_keywords (conversation_id, phrase, score, created_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![conversation_id, keyphrase.phrase, keyphrase.score, now],
+        )
+        .context("Failed to store keyphrases")?;
+    }
+    tx.commit().context("Failed to store keyphrases")
+}
+
+/// Conversations whose cached keyphrases predate their newest message, with how many keyphrases
+/// were cached for each.
+pub fn stale(conn: &Connection) -> Result<Vec<(String, usize)>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT k.conversation_id, COUNT(*) FROM conversation_keywords k
+             GROUP BY k.conversation_id
+             HAVING MAX(k.created_at) < (SELECT MAX(m.created_at) FROM messages m WHERE m.conversation_id = k.conversation_id)",
+        )
+        .context("Failed to look up cached keyphrases")?;
+    let stale = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to look up cached keyphrases")?;
+    Ok(stale)
+}
@@ -0,0 +1,86 @@
+//! Message language detection with whatlang. Detected languages are kept in `message_languages`,
+//! so searches can filter by language; they are ISO 639-3 codes such as `eng` and `pol`.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use whatlang::{Detector, Lang};
+
+/// Shorter messages ("ok", "thanks!") don't tell their language reliably.
+const MIN_DETECT_CHARS: usize = 20;
+/// Detections whatlang is less sure of are discarded, unless it calls them reliable.
+const MIN_CONFIDENCE: f64 = 0.5;
+
+/// Detector choosing among `languages` (codes or English names, see [`resolve`]); among every
+/// language whatlang knows when none are given. Choosing among fewer languages is far more
+/// accurate on short messages.
+pub fn detector(languages: &[String]) -> Detector {
+    let allowed: Vec<Lang> = languages
+        .iter()
+        .filter_map(|language| resolve(language))
+        .filter_map(Lang::from_code)
+        .collect();
+    if allowed.is_empty() {
+        Detector::new()
+    } else {
+        Detector::with_allowlist(allowed)
+    }
+}
+
+/// Language of `text`, or `None` when it is too short or too ambiguous to tell.
+pub fn detect(detector: &Detector, text: &str) -> Option<&'static str> {
+    if text.trim().chars().count() < MIN_DETECT_CHARS {
+        return None;
+    }
+    detector
+        .detect(text)
+        .filter(|info| info.is_reliable() || info.confidence() >= MIN_CONFIDENCE)
+        .map(|info| info.lang().code())
+}
+
+/// ISO 639-3 code of `language`, given as a code (`pol`) or an English name (`Polish`), ignoring
+/// case. `None` for languages detection doesn't know.
+pub fn resolve(language: &str) -> Option<&'static str> {
+    let language = language.trim().to_lowercase();
+    Lang::from_code(language.as_str())
+        .or_else(|| Lang::all().iter().copied().find(|lang| lang.eng_name().to_lowercase() == language))
+        .map(|lang| lang.code())
+}
+
+/// Detect the language of every user and assistant message not looked at yet with `detector`, in
+/// one transaction. Returns how many messages were looked at.
+pub fn index(conn: &Connection, detector: &Detector) -> Result<usize> {
+    // Immediate: a deferred transaction that reads before writing fails outright, without waiting,
+    // when another connection writes in between
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let pending = {
+        let mut stmt = tx
+            .prepare(
+                r#"
+                SELECT m.id, m.content FROM messages m
+                WHERE m.role IN ('user', 'assistant')
+                  AND NOT EXISTS (SELECT 1 FROM message_languages l WHERE l.message_id = m.id)
+                "#,
+            )
+            .context("Failed to read messages")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read messages")?
+    };
+    for (id, content) in &pending {
+        // Undetectable messages are stored too, so they aren't looked at again
+        tx.execute(
+            "INSERT INTO message_languages (message_id, language) VALUES (?1, ?2)",
+            rusqlite::params![id, detect(detector, content)],
+        )
+        .context("Failed to store message language")?;
+    }
+    tx.commit().context("Failed to store message languages")?;
+    Ok(pending.len())
+}
+
+/// SQL predicate (over alias `m`) for messages detected as language `code`, and its parameter.
+pub fn condition(code: &str) -> (String, Vec<String>) {
+    (
+        "m.id IN (SELECT message_id FROM message_languages WHERE language = ?)".to_string(),
+        vec![code.to_string()],
+    )
+}
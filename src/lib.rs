@@ -0,0 +1,69 @@
+//! Conversation history and long-term memory for Luna AI, served over MCP.
+//!
+//! The binary runs the MCP server; the library exposes the same building blocks (and, with the
+//! `client` feature, a typed client) to other Rust programs.
+
+pub mod archive;
+pub mod attachments;
+pub mod backup;
+pub mod bookmarks;
+pub mod budget;
+pub mod cancel;
+pub mod chunks;
+pub mod compact;
+pub mod config;
+pub mod confirm;
+pub mod conflicts;
+pub mod conversation_cache;
+pub mod conversation_pins;
+pub mod dates;
+pub mod db;
+pub mod embeddings;
+pub mod erase;
+pub mod export;
+pub mod facts;
+pub mod fts;
+pub mod health;
+pub mod http;
+pub mod import;
+pub mod indexer;
+pub mod info;
+pub mod journal;
+pub mod keywords;
+pub mod languages;
+pub mod key_messages;
+pub mod locate;
+pub mod logging;
+pub mod maintenance;
+pub mod markdown;
+pub mod memory_history;
+pub mod merge;
+pub mod models;
+pub mod people;
+pub mod pii;
+pub mod preferences;
+pub mod reminders;
+pub mod projects;
+pub mod prompts;
+pub mod queries;
+pub mod redact;
+pub mod related;
+pub mod resources;
+pub mod saved_searches;
+pub mod scratchpad;
+pub mod search_history;
+pub mod seed;
+pub mod service;
+pub mod shape;
+pub mod snippets;
+pub mod synonyms;
+pub mod tags;
+pub mod text;
+pub mod titles;
+pub mod tokens;
+pub mod tool_results;
+pub mod tools;
+pub mod topics;
+
+#[cfg(feature = "client")]
+pub mod luna_memory_client;
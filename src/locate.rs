@@ -0,0 +1,106 @@
+//! Finding the Cosmic LLM database when no path is configured.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::db::encryption;
+
+/// Directory names the Cosmic LLM app has kept its data under.
+const APP_DIRS: &[&str] = &["cosmic_llm", "cosmic-llm"];
+const DB_EXTENSION: &str = "db";
+
+/// Per-user data directory: `$XDG_DATA_HOME` or `~/.local/share` on Linux and other Unixes,
+/// `%APPDATA%` on Windows, `~/Library/Application Support` on macOS.
+pub fn data_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return env_dir("APPDATA");
+    }
+    let home = env_dir("HOME");
+    if cfg!(target_os = "macos") {
+        return home.map(|h| h.join("Library").join("Application Support"));
+    }
+    env_dir("XDG_DATA_HOME").or_else(|| home.map(|h| h.join(".local").join("share")))
+}
+
+/// Directories that may hold the app's database: the app directories under the platform data
+/// directory, and on Linux the same inside each Flatpak sandbox (`~/.var/app/<id>/data`).
+pub fn search_dirs() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = data_dir().into_iter().collect();
+    if cfg!(target_os = "linux") {
+        if let Some(home) = env_dir("HOME") {
+            if let Ok(apps) = std::fs::read_dir(home.join(".var").join("app")) {
+                roots.extend(apps.flatten().map(|app| app.path().join("data")));
+            }
+        }
+    }
+    roots
+        .iter()
+        .flat_map(|root| APP_DIRS.iter().map(move |app| root.join(app)))
+        .collect()
+}
+
+/// Conversation databases found in `search_dirs()`, most recently modified first. A file counts
+/// when it has the `.db` extension and a `conversations` table, which rules out unrelated files.
+pub fn discover_dbs() -> Vec<PathBuf> {
+    let mut found: Vec<(SystemTime, PathBuf)> = search_dirs()
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == DB_EXTENSION) && has_conversations(path))
+        .map(|path| {
+            let modified = std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (modified, path)
+        })
+        .collect();
+    found.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    found.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Pick the database path: an explicit `--db` wins, then `COSMIC_LLM_DB_PATH`, then the newest
+/// database `discover_dbs()` finds. The automatic choice is logged to stderr.
+pub fn resolve_db_path(cli_db: Option<PathBuf>) -> Result<String> {
+    let path = match cli_db.or_else(|| env_dir("COSMIC_LLM_DB_PATH")) {
+        Some(path) => path,
+        None => {
+            let mut found = discover_dbs().into_iter();
+            let Some(path) = found.next() else {
+                let searched: Vec<String> = search_dirs().iter().map(|d| d.display().to_string()).collect();
+                anyhow::bail!(
+                    "No database configured: pass --db or set COSMIC_LLM_DB_PATH (searched {})",
+                    if searched.is_empty() { "nothing: no data directory".to_string() } else { searched.join(", ") }
+                );
+            };
+            eprintln!("Using database {} (pass --db or set COSMIC_LLM_DB_PATH to choose another)", path.display());
+            for other in found {
+                eprintln!("  also found {}", other.display());
+            }
+            path
+        }
+    };
+    path.to_str()
+        .map(str::to_string)
+        .with_context(|| format!("Database path {} is not valid UTF-8", path.display()))
+}
+
+fn has_conversations(path: &Path) -> bool {
+    let Ok(conn) = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY) else {
+        return false;
+    };
+    encryption::unlock(&conn).is_ok()
+        && conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'conversations')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(false)
+}
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from)
+}
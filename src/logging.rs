@@ -0,0 +1,88 @@
+//! MCP logging: structured `notifications/message` sent to the client, filtered by the level it
+//! picked with `logging/setLevel`. stderr goes nowhere when a GUI client spawns the server, so
+//! this is how tool activity becomes visible there.
+
+use rmcp::model::{CallToolResult, LoggingLevel, LoggingMessageNotificationParam};
+use rmcp::{Peer, RoleServer};
+use serde_json::Value;
+use std::sync::Mutex;
+
+/// Logger name attached to every notification.
+pub const LOGGER: &str = "mcp_luna_history";
+
+/// Level used until the client sends `logging/setLevel`: failures only, no per-call noise.
+pub const DEFAULT_LEVEL: LoggingLevel = LoggingLevel::Warning;
+
+/// Parse a level name as used by `logging/setLevel` (`debug`, `info`, ..., `emergency`).
+pub fn parse_level(name: &str) -> Option<LoggingLevel> {
+    serde_json::from_value(Value::String(name.trim().to_ascii_lowercase())).ok()
+}
+
+/// Severity rank; `LoggingLevel` itself has no ordering.
+fn severity(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+/// Minimum level the client wants to receive.
+pub struct ClientLog {
+    level: Mutex<LoggingLevel>,
+}
+
+impl ClientLog {
+    pub fn new(level: LoggingLevel) -> Self {
+        Self { level: Mutex::new(level) }
+    }
+
+    pub fn set_level(&self, level: LoggingLevel) {
+        *self.level.lock().unwrap_or_else(|e| e.into_inner()) = level;
+    }
+
+    pub fn enabled(&self, level: LoggingLevel) -> bool {
+        severity(level) >= severity(*self.level.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Send `data` to the client when `level` passes its filter. Delivery failures are ignored;
+    /// logging must never fail the request it describes.
+    pub async fn log(&self, peer: &Peer<RoleServer>, level: LoggingLevel, data: Value) {
+        if !self.enabled(level) {
+            return;
+        }
+        let _ = peer
+            .notify_logging_message(LoggingMessageNotificationParam {
+                level,
+                logger: Some(LOGGER.to_string()),
+                data,
+            })
+            .await;
+    }
+}
+
+/// Rows a tool returned: the length of the structured result's lists (`results`, `items`,
+/// `messages`, ...), summed when there are several. `None` for results without a list.
+pub fn result_rows(result: &CallToolResult) -> Option<usize> {
+    match result.structured_content.as_ref()? {
+        Value::Array(items) => Some(items.len()),
+        Value::Object(fields) => {
+            let lists: Vec<usize> = fields
+                .values()
+                .filter_map(|v| v.as_array().map(Vec::len))
+                .collect();
+            (!lists.is_empty()).then(|| lists.iter().sum())
+        }
+        _ => None,
+    }
+}
+
+/// Error a tool folded into its structured result (the `error` field most responses carry).
+pub fn result_error(result: &CallToolResult) -> Option<&str> {
+    result.structured_content.as_ref()?.get("error")?.as_str()
+}
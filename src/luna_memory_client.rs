@@ -0,0 +1,1571 @@
+//! Typed client for talking to the server from other Rust programs without hand-rolling MCP JSON.
+//!
+//! ```no_run
+//! # async fn demo() -> anyhow::Result<()> {
+//! use mcp_luna_history::luna_memory_client::LunaMemoryClient;
+//! use mcp_luna_history::models::{SearchMemoryRequest, StoreMemoryRequest};
+//!
+//! let client = LunaMemoryClient::spawn("mcp_luna_history", "/path/to/conversations.db").await?;
+//! client.call(StoreMemoryRequest::new("Prefers helix").category("preferences").importance(8)).await?;
+//! let hits = client.call(SearchMemoryRequest::new(["helix"])).await?;
+//! println!("{} memories", hits.items.len());
+//! client.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use rmcp::model::{CallToolRequestParam, CallToolResult};
+use rmcp::service::RunningService;
+use rmcp::transport::TokioChildProcess;
+use rmcp::{RoleClient, ServiceExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ffi::OsStr;
+use tokio::process::Command;
+
+use crate::dates::DateInput;
+use crate::models::*;
+
+/// A request that maps onto one server tool and its typed response.
+pub trait ToolRequest: Serialize {
+    const TOOL: &'static str;
+    type Response: DeserializeOwned;
+}
+
+macro_rules! tool_requests {
+    ($($request:ty => $tool:literal: $response:ty,)*) => {
+        $(
+            impl ToolRequest for $request {
+                const TOOL: &'static str = $tool;
+                type Response = $response;
+            }
+        )*
+    };
+}
+
+/// Request for `get_failure_stats`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct GetFailureStatsRequest {}
+
+/// Request for `list_saved_searches`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct ListSavedSearchesRequest {}
+
+/// Request for `get_pinned_memories`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct GetPinnedMemoriesRequest {}
+
+/// Request for `list_preferences`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct ListPreferencesRequest {}
+
+/// Request for `list_projects`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct ListProjectsRequest {}
+
+/// Request for `list_tags`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct ListTagsRequest {}
+
+/// Request for `list_synonyms`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct ListSynonymsRequest {}
+
+/// Request for `list_memory_categories`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct ListMemoryCategoriesRequest {}
+
+/// Request for `health`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct HealthRequest {}
+
+/// Request for `get_database_info`, which takes no parameters.
+#[derive(Debug, Default, Serialize)]
+pub struct GetDatabaseInfoRequest {}
+
+tool_requests! {
+    SearchConversationsRequest => "search_conversations": SearchResultsResponse,
+    AdvancedSearchRequest => "advanced_search": AdvancedSearchResponse,
+    SaveSearchRequest => "save_search": SavedSearch,
+    ListSavedSearchesRequest => "list_saved_searches": SavedSearchesResponse,
+    RunSavedSearchRequest => "run_saved_search": SavedSearchRun,
+    GetSearchHistoryRequest => "get_search_history": SearchHistoryResponse,
+    GetConversationRequest => "get_conversation": Conversation,
+    GetConversationChunkRequest => "get_conversation_chunk": ConversationChunk,
+    GetKeyMessagesRequest => "get_key_messages": KeyMessagesResponse,
+    SearchTitlesRequest => "search_conversation_titles": ConversationSummariesResponse,
+    ListConversationsRequest => "list_conversations": ConversationSummariesResponse,
+    GetMessageRequest => "get_message": Message,
+    SearchFailuresRequest => "search_failures": FailureSearchResponse,
+    SearchToolActivityRequest => "search_tool_activity": ToolActivityResponse,
+    FindToolCallsRequest => "find_tool_calls": ToolActivityResponse,
+    GetFailureStatsRequest => "get_failure_stats": FailureStatsResponse,
+    ToolUsageReportRequest => "get_tool_usage_report": ToolUsageReport,
+    TagConversationRequest => "tag_conversation": ConversationTagsResponse,
+    UntagConversationRequest => "untag_conversation": ConversationTagsResponse,
+    FindRelatedConversationsRequest => "find_related_conversations": RelatedConversationsResponse,
+    ExtractKeywordsRequest => "extract_keywords": KeywordsResponse,
+    SuggestTitleRequest => "suggest_title": TitleSuggestions,
+    UpdateTitleRequest => "update_title": UpdateTitleResponse,
+    PinConversationRequest => "pin_conversation": PinConversationResponse,
+    UnpinConversationRequest => "unpin_conversation": PinConversationResponse,
+    PreviewDeleteConversationRequest => "preview_delete_conversation": DeleteConversationPreview,
+    DeleteConversationRequest => "delete_conversation": DeleteConversationResponse,
+    RestoreConversationRequest => "restore_conversation": RestoreConversationResponse,
+    PreviewRedactMessageRequest => "preview_redact_message": RedactMessagePreview,
+    RedactMessageRequest => "redact_message": RedactMessageResponse,
+    ArchiveConversationsRequest => "archive_conversations": ArchiveConversationsResponse,
+    ClusterTopicsRequest => "cluster_topics": TopicClusteringReport,
+    ListTagsRequest => "list_tags": TagsResponse,
+    AddSynonymsRequest => "add_synonyms": SynonymsResponse,
+    RemoveSynonymsRequest => "remove_synonyms": SynonymsResponse,
+    ListSynonymsRequest => "list_synonyms": SynonymSetsResponse,
+    SuggestSynonymsRequest => "suggest_synonyms": SynonymSuggestions,
+    StoreMemoryRequest => "store_memory": MemoryEntry,
+    SearchMemoryRequest => "search_memory": MemorySearchResponse,
+    SearchMemoryByCategoryRequest => "search_memory_by_category": MemorySearchResponse,
+    GetPinnedMemoriesRequest => "get_pinned_memories": MemorySearchResponse,
+    PinMemoryRequest => "pin_memory": PinMemoryResponse,
+    StoreAttachmentRequest => "store_attachment": StoreAttachmentResponse,
+    GetAttachmentRequest => "get_attachment": GetAttachmentResponse,
+    SetPreferenceRequest => "set_preference": Preference,
+    GetPreferenceRequest => "get_preference": Preference,
+    ListPreferencesRequest => "list_preferences": PreferencesResponse,
+    CreateProjectRequest => "create_project": Project,
+    ListProjectsRequest => "list_projects": ProjectsResponse,
+    AssignMemoryToProjectRequest => "assign_memory_to_project": AssignMemoryToProjectResponse,
+    AddProjectRuleRequest => "add_project_rule": ProjectRule,
+    ListProjectRulesRequest => "list_project_rules": ProjectRulesResponse,
+    ClassifyConversationsRequest => "classify_conversations": ClassifyConversationsReport,
+    ScratchpadWriteRequest => "scratchpad_write": ScratchpadResponse,
+    ScratchpadReadRequest => "scratchpad_read": ScratchpadResponse,
+    PreviewScratchpadClearRequest => "preview_scratchpad_clear": ScratchpadClearPreview,
+    ScratchpadClearRequest => "scratchpad_clear": ScratchpadClearResponse,
+    SetSessionScopeRequest => "set_session_scope": SessionScope,
+    CreateReminderRequest => "create_reminder": Reminder,
+    ListDueRemindersRequest => "list_due_reminders": RemindersResponse,
+    CompleteReminderRequest => "complete_reminder": CompleteReminderResponse,
+    AppendJournalRequest => "append_journal": JournalEntry,
+    GetJournalRequest => "get_journal": JournalResponse,
+    SaveBookmarkRequest => "save_bookmark": SaveBookmarkResponse,
+    SearchBookmarksRequest => "search_bookmarks": BookmarksResponse,
+    StoreSnippetRequest => "store_snippet": StoreSnippetResponse,
+    SearchSnippetsRequest => "search_snippets": SnippetsResponse,
+    AssertFactRequest => "assert_fact": AssertFactResponse,
+    QueryFactsRequest => "query_facts": FactsResponse,
+    ListConflictsRequest => "list_conflicts": ConflictsResponse,
+    PreviewResolveConflictRequest => "preview_resolve_conflict": ResolveConflictPreview,
+    ResolveConflictRequest => "resolve_conflict": ResolveConflictResponse,
+    RememberPersonRequest => "remember_person": PersonResponse,
+    GetPersonRequest => "get_person": PersonResponse,
+    SearchPeopleRequest => "search_people": PeopleResponse,
+    ListMemoryCategoriesRequest => "list_memory_categories": MemoryCategoriesResponse,
+    PreviewRenameCategoryRequest => "preview_rename_category": RenameCategoryPreview,
+    RenameCategoryRequest => "rename_category": RenameCategoryResponse,
+    PreviewDeleteMemoryRequest => "preview_delete_memory": DeletePreview,
+    DeleteMemoryRequest => "delete_memory": DeleteMemoryResponse,
+    HealthRequest => "health": HealthReport,
+    GetDatabaseInfoRequest => "get_database_info": DatabaseInfo,
+    ExportAllRequest => "export_all": ExportReport,
+    CreateBackupRequest => "create_backup": BackupReport,
+    MemoryHygieneReportRequest => "get_memory_hygiene_report": MemoryHygieneReport,
+    FindSimilarMemoriesRequest => "find_similar_memories": SimilarMemoriesResponse,
+    GetMemoryHistoryRequest => "get_memory_history": MemoryHistoryResponse,
+    RunMaintenanceRequest => "run_maintenance": MaintenanceReport,
+    PruneToolResultsRequest => "prune_tool_results": PruneToolResultsReport,
+    ReindexRequest => "reindex": ReindexReport,
+    ExplainSearchRequest => "explain_search": SearchExplanation,
+}
+
+/// Connected MCP client session.
+pub struct LunaMemoryClient {
+    service: RunningService<RoleClient, ()>,
+}
+
+impl LunaMemoryClient {
+    /// Spawn the server binary as a child process and talk to it over stdio.
+    pub async fn spawn(server_bin: impl AsRef<OsStr>, db_path: &str) -> Result<Self> {
+        let mut command = Command::new(server_bin);
+        command.env("COSMIC_LLM_DB_PATH", db_path);
+        let transport = TokioChildProcess::new(command).context("Failed to spawn MCP server")?;
+        let service = ().serve(transport).await.context("MCP handshake failed")?;
+        Ok(Self { service })
+    }
+
+    /// Connect to a server exposed over streamable HTTP, e.g. `http://127.0.0.1:8080/mcp`.
+    #[cfg(feature = "client-http")]
+    pub async fn connect_http(url: &str) -> Result<Self> {
+        use rmcp::transport::StreamableHttpClientTransport;
+
+        let transport = StreamableHttpClientTransport::from_uri(url.to_string());
+        let service = ().serve(transport).await.context("MCP handshake failed")?;
+        Ok(Self { service })
+    }
+
+    /// Connect to a server exposed over streamable HTTP that requires a bearer token.
+    #[cfg(feature = "client-http")]
+    pub async fn connect_http_with_token(url: &str, token: &str) -> Result<Self> {
+        use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
+        use rmcp::transport::StreamableHttpClientTransport;
+
+        let config = StreamableHttpClientTransportConfig::with_uri(url.to_string()).auth_header(token.to_string());
+        let transport = StreamableHttpClientTransport::from_config(config);
+        let service = ().serve(transport).await.context("MCP handshake failed")?;
+        Ok(Self { service })
+    }
+
+    /// Call the tool behind `request` and decode its structured response.
+    pub async fn call<R: ToolRequest>(&self, request: R) -> Result<R::Response> {
+        let arguments = match serde_json::to_value(&request)? {
+            serde_json::Value::Object(map) => Some(map),
+            serde_json::Value::Null => None,
+            other => return Err(anyhow!("{} arguments must be an object, got {}", R::TOOL, other)),
+        };
+        let result = self
+            .service
+            .call_tool(CallToolRequestParam { name: R::TOOL.into(), arguments, task: None })
+            .await
+            .with_context(|| format!("Calling {} failed", R::TOOL))?;
+        decode(R::TOOL, result)
+    }
+
+    /// Shut down the session (and the child process, for spawned servers).
+    pub async fn close(self) -> Result<()> {
+        self.service.cancel().await.context("Failed to close MCP session")?;
+        Ok(())
+    }
+}
+
+fn decode<T: DeserializeOwned>(tool: &str, result: CallToolResult) -> Result<T> {
+    if result.is_error == Some(true) {
+        let message = result
+            .content
+            .iter()
+            .filter_map(|c| c.as_text().map(|t| t.text.clone()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(anyhow!("{} returned an error: {}", tool, message));
+    }
+    let value = match result.structured_content {
+        Some(value) => value,
+        None => {
+            let text = result
+                .content
+                .iter()
+                .find_map(|c| c.as_text().map(|t| t.text.clone()))
+                .ok_or_else(|| anyhow!("{} returned no content", tool))?;
+            serde_json::from_str(&text).with_context(|| format!("{} returned non-JSON content", tool))?
+        }
+    };
+    serde_json::from_value(value).with_context(|| format!("Unexpected {} response shape", tool))
+}
+
+// Request builders
+
+/// Adds a `fields(...)` builder to requests that support trimming the response.
+/// Fields left out of the selection decode as their default values.
+macro_rules! with_fields {
+    ($($request:ty),* $(,)?) => {
+        $(
+            impl $request {
+                pub fn fields<I: IntoIterator<Item = S>, S: Into<String>>(mut self, fields: I) -> Self {
+                    self.fields = Some(fields.into_iter().map(Into::into).collect());
+                    self
+                }
+            }
+        )*
+    };
+}
+
+with_fields!(
+    SearchConversationsRequest,
+    AdvancedSearchRequest,
+    GetConversationRequest,
+    SearchTitlesRequest,
+    ListConversationsRequest,
+    GetMessageRequest,
+    RunSavedSearchRequest,
+    SearchFailuresRequest,
+    SearchToolActivityRequest,
+    FindToolCallsRequest,
+    SearchMemoryRequest,
+    SearchMemoryByCategoryRequest,
+);
+
+impl SearchConversationsRequest {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(keywords: I) -> Self {
+        Self {
+            keywords: keywords.into_iter().map(Into::into).collect(),
+            phrase: None,
+            near: None,
+            prefix: false,
+            include_reasoning: false,
+            include_archive: false,
+            preview_chars: None,
+            include_full_content: false,
+            count_only: false,
+            max_per_conversation: None,
+            sort: None,
+            half_life_days: None,
+            min_score: None,
+            project: None,
+            tags: None,
+            language: None,
+            expand_synonyms: None,
+            fields: None,
+        }
+    }
+
+    /// Only messages from conversations of `project`.
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    /// Only messages from conversations with every one of `tags`.
+    pub fn tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        self.tags = Some(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only messages in `language`, e.g. "pol" or "Polish".
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Search the keywords only, not their synonyms.
+    pub fn without_synonyms(mut self) -> Self {
+        self.expand_synonyms = Some(false);
+        self
+    }
+
+    /// Only messages scoring at least `min_score`.
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    pub fn phrase(mut self, phrase: impl Into<String>) -> Self {
+        self.phrase = Some(phrase.into());
+        self
+    }
+
+    pub fn near<I: IntoIterator<Item = S>, S: Into<String>>(mut self, terms: I, distance: Option<u32>) -> Self {
+        self.near = Some(NearQuery { terms: terms.into_iter().map(Into::into).collect(), distance });
+        self
+    }
+
+    pub fn prefix(mut self) -> Self {
+        self.prefix = true;
+        self
+    }
+
+    pub fn include_reasoning(mut self) -> Self {
+        self.include_reasoning = true;
+        self
+    }
+
+    /// Also search conversations moved to the archive.
+    pub fn include_archive(mut self) -> Self {
+        self.include_archive = true;
+        self
+    }
+
+    pub fn preview_chars(mut self, chars: u32) -> Self {
+        self.preview_chars = Some(chars);
+        self
+    }
+
+    pub fn include_full_content(mut self) -> Self {
+        self.include_full_content = true;
+        self
+    }
+
+    pub fn count_only(mut self) -> Self {
+        self.count_only = true;
+        self
+    }
+
+    pub fn max_per_conversation(mut self, max: u32) -> Self {
+        self.max_per_conversation = Some(max);
+        self
+    }
+
+    pub fn sort(mut self, sort: SearchSort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Rank by relevance weighted by recency, halving every `half_life_days`.
+    pub fn recency_boosted(mut self, half_life_days: f64) -> Self {
+        self.sort = Some(SearchSort::RecencyBoosted);
+        self.half_life_days = Some(half_life_days);
+        self
+    }
+}
+
+impl AdvancedSearchRequest {
+    /// All messages, newest first; narrow them with the builders below.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keywords<I: IntoIterator<Item = S>, S: Into<String>>(mut self, keywords: I) -> Self {
+        self.keywords = keywords.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn phrase(mut self, phrase: impl Into<String>) -> Self {
+        self.phrase = Some(phrase.into());
+        self
+    }
+
+    pub fn prefix(mut self) -> Self {
+        self.prefix = true;
+        self
+    }
+
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    pub fn profile(mut self, profile_name: impl Into<String>) -> Self {
+        self.profile_name = Some(profile_name.into());
+        self
+    }
+
+    pub fn conversation(mut self, conversation_id: impl Into<String>) -> Self {
+        self.conversation_id = Some(conversation_id.into());
+        self
+    }
+
+    pub fn tool_name(mut self, tool_name: impl Into<String>) -> Self {
+        self.tool_name = Some(tool_name.into());
+        self
+    }
+
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        self.tags = Some(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Search the keywords only, not their synonyms.
+    pub fn without_synonyms(mut self) -> Self {
+        self.expand_synonyms = Some(false);
+        self
+    }
+
+    /// Only messages scoring at least `min_score`.
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    /// Messages created from `since` on: unix seconds or text like "last week" or "2024-03-15".
+    pub fn since(mut self, since: impl Into<DateInput>) -> Self {
+        self.since = Some(since.into());
+        self
+    }
+
+    /// Messages created before `until`, in the same forms as [`since`](Self::since).
+    pub fn until(mut self, until: impl Into<DateInput>) -> Self {
+        self.until = Some(until.into());
+        self
+    }
+
+    pub fn sort(mut self, sort: SearchSort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Rank by relevance weighted by recency, halving every `half_life_days`.
+    pub fn recency_boosted(mut self, half_life_days: f64) -> Self {
+        self.sort = Some(SearchSort::RecencyBoosted);
+        self.half_life_days = Some(half_life_days);
+        self
+    }
+
+    /// Page `limit` messages starting after the first `offset`.
+    pub fn page(mut self, offset: u32, limit: u32) -> Self {
+        self.offset = Some(offset);
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn preview_chars(mut self, chars: u32) -> Self {
+        self.preview_chars = Some(chars);
+        self
+    }
+}
+
+impl SaveSearchRequest {
+    pub fn new(name: impl Into<String>, search: SearchConversationsRequest) -> Self {
+        Self { name: name.into(), search }
+    }
+}
+
+impl RunSavedSearchRequest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), fields: None }
+    }
+}
+
+impl GetSearchHistoryRequest {
+    /// The latest searches, newest first.
+    pub fn recent() -> Self {
+        Self::default()
+    }
+
+    /// Earlier successful queries similar to `query`, most similar first.
+    pub fn similar_to(query: impl Into<String>) -> Self {
+        Self { similar_to: Some(query.into()), ..Self::default() }
+    }
+
+    pub fn tool(mut self, tool: impl Into<String>) -> Self {
+        self.tool = Some(tool.into());
+        self
+    }
+
+    pub fn successful_only(mut self) -> Self {
+        self.successful_only = true;
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl GetConversationRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into(), fields: None }
+    }
+}
+
+impl SearchTitlesRequest {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self { query: query.into(), sort: None, collation: None, similarity_threshold: None, fields: None }
+    }
+
+    pub fn sort(mut self, sort: ConversationSort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    pub fn collation(mut self, collation: TitleCollation) -> Self {
+        self.collation = Some(collation);
+        self
+    }
+
+    pub fn similarity_threshold(mut self, threshold: f64) -> Self {
+        self.similarity_threshold = Some(threshold);
+        self
+    }
+}
+
+impl ListConversationsRequest {
+    pub fn new() -> Self {
+        Self { limit: None, offset: None, sort: None, collation: None, tags: None, pinned_only: None, fields: None }
+    }
+
+    /// Only pinned conversations.
+    pub fn pinned_only(mut self) -> Self {
+        self.pinned_only = Some(true);
+        self
+    }
+
+    /// Only conversations with every one of `tags`.
+    pub fn tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        self.tags = Some(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn sort(mut self, sort: ConversationSort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    pub fn collation(mut self, collation: TitleCollation) -> Self {
+        self.collation = Some(collation);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+impl Default for ListConversationsRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TagConversationRequest {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(conversation_id: impl Into<String>, tags: I) -> Self {
+        Self { conversation_id: conversation_id.into(), tags: tags.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl UntagConversationRequest {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(conversation_id: impl Into<String>, tags: I) -> Self {
+        Self { conversation_id: conversation_id.into(), tags: tags.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl AddSynonymsRequest {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(term: impl Into<String>, synonyms: I) -> Self {
+        Self { term: term.into(), synonyms: synonyms.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl RemoveSynonymsRequest {
+    /// Unlink `synonyms` from `term`; all of them when empty.
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(term: impl Into<String>, synonyms: I) -> Self {
+        Self { term: term.into(), synonyms: synonyms.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl SuggestSynonymsRequest {
+    pub fn new(term: impl Into<String>) -> Self {
+        Self { term: term.into() }
+    }
+}
+
+impl FindRelatedConversationsRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into(), limit: None }
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl ExtractKeywordsRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into(), limit: None }
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl SuggestTitleRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into() }
+    }
+}
+
+impl UpdateTitleRequest {
+    pub fn new(conversation_id: impl Into<String>, title: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into(), title: title.into() }
+    }
+}
+
+impl ClusterTopicsRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of topics to form.
+    pub fn topics(mut self, topics: u32) -> Self {
+        self.topics = Some(topics);
+        self
+    }
+}
+
+impl PinConversationRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into() }
+    }
+}
+
+impl UnpinConversationRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into() }
+    }
+}
+
+impl PreviewDeleteConversationRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into() }
+    }
+}
+
+impl DeleteConversationRequest {
+    /// Move `conversation_id` to the trash.
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into(), permanent: false, confirmation_token: None }
+    }
+
+    /// Delete it for good instead.
+    pub fn permanent(mut self) -> Self {
+        self.permanent = true;
+        self
+    }
+
+    pub fn confirmation_token(mut self, token: impl Into<String>) -> Self {
+        self.confirmation_token = Some(token.into());
+        self
+    }
+}
+
+impl RestoreConversationRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into() }
+    }
+}
+
+impl PreviewRedactMessageRequest {
+    /// Blank the whole of message `message_id`.
+    pub fn new(message_id: i64) -> Self {
+        Self { message_id, text: None }
+    }
+
+    /// Only replace `text` wherever it appears in the message.
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+}
+
+impl RedactMessageRequest {
+    /// Blank the whole of message `message_id`.
+    pub fn new(message_id: i64) -> Self {
+        Self { message_id, text: None, confirmation_token: None }
+    }
+
+    /// Only replace `text` wherever it appears in the message.
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub fn confirmation_token(mut self, token: impl Into<String>) -> Self {
+        self.confirmation_token = Some(token.into());
+        self
+    }
+}
+
+impl ArchiveConversationsRequest {
+    pub fn new(before_date: impl Into<DateInput>) -> Self {
+        Self { before_date: before_date.into() }
+    }
+}
+
+impl GetMessageRequest {
+    pub fn new(message_id: i64) -> Self {
+        Self { message_id, original_tool_result: false, fields: None }
+    }
+
+    /// Return the full tool result kept when it was pruned.
+    pub fn original_tool_result(mut self) -> Self {
+        self.original_tool_result = true;
+        self
+    }
+}
+
+impl SearchFailuresRequest {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self { query: query.into(), fields: None }
+    }
+}
+
+impl SearchToolActivityRequest {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self { query: query.into(), fields: None }
+    }
+}
+
+impl FindToolCallsRequest {
+    /// All tool calls, newest first; narrow them with the builders below.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tool_name(mut self, tool_name: impl Into<String>) -> Self {
+        self.tool_name = Some(tool_name.into());
+        self
+    }
+
+    pub fn status(mut self, status: ToolStatusFilter) -> Self {
+        self.tool_status = Some(status);
+        self
+    }
+
+    pub fn query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    /// Calls made from `since` on: unix seconds or text like "last week" or "2024-03-15".
+    pub fn since(mut self, since: impl Into<DateInput>) -> Self {
+        self.since = Some(since.into());
+        self
+    }
+
+    /// Calls made before `until`, in the same forms as [`since`](Self::since).
+    pub fn until(mut self, until: impl Into<DateInput>) -> Self {
+        self.until = Some(until.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl ToolUsageReportRequest {
+    pub fn new(period: UsagePeriod) -> Self {
+        Self { period: Some(period) }
+    }
+}
+
+impl StoreMemoryRequest {
+    pub fn new(content: impl Into<String>) -> Self {
+        Self { content: content.into(), category: None, importance: None, ttl_days: None, pinned: false, key: None }
+    }
+
+    /// What the memory states a value of; a later memory with the key and other content conflicts.
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn pinned(mut self) -> Self {
+        self.pinned = true;
+        self
+    }
+
+    /// Forget the memory after `days`.
+    pub fn ttl_days(mut self, days: f64) -> Self {
+        self.ttl_days = Some(days);
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    pub fn importance(mut self, importance: i32) -> Self {
+        self.importance = Some(importance);
+        self
+    }
+}
+
+impl PinMemoryRequest {
+    pub fn new(memory_id: i64) -> Self {
+        Self { memory_id, pinned: None }
+    }
+
+    pub fn unpin(mut self) -> Self {
+        self.pinned = Some(false);
+        self
+    }
+}
+
+impl StoreAttachmentRequest {
+    /// Store `text` under the file name `name`.
+    pub fn text(name: impl Into<String>, text: impl Into<String>) -> Self {
+        Self { name: name.into(), text: Some(text.into()), ..Default::default() }
+    }
+
+    /// Store `bytes` under the file name `name`.
+    pub fn bytes(name: impl Into<String>, bytes: impl AsRef<[u8]>) -> Self {
+        use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+        Self { name: name.into(), base64: Some(BASE64.encode(bytes)), ..Default::default() }
+    }
+
+    /// Reference `path`, relative to the server's attachments directory, under the file name `name`.
+    pub fn file(name: impl Into<String>, path: impl Into<String>) -> Self {
+        Self { name: name.into(), file_path: Some(path.into()), ..Default::default() }
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    pub fn memory(mut self, memory_id: i64) -> Self {
+        self.memory_id = Some(memory_id);
+        self
+    }
+
+    pub fn message(mut self, message_id: i64) -> Self {
+        self.message_id = Some(message_id);
+        self
+    }
+}
+
+impl GetAttachmentRequest {
+    pub fn new(attachment_id: i64) -> Self {
+        Self { attachment_id }
+    }
+}
+
+impl SetPreferenceRequest {
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self { key: key.into(), value: value.into() }
+    }
+}
+
+impl GetPreferenceRequest {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self { key: key.into() }
+    }
+}
+
+impl ScratchpadWriteRequest {
+    pub fn new(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Self { name: Some(name.into()), content: content.into(), ..Default::default() }
+    }
+
+    /// Write the session's working scratchpad (see [`SetSessionScopeRequest`]).
+    pub fn working(content: impl Into<String>) -> Self {
+        Self { content: content.into(), ..Default::default() }
+    }
+
+    pub fn append(mut self) -> Self {
+        self.append = true;
+        self
+    }
+
+    pub fn ttl_hours(mut self, hours: f64) -> Self {
+        self.ttl_hours = Some(hours);
+        self
+    }
+}
+
+impl ScratchpadReadRequest {
+    /// Read one scratchpad; `ScratchpadReadRequest::default()` reads the session's working
+    /// scratchpad, or lists them all when it has none.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: Some(name.into()) }
+    }
+}
+
+impl PreviewScratchpadClearRequest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl ScratchpadClearRequest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), confirmation_token: None }
+    }
+
+    pub fn confirmation_token(mut self, token: impl Into<String>) -> Self {
+        self.confirmation_token = Some(token.into());
+        self
+    }
+}
+
+impl SetSessionScopeRequest {
+    /// Limit conversation listings and searches to `profile`; an empty name clears it.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Make `name` the working scratchpad; an empty name clears it.
+    pub fn scratchpad(mut self, name: impl Into<String>) -> Self {
+        self.scratchpad = Some(name.into());
+        self
+    }
+}
+
+impl CreateReminderRequest {
+    pub fn new(content: impl Into<String>, due_at: impl Into<DateInput>) -> Self {
+        Self { content: content.into(), due_at: due_at.into() }
+    }
+}
+
+impl ListDueRemindersRequest {
+    /// Reminders due by `now` instead of the current time, e.g. "today" for the rest of the day.
+    pub fn by(mut self, now: impl Into<DateInput>) -> Self {
+        self.now = Some(now.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl CompleteReminderRequest {
+    pub fn new(reminder_id: i64) -> Self {
+        Self { reminder_id }
+    }
+}
+
+impl AppendJournalRequest {
+    /// An entry for today.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { date: None, text: text.into() }
+    }
+
+    pub fn date(mut self, date: impl Into<DateInput>) -> Self {
+        self.date = Some(date.into());
+        self
+    }
+}
+
+impl GetJournalRequest {
+    pub fn since(mut self, since: impl Into<DateInput>) -> Self {
+        self.since = Some(since.into());
+        self
+    }
+
+    pub fn until(mut self, until: impl Into<DateInput>) -> Self {
+        self.until = Some(until.into());
+        self
+    }
+
+    pub fn keywords<I, S>(mut self, keywords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.keywords = Some(keywords.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl SaveBookmarkRequest {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into(), ..Default::default() }
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    pub fn tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        self.tags = Some(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Save the bookmark without fetching the page.
+    pub fn offline(mut self) -> Self {
+        self.fetch_metadata = Some(false);
+        self
+    }
+}
+
+impl SearchBookmarksRequest {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(keywords: I) -> Self {
+        Self { keywords: Some(keywords.into_iter().map(Into::into).collect()), ..Default::default() }
+    }
+
+    pub fn tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        self.tags = Some(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl StoreSnippetRequest {
+    pub fn new(code: impl Into<String>, language: impl Into<String>, description: impl Into<String>) -> Self {
+        Self { code: code.into(), language: language.into(), description: description.into() }
+    }
+}
+
+impl SearchSnippetsRequest {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self { query: Some(query.into()), ..Default::default() }
+    }
+
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl AssertFactRequest {
+    pub fn new(subject: impl Into<String>, predicate: impl Into<String>, object: impl Into<String>) -> Self {
+        Self { subject: subject.into(), predicate: predicate.into(), object: object.into(), ..Default::default() }
+    }
+
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.confidence = Some(confidence);
+        self
+    }
+
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
+
+impl QueryFactsRequest {
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    pub fn predicate(mut self, predicate: impl Into<String>) -> Self {
+        self.predicate = Some(predicate.into());
+        self
+    }
+
+    pub fn object(mut self, object: impl Into<String>) -> Self {
+        self.object = Some(object.into());
+        self
+    }
+
+    pub fn min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = Some(min_confidence);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl ListConflictsRequest {
+    pub fn include_resolved(mut self) -> Self {
+        self.include_resolved = true;
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl PreviewResolveConflictRequest {
+    pub fn new(conflict_id: i64, keep: ConflictSide) -> Self {
+        Self { conflict_id, keep }
+    }
+}
+
+impl ResolveConflictRequest {
+    pub fn new(conflict_id: i64, keep: ConflictSide) -> Self {
+        Self { conflict_id, keep, confirmation_token: None }
+    }
+
+    pub fn confirmation_token(mut self, token: impl Into<String>) -> Self {
+        self.confirmation_token = Some(token.into());
+        self
+    }
+}
+
+impl RememberPersonRequest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..Default::default() }
+    }
+
+    pub fn aliases<I, S>(mut self, aliases: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.aliases = Some(aliases.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn relationship(mut self, relationship: impl Into<String>) -> Self {
+        self.relationship = Some(relationship.into());
+        self
+    }
+
+    pub fn notes(mut self, notes: impl Into<String>) -> Self {
+        self.notes = Some(notes.into());
+        self
+    }
+
+    pub fn message_id(mut self, message_id: i64) -> Self {
+        self.message_id = Some(message_id);
+        self
+    }
+}
+
+impl GetPersonRequest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), mentions_limit: None }
+    }
+
+    pub fn mentions_limit(mut self, limit: u32) -> Self {
+        self.mentions_limit = Some(limit);
+        self
+    }
+}
+
+impl SearchPeopleRequest {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self { query: query.into(), limit: None }
+    }
+}
+
+impl CreateProjectRequest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..Default::default() }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn keywords<I, S>(mut self, keywords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.keywords = Some(keywords.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+impl AssignMemoryToProjectRequest {
+    pub fn new(memory_id: i64, project: impl Into<String>) -> Self {
+        Self { memory_id, project: Some(project.into()) }
+    }
+
+    /// Take the memory out of its project.
+    pub fn unassign(memory_id: i64) -> Self {
+        Self { memory_id, project: None }
+    }
+}
+
+impl AddProjectRuleRequest {
+    pub fn new(project: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self { project: project.into(), pattern: pattern.into(), scope: RuleScope::Any }
+    }
+
+    pub fn scope(mut self, scope: RuleScope) -> Self {
+        self.scope = scope;
+        self
+    }
+}
+
+impl ClassifyConversationsRequest {
+    /// Only apply the rules of `project`.
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+}
+
+impl PreviewRenameCategoryRequest {
+    pub fn new(old_name: impl Into<String>, new_name: impl Into<String>) -> Self {
+        Self { old_name: old_name.into(), new_name: new_name.into() }
+    }
+}
+
+impl RenameCategoryRequest {
+    pub fn new(old_name: impl Into<String>, new_name: impl Into<String>) -> Self {
+        Self { old_name: old_name.into(), new_name: new_name.into(), merge: false, confirmation_token: None }
+    }
+
+    /// Fold into `new_name` even if it is already a category.
+    pub fn merge(mut self) -> Self {
+        self.merge = true;
+        self
+    }
+
+    pub fn confirmation_token(mut self, token: impl Into<String>) -> Self {
+        self.confirmation_token = Some(token.into());
+        self
+    }
+}
+
+impl SearchMemoryRequest {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(keywords: I) -> Self {
+        Self { keywords: keywords.into_iter().map(Into::into).collect(), ..Default::default() }
+    }
+
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn min_importance(mut self, importance: i32) -> Self {
+        self.min_importance = Some(importance);
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Memories stored from `after` on: unix seconds or text like "last month" or "2024-03-15".
+    pub fn after(mut self, after: impl Into<DateInput>) -> Self {
+        self.after = Some(after.into());
+        self
+    }
+
+    /// Memories stored before `before`, in the same forms as [`after`](Self::after).
+    pub fn before(mut self, before: impl Into<DateInput>) -> Self {
+        self.before = Some(before.into());
+        self
+    }
+
+    pub fn sort_by(mut self, sort: MemorySort) -> Self {
+        self.sort_by = Some(sort);
+        self
+    }
+}
+
+impl SearchMemoryByCategoryRequest {
+    pub fn new(category: impl Into<String>) -> Self {
+        Self { category: category.into(), fields: None }
+    }
+}
+
+impl GetConversationChunkRequest {
+    pub fn new(chunk_token: impl Into<String>) -> Self {
+        Self { chunk_token: chunk_token.into() }
+    }
+}
+
+impl GetKeyMessagesRequest {
+    pub fn new(conversation_id: impl Into<String>) -> Self {
+        Self { conversation_id: conversation_id.into(), preview_chars: None }
+    }
+
+    pub fn preview_chars(mut self, chars: u32) -> Self {
+        self.preview_chars = Some(chars);
+        self
+    }
+}
+
+impl PreviewDeleteMemoryRequest {
+    pub fn new(memory_id: i64) -> Self {
+        Self { memory_id, forget_history: None }
+    }
+
+    /// Preview deleting the memory together with its history.
+    pub fn forget_history(mut self) -> Self {
+        self.forget_history = Some(true);
+        self
+    }
+}
+
+impl DeleteMemoryRequest {
+    pub fn new(memory_id: i64) -> Self {
+        Self { memory_id, forget_history: None, confirmation_token: None }
+    }
+
+    /// Erase the memory's history too.
+    pub fn forget_history(mut self) -> Self {
+        self.forget_history = Some(true);
+        self
+    }
+
+    pub fn confirmation_token(mut self, token: impl Into<String>) -> Self {
+        self.confirmation_token = Some(token.into());
+        self
+    }
+}
+
+impl ExportAllRequest {
+    pub fn new() -> Self {
+        Self { output_dir: None, mask_pii: None }
+    }
+
+    pub fn output_dir(mut self, output_dir: impl Into<String>) -> Self {
+        self.output_dir = Some(output_dir.into());
+        self
+    }
+
+    pub fn mask_pii(mut self, mask: bool) -> Self {
+        self.mask_pii = Some(mask);
+        self
+    }
+}
+
+impl Default for ExportAllRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CreateBackupRequest {
+    pub fn new() -> Self {
+        Self { output_dir: None }
+    }
+
+    pub fn output_dir(mut self, output_dir: impl Into<String>) -> Self {
+        self.output_dir = Some(output_dir.into());
+        self
+    }
+}
+
+impl Default for CreateBackupRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryHygieneReportRequest {
+    pub fn new() -> Self {
+        Self { stale_after_days: None, similarity_threshold: None, limit: None }
+    }
+
+    pub fn stale_after_days(mut self, days: u32) -> Self {
+        self.stale_after_days = Some(days);
+        self
+    }
+
+    pub fn similarity_threshold(mut self, threshold: f64) -> Self {
+        self.similarity_threshold = Some(threshold);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl Default for MemoryHygieneReportRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FindSimilarMemoriesRequest {
+    pub fn new(memory_id: i64) -> Self {
+        Self { memory_id, limit: None }
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl GetMemoryHistoryRequest {
+    pub fn memory(memory_id: i64) -> Self {
+        Self { memory_id: Some(memory_id), ..Default::default() }
+    }
+
+    /// Versions of every memory stored under `key`.
+    pub fn key(key: impl Into<String>) -> Self {
+        Self { key: Some(key.into()), ..Default::default() }
+    }
+
+    /// Each memory as it was at `as_of` instead of every version.
+    pub fn as_of(mut self, as_of: impl Into<DateInput>) -> Self {
+        self.as_of = Some(as_of.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl RunMaintenanceRequest {
+    pub fn new() -> Self {
+        Self { vacuum: None }
+    }
+
+    pub fn vacuum(mut self, vacuum: bool) -> Self {
+        self.vacuum = Some(vacuum);
+        self
+    }
+}
+
+impl Default for RunMaintenanceRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PruneToolResultsRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_kb(mut self, min_kb: u32) -> Self {
+        self.min_kb = Some(min_kb);
+        self
+    }
+
+    pub fn older_than_days(mut self, days: u32) -> Self {
+        self.older_than_days = Some(days);
+        self
+    }
+
+    /// Drop the originals instead of keeping them compressed.
+    pub fn discard_original(mut self) -> Self {
+        self.keep_original = Some(false);
+        self
+    }
+
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    pub fn confirmation_token(mut self, token: impl Into<String>) -> Self {
+        self.confirmation_token = Some(token.into());
+        self
+    }
+}
+
+impl ReindexRequest {
+    /// Rebuild every full-text index.
+    pub fn all() -> Self {
+        Self { tables: None }
+    }
+
+    pub fn tables<I: IntoIterator<Item = S>, S: Into<String>>(tables: I) -> Self {
+        Self { tables: Some(tables.into_iter().map(Into::into).collect()) }
+    }
+}
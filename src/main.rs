@@ -1,21 +1,278 @@
-mod db;
-mod models;
-mod service;
+mod cli;
 
 use anyhow::{Context, Result};
+use clap::Parser;
+use cli::{Cli, Command, ExportFormat};
 use rmcp::ServiceExt;
+use mcp_luna_history::service::ConversationService;
+use mcp_luna_history::config::Config;
+use mcp_luna_history::dates::DateInput;
+use mcp_luna_history::{archive, backup, db, embeddings, export, import, health, http, indexer, locate, maintenance, merge, seed, tool_results, topics};
 use rmcp::transport::stdio;
-use service::ConversationService;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let db_path = std::env::var("COSMIC_LLM_DB_PATH")
-        .context("COSMIC_LLM_DB_PATH environment variable must be set")?;
-    let service = ConversationService::new(&db_path)?;
+    let cli = Cli::parse();
+    let db_path = locate::resolve_db_path(cli.db)?;
+    let mut config = Config::from_env();
+    config.read_only |= cli.read_only;
+    config.http.addr = cli.http.or(config.http.addr);
 
-    let server = service.serve(stdio()).await?;
-    
-    server.waiting().await?;
+    match cli.command {
+        Some(Command::Export { format: ExportFormat::Jsonl, output, mask_pii }) => {
+            let conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let pii = (mask_pii || config.pii.applies_to("export_all")).then_some(&config.pii);
+            let report = export::export_all(&conn, &output, pii)?;
+            eprintln!(
+                "Exported {} conversations ({} messages) and {} memories to {}",
+                report.conversations, report.messages, report.memories, report.output_dir
+            );
+            Ok(())
+        }
+        Some(Command::Import { format, file }) => {
+            let json = std::fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            let detected = import::detect_format(&json);
+            let format = match format.explicit() {
+                None => detected?,
+                Some(format) => {
+                    if let Ok(detected) = detected {
+                        anyhow::ensure!(
+                            detected == format,
+                            "{} looks like a {} export, not {}",
+                            file.display(),
+                            detected.name(),
+                            format.name()
+                        );
+                    }
+                    format
+                }
+            };
+            let conversations = import::parse(format, &json)?;
+            let mut conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let report = import::write_conversations(&mut conn, format.name(), conversations)?;
+            eprintln!(
+                "Imported {} conversations ({} messages) from {}, updated {}, skipped {}",
+                report.conversations_imported,
+                report.messages_imported,
+                report.source,
+                report.conversations_updated,
+                report.conversations_skipped
+            );
+            Ok(())
+        }
+        Some(Command::Backup { output, keep_daily, keep_weekly }) => {
+            let dir = output
+                .or(config.backup.dir)
+                .context("Pass --output or set MCP_LUNA_BACKUP_DIR")?;
+            let retention = backup::RetentionPolicy {
+                daily: keep_daily.unwrap_or(config.backup.retention.daily),
+                weekly: keep_weekly.unwrap_or(config.backup.retention.weekly),
+            };
+            let conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let report = backup::create_backup(&conn, &dir, retention)?;
+            eprintln!(
+                "Backup written to {} ({} bytes), {} old snapshots removed",
+                report.path,
+                report.size_bytes,
+                report.removed.len()
+            );
+            Ok(())
+        }
+        Some(Command::Maintenance { vacuum }) => {
+            let conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let report = maintenance::run_maintenance(&conn, vacuum)?;
+            for problem in &report.integrity_problems {
+                eprintln!("Integrity problem: {}", problem);
+            }
+            eprintln!(
+                "Integrity {}; optimized {}; purged {} expired memories; analyzed{}; {} -> {} bytes in {} ms",
+                if report.integrity_ok { "ok" } else { "FAILED" },
+                report.fts_optimized.join(", "),
+                report.expired_memories_purged,
+                if report.vacuumed { ", vacuumed" } else { "" },
+                report.size_before_bytes,
+                report.size_after_bytes,
+                report.duration_ms
+            );
+            anyhow::ensure!(report.integrity_ok, "Database integrity check failed");
+            Ok(())
+        }
+        Some(Command::PruneToolResults { min_kb, older_than_days, discard_original, dry_run }) => {
+            let conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let options = tool_results::PruneOptions {
+                min_bytes: u64::from(min_kb) * 1024,
+                older_than_days,
+                keep_original: !discard_original,
+                dry_run,
+            };
+            let report = tool_results::prune(&conn, &options)?;
+            eprintln!(
+                "{} {} tool results: {} -> {} bytes, {} bytes of compressed originals kept, in {} ms",
+                if dry_run { "Would prune" } else { "Pruned" },
+                report.messages_pruned,
+                report.bytes_before,
+                report.bytes_after,
+                report.bytes_kept,
+                report.duration_ms
+            );
+            Ok(())
+        }
+        Some(Command::Archive { before, archive: archive_path }) => {
+            let before = config.timezone.since(&DateInput::Text(before))?;
+            let archive_path = archive_path.or(config.archive_path).unwrap_or_else(|| archive::default_path(&db_path));
+            let conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let report = archive::archive_conversations(&conn, &archive_path, before)?;
+            eprintln!(
+                "Archived {} conversations ({} messages) to {}; {} pinned conversations kept",
+                report.conversations_archived, report.messages_archived, report.archive_path, report.pinned_skipped
+            );
+            Ok(())
+        }
+        Some(Command::Reindex { tables }) => {
+            let conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let tables = (!tables.is_empty()).then_some(tables);
+            let report = maintenance::reindex(&conn, tables.as_deref(), &config.fts_tokenizer)?;
+            for table in &report.tables {
+                eprintln!(
+                    "{}: {} ({} -> {}), {} rows",
+                    table.table,
+                    if table.rebuilt { "rebuilt" } else { "unchanged" },
+                    table.previous_tokenizer,
+                    report.tokenizer,
+                    table.rows
+                );
+            }
+            eprintln!("Reindexed in {} ms", report.duration_ms);
+            Ok(())
+        }
+        Some(Command::ClusterTopics { topics: count }) => {
+            let conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let report = topics::cluster(&conn, count, config.timezone)?;
+            for topic in &report.topics {
+                eprintln!(
+                    "{:>3}. {} ({} conversations, {} to {})",
+                    topic.id,
+                    topic.label,
+                    topic.size,
+                    topic.first_date.as_deref().unwrap_or("?"),
+                    topic.last_date.as_deref().unwrap_or("?")
+                );
+                for conversation in &topic.representatives {
+                    eprintln!("       {} [{}]", conversation.title, conversation.conversation_id);
+                }
+            }
+            eprintln!(
+                "Clustered {} of {} conversations into {} topics in {} ms",
+                report.conversations - report.unclustered,
+                report.conversations,
+                report.topics.len(),
+                report.duration_ms
+            );
+            Ok(())
+        }
+        Some(Command::IndexEmbeddings { batch_size, limit, restart }) => {
+            let conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let embedder = embeddings::Embedder::new(&config.embedding)?;
+            if restart {
+                embeddings::reset(&conn, embedder.model())?;
+            }
+            let started = std::time::Instant::now();
+            let progress = embeddings::backfill(&conn, &embedder, batch_size, limit, |progress| {
+                eprintln!(
+                    "Embedded {} messages, {} left (checkpoint: message {})",
+                    progress.embedded, progress.remaining, progress.checkpoint
+                );
+            })?;
+            eprintln!(
+                "Embedded {} messages with {} in {} s; {} left",
+                progress.embedded,
+                embedder.model(),
+                started.elapsed().as_secs(),
+                progress.remaining
+            );
+            Ok(())
+        }
+        Some(Command::MergeDb { source }) => {
+            let mut conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let report = merge::merge_database(&mut conn, &source)?;
+            eprintln!(
+                "Merged {}: {} conversations added, {} updated, {} renamed, {} unchanged, {} left in the trash, {} moved to the trash; {} messages added; {} memories added, {} duplicates skipped",
+                source.display(),
+                report.conversations_added,
+                report.conversations_updated,
+                report.conversations_renamed,
+                report.conversations_skipped,
+                report.conversations_in_trash,
+                report.conversations_trashed,
+                report.messages_added,
+                report.memories_added,
+                report.memories_skipped
+            );
+            Ok(())
+        }
+        Some(Command::Seed { conversations, messages, seed: seed_value }) => {
+            let mut conn = db::open(&db_path, &config.fts_tokenizer)?;
+            let started = std::time::Instant::now();
+            let report = seed::seed(&mut conn, conversations, messages, seed_value)?;
+            eprintln!(
+                "Seeded {} with {} conversations and {} messages ({} tool calls) in {:.1} s",
+                db_path,
+                report.conversations,
+                report.messages,
+                report.tool_calls,
+                started.elapsed().as_secs_f64()
+            );
+            Ok(())
+        }
+        Some(Command::Health) => {
+            let report = health::check(&db_path, config.read_only);
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            anyhow::ensure!(report.healthy, "Database is unhealthy");
+            Ok(())
+        }
+        Some(Command::MigrateToEncrypted { output }) => {
+            let key = db::encryption::key().with_context(|| {
+                format!(
+                    "No key: set MCP_LUNA_DB_KEY or store one with `secret-tool store --label='Luna memory database' {}`",
+                    db::encryption::KEYRING_ATTRIBUTES.join(" ")
+                )
+            })?;
+            let source = std::path::PathBuf::from(&db_path);
+            let encrypted = output.clone().unwrap_or_else(|| format!("{}.encrypted", db_path).into());
+            let tables = db::encryption::encrypt_copy(&source, &encrypted, key)?;
+            eprintln!("Encrypted copy of {} tables written to {} and verified", tables, encrypted.display());
+            if output.is_none() {
+                let plaintext = std::path::PathBuf::from(format!("{}.plaintext", db_path));
+                anyhow::ensure!(!plaintext.exists(), "{} already exists", plaintext.display());
+                std::fs::rename(&source, &plaintext)
+                    .with_context(|| format!("Failed to move {} aside", source.display()))?;
+                std::fs::rename(&encrypted, &source)
+                    .with_context(|| format!("Failed to move {} into place", encrypted.display()))?;
+                eprintln!(
+                    "{} is now encrypted; the plaintext original is at {}. Delete it once the server opens the new file.",
+                    source.display(),
+                    plaintext.display()
+                );
+            }
+            Ok(())
+        }
+        None => {
+            tokio::spawn(backup::run_schedule(db_path.clone(), config.backup.clone()));
+            tokio::spawn(indexer::run(db_path.clone(), config.clone()));
+            if let Some(addr) = config.http.addr {
+                let listener = tokio::net::TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("Failed to listen on {}", addr))?;
+                return http::serve(listener, &db_path, config).await;
+            }
+            let service = ConversationService::new(&db_path, config)?;
 
-    Ok(())
+            let server = service.serve(stdio()).await?;
+            
+            server.waiting().await?;
+
+            Ok(())
+        }
+    }
 }
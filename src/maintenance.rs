@@ -0,0 +1,140 @@
+//! Database upkeep: integrity check, FTS index optimization, expired memory purge, planner statistics
+//! and optional VACUUM, plus rebuilding the full-text indexes with another tokenizer.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::time::Instant;
+
+use crate::attachments;
+use crate::db;
+use crate::erase;
+use crate::fts::{self, Tokenizer};
+use crate::memory_history;
+use crate::models::{MaintenanceReport, ReindexReport};
+
+/// Full-text indexes merged into a single segment by `optimize`, and rebuilt by `reindex`.
+pub const FTS_TABLES: &[&str] = &["messages_fts", "memory_fts", "titles_fts", "tool_activity_fts", "reasoning_fts", "journal_fts", "bookmarks_fts", "snippets_fts"];
+
+/// Run every maintenance step on `conn`. VACUUM rewrites the whole file and needs as much free
+/// disk space again, so it only runs when asked for.
+pub fn run_maintenance(conn: &Connection, vacuum: bool) -> Result<MaintenanceReport> {
+    let started = Instant::now();
+    let size_before_bytes = database_size(conn)?;
+
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .context("Failed to prepare integrity check")?;
+    let integrity_problems: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .context("Integrity check failed")?
+        .collect::<Result<Vec<String>, _>>()
+        .context("Integrity check failed")?
+        .into_iter()
+        .filter(|line| line != "ok")
+        .collect();
+
+    let mut fts_optimized = Vec::new();
+    for table in FTS_TABLES {
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )
+            .context("Failed to inspect schema")?;
+        if exists {
+            conn.execute(&format!("INSERT INTO {0}({0}) VALUES('optimize')", table), [])
+                .with_context(|| format!("Failed to optimize {}", table))?;
+            fts_optimized.push(table.to_string());
+        }
+    }
+
+    let expired_memories_purged = purge_expired_memories(conn)?;
+    let trashed_conversations_purged = erase::purge_trash(conn)?;
+
+    conn.execute_batch("ANALYZE").context("ANALYZE failed")?;
+
+    if vacuum {
+        conn.execute_batch("VACUUM").context("VACUUM failed")?;
+    }
+
+    Ok(MaintenanceReport {
+        integrity_ok: integrity_problems.is_empty(),
+        integrity_problems,
+        fts_optimized,
+        expired_memories_purged,
+        trashed_conversations_purged,
+        analyzed: true,
+        vacuumed: vacuum,
+        size_before_bytes,
+        size_after_bytes: database_size(conn)?,
+        duration_ms: started.elapsed().as_millis() as u64,
+        error: None,
+    })
+}
+
+/// Delete memories whose `expires_at` has passed, with their attachments and history. Searches
+/// skip them already; this drops the rows.
+pub fn purge_expired_memories(conn: &Connection) -> Result<usize> {
+    if !db::has_column(conn, "memory", "expires_at") {
+        return Ok(0);
+    }
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    let expired: Vec<i64> = {
+        let mut stmt = tx.prepare("SELECT id FROM memory WHERE expires_at <= ?")?;
+        let ids = stmt.query_map([db::unix_now()], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        ids
+    };
+    for &id in &expired {
+        tx.execute("DELETE FROM attachments WHERE memory_id = ?", [id])
+            .context("Failed to delete attachments of expired memories")?;
+        tx.execute("DELETE FROM memory WHERE id = ?", [id]).context("Failed to purge expired memories")?;
+        // After the delete, so the version its trigger records goes too
+        memory_history::forget(&tx, id).context("Failed to erase the history of expired memories")?;
+    }
+    attachments::delete_orphan_blobs(&tx).context("Failed to delete attachment content")?;
+    tx.commit().context("Failed to commit purge")?;
+    Ok(expired.len())
+}
+
+fn database_size(conn: &Connection) -> Result<u64> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|size| size as u64)
+    .context("Failed to read database size")
+}
+
+/// Rebuild the full-text indexes named in `tables` (default: all that exist) with `tokenizer`.
+/// Each index is rebuilt in its own transaction; rebuilding `messages_fts` re-reads every message.
+pub fn reindex(conn: &Connection, tables: Option<&[String]>, tokenizer: &Tokenizer) -> Result<ReindexReport> {
+    let started = Instant::now();
+    if let Some(tables) = tables {
+        for table in tables {
+            anyhow::ensure!(
+                FTS_TABLES.contains(&table.as_str()),
+                "Unknown full-text index {}; expected one of {}",
+                table,
+                FTS_TABLES.join(", ")
+            );
+        }
+    }
+
+    let mut reindexed = Vec::new();
+    for table in FTS_TABLES.iter().filter(|t| tables.is_none_or(|tables| tables.iter().any(|name| name == *t))) {
+        let tx = conn.unchecked_transaction().context("Failed to start reindex")?;
+        if let Some(report) = fts::retokenize(&tx, table, tokenizer)? {
+            reindexed.push(report);
+        }
+        tx.commit().with_context(|| format!("Failed to commit reindex of {}", table))?;
+    }
+
+    Ok(ReindexReport {
+        tokenizer: tokenizer.spec(),
+        tables: reindexed,
+        duration_ms: started.elapsed().as_millis() as u64,
+        error: None,
+    })
+}
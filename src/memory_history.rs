@@ -0,0 +1,78 @@
+//! Memory history: every version of every memory, so a changed or deleted memory can be traced
+//! back to what it said and when. Triggers on `memory` write the history; this module reads it.
+//!
+//! Memories are usually replaced by deleting and storing anew, so besides a single memory's
+//! versions the history is read by key, across the memories stored under it. A deleted memory's
+//! versions stay unless its history is forgotten with it; an expired memory's go when it is purged.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, Row};
+
+use crate::dates::Timezone;
+use crate::facts;
+use crate::models::{MemoryChange, MemoryVersion};
+
+const COLUMNS: &str = "h.memory_id, h.change, h.content, h.category, h.importance, h.pinned, h.expires_at, h.key, h.changed_at";
+
+fn from_row(row: &Row, timezone: Timezone) -> rusqlite::Result<MemoryVersion> {
+    let changed_at = row.get(8)?;
+    Ok(MemoryVersion {
+        memory_id: row.get(0)?,
+        change: match row.get::<_, String>(1)?.as_str() {
+            "created" => MemoryChange::Created,
+            "deleted" => MemoryChange::Deleted,
+            _ => MemoryChange::Updated,
+        },
+        content: row.get(2)?,
+        category: row.get(3)?,
+        importance: row.get::<_, Option<i32>>(4)?.unwrap_or(5),
+        pinned: row.get(5)?,
+        expires_at: row.get(6)?,
+        key: row.get(7)?,
+        changed_at,
+        changed_at_iso: timezone.iso(changed_at),
+    })
+}
+
+/// Which versions to read; every filter given must match.
+#[derive(Debug, Default)]
+pub struct HistoryQuery<'a> {
+    pub memory_id: Option<i64>,
+    /// Compared as `store_memory` stores keys
+    pub key: Option<&'a str>,
+    /// Only the version of each memory current at this time (unix seconds), for memories that
+    /// existed and hadn't expired then
+    pub as_of: Option<i64>,
+}
+
+/// Erase every version of memory `memory_id`; returns how many there were.
+pub fn forget(conn: &Connection, memory_id: i64) -> rusqlite::Result<usize> {
+    conn.prepare_cached("DELETE FROM memory_history WHERE memory_id = ?")?.execute([memory_id])
+}
+
+/// Up to `limit` versions matching `query`, newest first.
+pub fn versions(conn: &Connection, query: &HistoryQuery, limit: u32, timezone: Timezone) -> Result<Vec<MemoryVersion>> {
+    let mut conditions = Vec::new();
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(memory_id) = query.memory_id {
+        conditions.push("h.memory_id = ?");
+        params.push(memory_id.into());
+    }
+    if let Some(key) = query.key.map(facts::key).filter(|k| !k.is_empty()) {
+        conditions.push("h.key = ?");
+        params.push(key.into());
+    }
+    if let Some(as_of) = query.as_of {
+        conditions.push("h.id IN (SELECT MAX(id) FROM memory_history WHERE changed_at <= ? GROUP BY memory_id)");
+        conditions.push("h.change != 'deleted'");
+        conditions.push("(h.expires_at IS NULL OR h.expires_at > ?)");
+        params.extend([as_of.into(), as_of.into()]);
+    }
+    let filter = if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) };
+    params.push(i64::from(limit).into());
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM memory_history h {} ORDER BY h.changed_at DESC, h.id DESC LIMIT ?", COLUMNS, filter))
+        .context("Failed to query memory history")?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| from_row(row, timezone))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read memory history")
+}
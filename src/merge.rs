@@ -0,0 +1,360 @@
+//! Merge another Cosmic LLM / mcp_luna_history database into the current one.
+//!
+//! Conversations are matched by ID (or, failing that, by title and creation time) and only the
+//! messages missing from the target are copied. A conversation ID that exists in both files but
+//! belongs to a different conversation is copied under a new ID. Message and memory row IDs are
+//! never copied; they are reassigned by the target database.
+//!
+//! Deletions carry over through the trash: a conversation in the target's trash isn't copied back,
+//! and one the source moved to its trash goes to the target's, still restorable there. Memories
+//! keep every column both schemas have; their projects are matched by name.
+
+use anyhow::{ensure, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::path::Path;
+
+use crate::db::{self, encryption};
+use crate::erase;
+
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    pub conversations_added: i64,
+    pub conversations_updated: i64,
+    /// Conversations copied under a new ID because their ID was taken by a different conversation
+    pub conversations_renamed: i64,
+    pub conversations_skipped: i64,
+    /// Conversations not copied because they are in the target's trash
+    pub conversations_in_trash: i64,
+    /// Conversations moved to the target's trash because the source deleted them
+    pub conversations_trashed: i64,
+    pub messages_added: i64,
+    pub memories_added: i64,
+    pub memories_skipped: i64,
+}
+
+/// Merge the database at `source` into `conn`, in a single transaction.
+pub fn merge_database(conn: &mut Connection, source: &Path) -> Result<MergeReport> {
+    let source = source
+        .canonicalize()
+        .with_context(|| format!("Cannot open source database {}", source.display()))?;
+    let target: Option<String> = conn.query_row("SELECT file FROM pragma_database_list WHERE name = 'main'", [], |row| row.get(0))?;
+    ensure!(
+        target.map(|t| Path::new(&t) != source).unwrap_or(true),
+        "Source and target are the same database"
+    );
+
+    db::init_conversation_schema(conn)?;
+    let sync_fts = !db::messages_fts_has_triggers(conn)?;
+
+    match encryption::attach_key(&source) {
+        Some(key) => conn.execute("ATTACH DATABASE ?1 AS src KEY ?2", params![source.to_string_lossy(), key]),
+        None => conn.execute("ATTACH DATABASE ?1 AS src", [source.to_string_lossy()]),
+    }
+    .context("Failed to attach source database")?;
+    let result = merge_attached(conn, sync_fts);
+    conn.execute("DETACH DATABASE src", []).context("Failed to detach source database")?;
+    result
+}
+
+fn merge_attached(conn: &mut Connection, sync_fts: bool) -> Result<MergeReport> {
+    let mut report = MergeReport::default();
+    let tx = conn.transaction().context("Failed to start merge transaction")?;
+
+    if has_table(&tx, "src", "conversations")? && has_table(&tx, "src", "messages")? {
+        merge_conversations(&tx, sync_fts, &mut report)?;
+        if has_table(&tx, "src", "conversation_trash")? && has_table(&tx, "main", "conversation_trash")? {
+            carry_over_trash(&tx, &mut report)?;
+        }
+    }
+    if has_table(&tx, "src", "memory")? {
+        merge_memories(&tx, &mut report)?;
+    }
+
+    tx.commit().context("Failed to commit merge")?;
+    Ok(report)
+}
+
+/// Whether database `schema` (`main` or `src`) has table `name`.
+fn has_table(tx: &Transaction, schema: &str, name: &str) -> Result<bool> {
+    tx.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM {}.sqlite_master WHERE type = 'table' AND name = ?)", schema),
+        [name],
+        |row| row.get(0),
+    )
+    .with_context(|| format!("Failed to inspect {} schema", schema))
+}
+
+/// Columns of `table` in database `schema`.
+fn columns(tx: &Transaction, schema: &str, table: &str) -> Result<Vec<String>> {
+    let mut stmt = tx.prepare("SELECT name FROM pragma_table_info(?1, ?2) ORDER BY cid")?;
+    let columns = stmt.query_map([table, schema], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(columns)
+}
+
+struct SourceConversation {
+    id: String,
+    title: String,
+    created_at: i64,
+    title_generated: i32,
+    profile_name: Option<String>,
+}
+
+fn merge_conversations(tx: &Transaction, sync_fts: bool, report: &mut MergeReport) -> Result<()> {
+    let conversations = {
+        let mut stmt = tx.prepare("SELECT id, title, created_at, title_generated, profile_name FROM src.conversations")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SourceConversation {
+                id: row.get(0)?,
+                title: row.get(1).unwrap_or_default(),
+                created_at: row.get(2).unwrap_or(0),
+                title_generated: row.get(3).unwrap_or(0),
+                profile_name: row.get(4).ok(),
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut msg_stmt = tx.prepare(
+        r#"
+        SELECT
+            role, content, created_at,
+            tool_calls, tool_call_id, tool_name, tool_status,
+            tool_params_json, tool_result_json, reasoning_content
+        FROM src.messages
+        WHERE conversation_id = ?
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )?;
+    let mut exists_stmt = tx.prepare(
+        "SELECT 1 FROM main.messages WHERE conversation_id = ?1 AND role = ?2 AND content = ?3 AND created_at = ?4",
+    )?;
+    let mut insert_stmt = tx.prepare(
+        r#"
+        INSERT INTO main.messages (
+            conversation_id, role, content, created_at,
+            tool_calls, tool_call_id, tool_name, tool_status,
+            tool_params_json, tool_result_json, reasoning_content
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        "#,
+    )?;
+
+    let in_target_trash = has_table(tx, "main", "conversation_trash")?;
+    for conversation in conversations {
+        if in_target_trash && trashed(tx, "main", &conversation.id, conversation.created_at)? {
+            report.conversations_in_trash += 1;
+            continue;
+        }
+        let (target_id, existing) = resolve_conversation_id(tx, &conversation, report)?;
+        if !existing {
+            tx.execute(
+                "INSERT INTO main.conversations (id, title, created_at, title_generated, profile_name) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    target_id,
+                    conversation.title,
+                    conversation.created_at,
+                    conversation.title_generated,
+                    conversation.profile_name
+                ],
+            )
+            .context("Failed to insert conversation")?;
+        }
+
+        let mut added = 0;
+        let mut rows = msg_stmt.query([&conversation.id])?;
+        while let Some(row) = rows.next()? {
+            let role: String = row.get(0)?;
+            let content: String = row.get(1).unwrap_or_default();
+            let created_at: i64 = row.get(2).unwrap_or(0);
+            if existing
+                && exists_stmt
+                    .query_row(params![target_id, role, content, created_at], |_| Ok(()))
+                    .optional()?
+                    .is_some()
+            {
+                continue;
+            }
+            insert_stmt
+                .execute(params![
+                    target_id,
+                    role,
+                    content,
+                    created_at,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, Option<String>>(8)?,
+                    row.get::<_, Option<String>>(9)?,
+                ])
+                .context("Failed to insert message")?;
+            if sync_fts {
+                tx.execute(
+                    "INSERT INTO main.messages_fts (rowid, content) VALUES (?1, ?2)",
+                    params![tx.last_insert_rowid(), content],
+                )
+                .context("Failed to index message")?;
+            }
+            added += 1;
+        }
+
+        report.messages_added += added;
+        match (existing, added) {
+            (false, _) => report.conversations_added += 1,
+            (true, 0) => report.conversations_skipped += 1,
+            (true, _) => report.conversations_updated += 1,
+        }
+    }
+    Ok(())
+}
+
+/// Whether the trash of database `schema` holds conversation `id` created at `created_at`.
+fn trashed(tx: &Transaction, schema: &str, id: &str, created_at: i64) -> Result<bool> {
+    tx.query_row(
+        &format!(
+            "SELECT EXISTS(SELECT 1 FROM {}.conversation_trash
+             WHERE conversation_id = ?1 AND json_extract(conversation_json, '$.created_at') = ?2)",
+            schema
+        ),
+        params![id, created_at],
+        |row| row.get(0),
+    )
+    .context("Failed to look up the trash")
+}
+
+/// Move to the target's trash the conversations the source has in its trash, so they stay
+/// deleted after the merge.
+fn carry_over_trash(tx: &Transaction, report: &mut MergeReport) -> Result<()> {
+    let live: Vec<(String, i64)> = {
+        let mut stmt = tx.prepare(
+            "SELECT c.id, c.created_at FROM main.conversations c
+             JOIN src.conversation_trash t ON t.conversation_id = c.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<rusqlite::Result<_>>()?;
+        rows
+    };
+    for (id, created_at) in live {
+        if trashed(tx, "src", &id, created_at)? {
+            erase::move_to_trash(tx, &id)?;
+            report.conversations_trashed += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Pick the target conversation ID for a source conversation.
+/// Returns the ID and whether it refers to a conversation already present in the target.
+fn resolve_conversation_id(
+    tx: &Transaction,
+    conversation: &SourceConversation,
+    report: &mut MergeReport,
+) -> Result<(String, bool)> {
+    let same_id_created_at: Option<i64> = tx
+        .query_row("SELECT created_at FROM main.conversations WHERE id = ?", [&conversation.id], |row| row.get(0))
+        .optional()?;
+    match same_id_created_at {
+        Some(created_at) if created_at == conversation.created_at => Ok((conversation.id.clone(), true)),
+        Some(_) => {
+            // Same ID, different conversation: copy under a fresh ID, reusing the copy from an
+            // earlier merge if there is one
+            let mut suffix = 1;
+            loop {
+                let candidate = format!("{}-merged-{}", conversation.id, suffix);
+                let candidate_created_at: Option<i64> = tx
+                    .query_row("SELECT created_at FROM main.conversations WHERE id = ?", [&candidate], |row| row.get(0))
+                    .optional()?;
+                match candidate_created_at {
+                    Some(created_at) if created_at == conversation.created_at => return Ok((candidate, true)),
+                    Some(_) => suffix += 1,
+                    None => {
+                        report.conversations_renamed += 1;
+                        return Ok((candidate, false));
+                    }
+                }
+            }
+        }
+        None => {
+            // The same conversation may already exist under another ID (e.g. imported on both machines)
+            let duplicate: Option<String> = tx
+                .query_row(
+                    "SELECT id FROM main.conversations WHERE title = ?1 AND created_at = ?2 LIMIT 1",
+                    params![conversation.title, conversation.created_at],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(match duplicate {
+                Some(id) => (id, true),
+                None => (conversation.id.clone(), false),
+            })
+        }
+    }
+}
+
+/// Copy the source's memories the target lacks, with every column both have. Project IDs are
+/// mapped by project name, creating the projects the target lacks.
+fn merge_memories(tx: &Transaction, report: &mut MergeReport) -> Result<()> {
+    let target_columns = columns(tx, "main", "memory")?;
+    let shared: Vec<String> = columns(tx, "src", "memory")?
+        .into_iter()
+        .filter(|c| c != "id" && c != "project_id" && target_columns.contains(c))
+        .collect();
+    let projects = target_columns.iter().any(|c| c == "project_id")
+        && columns(tx, "src", "memory")?.iter().any(|c| c == "project_id")
+        && has_table(tx, "src", "projects")?;
+    let list = shared.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ");
+    let insert = if projects {
+        format!("INSERT INTO main.memory ({}, project_id) SELECT {}, ?2 FROM src.memory WHERE id = ?1", list, list)
+    } else {
+        format!("INSERT INTO main.memory ({}) SELECT {} FROM src.memory WHERE id = ?1", list, list)
+    };
+    let mut stmt = tx.prepare(&format!(
+        "SELECT id, content, category, {} FROM src.memory ORDER BY id ASC",
+        if projects { "project_id" } else { "NULL" }
+    ))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let content: String = row.get(1).unwrap_or_default();
+        let category: Option<String> = row.get(2)?;
+        let duplicate = tx
+            .query_row(
+                "SELECT 1 FROM main.memory WHERE content = ?1 AND category IS ?2",
+                params![content, category],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if duplicate {
+            report.memories_skipped += 1;
+            continue;
+        }
+        let id: i64 = row.get(0)?;
+        if projects {
+            let project_id = row.get::<_, Option<i64>>(3)?.map(|id| target_project(tx, id)).transpose()?.flatten();
+            tx.execute(&insert, params![id, project_id])
+        } else {
+            tx.execute(&insert, [id])
+        }
+        .context("Failed to insert memory")?;
+        report.memories_added += 1;
+    }
+    Ok(())
+}
+
+/// The target's ID for source project `source_id`: the project of the same name, created if
+/// missing. `None` when the source has no such project.
+fn target_project(tx: &Transaction, source_id: i64) -> Result<Option<i64>> {
+    let Some(name): Option<String> = tx
+        .query_row("SELECT name FROM src.projects WHERE id = ?", [source_id], |row| row.get(0))
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    tx.execute(
+        "INSERT OR IGNORE INTO main.projects (name, description, keywords, created_at)
+         SELECT name, description, keywords, created_at FROM src.projects WHERE id = ?",
+        [source_id],
+    )
+    .context("Failed to copy project")?;
+    let id = tx.query_row("SELECT id FROM main.projects WHERE name = ?", [&name], |row| row.get(0))?;
+    Ok(Some(id))
+}
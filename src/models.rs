@@ -1,23 +1,115 @@
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
 
+use crate::dates::DateInput;
+use crate::key_messages::KeyReason;
+
 // Conversation-related request types
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SearchConversationsRequest {
     #[schemars(description = "Keywords to search in conversation messages (OR semantics)")]
+    #[serde(default)]
     pub keywords: Vec<String>,
+    #[schemars(description = "Exact phrase the message must contain, words in this order (e.g. 'error code 137')")]
+    pub phrase: Option<String>,
+    #[schemars(description = "Terms that must all appear within a few words of each other")]
+    pub near: Option<NearQuery>,
+    #[schemars(description = "Treat the last word of each keyword, phrase and near term as a prefix, for search-as-you-type ('kube' matches 'kubernetes' and 'kubectl')")]
+    #[serde(default)]
+    pub prefix: bool,
+    #[schemars(description = "Also search the model's reasoning traces, not just message content; results then carry a reasoning_preview")]
+    #[serde(default)]
+    pub include_reasoning: bool,
+    #[schemars(description = "Also search conversations moved to the archive with archive_conversations; their matches follow those from the live history and carry archived: true")]
+    #[serde(default)]
+    pub include_archive: bool,
+    #[schemars(description = "Length of content_preview in characters (default: 200, max: 2000)")]
+    pub preview_chars: Option<u32>,
+    #[schemars(description = "Also return each message's full content, saving a get_message call per result")]
+    #[serde(default)]
+    pub include_full_content: bool,
+    #[schemars(description = "Only count matching messages and conversations, without returning them; for checking whether a topic was ever discussed")]
+    #[serde(default)]
+    pub count_only: bool,
+    #[schemars(description = "Return at most this many messages from any one conversation, so one long thread can't fill every result slot")]
+    pub max_per_conversation: Option<u32>,
+    #[schemars(description = "Result order: 'recent' (default, newest first), 'oldest', 'relevance' (best bm25 match first) or 'recency_boosted' (relevance weighted by age, halving every half_life_days)")]
+    pub sort: Option<SearchSort>,
+    #[schemars(description = "Age at which a match counts half as much under 'recency_boosted' (default: 30)")]
+    pub half_life_days: Option<f64>,
+    #[schemars(description = "Only messages whose relevance score (bm25, or recency-weighted under 'recency_boosted') is at least this; results then carry their score whatever the sort. Scores grow with how often and how rarely-used the matched terms are; 0 means no match in the message content")]
+    pub min_score: Option<f64>,
+    #[schemars(description = "Only messages from conversations of this project: ones whose title or messages mention any of its keywords (see create_project)")]
+    pub project: Option<String>,
+    #[schemars(description = "Only messages from conversations with every one of these tags (see tag_conversation)")]
+    pub tags: Option<Vec<String>>,
+    #[schemars(description = "Only user and assistant messages detected to be in this language: an ISO 639-3 code ('eng', 'pol') or an English name ('Polish')")]
+    pub language: Option<String>,
+    #[schemars(description = "Also search the synonyms of each keyword (see add_synonyms); the response lists them as expansions (default: true)")]
+    pub expand_synonyms: Option<bool>,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSort {
+    #[default]
+    Recent,
+    Oldest,
+    Relevance,
+    RecencyBoosted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NearQuery {
+    #[schemars(description = "Terms (words or multi-word phrases) that must all appear")]
+    pub terms: Vec<String>,
+    #[schemars(description = "Maximum number of words between the terms (default: 10)")]
+    pub distance: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct GetConversationRequest {
     #[schemars(description = "The unique identifier of the conversation to retrieve")]
     pub conversation_id: String,
+    #[schemars(description = "Only return these fields of the conversation and of each message (include 'messages' to get messages at all, e.g. ['id', 'title', 'messages', 'role', 'content']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationSort {
+    #[default]
+    Recent,
+    Oldest,
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleCollation {
+    /// Case- and diacritic-insensitive ("Ćma" sorts next to "cma")
+    #[default]
+    Folded,
+    /// Case-insensitive for ASCII only
+    Nocase,
+    /// Raw byte order
+    Binary,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SearchTitlesRequest {
     #[schemars(description = "Search query to find in conversation titles")]
     pub query: String,
+    #[schemars(description = "Result order: 'recent', 'oldest' or 'title'; omit to rank by relevance")]
+    pub sort: Option<ConversationSort>,
+    #[schemars(description = "How titles compare when sort is 'title': 'folded' (default, ignores case and diacritics), 'nocase' or 'binary'")]
+    pub collation: Option<TitleCollation>,
+    #[schemars(description = "Minimum match_score (0.0-1.0) for fuzzy matches, used when no title matches the query words (default: 0.5)")]
+    pub similarity_threshold: Option<f64>,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
@@ -27,16 +119,32 @@ pub struct ListConversationsRequest {
     pub limit: Option<u32>,
     #[schemars(description = "Number of conversations to skip (default: 0)")]
     pub offset: Option<u32>,
+    #[schemars(description = "Result order: 'recent' (default), 'oldest' or 'title'")]
+    pub sort: Option<ConversationSort>,
+    #[schemars(description = "How titles compare when sort is 'title': 'folded' (default, ignores case and diacritics), 'nocase' or 'binary'")]
+    pub collation: Option<TitleCollation>,
+    #[schemars(description = "Only conversations with every one of these tags (see tag_conversation)")]
+    pub tags: Option<Vec<String>>,
+    #[schemars(description = "Only pinned conversations (see pin_conversation) (default: false)")]
+    pub pinned_only: Option<bool>,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct GetMessageRequest {
     #[schemars(description = "The unique identifier of the message to retrieve")]
     pub message_id: i64,
+    #[schemars(description = "Return the full tool result of a message whose result was pruned to a summary, when the original was kept (default: false)")]
+    #[serde(default)]
+    pub original_tool_result: bool,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
 }
 
 // Conversation-related response types
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 #[schemars(description = "Search result from conversation messages")]
 pub struct SearchResult {
     pub conversation_id: String,
@@ -44,15 +152,84 @@ pub struct SearchResult {
     pub role: String,
     pub content_preview: String,
     pub created_at: i64,
+    #[schemars(description = "created_at as an RFC 3339 date and time in the server's timezone")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+    #[schemars(description = "How long ago the message was created, e.g. '3 days ago'")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age: Option<String>,
+    /// Full message content, when asked for with `include_full_content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Start of the message's reasoning trace, when searched with `include_reasoning`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_preview: Option<String>,
+    /// Ranking score when sorted by relevance; higher is better
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Search keywords this message matched
+    pub matched_keywords: Vec<String>,
+    #[schemars(description = "Rough number of tokens of the full message content (characters / 4), to judge whether fetching it fits")]
+    pub estimated_tokens: u64,
+    #[schemars(description = "The message is in the archive (see archive_conversations); get_conversation still reads it")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Number of messages matching one search keyword")]
+pub struct KeywordHits {
+    pub keyword: String,
+    pub hits: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 #[schemars(description = "Wrapper for search results array")]
 pub struct SearchResultsResponse {
     pub items: Vec<SearchResult>,
+    /// Matching messages per keyword across the whole history, not just the returned items
+    pub keyword_hits: Vec<KeywordHits>,
+    /// Totals across the whole history, when searched with `count_only`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counts: Option<SearchCounts>,
+    /// Earlier queries that found results, offered when this search found nothing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<SearchSuggestion>,
+    /// Synonyms searched on top of the keywords
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expansions: Vec<SynonymExpansion>,
+    /// Why nothing was found, when nothing was
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<SearchDiagnostics>,
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Why a search found nothing and what to try instead, so an empty result isn't mistaken for a topic never discussed")]
+pub struct SearchDiagnostics {
+    #[schemars(description = "Keywords, phrase and near terms no message contains at all")]
+    pub terms_without_matches: Vec<String>,
+    #[schemars(description = "Messages the text alone matches, before filters and min_score")]
+    pub text_matches: i64,
+    #[schemars(description = "Filters the search applied, e.g. 'language' or 'min_score'")]
+    pub filters: Vec<String>,
+    #[schemars(description = "Best score of the messages min_score excluded")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_score: Option<f64>,
+    #[schemars(description = "Suggestions for a search that finds something")]
+    pub hints: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Number of matching messages and of the conversations they belong to")]
+pub struct SearchCounts {
+    pub messages: i64,
+    pub conversations: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 #[schemars(rename_all = "camelCase")]
 pub struct Conversation {
     pub id: String,
@@ -61,9 +238,18 @@ pub struct Conversation {
     pub title_generated: i32,
     pub profile_name: Option<String>,
     pub messages: Vec<Message>,
+    #[schemars(description = "Rough number of tokens of the title and all messages (characters / 4), including those not returned when the conversation comes in chunks")]
+    pub estimated_tokens: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Set when the conversation is too large to return at once: messages holds its first chunk, and get_conversation_chunk takes this token for the next")]
+    pub chunk_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Messages in the whole conversation, when only a chunk of them is returned")]
+    pub total_messages: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 #[schemars(rename_all = "camelCase")]
 pub struct Message {
     pub id: i64,
@@ -71,6 +257,9 @@ pub struct Message {
     pub role: String,
     pub content: String,
     pub created_at: i64,
+    #[schemars(description = "created_at as an RFC 3339 date and time in the server's timezone")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
     pub tool_calls: Option<String>,
     pub tool_call_id: Option<String>,
     pub tool_name: Option<String>,
@@ -78,16 +267,40 @@ pub struct Message {
     pub tool_params_json: Option<String>,
     pub tool_result_json: Option<String>,
     pub reasoning_content: Option<String>,
+    #[schemars(description = "Heuristically flagged as a key message of its conversation (set by get_conversation)")]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub key: bool,
+    #[schemars(description = "Rough number of tokens of the message's content, reasoning and tool calls (characters / 4)")]
+    pub estimated_tokens: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct ConversationSummary {
     pub id: String,
     pub title: String,
     pub created_at: i64,
+    #[schemars(description = "created_at as an RFC 3339 date and time in the server's timezone")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+    #[schemars(description = "How long ago the conversation was created, e.g. '3 days ago'")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age: Option<String>,
     pub title_generated: i32,
     pub profile_name: Option<String>,
     pub message_count: i64,
+    #[schemars(description = "Title similarity to the query from search_conversation_titles: 1.0 when the title matches every query word, lower for fuzzy matches")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_score: Option<f64>,
+    #[schemars(description = "Tags of the conversation, from list_conversations")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[schemars(description = "Whether the conversation is pinned, from list_conversations")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[schemars(description = "Keyphrases cached by extract_keywords, best first, from list_conversations")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
@@ -97,53 +310,2287 @@ pub struct ConversationSummariesResponse {
 }
 
 // Memory Module Types
-#[derive(Debug, Deserialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct StoreMemoryRequest {
     #[schemars(description = "The fact or information to remember")]
     pub content: String,
-    #[schemars(description = "A tag for grouping (e.g., 'workflow', 'crate-info')")]
+    #[schemars(description = "A tag for grouping (e.g., 'workflow', 'crate-info'); reuse one from list_memory_categories where it fits")]
     pub category: Option<String>,
     #[schemars(description = "Priority score 1-10 (default: 5)")]
     pub importance: Option<i32>,
+    #[schemars(description = "Forget the memory after this many days, for facts that go stale such as 'traveling next week'. Omit to keep it for good")]
+    pub ttl_days: Option<f64>,
+    #[schemars(description = "Pin the memory so it tops every search_memory result and get_pinned_memories returns it, for facts like the user's name, timezone or coding style (default: false)")]
+    #[serde(default)]
+    pub pinned: bool,
+    #[schemars(description = "What the memory states a value of, e.g. 'user editor' or 'project deadline'. A later memory with the same key (ignoring case and spacing) and different content is reported as conflicting; see resolve_conflict")]
+    pub key: Option<String>,
 }
 
-#[derive(Debug, Deserialize, JsonSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
 pub struct SearchMemoryRequest {
-    #[schemars(description = "Keywords to search in memory (OR semantics)")]
+    #[schemars(description = "Keywords to search in memory (OR semantics). Optional when a filter is given; the filters alone then select memories")]
+    #[serde(default)]
     pub keywords: Vec<String>,
+    #[schemars(description = "Only memories with at least this importance (1-10)")]
+    pub min_importance: Option<i32>,
+    #[schemars(description = "Only memories in this category (exact name)")]
+    pub category: Option<String>,
+    #[schemars(description = "Only memories stored at or after this time: unix seconds, an ISO date, or a phrase like 'last month' (from its start)")]
+    pub after: Option<DateInput>,
+    #[schemars(description = "Only memories stored before this time, in the same forms as 'after'; a phrase counts up to its end")]
+    pub before: Option<DateInput>,
+    #[schemars(description = "Result order: 'weighted' (default: keyword relevance weighted by importance and recency), 'relevance' (keywords only; by importance without keywords), 'importance' (highest first, then newest), 'recent' or 'oldest'")]
+    pub sort_by: Option<MemorySort>,
+    #[schemars(description = "Put pinned memories at the top of the results, whether or not they match (default: true)")]
+    pub include_pinned: Option<bool>,
+    #[schemars(description = "Only memories assigned to this project (see create_project)")]
+    pub project: Option<String>,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MemorySort {
+    /// Keyword relevance weighted by importance and recency, best first
+    #[default]
+    Weighted,
+    /// Best keyword match first
+    Relevance,
+    /// Highest importance first, newest first among equals
+    Importance,
+    /// Newest first
+    Recent,
+    /// Oldest first
+    Oldest,
 }
 
-#[derive(Debug, Deserialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SearchMemoryByCategoryRequest {
     #[schemars(description = "Category to filter memory entries (e.g. 'moltbook', 'work', 'personal')")]
     pub category: String,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GetKeyMessagesRequest {
+    #[schemars(description = "The unique identifier of the conversation to skim")]
+    pub conversation_id: String,
+    #[schemars(description = "Characters of each message to include (default: 300)")]
+    pub preview_chars: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KeyMessage {
+    pub id: i64,
+    pub role: String,
+    pub created_at: i64,
+    pub content_preview: String,
+    #[schemars(description = "Why the message was picked")]
+    pub reasons: Vec<KeyReason>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KeyMessagesResponse {
+    pub conversation_id: String,
+    pub title: String,
+    #[schemars(description = "Messages in the whole conversation, for scale")]
+    pub total_messages: i64,
+    pub items: Vec<KeyMessage>,
+    #[schemars(description = "Error message if the conversation could not be read")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GetConversationChunkRequest {
+    #[schemars(description = "chunk_token from get_conversation, or next_chunk_token from the previous chunk")]
+    pub chunk_token: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationChunk {
+    pub conversation_id: String,
+    #[schemars(description = "The chunk's messages, in chronological order")]
+    pub items: Vec<Message>,
+    #[schemars(description = "Position of the chunk's first message in the conversation, from 0")]
+    pub first_message: i64,
+    pub total_messages: i64,
+    #[schemars(description = "Token for the next chunk; null after the last")]
+    pub next_chunk_token: Option<String>,
+    #[schemars(description = "Error message if the chunk could not be read")]
+    pub error: Option<String>,
 }
 
-#[derive(Debug, Deserialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DeleteMemoryRequest {
     #[schemars(description = "The ID of the memory entry to remove")]
     pub memory_id: i64,
+    #[schemars(description = "Also erase the memory's versions from get_memory_history, so nothing it said is kept (default: false; the history keeps what it said when deleted)")]
+    pub forget_history: Option<bool>,
+    #[schemars(description = "Token from preview_delete_memory for this memory_id and forget_history. Required when the server enforces confirmations")]
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RenameCategoryRequest {
+    #[schemars(description = "Category to rename (exact name)")]
+    pub old_name: String,
+    #[schemars(description = "New name for the category")]
+    pub new_name: String,
+    #[schemars(description = "If new_name is already a category, fold old_name's memories into it instead of failing (default: false)")]
+    #[serde(default)]
+    pub merge: bool,
+    #[schemars(description = "Token from preview_rename_category for these names. Required when the server enforces confirmations and the rename merges")]
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewRenameCategoryRequest {
+    #[schemars(description = "Category to rename (exact name)")]
+    pub old_name: String,
+    #[schemars(description = "New name for the category")]
+    pub new_name: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RenameCategoryPreview {
+    #[schemars(description = "Number of memories that would move to new_name")]
+    pub renamed: i64,
+    #[schemars(description = "Whether new_name already exists, so the rename would merge the two for good")]
+    pub merged: bool,
+    #[schemars(description = "Single-use token to pass to rename_category; only issued for a merge")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+    #[schemars(description = "Unix timestamp after which the token is no longer accepted")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[schemars(description = "Error message if the preview failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RenameCategoryResponse {
+    pub success: bool,
+    #[schemars(description = "Number of memories moved to new_name")]
+    pub renamed: i64,
+    #[schemars(description = "Whether the memories joined a category that already existed")]
+    pub merged: bool,
+    #[schemars(description = "Error message if the rename failed; nothing is changed then")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PinMemoryRequest {
+    #[schemars(description = "The ID of the memory entry to pin or unpin")]
+    pub memory_id: i64,
+    #[schemars(description = "true to pin, false to unpin (default: true)")]
+    pub pinned: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PinMemoryResponse {
+    pub success: bool,
+    #[schemars(description = "Error message if the memory could not be updated")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewDeleteMemoryRequest {
+    #[schemars(description = "The ID of the memory entry to be removed")]
+    pub memory_id: i64,
+    #[schemars(description = "Whether the deletion will also erase the memory's history, as passed to delete_memory (default: false)")]
+    pub forget_history: Option<bool>,
 }
 
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct MemoryEntry {
     pub id: i64,
     pub content: String,
     pub category: Option<String>,
     pub importance: i32,
     pub created_at: i64,
+    #[schemars(description = "created_at as an RFC 3339 date and time in the server's timezone")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+    #[schemars(description = "Ranking score from search_memory: keyword relevance, weighted by importance and recency under the default order; higher is better")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    #[schemars(description = "When the memory expires (unix seconds); absent for memories kept for good")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[schemars(description = "Pinned: returned at the top of every search_memory result")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[schemars(description = "What the memory states a value of; memories with the same key and different content conflict")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[schemars(description = "Files stored with the memory by store_attachment; read them with get_attachment")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<AttachmentInfo>,
+    #[schemars(description = "From store_memory: earlier memories with the same key this one contradicts. Settle each with resolve_conflict")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Conflict>,
 }
 
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Wrapper for memory search results array")]
 pub struct MemorySearchResponse {
     pub items: Vec<MemoryEntry>,
+    #[schemars(description = "Error message if a filter could not be understood")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryCategory {
+    pub category: String,
+    #[schemars(description = "Number of memories in the category")]
+    pub count: i64,
+    #[schemars(description = "When the newest memory in the category was stored (unix seconds)")]
+    pub latest_created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_created_at_iso: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Memory categories in use, largest first")]
+pub struct MemoryCategoriesResponse {
+    pub items: Vec<MemoryCategory>,
+    #[schemars(description = "Number of memories without a category")]
+    pub uncategorized: i64,
 }
 
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DeleteMemoryResponse {
     pub success: bool,
     #[schemars(description = "Error message if deletion failed")]
     pub error: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DeletePreview {
+    #[schemars(description = "The memory entry that would be deleted")]
+    pub memory: Option<MemoryEntry>,
+    #[schemars(description = "Single-use token to pass to delete_memory")]
+    pub confirmation_token: Option<String>,
+    #[schemars(description = "Unix timestamp after which the token is no longer accepted")]
+    pub expires_at: Option<i64>,
+    #[schemars(description = "Error message if the preview failed")]
+    pub error: Option<String>,
+}
+
+
+// Failure-tracking types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SearchFailuresRequest {
+    #[schemars(description = "Text to look for in failed tool messages (content, tool name, parameters or result). Empty returns the most recent failures")]
+    pub query: String,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A failed tool call and the assistant message that followed it")]
+pub struct FailureResult {
+    pub message_id: i64,
+    pub conversation_id: String,
+    pub tool_name: Option<String>,
+    pub tool_status: Option<String>,
+    pub content_preview: String,
+    pub created_at: i64,
+    #[schemars(description = "Preview of the next assistant message in the conversation, usually how the failure was handled")]
+    pub follow_up_preview: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Wrapper for failure search results array")]
+pub struct FailureSearchResponse {
+    pub items: Vec<FailureResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ToolFailureStat {
+    pub tool_name: String,
+    pub failure_count: i64,
+    pub total_calls: i64,
+    #[schemars(description = "failure_count / total_calls")]
+    pub failure_rate: f64,
+    pub last_failed_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Wrapper for per-tool failure statistics, most failing tools first")]
+pub struct FailureStatsResponse {
+    pub items: Vec<ToolFailureStat>,
+}
+
+// Tool activity search types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SearchToolActivityRequest {
+    #[schemars(description = "Command, file path or other text to find in tool names, parameters and results, matched as a phrase (e.g. 'terraform apply', 'src/main.rs')")]
+    pub query: String,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['conversation_id', 'params_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A tool call whose name, parameters or result matched")]
+pub struct ToolActivityResult {
+    pub message_id: i64,
+    pub conversation_id: String,
+    pub conversation_title: Option<String>,
+    pub tool_name: Option<String>,
+    pub tool_status: Option<String>,
+    #[schemars(description = "First 200 characters of the tool parameters JSON")]
+    pub params_preview: Option<String>,
+    #[schemars(description = "First 200 characters of the tool result JSON")]
+    pub result_preview: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for tool activity search results, newest first")]
+pub struct ToolActivityResponse {
+    pub items: Vec<ToolActivityResult>,
+    #[schemars(description = "Error message if a filter could not be understood")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolStatusFilter {
+    /// Calls with a status that doesn't report a failure
+    Success,
+    /// Calls whose status is `error`, `failed` or `failure`
+    Error,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct FindToolCallsRequest {
+    #[schemars(description = "Only calls of this tool (exact name, case-insensitive)")]
+    pub tool_name: Option<String>,
+    #[schemars(description = "Only calls that succeeded ('success') or failed ('error')")]
+    pub tool_status: Option<ToolStatusFilter>,
+    #[schemars(description = "Text the tool name, parameters or result must contain, matched as a phrase (e.g. 'terraform apply')")]
+    pub query: Option<String>,
+    #[schemars(description = "Only calls made at or after this time: unix seconds, an ISO date, or a phrase like 'yesterday', 'last week', '3 days ago' or 'March 2024' (from its start)")]
+    pub since: Option<DateInput>,
+    #[schemars(description = "Only calls made before this time, in the same forms as 'since'; a phrase counts up to its end, so 'yesterday' includes all of yesterday")]
+    pub until: Option<DateInput>,
+    #[schemars(description = "Maximum number of calls to return (default: 50, max: 200)")]
+    pub limit: Option<u32>,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['conversation_id', 'params_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+// Advanced search types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AdvancedSearchRequest {
+    #[schemars(description = "Keywords to search in message content (OR semantics). Optional: without keywords or phrase, the filters alone select messages")]
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[schemars(description = "Exact phrase the message must contain")]
+    pub phrase: Option<String>,
+    #[schemars(description = "Treat the last word of each keyword and of the phrase as a prefix")]
+    #[serde(default)]
+    pub prefix: bool,
+    #[schemars(description = "Only messages with this role: 'user', 'assistant', 'tool' or 'system'")]
+    pub role: Option<String>,
+    #[schemars(description = "Only messages from conversations of this profile")]
+    pub profile_name: Option<String>,
+    #[schemars(description = "Only messages from this conversation")]
+    pub conversation_id: Option<String>,
+    #[schemars(description = "Only calls of this tool (exact name, case-insensitive)")]
+    pub tool_name: Option<String>,
+    #[schemars(description = "Only messages from conversations of this project: ones whose title or messages mention any of its keywords (see create_project)")]
+    pub project: Option<String>,
+    #[schemars(description = "Only messages from conversations with every one of these tags (see tag_conversation)")]
+    pub tags: Option<Vec<String>>,
+    #[schemars(description = "Only user and assistant messages detected to be in this language: an ISO 639-3 code ('eng', 'pol') or an English name ('Polish')")]
+    pub language: Option<String>,
+    #[schemars(description = "Also search the synonyms of each keyword (see add_synonyms); the response lists them as expansions (default: true)")]
+    pub expand_synonyms: Option<bool>,
+    #[schemars(description = "Only messages created at or after this time: unix seconds, an ISO date, or a phrase like 'yesterday', 'last week', '3 days ago' or 'March 2024' (from its start)")]
+    pub since: Option<DateInput>,
+    #[schemars(description = "Only messages created before this time, in the same forms as 'since'; a phrase counts up to its end, so 'yesterday' includes all of yesterday")]
+    pub until: Option<DateInput>,
+    #[schemars(description = "Result order: 'recent' (default), 'oldest', 'relevance' or 'recency_boosted'; the relevance orders need keywords or a phrase")]
+    pub sort: Option<SearchSort>,
+    #[schemars(description = "Age at which a match counts half as much under 'recency_boosted' (default: 30)")]
+    pub half_life_days: Option<f64>,
+    #[schemars(description = "Only messages whose relevance score (bm25, or recency-weighted under 'recency_boosted') is at least this; results then carry their score whatever the sort. Scores grow with how often and how rarely-used the matched terms are; 0 means no match in the message content")]
+    pub min_score: Option<f64>,
+    #[schemars(description = "Maximum number of messages to return (default: 50, max: 200)")]
+    pub limit: Option<u32>,
+    #[schemars(description = "Number of messages to skip, for the next page (default: 0)")]
+    pub offset: Option<u32>,
+    #[schemars(description = "Length of content_preview in characters (default: 200, max: 2000)")]
+    pub preview_chars: Option<u32>,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['message_id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "One page of advanced search results")]
+pub struct AdvancedSearchResponse {
+    pub items: Vec<SearchResult>,
+    #[schemars(description = "Number of matching messages across all pages")]
+    pub total: i64,
+    pub offset: u32,
+    pub limit: u32,
+    #[schemars(description = "Whether another page follows; request it with offset + limit")]
+    pub has_more: bool,
+    #[schemars(description = "Synonyms searched on top of the keywords")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expansions: Vec<SynonymExpansion>,
+    #[schemars(description = "Why nothing was found, when nothing was")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<SearchDiagnostics>,
+    #[schemars(description = "Error message if the search failed")]
+    pub error: Option<String>,
+}
+
+// Saved search types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SaveSearchRequest {
+    #[schemars(description = "Name to run the search by later (e.g. 'project-luna status'); saving under an existing name replaces it")]
+    pub name: String,
+    #[schemars(description = "search_conversations parameters to save. fields is not saved; pass it to run_saved_search instead")]
+    pub search: SearchConversationsRequest,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RunSavedSearchRequest {
+    #[schemars(description = "Name the search was saved under")]
+    pub name: String,
+    #[schemars(description = "Only return these fields of each result object (e.g. ['id', 'content_preview']). Omit to return all fields")]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A saved search_conversations query and its last run")]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    #[schemars(description = "The saved search_conversations parameters")]
+    pub search: serde_json::Value,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub run_count: i64,
+    pub last_run_at: Option<i64>,
+    #[schemars(description = "Number of results the last run returned")]
+    pub last_result_count: Option<i64>,
+    #[schemars(description = "Error message if saving failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for saved searches, by name")]
+pub struct SavedSearchesResponse {
+    pub items: Vec<SavedSearch>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Results of a saved search, with what changed since its previous run")]
+pub struct SavedSearchRun {
+    pub name: String,
+    pub previous_run_at: Option<i64>,
+    pub previous_result_count: Option<i64>,
+    #[schemars(description = "Results created after the previous run (all of them on the first run)")]
+    pub new_since_last_run: i64,
+    pub items: Vec<SearchResult>,
+    pub keyword_hits: Vec<KeywordHits>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counts: Option<SearchCounts>,
+    #[schemars(description = "Error message if the search could not be run")]
+    pub error: Option<String>,
+}
+
+// Preference types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SetPreferenceRequest {
+    #[schemars(description = "Preference key, e.g. 'editor' or 'timezone'; matched exactly, case included")]
+    pub key: String,
+    #[schemars(description = "Value to store, e.g. 'helix' or 'Europe/Warsaw'; replaces any previous value")]
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GetPreferenceRequest {
+    #[schemars(description = "Preference key, matched exactly")]
+    pub key: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A user preference: an exact key-value setting")]
+pub struct Preference {
+    pub key: String,
+    pub value: String,
+    pub updated_at: i64,
+    #[schemars(description = "Error message if the preference is not set or could not be saved")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for preferences, by key")]
+pub struct PreferencesResponse {
+    pub items: Vec<Preference>,
+}
+
+// Scratchpad types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ScratchpadWriteRequest {
+    #[schemars(description = "Session or topic name to keep the notes under, e.g. 'refactor-plan'. Omit to write the session's working scratchpad (see set_session_scope)")]
+    pub name: Option<String>,
+    #[schemars(description = "Notes to write, e.g. the current plan and its remaining steps")]
+    pub content: String,
+    #[schemars(description = "Add content as a new line after the existing notes instead of replacing them (default: false)")]
+    #[serde(default)]
+    pub append: bool,
+    #[schemars(description = "Delete the scratchpad this many hours after this write (default: 168, one week)")]
+    pub ttl_hours: Option<f64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ScratchpadReadRequest {
+    #[schemars(description = "Scratchpad to read. Omit to read the session's working scratchpad (see set_session_scope), or without one to list every scratchpad, most recently written first")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ScratchpadClearRequest {
+    #[schemars(description = "Scratchpad to delete")]
+    pub name: String,
+    #[schemars(description = "Token from preview_scratchpad_clear for this name. Required when the server enforces confirmations")]
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewScratchpadClearRequest {
+    #[schemars(description = "Scratchpad to be deleted")]
+    pub name: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ScratchpadClearPreview {
+    #[schemars(description = "The scratchpad that would be deleted")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scratchpad: Option<ScratchpadEntry>,
+    #[schemars(description = "Single-use token to pass to scratchpad_clear")]
+    pub confirmation_token: Option<String>,
+    #[schemars(description = "Unix timestamp after which the token is no longer accepted")]
+    pub expires_at: Option<i64>,
+    #[schemars(description = "Error message if the preview failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Working notes kept under a session or topic name")]
+pub struct ScratchpadEntry {
+    pub name: String,
+    pub content: String,
+    pub updated_at: i64,
+    #[schemars(description = "When the scratchpad is deleted (unix seconds) unless written again")]
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for scratchpads")]
+pub struct ScratchpadResponse {
+    pub items: Vec<ScratchpadEntry>,
+    #[schemars(description = "Error message if the scratchpad could not be written or does not exist")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ScratchpadClearResponse {
+    pub success: bool,
+    #[schemars(description = "Error message if nothing was deleted")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Session scope types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SetSessionScopeRequest {
+    #[schemars(description = "Profile to work in: list_conversations and search_conversations only return its conversations, and advanced_search defaults profile_name to it. An empty string clears it; omit to keep the current one")]
+    pub profile: Option<String>,
+    #[schemars(description = "Scratchpad scratchpad_write and scratchpad_read use when called without a name. An empty string clears it; omit to keep the current one")]
+    pub scratchpad: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Defaults that apply to this session only, until it ends or they are changed")]
+pub struct SessionScope {
+    #[schemars(description = "Profile conversation listings and searches are limited to")]
+    pub profile: Option<String>,
+    #[schemars(description = "Working scratchpad for scratchpad_write and scratchpad_read")]
+    pub scratchpad: Option<String>,
+}
+
+// Attachment types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StoreAttachmentRequest {
+    #[schemars(description = "File name of the attachment, e.g. 'architecture.svg'; its extension sets the default mime_type")]
+    pub name: String,
+    #[schemars(description = "Text content to store, e.g. a config file or a Mermaid diagram")]
+    pub text: Option<String>,
+    #[schemars(description = "Binary content to store, base64-encoded, e.g. an image")]
+    pub base64: Option<String>,
+    #[schemars(description = "Path of a file to reference instead of storing content, relative to the server's attachments directory; it is read from disk whenever the attachment is")]
+    pub file_path: Option<String>,
+    #[schemars(description = "MIME type of the content (default: guessed from the name)")]
+    pub mime_type: Option<String>,
+    #[schemars(description = "ID of the memory entry to attach it to, so the memory's search results list it")]
+    pub memory_id: Option<i64>,
+    #[schemars(description = "ID of the message to attach it to")]
+    pub message_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AttachmentInfo {
+    pub id: i64,
+    #[schemars(description = "Resource URI to read the attachment by, attachment://{id}")]
+    pub uri: String,
+    pub name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    #[schemars(description = "SHA-256 of the content, hex-encoded")]
+    pub sha256: String,
+    #[schemars(description = "Referenced file, for attachments whose content stays on disk")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<i64>,
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StoreAttachmentResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<AttachmentInfo>,
+    #[schemars(description = "Error message if the attachment could not be stored")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GetAttachmentRequest {
+    #[schemars(description = "ID of the attachment, as listed in a memory's attachments")]
+    pub attachment_id: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GetAttachmentResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<AttachmentInfo>,
+    #[schemars(description = "Content of a text attachment")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[schemars(description = "Content of a binary attachment, base64-encoded")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+    #[schemars(description = "The referenced file changed since it was attached; the content is the current file")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub file_changed: bool,
+    #[schemars(description = "Error message if the attachment could not be read")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Bookmark types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SaveBookmarkRequest {
+    #[schemars(description = "The http(s) URL to bookmark")]
+    pub url: String,
+    #[schemars(description = "Why the link matters, in your own words, e.g. 'the fix for the helm rollback bug'")]
+    pub note: Option<String>,
+    #[schemars(description = "Tags to file the bookmark under, added to any it already has")]
+    pub tags: Option<Vec<String>>,
+    #[schemars(description = "Fetch the page to store its title and description (default: true)")]
+    pub fetch_metadata: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Bookmark {
+    pub id: i64,
+    pub url: String,
+    #[schemars(description = "Page title, from its Open Graph or <title> tag")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[schemars(description = "Page description, from its Open Graph or description meta tag")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+    pub updated_at: i64,
+    #[schemars(description = "When the page's metadata was last fetched (unix seconds)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetched_at: Option<i64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SaveBookmarkResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bookmark: Option<Bookmark>,
+    #[schemars(description = "Why the page's metadata couldn't be fetched; the bookmark is saved without it")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_error: Option<String>,
+    #[schemars(description = "Error message if the bookmark could not be saved")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SearchBookmarksRequest {
+    #[schemars(description = "Keywords to find in the URL, title, description, note or tags; prefixes match. Omit to list the most recently saved bookmarks")]
+    pub keywords: Option<Vec<String>>,
+    #[schemars(description = "Only bookmarks carrying all of these tags")]
+    pub tags: Option<Vec<String>>,
+    #[schemars(description = "Maximum number of bookmarks to return (default: 20)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct BookmarksResponse {
+    pub items: Vec<Bookmark>,
+    #[schemars(description = "Error message if the search failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Snippet types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StoreSnippetRequest {
+    #[schemars(description = "The code or command, verbatim")]
+    pub code: String,
+    #[schemars(description = "Language of the code, e.g. 'rust', 'python' or 'shell'; common short names like 'py' or 'sh' are stored under the full name")]
+    pub language: String,
+    #[schemars(description = "What the snippet does, in words you'd search for later, e.g. 'list pods stuck in Pending'")]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SnippetEntry {
+    pub id: i64,
+    pub language: String,
+    pub description: String,
+    pub code: String,
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StoreSnippetResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<SnippetEntry>,
+    #[schemars(description = "Error message if the snippet could not be stored")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SearchSnippetsRequest {
+    #[schemars(description = "Words or identifiers to find in the description or code; getUserName, get_user_name and 'user name' find each other. Omit to list the newest snippets")]
+    pub query: Option<String>,
+    #[schemars(description = "Only snippets in this language, e.g. 'rust' or 'sh'")]
+    pub language: Option<String>,
+    #[schemars(description = "Maximum number of snippets to return (default: 10)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SnippetsResponse {
+    pub items: Vec<SnippetEntry>,
+    #[schemars(description = "Error message if the search failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Fact types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AssertFactRequest {
+    #[schemars(description = "What the fact is about, e.g. 'user', \"user's dog\" or 'project luna'")]
+    pub subject: String,
+    #[schemars(description = "The relation or attribute, e.g. 'name', 'lives in' or 'prefers editor'")]
+    pub predicate: String,
+    #[schemars(description = "The value, e.g. 'Rex', 'Kraków' or 'helix'")]
+    pub object: String,
+    #[schemars(description = "How sure the fact is, from 0.0 to 1.0 (default: 1.0)")]
+    pub confidence: Option<f64>,
+    #[schemars(description = "Where the fact comes from, e.g. 'user said so' or a conversation ID")]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Fact {
+    pub id: i64,
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+    pub confidence: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+    #[schemars(description = "When the fact was last asserted (unix seconds)")]
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AssertFactResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fact: Option<Fact>,
+    #[schemars(description = "The triple was already known; its confidence and source were updated")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub already_known: bool,
+    #[schemars(description = "Facts with the same subject and predicate but another object, which this one contradicts. Settle each with resolve_conflict")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Conflict>,
+    #[schemars(description = "Error message if the fact could not be stored")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct QueryFactsRequest {
+    #[schemars(description = "Only facts about this subject (exact, ignoring case and spacing)")]
+    pub subject: Option<String>,
+    #[schemars(description = "Only facts with this predicate (exact, ignoring case and spacing)")]
+    pub predicate: Option<String>,
+    #[schemars(description = "Only facts with this object (exact, ignoring case and spacing)")]
+    pub object: Option<String>,
+    #[schemars(description = "Only facts at least this confident")]
+    pub min_confidence: Option<f64>,
+    #[schemars(description = "Maximum number of facts to return (default: 50)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct FactsResponse {
+    pub items: Vec<Fact>,
+    #[schemars(description = "Error message if the query failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Conflict types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Two memories stored with the same key
+    Memory,
+    /// Two facts with the same subject and predicate
+    Fact,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictSide {
+    /// Keep the newer value and delete the existing one
+    New,
+    /// Keep the existing value and delete the newer one
+    Existing,
+    /// Both hold, e.g. the user has two dogs; delete neither
+    Both,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Conflict {
+    pub id: i64,
+    pub kind: ConflictKind,
+    #[schemars(description = "What both sides are about: the memories' key, or the facts' subject and predicate")]
+    pub key: String,
+    #[schemars(description = "Id of the earlier memory or fact")]
+    pub existing_id: i64,
+    #[schemars(description = "What the earlier one said when the conflict was found")]
+    pub existing_value: String,
+    #[schemars(description = "Id of the memory or fact that contradicted it")]
+    pub new_id: i64,
+    pub new_value: String,
+    pub detected_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detected_at_iso: Option<String>,
+    #[schemars(description = "Which side resolve_conflict kept; absent while the conflict is open")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kept: Option<ConflictSide>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<i64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ListConflictsRequest {
+    #[schemars(description = "Also list resolved conflicts (default: false)")]
+    #[serde(default)]
+    pub include_resolved: bool,
+    #[schemars(description = "Maximum number of conflicts to return (default: 50)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ConflictsResponse {
+    #[schemars(description = "Open conflicts first, newest first")]
+    pub items: Vec<Conflict>,
+    #[schemars(description = "Error message if the conflicts could not be read")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ResolveConflictRequest {
+    #[schemars(description = "Conflict id from list_conflicts or a store_memory / assert_fact response")]
+    pub conflict_id: i64,
+    #[schemars(description = "Which side to keep: 'new' or 'existing' deletes the other memory or fact, 'both' keeps both")]
+    pub keep: ConflictSide,
+    #[schemars(description = "Token from preview_resolve_conflict for this conflict_id and keep. Required when the server enforces confirmations and a side is deleted")]
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewResolveConflictRequest {
+    #[schemars(description = "Conflict id from list_conflicts or a store_memory / assert_fact response")]
+    pub conflict_id: i64,
+    #[schemars(description = "Which side resolve_conflict would keep: 'new', 'existing' or 'both'")]
+    pub keep: ConflictSide,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ResolveConflictPreview {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<Conflict>,
+    #[schemars(description = "Id of the memory or fact resolve_conflict would delete, if any")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_id: Option<i64>,
+    #[schemars(description = "What the deleted memory or fact says")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_value: Option<String>,
+    #[schemars(description = "Single-use token to pass to resolve_conflict")]
+    pub confirmation_token: Option<String>,
+    #[schemars(description = "Unix timestamp after which the token is no longer accepted")]
+    pub expires_at: Option<i64>,
+    #[schemars(description = "Error message if the preview failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ResolveConflictResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<Conflict>,
+    #[schemars(description = "Id of the memory or fact deleted, if any")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_id: Option<i64>,
+    #[schemars(description = "Error message if the conflict could not be resolved")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Reminder types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CreateReminderRequest {
+    #[schemars(description = "What to remind the user of")]
+    pub content: String,
+    #[schemars(description = "When the reminder is due: unix seconds or text such as 'tomorrow', 'in 3 days', 'next friday' or '2024-03-15 09:00'. A day is due from its start")]
+    pub due_at: DateInput,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ListDueRemindersRequest {
+    #[schemars(description = "Reminders due by this time: unix seconds or text such as 'today' (all of today) or 'this week' (default: now)")]
+    pub now: Option<DateInput>,
+    #[schemars(description = "Maximum number of reminders to return (default: 50, max: 500)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CompleteReminderRequest {
+    #[schemars(description = "The ID of the reminder to mark done")]
+    pub reminder_id: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A reminder and when it is due")]
+pub struct Reminder {
+    pub id: i64,
+    pub content: String,
+    pub due_at: i64,
+    #[schemars(description = "due_at as an RFC 3339 date and time in the server's timezone")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_at_iso: Option<String>,
+    #[schemars(description = "due_at relative to now, e.g. '2 days ago' (overdue) or 'in 3 hours'")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    pub created_at: i64,
+    #[schemars(description = "When the reminder was completed; absent while it is open")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<i64>,
+    #[schemars(description = "Error message if the reminder could not be created")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for reminders, earliest due first")]
+pub struct RemindersResponse {
+    pub items: Vec<Reminder>,
+    #[schemars(description = "Error message if `now` could not be understood")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CompleteReminderResponse {
+    pub success: bool,
+    #[schemars(description = "Error message if the reminder could not be completed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Journal types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AppendJournalRequest {
+    #[schemars(description = "Day the entry belongs to: a date such as '2024-03-15' or text such as 'yesterday' (default: today)")]
+    pub date: Option<DateInput>,
+    #[schemars(description = "What was worked on, decided or left open, in a few sentences")]
+    pub text: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GetJournalRequest {
+    #[schemars(description = "First day to include: a date or text such as 'last week' or 'March 2024'")]
+    pub since: Option<DateInput>,
+    #[schemars(description = "Last day to include: a date or text such as 'yesterday'")]
+    pub until: Option<DateInput>,
+    #[schemars(description = "Only entries mentioning any of these keywords")]
+    pub keywords: Option<Vec<String>>,
+    #[schemars(description = "Maximum number of entries; the latest are kept (default: 100, max: 1000)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A journal entry")]
+pub struct JournalEntry {
+    pub id: i64,
+    #[schemars(description = "Day the entry belongs to (YYYY-MM-DD)")]
+    pub date: String,
+    pub text: String,
+    pub created_at: i64,
+    #[schemars(description = "created_at as an RFC 3339 date and time in the server's timezone")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+    #[schemars(description = "Error message if the entry could not be added")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for journal entries, oldest day first")]
+pub struct JournalResponse {
+    pub items: Vec<JournalEntry>,
+    #[schemars(description = "Error message if a date could not be understood")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// People types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RememberPersonRequest {
+    #[schemars(description = "The person's name; someone already known by this name or alias is updated")]
+    pub name: String,
+    #[schemars(description = "Other names they go by, e.g. nicknames or a surname alone; added to the ones already known")]
+    pub aliases: Option<Vec<String>>,
+    #[schemars(description = "How they relate to the user, e.g. 'coworker on project Luna' or 'sister'; replaces the previous one")]
+    pub relationship: Option<String>,
+    #[schemars(description = "Facts worth remembering about them; appended to earlier notes")]
+    pub notes: Option<String>,
+    #[schemars(description = "ID of the message that last mentioned them")]
+    pub message_id: Option<i64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GetPersonRequest {
+    #[schemars(description = "Name or alias, ignoring case")]
+    pub name: String,
+    #[schemars(description = "Number of latest messages mentioning them by name or alias to include (default: 10, max: 50; 0 for none)")]
+    pub mentions_limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SearchPeopleRequest {
+    #[schemars(description = "Text to look for in names, aliases, relationships and notes, ignoring case, e.g. 'coworker'. Omit to list everyone")]
+    #[serde(default)]
+    pub query: String,
+    #[schemars(description = "Maximum number of people to return (default: 50, max: 500)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A person the user mentions")]
+pub struct Person {
+    pub id: i64,
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub relationship: Option<String>,
+    pub notes: Option<String>,
+    #[schemars(description = "ID of the message that last mentioned them, as recorded by remember_person")]
+    pub last_mentioned_message_id: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A person and the latest messages mentioning them")]
+pub struct PersonResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub person: Option<Person>,
+    #[schemars(description = "Latest messages mentioning the person by name or alias, newest first")]
+    pub mentions: Vec<SearchResult>,
+    #[schemars(description = "Error message if the person is unknown or could not be saved")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for people, by name")]
+pub struct PeopleResponse {
+    pub items: Vec<Person>,
+}
+
+// Tag types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TagConversationRequest {
+    #[schemars(description = "The conversation to tag")]
+    pub conversation_id: String,
+    #[schemars(description = "Tags to add, e.g. ['bug-hunt', 'cosmic-applet']; compared ignoring case")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UntagConversationRequest {
+    #[schemars(description = "The conversation to untag")]
+    pub conversation_id: String,
+    #[schemars(description = "Tags to remove, ignoring case")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationTagsResponse {
+    pub success: bool,
+    #[schemars(description = "The conversation's tags after the change")]
+    pub tags: Vec<String>,
+    #[schemars(description = "Error message if the conversation could not be tagged")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Synonym types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Synonyms a search keyword was expanded with")]
+pub struct SynonymExpansion {
+    pub keyword: String,
+    pub synonyms: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AddSynonymsRequest {
+    #[schemars(description = "The term, e.g. 'k8s'; compared ignoring case")]
+    pub term: String,
+    #[schemars(description = "Terms to search along with it, e.g. ['kubernetes']. Links work both ways")]
+    pub synonyms: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RemoveSynonymsRequest {
+    #[schemars(description = "The term to unlink synonyms from")]
+    pub term: String,
+    #[schemars(description = "Synonyms to unlink; omit to unlink all of the term's synonyms")]
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SynonymsResponse {
+    pub success: bool,
+    pub term: String,
+    #[schemars(description = "The term's synonyms after the change")]
+    pub synonyms: Vec<String>,
+    #[schemars(description = "Error message if the synonyms could not be changed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A term and the synonyms added for it")]
+pub struct SynonymSet {
+    pub term: String,
+    pub synonyms: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SynonymSetsResponse {
+    pub items: Vec<SynonymSet>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SuggestSynonymsRequest {
+    #[schemars(description = "The term to suggest synonyms for, e.g. 'deployment'")]
+    pub term: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SynonymSuggestions {
+    pub term: String,
+    #[schemars(description = "Suggested synonyms the term doesn't have yet; add the good ones with add_synonyms")]
+    pub suggestions: Vec<String>,
+    #[schemars(description = "Synonyms the term already has")]
+    pub existing: Vec<String>,
+    #[schemars(description = "Model the client sampled the suggestions from")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[schemars(description = "Error message if no suggestions could be sampled")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct FindRelatedConversationsRequest {
+    #[schemars(description = "The conversation to find related threads for")]
+    pub conversation_id: String,
+    #[schemars(description = "Maximum number of conversations to return (default: 10, max: 50)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A conversation sharing distinctive terms with the given one")]
+pub struct RelatedConversation {
+    pub conversation_id: String,
+    pub title: String,
+    pub created_at: i64,
+    #[schemars(description = "created_at as an RFC 3339 date and time in the server's timezone")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+    #[schemars(description = "Share of the given conversation's term weight this one shares, 0.0 to 1.0")]
+    pub score: f64,
+    #[schemars(description = "The shared terms, most distinctive first")]
+    pub shared_terms: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for related conversations, most similar first")]
+pub struct RelatedConversationsResponse {
+    pub items: Vec<RelatedConversation>,
+    #[schemars(description = "Error message if related conversations could not be found")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ExtractKeywordsRequest {
+    #[schemars(description = "The conversation to extract keyphrases from")]
+    pub conversation_id: String,
+    #[schemars(description = "Maximum number of keyphrases (default: 10, max: 50)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Keyphrase {
+    pub phrase: String,
+    #[schemars(description = "RAKE score: the sum of the phrase's word scores, times its occurrences; higher is more characteristic")]
+    pub score: f64,
+    #[schemars(description = "How often the phrase occurs in the conversation")]
+    pub occurrences: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct KeywordsResponse {
+    pub conversation_id: String,
+    #[schemars(description = "Keyphrases, best first")]
+    pub keyphrases: Vec<Keyphrase>,
+    #[schemars(description = "Whether the keyphrases were cached for list_conversations and search_conversation_titles")]
+    pub cached: bool,
+    #[schemars(description = "Error message if keyphrases could not be extracted")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SuggestTitleRequest {
+    #[schemars(description = "The conversation to suggest a title for")]
+    pub conversation_id: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TitleSuggestions {
+    pub conversation_id: String,
+    pub current_title: String,
+    #[schemars(description = "Suggested titles, best first")]
+    pub suggestions: Vec<String>,
+    #[schemars(description = "Error message if no titles could be suggested")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateTitleRequest {
+    #[schemars(description = "The conversation to rename")]
+    pub conversation_id: String,
+    #[schemars(description = "The new title")]
+    pub title: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct UpdateTitleResponse {
+    pub success: bool,
+    #[schemars(description = "The title before the change")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_title: Option<String>,
+    #[schemars(description = "Error message if the title could not be changed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteConversationRequest {
+    #[schemars(description = "The conversation to delete")]
+    pub conversation_id: String,
+    #[schemars(description = "Delete it for good instead of moving it to the trash; also empties it from the trash. Clients that support elicitation ask the user first (default: false)")]
+    #[serde(default)]
+    pub permanent: bool,
+    #[schemars(description = "Token from preview_delete_conversation for this conversation_id. Required for permanent deletes when the server enforces confirmations")]
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewDeleteConversationRequest {
+    #[schemars(description = "The conversation to be deleted for good")]
+    pub conversation_id: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DeleteConversationPreview {
+    pub conversation_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[schemars(description = "Messages that would be deleted")]
+    pub message_count: usize,
+    #[schemars(description = "Whether the conversation is already in the trash")]
+    pub in_trash: bool,
+    #[schemars(description = "Single-use token to pass to delete_conversation with permanent")]
+    pub confirmation_token: Option<String>,
+    #[schemars(description = "Unix timestamp after which the token is no longer accepted")]
+    pub expires_at: Option<i64>,
+    #[schemars(description = "Error message if the preview failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DeleteConversationResponse {
+    pub success: bool,
+    pub conversation_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[schemars(description = "Messages removed from the history")]
+    pub messages_deleted: usize,
+    #[schemars(description = "Whether the conversation is gone for good rather than in the trash")]
+    pub permanent: bool,
+    #[schemars(description = "Until when restore_conversation can bring it back, in unix seconds; absent for permanent deletes")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restorable_until: Option<i64>,
+    #[schemars(description = "Error message if the conversation could not be deleted")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RestoreConversationRequest {
+    #[schemars(description = "The deleted conversation to bring back from the trash")]
+    pub conversation_id: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RestoreConversationResponse {
+    pub success: bool,
+    pub conversation_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub messages_restored: usize,
+    #[schemars(description = "Error message if the conversation could not be restored")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RedactMessageRequest {
+    #[schemars(description = "The message to redact")]
+    pub message_id: i64,
+    #[schemars(description = "Only replace this exact text, e.g. a pasted password, wherever it appears in the message, its tool parameters and result and its reasoning (default: blank the whole message)")]
+    pub text: Option<String>,
+    #[schemars(description = "Token from preview_redact_message for this message_id and text. Required when the server enforces confirmations")]
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewRedactMessageRequest {
+    #[schemars(description = "The message to be redacted")]
+    pub message_id: i64,
+    #[schemars(description = "The exact text to be replaced (default: blank the whole message)")]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RedactMessagePreview {
+    pub message_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    #[schemars(description = "Columns that would change: content, tool_params_json, tool_result_json, reasoning_content")]
+    pub fields_redacted: Vec<String>,
+    #[schemars(description = "The message content as it would read afterwards")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redacted_content: Option<String>,
+    #[schemars(description = "Single-use token to pass to redact_message")]
+    pub confirmation_token: Option<String>,
+    #[schemars(description = "Unix timestamp after which the token is no longer accepted")]
+    pub expires_at: Option<i64>,
+    #[schemars(description = "Error message if the preview failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RedactMessageResponse {
+    pub success: bool,
+    pub message_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    #[schemars(description = "Columns that were changed: content, tool_params_json, tool_result_json, reasoning_content")]
+    pub fields_redacted: Vec<String>,
+    #[schemars(description = "Error message if the message could not be redacted")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveConversationsRequest {
+    #[schemars(description = "Archive conversations whose last message is older than this: unix seconds, an ISO date, or a phrase like 'last year' (from its start)")]
+    pub before_date: DateInput,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ArchiveConversationsResponse {
+    pub success: bool,
+    #[schemars(description = "Archive database file the conversations were moved to")]
+    pub archive_path: String,
+    pub conversations_archived: usize,
+    pub messages_archived: usize,
+    #[schemars(description = "Conversations old enough to archive that stayed because they are pinned")]
+    pub pinned_skipped: usize,
+    #[schemars(description = "Error message if the conversations could not be archived")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ClusterTopicsRequest {
+    #[schemars(description = "Number of topics (default: about the square root of half the number of conversations, 2 to 30; max: 100)")]
+    pub topics: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A conversation representing a topic")]
+pub struct TopicConversation {
+    pub conversation_id: String,
+    pub title: String,
+    pub created_at: i64,
+    #[schemars(description = "created_at as an RFC 3339 date and time in the server's timezone")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_iso: Option<String>,
+    #[schemars(description = "Cosine similarity to the topic's center, 0.0 to 1.0")]
+    pub similarity: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A group of conversations with similar vocabulary")]
+pub struct Topic {
+    pub id: i64,
+    #[schemars(description = "The topic's three most central terms")]
+    pub label: String,
+    #[schemars(description = "The topic's most central terms, most central first")]
+    pub terms: Vec<String>,
+    #[schemars(description = "Number of conversations in the topic")]
+    pub size: usize,
+    #[schemars(description = "Date (YYYY-MM-DD) of the topic's oldest conversation")]
+    pub first_date: Option<String>,
+    #[schemars(description = "Date (YYYY-MM-DD) of the topic's newest conversation")]
+    pub last_date: Option<String>,
+    #[schemars(description = "The conversations closest to the topic's center")]
+    pub representatives: Vec<TopicConversation>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TopicClusteringReport {
+    #[schemars(description = "Conversations in the history")]
+    pub conversations: usize,
+    #[schemars(description = "Conversations without terms in common with others, left out of every topic")]
+    pub unclustered: usize,
+    #[schemars(description = "k-means rounds until the topics settled")]
+    pub iterations: usize,
+    #[schemars(description = "Topics, largest first")]
+    pub topics: Vec<Topic>,
+    pub duration_ms: u64,
+    #[schemars(description = "Error message if clustering failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PinConversationRequest {
+    #[schemars(description = "The conversation to pin")]
+    pub conversation_id: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UnpinConversationRequest {
+    #[schemars(description = "The conversation to unpin")]
+    pub conversation_id: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PinConversationResponse {
+    pub success: bool,
+    #[schemars(description = "Error message if the conversation could not be pinned or unpinned")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A tag and how many conversations carry it")]
+pub struct TagCount {
+    pub tag: String,
+    pub conversations: i64,
+    #[schemars(description = "When the tag was last added to a conversation")]
+    pub last_used_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for tags, most used first")]
+pub struct TagsResponse {
+    pub items: Vec<TagCount>,
+}
+
+// Project types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CreateProjectRequest {
+    #[schemars(description = "Project name, e.g. 'mcp_luna_memory'; unique, ignoring case")]
+    pub name: String,
+    #[schemars(description = "What the project is")]
+    pub description: Option<String>,
+    #[schemars(description = "Terms that identify the project's conversations, e.g. its crate name, repository or main modules. Default: the project name")]
+    pub keywords: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AssignMemoryToProjectRequest {
+    #[schemars(description = "The ID of the memory entry to assign")]
+    pub memory_id: i64,
+    #[schemars(description = "Project to assign it to; omit to take the memory out of its project")]
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A project scoping memory and conversation search")]
+pub struct Project {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    #[schemars(description = "Terms identifying the project's conversations; the name is used when empty")]
+    pub keywords: Vec<String>,
+    pub created_at: i64,
+    #[schemars(description = "Number of memories assigned to the project")]
+    pub memory_count: i64,
+    #[schemars(description = "Error message if the project could not be created")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for projects, by name")]
+pub struct ProjectsResponse {
+    pub items: Vec<Project>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AddProjectRuleRequest {
+    #[schemars(description = "Project the rule tags conversations with")]
+    pub project: String,
+    #[schemars(description = "Text a conversation must mention, ignoring case, e.g. 'mcp_luna' or 'cosmic applet'")]
+    pub pattern: String,
+    #[schemars(description = "Where to look for the pattern: 'any' (default: title or messages), 'title' or 'content'")]
+    #[serde(default)]
+    pub scope: RuleScope,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleScope {
+    /// Conversation title or any message
+    #[default]
+    Any,
+    /// Conversation title only
+    Title,
+    /// Message content only
+    Content,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ListProjectRulesRequest {
+    #[schemars(description = "Only rules of this project")]
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ClassifyConversationsRequest {
+    #[schemars(description = "Only apply the rules of this project (default: every project)")]
+    pub project: Option<String>,
+    #[schemars(description = "Report what would be tagged without tagging anything (default: false)")]
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A rule tagging conversations that mention a pattern with a project")]
+pub struct ProjectRule {
+    pub id: i64,
+    pub project: String,
+    pub pattern: String,
+    pub scope: RuleScope,
+    pub created_at: i64,
+    #[schemars(description = "Error message if the rule could not be added")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for project rules, by project and pattern")]
+pub struct ProjectRulesResponse {
+    pub items: Vec<ProjectRule>,
+    #[schemars(description = "Error message if the project does not exist")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "What classification tagged for one project")]
+pub struct ProjectClassification {
+    pub project: String,
+    #[schemars(description = "Number of the project's rules applied")]
+    pub rules: i64,
+    #[schemars(description = "Conversations tagged by this run")]
+    pub newly_tagged: i64,
+    #[schemars(description = "Conversations tagged with the project in total")]
+    pub conversations: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Result of classify_conversations")]
+pub struct ClassifyConversationsReport {
+    pub projects: Vec<ProjectClassification>,
+    #[schemars(description = "Whether nothing was saved")]
+    pub dry_run: bool,
+    #[schemars(description = "Error message if classification failed; nothing is tagged then")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AssignMemoryToProjectResponse {
+    pub success: bool,
+    #[schemars(description = "Error message if the memory or project does not exist")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Search history types
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GetSearchHistoryRequest {
+    #[schemars(description = "Only searches run by this tool (e.g. 'search_conversations')")]
+    pub tool: Option<String>,
+    #[schemars(description = "Only searches that found something")]
+    #[serde(default)]
+    pub successful_only: bool,
+    #[schemars(description = "Instead of the latest searches, suggest earlier successful queries similar to this one, most similar first")]
+    pub similar_to: Option<String>,
+    #[schemars(description = "Maximum number of entries to return (default: 50, max: 500)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "A search a tool ran")]
+pub struct SearchHistoryEntry {
+    pub id: i64,
+    pub tool: String,
+    pub query: String,
+    #[schemars(description = "Number of results the search returned")]
+    pub hit_count: i64,
+    pub searched_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "An earlier query that found results, similar to the current one")]
+pub struct SearchSuggestion {
+    pub tool: String,
+    pub query: String,
+    #[schemars(description = "Results of its most successful run")]
+    pub hit_count: i64,
+    pub last_searched_at: i64,
+    #[schemars(description = "Character trigram similarity to the current query (0.0 to 1.0)")]
+    pub similarity: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for search history entries (newest first) or suggestions (most similar first)")]
+pub struct SearchHistoryResponse {
+    pub items: Vec<SearchHistoryEntry>,
+    pub suggestions: Vec<SearchSuggestion>,
+}
+
+// Export types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExportAllRequest {
+    #[schemars(description = "Subdirectory of the configured MCP_LUNA_EXPORT_DIR to write conversations.jsonl, memories.jsonl and manifest.json into (created if missing; default: MCP_LUNA_EXPORT_DIR itself)")]
+    pub output_dir: Option<String>,
+    #[schemars(description = "Mask email addresses, phone numbers and street addresses in the exported messages and memories (default: on when the server masks personal data in exports)")]
+    pub mask_pii: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExportReport {
+    pub schema_version: u32,
+    pub output_dir: String,
+    pub conversations: i64,
+    pub messages: i64,
+    pub memories: i64,
+    pub files: Vec<String>,
+    #[schemars(description = "Rough number of tokens of all exported conversations and memories (characters / 4)")]
+    pub estimated_tokens: u64,
+    #[schemars(description = "Whether personal data was masked in the exported records")]
+    pub pii_masked: bool,
+    #[schemars(description = "Error message if the export failed")]
+    pub error: Option<String>,
+}
+
+// Tool usage report types
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UsagePeriod {
+    Day,
+    Week,
+    Month,
+    Year,
+    #[default]
+    All,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ToolUsageReportRequest {
+    #[schemars(description = "Time window to aggregate: 'day', 'week', 'month', 'year' or 'all' (default: 'all')")]
+    pub period: Option<UsagePeriod>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ToolUsageConversation {
+    pub conversation_id: String,
+    pub title: Option<String>,
+    pub call_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ToolUsage {
+    pub tool_name: String,
+    pub call_count: i64,
+    pub conversation_count: i64,
+    pub last_used_at: i64,
+    #[schemars(description = "Conversations that used this tool the most")]
+    pub top_conversations: Vec<ToolUsageConversation>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Tool invocation counts over a period, most used tools first")]
+pub struct ToolUsageReport {
+    pub period: UsagePeriod,
+    #[schemars(description = "Start of the window (unix seconds); null for 'all'")]
+    pub since: Option<i64>,
+    pub total_calls: i64,
+    pub items: Vec<ToolUsage>,
+}
+
+// Import types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ImportReport {
+    pub source: String,
+    pub conversations_imported: i64,
+    pub messages_imported: i64,
+    #[schemars(description = "Previously imported conversations that received newer messages")]
+    pub conversations_updated: i64,
+    #[schemars(description = "Conversations skipped because they were already fully imported or empty")]
+    pub conversations_skipped: i64,
+}
+
+// Backup types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CreateBackupRequest {
+    #[schemars(description = "Subdirectory of the configured MCP_LUNA_BACKUP_DIR to write the snapshot into (default: MCP_LUNA_BACKUP_DIR itself)")]
+    pub output_dir: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BackupReport {
+    #[schemars(description = "Path of the snapshot file")]
+    pub path: String,
+    pub size_bytes: u64,
+    pub created_at: i64,
+    #[schemars(description = "Older snapshots deleted by the retention policy")]
+    pub removed: Vec<String>,
+    #[schemars(description = "Error message if the backup failed")]
+    pub error: Option<String>,
+}
+
+// Memory hygiene types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryHygieneReportRequest {
+    #[schemars(description = "Memories created and not retrieved for this many days count as stale (default: 180)")]
+    pub stale_after_days: Option<u32>,
+    #[schemars(description = "Word-overlap similarity (0-1) at which two memories count as near-duplicates (default: 0.8)")]
+    pub similarity_threshold: Option<f64>,
+    #[schemars(description = "Maximum entries per section (default: 50)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryUsage {
+    #[serde(flatten)]
+    pub entry: MemoryEntry,
+    #[schemars(description = "How many times search tools returned this memory")]
+    pub access_count: i64,
+    pub last_accessed_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct NearDuplicateMemories {
+    pub first: MemoryEntry,
+    pub second: MemoryEntry,
+    pub similarity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FindSimilarMemoriesRequest {
+    #[schemars(description = "The memory entry to compare the others with")]
+    pub memory_id: i64,
+    #[schemars(description = "Maximum number of memories to return (default: 10, max: 100)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SimilarMemory {
+    #[serde(flatten)]
+    pub entry: MemoryEntry,
+    #[schemars(description = "Share of distinct words the two memories have in common, 0.0 to 1.0")]
+    pub similarity: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(description = "Wrapper for similar memories, most similar first")]
+pub struct SimilarMemoriesResponse {
+    pub items: Vec<SimilarMemory>,
+    #[schemars(description = "Error message if the memory could not be compared")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GetMemoryHistoryRequest {
+    #[schemars(description = "The memory whose versions to list, deleted or not")]
+    pub memory_id: Option<i64>,
+    #[schemars(description = "List the versions of every memory stored under this key (see store_memory), following a value replaced by deleting and storing anew")]
+    pub key: Option<String>,
+    #[schemars(description = "Instead of every version, return each memory as it was at this time: unix seconds, an ISO date, or a phrase like 'last month' (up to its end). Without memory_id or key, every memory that existed then")]
+    pub as_of: Option<DateInput>,
+    #[schemars(description = "Maximum number of versions to return (default: 50, max: 500)")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryChange {
+    /// The memory as stored
+    Created,
+    /// The memory after a change to its content, category, importance, pinning, expiry or key
+    Updated,
+    /// The memory as it was when deleted
+    Deleted,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryVersion {
+    pub memory_id: i64,
+    pub change: MemoryChange,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    pub importance: i32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[schemars(description = "When the change happened (unix seconds)")]
+    pub changed_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_at_iso: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryHistoryResponse {
+    #[schemars(description = "Versions, newest first; with as_of, one per memory")]
+    pub items: Vec<MemoryVersion>,
+    #[schemars(description = "Error message if the history could not be read")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Curation checklist for long-term memory")]
+pub struct MemoryHygieneReport {
+    pub total_memories: i64,
+    #[schemars(description = "Memories no search has returned since retrieval tracking started")]
+    pub never_retrieved: Vec<MemoryUsage>,
+    #[schemars(description = "Old memories not retrieved within the stale threshold")]
+    pub stale: Vec<MemoryUsage>,
+    #[schemars(description = "Memories without a category")]
+    pub uncategorized: Vec<MemoryUsage>,
+    #[schemars(description = "Pairs of memories with nearly the same wording, most similar first")]
+    pub near_duplicates: Vec<NearDuplicateMemories>,
+}
+
+// Maintenance types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RunMaintenanceRequest {
+    #[schemars(description = "Also VACUUM to reclaim free pages (default: false). Rewrites the whole file and needs that much free disk space")]
+    pub vacuum: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MaintenanceReport {
+    pub integrity_ok: bool,
+    #[schemars(description = "Problems reported by PRAGMA integrity_check; empty when the database is healthy")]
+    pub integrity_problems: Vec<String>,
+    #[schemars(description = "Full-text indexes that were optimized")]
+    pub fts_optimized: Vec<String>,
+    #[schemars(description = "Memories deleted because their time to live ran out")]
+    pub expired_memories_purged: usize,
+    #[schemars(description = "Deleted conversations removed from the trash because their retention ran out")]
+    pub trashed_conversations_purged: usize,
+    pub analyzed: bool,
+    pub vacuumed: bool,
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+    pub duration_ms: u64,
+    #[schemars(description = "Error message if maintenance failed")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PruneToolResultsRequest {
+    #[schemars(description = "Prune tool results larger than this many kilobytes (default: 16)")]
+    pub min_kb: Option<u32>,
+    #[schemars(description = "Only prune results of tool calls older than this many days (default: 90)")]
+    pub older_than_days: Option<u32>,
+    #[schemars(description = "Keep each original compressed in a side table, so get_message with original_tool_result can still return it (default: true)")]
+    pub keep_original: Option<bool>,
+    #[schemars(description = "Only report what would be pruned, with a confirmation token for pruning it (default: false)")]
+    #[serde(default)]
+    pub dry_run: bool,
+    #[schemars(description = "Token from a dry_run with the same options. Required when the server enforces confirmations")]
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PruneToolResultsReport {
+    pub messages_pruned: usize,
+    #[schemars(description = "Size of the pruned tool results before")]
+    pub bytes_before: u64,
+    #[schemars(description = "Size of the summaries that replaced them")]
+    pub bytes_after: u64,
+    #[schemars(description = "Size of the compressed originals kept")]
+    pub bytes_kept: u64,
+    pub dry_run: bool,
+    pub duration_ms: u64,
+    #[schemars(description = "With dry_run, a single-use token to pass to prune_tool_results with the same options")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+    #[schemars(description = "Unix timestamp after which the token is no longer accepted")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[schemars(description = "Error message if pruning failed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReindexRequest {
+    #[schemars(description = "Full-text indexes to rebuild: any of 'messages_fts', 'memory_fts', 'titles_fts' (default: all that exist)")]
+    pub tables: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ReindexedTable {
+    pub table: String,
+    #[schemars(description = "Tokenizer the index used before")]
+    pub previous_tokenizer: String,
+    #[schemars(description = "Whether the index was rebuilt; false when it already used the configured tokenizer")]
+    pub rebuilt: bool,
+    #[schemars(description = "Rows in the index afterwards")]
+    pub rows: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ReindexReport {
+    #[schemars(description = "Configured tokenizer the indexes now use")]
+    pub tokenizer: String,
+    pub tables: Vec<ReindexedTable>,
+    pub duration_ms: u64,
+    #[schemars(description = "Error message if reindexing failed")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExplainSearchRequest {
+    #[schemars(description = "Keywords to search for, separated by spaces, as search_conversations would get them")]
+    pub query: String,
+    #[schemars(description = "Treat the last word of each keyword as a prefix, as in search_conversations")]
+    #[serde(default)]
+    pub prefix: bool,
+    #[schemars(description = "Also search reasoning traces, as in search_conversations")]
+    #[serde(default)]
+    pub include_reasoning: bool,
+    #[schemars(description = "Result order, as in search_conversations (default: 'recent')")]
+    pub sort: Option<SearchSort>,
+    #[schemars(description = "Also search the synonyms of each keyword (default: true)")]
+    pub expand_synonyms: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SearchTermStrategy {
+    pub term: String,
+    #[schemars(description = "Full-text index the term is looked up for, e.g. 'messages_fts'")]
+    pub index: String,
+    #[schemars(description = "Whether the index uses the trigram tokenizer")]
+    pub trigram: bool,
+    #[schemars(description = "'fts' for an index lookup, 'like' for a scan of the column, which the index can't do for the term")]
+    pub strategy: String,
+    #[schemars(description = "FTS5 expression, or LIKE pattern, the term becomes")]
+    pub expression: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SearchStage {
+    #[schemars(description = "'plan' (choosing indexes and expanding synonyms), 'count' (all matches), 'page' (the first 50 results) or 'keyword_hits' (hits per keyword)")]
+    pub stage: String,
+    #[schemars(description = "SQL the stage ran")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sql: Option<String>,
+    #[schemars(description = "EXPLAIN QUERY PLAN of the SQL, one step per line, indented under its parent step")]
+    pub query_plan: Vec<String>,
+    #[schemars(description = "Rows the stage returned or counted")]
+    pub rows: i64,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SearchExplanation {
+    #[schemars(description = "Keywords searched, synonyms included")]
+    pub keywords: Vec<String>,
+    #[schemars(description = "How each keyword is matched in each index searched")]
+    pub terms: Vec<SearchTermStrategy>,
+    #[schemars(description = "How results are ordered: 'bm25' scores from messages_fts or 'unscored' date order")]
+    pub ranking: String,
+    pub stages: Vec<SearchStage>,
+    pub total_ms: f64,
+    #[schemars(description = "Error message if the search failed")]
+    pub error: Option<String>,
+}
+
+// Database info types
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TableCount {
+    pub table: String,
+    pub rows: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FtsIndexStatus {
+    pub name: String,
+    pub exists: bool,
+    #[schemars(description = "Rows present in the full-text index")]
+    pub indexed_rows: i64,
+    #[schemars(description = "Rows in the table the index mirrors")]
+    pub content_rows: i64,
+    #[schemars(description = "Whether every content row is indexed")]
+    pub in_sync: bool,
+    #[schemars(description = "Whether triggers keep the index updated on insert")]
+    pub has_triggers: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DatabaseInfo {
+    #[schemars(description = "Database file the server is using (COSMIC_LLM_DB_PATH)")]
+    pub path: String,
+    pub file_size_bytes: u64,
+    pub page_count: i64,
+    pub page_size: i64,
+    #[schemars(description = "Unused pages that VACUUM would reclaim")]
+    pub freelist_count: i64,
+    pub journal_mode: String,
+    #[schemars(description = "Memory schema version (see schema_version table)")]
+    pub schema_version: i64,
+    #[schemars(description = "Row counts of regular tables")]
+    pub tables: Vec<TableCount>,
+    pub fts_indexes: Vec<FtsIndexStatus>,
+    #[schemars(description = "Likely misconfigurations, e.g. a database without conversations or an out-of-sync index")]
+    pub warnings: Vec<String>,
+    #[schemars(description = "Error message if the database could not be inspected")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HealthReport {
+    #[schemars(description = "\"ok\" when every check passed, \"unhealthy\" otherwise")]
+    pub status: String,
+    pub healthy: bool,
+    #[schemars(description = "Database file checked (COSMIC_LLM_DB_PATH)")]
+    pub path: String,
+    #[schemars(description = "Checks in the order they ran; later ones are skipped when the database doesn't open")]
+    pub checks: Vec<HealthCheck>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HealthCheck {
+    #[schemars(description = "open, tables, query, schema, or connection (the service's own connection, health tool only)")]
+    pub name: String,
+    pub ok: bool,
+    #[schemars(description = "What failed, or what was found when it passed")]
+    pub detail: Option<String>,
+}
+
+// Budget types
+/// Carried as `budget_exceeded` by the error result of a call over one of the session's limits.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BudgetExceeded {
+    #[schemars(description = "calls_per_minute, max_result_bytes or max_session_bytes")]
+    pub limit: String,
+    #[schemars(description = "The configured limit")]
+    pub max: u64,
+    #[schemars(description = "Calls in the last minute, or bytes of the result or of the session including it")]
+    pub used: u64,
+    #[schemars(description = "Seconds until another call is allowed (calls_per_minute only)")]
+    pub retry_after_secs: Option<u64>,
+}
+
+/// Carried as `query_timeout` by the error result of a call whose queries ran longer than the
+/// query timeout.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QueryTimeout {
+    #[schemars(description = "The configured timeout, in seconds")]
+    pub timeout_secs: f64,
+}
+
+// Prompt argument types
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RecallTopicArgs {
+    #[schemars(description = "Topic to recall, e.g. 'kubernetes upgrade' or 'holiday plans'")]
+    pub topic: String,
+}
@@ -0,0 +1,124 @@
+//! People: who the user mentions (colleagues, family, clients) with structured fields, so "who is
+//! Marek" is a lookup by name or alias instead of a hunt through free-text memory.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::db::unix_now;
+use crate::fts;
+use crate::models::Person;
+
+const COLUMNS: &str = "id, name, aliases, relationship, notes, last_mentioned_message_id, created_at, updated_at";
+
+/// Matches a person by name or any alias, ignoring case.
+const NAME_MATCH_SQL: &str =
+    "(name = ?1 COLLATE NOCASE OR EXISTS (SELECT 1 FROM json_each(people.aliases) WHERE value = ?1 COLLATE NOCASE))";
+
+fn from_row(row: &Row) -> rusqlite::Result<Person> {
+    let aliases: String = row.get(2)?;
+    Ok(Person {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        aliases: serde_json::from_str(&aliases).unwrap_or_default(),
+        relationship: row.get(3)?,
+        notes: row.get(4)?,
+        last_mentioned_message_id: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+/// What `remember` records about a person; fields left `None` keep their current value.
+#[derive(Debug, Default)]
+pub struct PersonUpdate<'a> {
+    pub aliases: &'a [String],
+    pub relationship: Option<&'a str>,
+    pub notes: Option<&'a str>,
+    pub message_id: Option<i64>,
+}
+
+/// Create the person `name`, or update the one known by that name or alias: aliases are added,
+/// the relationship replaced, and notes appended as a new line.
+pub fn remember(conn: &Connection, name: &str, update: PersonUpdate) -> Result<Person> {
+    let name = name.trim();
+    anyhow::ensure!(!name.is_empty(), "Person name must not be empty");
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    let now = unix_now();
+    let mut person = match get(&tx, name)? {
+        Some(person) => person,
+        None => {
+            tx.execute(
+                "INSERT INTO people (name, aliases, created_at, updated_at) VALUES (?1, '[]', ?2, ?2)",
+                rusqlite::params![name, now],
+            )
+            .context("Failed to add person")?;
+            get(&tx, name)?.context("Person disappeared")?
+        }
+    };
+
+    for alias in update.aliases.iter().map(|a| a.trim()).filter(|a| !a.is_empty()) {
+        let known = person.name.eq_ignore_ascii_case(alias) || person.aliases.iter().any(|a| a.eq_ignore_ascii_case(alias));
+        if !known {
+            person.aliases.push(alias.to_string());
+        }
+    }
+    if let Some(relationship) = update.relationship.map(str::trim).filter(|r| !r.is_empty()) {
+        person.relationship = Some(relationship.to_string());
+    }
+    if let Some(notes) = update.notes.map(str::trim).filter(|n| !n.is_empty()) {
+        person.notes = Some(match person.notes.take() {
+            Some(existing) if !existing.is_empty() => format!("{}\n{}", existing, notes),
+            _ => notes.to_string(),
+        });
+    }
+    if update.message_id.is_some() {
+        person.last_mentioned_message_id = update.message_id;
+    }
+    person.updated_at = now;
+
+    tx.execute(
+        "UPDATE people SET aliases = ?1, relationship = ?2, notes = ?3, last_mentioned_message_id = ?4, updated_at = ?5 WHERE id = ?6",
+        rusqlite::params![
+            serde_json::to_string(&person.aliases)?,
+            person.relationship,
+            person.notes,
+            person.last_mentioned_message_id,
+            person.updated_at,
+            person.id
+        ],
+    )
+    .context("Failed to update person")?;
+    tx.commit().context("Failed to commit person")?;
+    Ok(person)
+}
+
+/// The person known by `name` or alias, ignoring case.
+pub fn get(conn: &Connection, name: &str) -> Result<Option<Person>> {
+    conn.query_row(
+        &format!("SELECT {} FROM people WHERE {}", COLUMNS, NAME_MATCH_SQL),
+        [name.trim()],
+        from_row,
+    )
+    .optional()
+    .context("Failed to read person")
+}
+
+/// People whose name, aliases, relationship or notes contain `query`, ignoring case, by name.
+/// A blank query lists everyone.
+pub fn search(conn: &Connection, query: &str, limit: u32) -> Result<Vec<Person>> {
+    let pattern = fts::like_pattern(query.trim());
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+            SELECT {} FROM people
+            WHERE name LIKE ?1 ESCAPE '\' OR aliases LIKE ?1 ESCAPE '\'
+                OR relationship LIKE ?1 ESCAPE '\' OR notes LIKE ?1 ESCAPE '\'
+            ORDER BY name COLLATE NOCASE
+            LIMIT ?2
+            "#,
+            COLUMNS
+        ))
+        .context("Failed to search people")?;
+    let rows = stmt.query_map(rusqlite::params![pattern, limit], from_row)?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read people")
+}
@@ -0,0 +1,266 @@
+//! Personal data masking for results that leave the user's hands: search previews and exports
+//! shared with other people. Off by default. Like secret redaction it works by named detectors,
+//! here for email addresses, phone numbers and street addresses; all three are heuristics that
+//! favour masking a little too much over leaking.
+
+use crate::config::{env_flag, env_list};
+use crate::redact::{Masker, Span};
+
+/// Every detector, in the order they run.
+pub const DETECTORS: &[&str] = &["email", "phone", "address"];
+
+/// What gets masked unless `MCP_LUNA_PII_TOOLS` says otherwise: the tools that return previews of
+/// past conversations and memories, and the exports. `conversation-export` is the Markdown
+/// resource.
+const DEFAULT_TOOLS: &[&str] = &[
+    "search_conversations",
+    "advanced_search",
+    "run_saved_search",
+    "search_conversation_titles",
+    "search_failures",
+    "search_tool_activity",
+    "find_related_conversations",
+    "search_memory",
+    "search_memory_by_category",
+    "export_all",
+    "conversation-export",
+];
+
+/// Phone numbers have at least this many digits; shorter runs are codes, counts and years.
+const MIN_PHONE_DIGITS: usize = 9;
+const MAX_PHONE_DIGITS: usize = 15;
+
+/// Words ending a street name that follows its house number: "221B Baker Street".
+const STREET_SUFFIXES: &[&str] = &[
+    "street", "st", "avenue", "ave", "road", "rd", "boulevard", "blvd", "lane", "ln", "drive", "dr", "court", "ct",
+    "way", "place", "pl", "square", "sq", "terrace", "crescent", "close", "parkway", "highway",
+];
+
+/// Words starting a street name that precedes its house number: "ul. Długa 5", "Rue de Rivoli 12".
+const STREET_PREFIXES: &[&str] = &["ul.", "ul", "al.", "aleja", "rue", "via", "calle", "avenida", "rua", "plac", "pl."];
+
+/// Endings of single-word street names that precede their house number: "Hauptstraße 5".
+const STREET_WORD_ENDINGS: &[&str] = &["straße", "strasse", "gasse", "weg", "straat", "laan", "vej", "gatan", "gata"];
+
+/// Lowercase words inside street names: "Rue de Rivoli".
+const NAME_PARTICLES: &[&str] = &["de", "del", "la", "le", "di", "da", "do", "dos", "van", "von", "der"];
+
+#[derive(Debug, Clone)]
+pub struct PiiConfig {
+    /// Mask personal data in the results of `tools` (`MCP_LUNA_MASK_PII`)
+    pub enabled: bool,
+    /// Detectors to run (`MCP_LUNA_PII_DETECTORS`, default all)
+    pub detectors: Vec<String>,
+    /// Tools whose results are masked, or `all` (`MCP_LUNA_PII_TOOLS`, default the search tools
+    /// and exports)
+    pub tools: Vec<String>,
+}
+
+impl Default for PiiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            detectors: DETECTORS.iter().map(|d| d.to_string()).collect(),
+            tools: DEFAULT_TOOLS.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+impl PiiConfig {
+    pub fn from_env() -> Self {
+        let mut config = Self { enabled: env_flag("MCP_LUNA_MASK_PII"), ..Self::default() };
+        if let Some(detectors) = env_list("MCP_LUNA_PII_DETECTORS") {
+            config.detectors = detectors.into_iter().map(|d| d.to_ascii_lowercase()).collect();
+        }
+        if let Some(tools) = env_list("MCP_LUNA_PII_TOOLS") {
+            config.tools = tools;
+        }
+        config
+    }
+
+    /// Whether results of `tool` (or the `conversation-export` resource) are masked.
+    pub fn applies_to(&self, tool: &str) -> bool {
+        self.enabled && self.tools.iter().any(|t| t == tool || t == "all")
+    }
+
+    fn runs(&self, detector: &str) -> bool {
+        self.detectors.iter().any(|d| d == detector)
+    }
+}
+
+impl Masker for PiiConfig {
+    fn spans(&self, text: &str) -> Vec<Span> {
+        let mut spans = Vec::new();
+        if self.runs("email") {
+            emails(text, &mut spans);
+        }
+        if self.runs("phone") {
+            phones(text, &mut spans);
+        }
+        if self.runs("address") {
+            addresses(text, &mut spans);
+        }
+        spans
+    }
+}
+
+fn is_local_part_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-')
+}
+
+fn is_domain_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | '-')
+}
+
+/// `local@domain.tld`, with a domain of at least two labels and an alphabetic top-level label.
+fn emails(text: &str, spans: &mut Vec<Span>) {
+    for (at, _) in text.match_indices('@') {
+        let local = text[..at]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_local_part_char(c))
+            .last()
+            .map(|(i, _)| i);
+        let Some(start) = local else {
+            continue;
+        };
+        let domain_len: usize = text[at + 1..].chars().take_while(|&c| is_domain_char(c)).map(char::len_utf8).sum();
+        let domain = text[at + 1..at + 1 + domain_len].trim_end_matches(['.', '-']);
+        let valid = domain
+            .rsplit_once('.')
+            .is_some_and(|(name, tld)| !name.is_empty() && tld.len() >= 2 && tld.chars().all(|c| c.is_alphabetic()));
+        let local = text[start..at].trim_start_matches('.');
+        if valid && !local.is_empty() {
+            spans.push(Span { start: at - local.len(), end: at + 1 + domain.len(), detector: "email" });
+        }
+    }
+}
+
+/// Digit groups separated by single spaces, dashes or parentheses, optionally after a `+`, with
+/// 9 to 15 digits in all. Unbroken digit runs count only with a `+`; they are usually IDs and
+/// timestamps. Dates like `2024-03-09 16` are skipped.
+fn phones(text: &str, spans: &mut Vec<Span>) {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let starts = (bytes[i] == b'+' || bytes[i] == b'(' || bytes[i].is_ascii_digit())
+            && (i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || matches!(bytes[i - 1], b'.' | b'-' | b'/' | b'+')));
+        if !starts {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut end = i;
+        let mut digits = 0;
+        let mut separated = false;
+        let mut j = i + usize::from(bytes[i] == b'+');
+        while j < bytes.len() {
+            match bytes[j] {
+                b'0'..=b'9' => {
+                    digits += 1;
+                    end = j + 1;
+                }
+                b' ' | b'-' | b'(' | b')'
+                    if j + 1 < bytes.len() && (bytes[j + 1].is_ascii_digit() || bytes[j + 1] == b'(') =>
+                {
+                    separated = true;
+                }
+                b')' => {}
+                _ => break,
+            }
+            j += 1;
+        }
+        let followed_by_word = bytes.get(end).is_some_and(|b| b.is_ascii_alphanumeric());
+        let candidate = &text[start..end];
+        if (MIN_PHONE_DIGITS..=MAX_PHONE_DIGITS).contains(&digits)
+            && (separated || bytes[start] == b'+')
+            && !followed_by_word
+            && !is_date(candidate)
+        {
+            spans.push(Span { start, end, detector: "phone" });
+        }
+        i = j.max(i + 1);
+    }
+}
+
+/// Starts like `2024-03-09` or `09-03-2024`.
+fn is_date(candidate: &str) -> bool {
+    let groups: Vec<&str> = candidate.splitn(4, '-').collect();
+    groups.len() >= 3
+        && matches!(
+            (groups[0].len(), groups[1].len(), groups[2].get(..4).unwrap_or(groups[2]).trim().len()),
+            (4, 2, _) | (2, 2, 4)
+        )
+}
+
+/// Words with their byte offsets, trailing punctuation trimmed.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut offset = 0;
+    for word in text.split_inclusive(char::is_whitespace) {
+        let trimmed = word.trim_end().trim_end_matches([',', ';', ':', ')']);
+        if !trimmed.is_empty() {
+            words.push((offset, trimmed));
+        }
+        offset += word.len();
+    }
+    words
+}
+
+/// A house number such as `12`, `221B` or `10/4`.
+fn is_house_number(word: &str) -> bool {
+    let word = word.trim_end_matches('.');
+    let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = &word[digits..];
+    (1..=5).contains(&digits)
+        && (rest.is_empty()
+            || (rest.len() == 1 && rest.chars().all(|c| c.is_ascii_alphabetic()))
+            || rest.strip_prefix('/').is_some_and(|r| !r.is_empty() && r.chars().all(|c| c.is_ascii_digit())))
+}
+
+fn is_capitalized(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_uppercase)
+}
+
+/// "221B Baker Street" and "1600 Pennsylvania Avenue", or "ul. Długa 5", "Rue de Rivoli 12" and
+/// "Hauptstraße 5".
+fn addresses(text: &str, spans: &mut Vec<Span>) {
+    let words = words(text);
+    for (i, &(start, word)) in words.iter().enumerate() {
+        // Number first: up to four capitalized words ending in a street suffix
+        if is_house_number(word) {
+            for (k, &(offset, name)) in words.iter().enumerate().skip(i + 1).take(4) {
+                if !is_capitalized(name) {
+                    break;
+                }
+                let suffix = name.trim_end_matches('.').to_lowercase();
+                if k > i + 1 && STREET_SUFFIXES.contains(&suffix.as_str()) {
+                    spans.push(Span { start, end: offset + name.len(), detector: "address" });
+                    break;
+                }
+            }
+            continue;
+        }
+        // Street first: a prefix and one to three name words, or a street word, then the number
+        let lower = word.to_lowercase();
+        let names = if STREET_PREFIXES.contains(&lower.as_str()) {
+            1..=3
+        } else if is_capitalized(word) && STREET_WORD_ENDINGS.iter().any(|e| lower.ends_with(e)) {
+            0..=0
+        } else {
+            continue;
+        };
+        for (n, &(offset, next)) in words.iter().skip(i + 1).enumerate().take(names.end() + 1) {
+            if is_house_number(next) {
+                if names.contains(&n) {
+                    let end = offset + next.trim_end_matches('.').len();
+                    spans.push(Span { start, end, detector: "address" });
+                }
+                break;
+            }
+            if !is_capitalized(next) && !NAME_PARTICLES.contains(&next) {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Preferences: exact key-value settings such as `editor=helix` or `timezone=Europe/Warsaw`, kept
+//! apart from free-text memory so they're looked up by key rather than found by a fuzzy search.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::db::unix_now;
+use crate::models::Preference;
+
+const COLUMNS: &str = "key, value, updated_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<Preference> {
+    Ok(Preference {
+        key: row.get(0)?,
+        value: row.get(1)?,
+        updated_at: row.get(2)?,
+        error: None,
+    })
+}
+
+/// Set `key` to `value`, replacing any previous value.
+pub fn set(conn: &Connection, key: &str, value: &str) -> Result<Preference> {
+    let key = key.trim();
+    anyhow::ensure!(!key.is_empty(), "Preference key must not be empty");
+    conn.execute(
+        r#"
+        INSERT INTO preferences (key, value, updated_at) VALUES (?1, ?2, ?3)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#,
+        rusqlite::params![key, value, unix_now()],
+    )
+    .context("Failed to set preference")?;
+    get(conn, key)?.context("Preference disappeared")
+}
+
+/// The preference stored under `key`, if any. Keys match exactly, case included.
+pub fn get(conn: &Connection, key: &str) -> Result<Option<Preference>> {
+    conn.query_row(
+        &format!("SELECT {} FROM preferences WHERE key = ?", COLUMNS),
+        [key.trim()],
+        from_row,
+    )
+    .optional()
+    .context("Failed to read preference")
+}
+
+/// Every preference, by key.
+pub fn list(conn: &Connection) -> Result<Vec<Preference>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM preferences ORDER BY key", COLUMNS))
+        .context("Failed to list preferences")?;
+    let rows = stmt.query_map([], from_row)?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read preferences")
+}
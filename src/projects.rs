@@ -0,0 +1,212 @@
+//! Projects: named workspaces that scope recall to one codebase or effort. Memories are assigned to
+//! a project explicitly; conversations belong to one when their title or messages mention any of
+//! its keywords (its name when it has none), or once its rules have tagged them.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::db::unix_now;
+use crate::fts;
+use crate::models::{Project, ProjectClassification, ProjectRule, RuleScope};
+
+const COLUMNS: &str = "p.id, p.name, p.description, p.keywords, p.created_at, \
+    (SELECT COUNT(*) FROM memory WHERE memory.project_id = p.id)";
+
+fn from_row(row: &Row) -> rusqlite::Result<Project> {
+    let keywords: String = row.get(3)?;
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        keywords: serde_json::from_str(&keywords).unwrap_or_default(),
+        created_at: row.get(4)?,
+        memory_count: row.get(5)?,
+        error: None,
+    })
+}
+
+/// Create the project `name`. Fails if one by that name, ignoring case, exists.
+pub fn create(conn: &Connection, name: &str, description: Option<&str>, keywords: &[String]) -> Result<Project> {
+    let name = name.trim();
+    anyhow::ensure!(!name.is_empty(), "Project name must not be empty");
+    anyhow::ensure!(get(conn, name)?.is_none(), "A project named '{}' already exists", name);
+    let keywords = fts::clean_keywords(keywords);
+    conn.execute(
+        "INSERT INTO projects (name, description, keywords, created_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![
+            name,
+            description.map(str::trim).filter(|d| !d.is_empty()),
+            serde_json::to_string(&keywords)?,
+            unix_now()
+        ],
+    )
+    .context("Failed to create project")?;
+    get(conn, name)?.context("Project disappeared")
+}
+
+/// The project named `name`, ignoring case.
+pub fn get(conn: &Connection, name: &str) -> Result<Option<Project>> {
+    conn.query_row(
+        &format!("SELECT {} FROM projects p WHERE p.name = ? COLLATE NOCASE", COLUMNS),
+        [name.trim()],
+        from_row,
+    )
+    .optional()
+    .context("Failed to read project")
+}
+
+/// Every project, by name.
+pub fn list(conn: &Connection) -> Result<Vec<Project>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM projects p ORDER BY p.name COLLATE NOCASE", COLUMNS))
+        .context("Failed to list projects")?;
+    let rows = stmt.query_map([], from_row)?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read projects")
+}
+
+/// Assign the memory `memory_id` to `project`, or take it out of its project with `None`. Returns
+/// false if there is no such memory.
+pub fn assign_memory(conn: &Connection, memory_id: i64, project: Option<&Project>) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE memory SET project_id = ?1 WHERE id = ?2",
+            rusqlite::params![project.map(|p| p.id), memory_id],
+        )
+        .context("Failed to assign memory")?;
+    Ok(updated > 0)
+}
+
+/// Terms a conversation must mention to belong to `project`: its keywords, or its name.
+pub fn terms(project: &Project) -> Vec<String> {
+    if project.keywords.is_empty() {
+        vec![project.name.clone()]
+    } else {
+        project.keywords.clone()
+    }
+}
+
+const RULE_COLUMNS: &str = "r.id, p.name, r.pattern, r.scope, r.created_at";
+
+fn rule_from_row(row: &Row) -> rusqlite::Result<ProjectRule> {
+    let scope: String = row.get(3)?;
+    Ok(ProjectRule {
+        id: row.get(0)?,
+        project: row.get(1)?,
+        pattern: row.get(2)?,
+        scope: match scope.as_str() {
+            "title" => RuleScope::Title,
+            "content" => RuleScope::Content,
+            _ => RuleScope::Any,
+        },
+        created_at: row.get(4)?,
+        error: None,
+    })
+}
+
+fn scope_name(scope: RuleScope) -> &'static str {
+    match scope {
+        RuleScope::Any => "any",
+        RuleScope::Title => "title",
+        RuleScope::Content => "content",
+    }
+}
+
+/// Add a rule tagging conversations that mention `pattern` with `project`.
+pub fn add_rule(conn: &Connection, project: &Project, pattern: &str, scope: RuleScope) -> Result<ProjectRule> {
+    let pattern = pattern.trim();
+    anyhow::ensure!(!pattern.is_empty(), "Rule pattern must not be empty");
+    conn.execute(
+        "INSERT INTO project_rules (project_id, pattern, scope, created_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![project.id, pattern, scope_name(scope), unix_now()],
+    )
+    .context("Failed to add rule")?;
+    conn.query_row(
+        &format!("SELECT {} FROM project_rules r JOIN projects p ON p.id = r.project_id WHERE r.id = ?", RULE_COLUMNS),
+        [conn.last_insert_rowid()],
+        rule_from_row,
+    )
+    .context("Failed to read rule")
+}
+
+/// Rules of `project`, or of every project, by project and pattern.
+pub fn rules(conn: &Connection, project: Option<&Project>) -> Result<Vec<ProjectRule>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+            SELECT {} FROM project_rules r JOIN projects p ON p.id = r.project_id
+            WHERE ?1 IS NULL OR r.project_id = ?1
+            ORDER BY p.name COLLATE NOCASE, r.pattern
+            "#,
+            RULE_COLUMNS
+        ))
+        .context("Failed to list rules")?;
+    let rows = stmt.query_map([project.map(|p| p.id)], rule_from_row)?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read rules")
+}
+
+/// Tag conversations with the projects whose rules they match, for `project` or every project.
+/// `content_condition` gives the SQL predicate (over alias `m`) for messages mentioning a pattern.
+/// Conversations already tagged stay tagged. Returns what each project gained.
+pub fn classify(
+    conn: &Connection,
+    project: Option<&Project>,
+    content_condition: impl Fn(&str) -> (String, Vec<String>),
+) -> Result<Vec<ProjectClassification>> {
+    let now = unix_now();
+    let mut classifications: Vec<ProjectClassification> = Vec::new();
+    for rule in rules(conn, project)? {
+        let mut sources = Vec::new();
+        let mut params: Vec<String> = Vec::new();
+        if rule.scope != RuleScope::Content {
+            sources.push("SELECT id AS conversation_id FROM conversations WHERE title LIKE ? ESCAPE '\\'".to_string());
+            params.push(fts::like_pattern(&rule.pattern));
+        }
+        if rule.scope != RuleScope::Title {
+            let (sql, content_params) = content_condition(&rule.pattern);
+            sources.push(format!("SELECT m.conversation_id FROM messages m WHERE {}", sql));
+            params.extend(content_params);
+        }
+        let tagged = conn
+            .execute(
+                &format!(
+                    r#"
+                    INSERT OR IGNORE INTO conversation_projects (conversation_id, project_id, rule_id, classified_at)
+                    SELECT matched.conversation_id, p.id, ?, ? FROM ({}) matched, projects p WHERE p.name = ? COLLATE NOCASE
+                    "#,
+                    sources.join(" UNION ")
+                ),
+                rusqlite::params_from_iter(
+                    [rule.id.to_string(), now.to_string()]
+                        .into_iter()
+                        .chain(params)
+                        .chain([rule.project.clone()]),
+                ),
+            )
+            .with_context(|| format!("Failed to apply rule '{}'", rule.pattern))?;
+        match classifications.iter_mut().find(|c| c.project == rule.project) {
+            Some(classification) => {
+                classification.rules += 1;
+                classification.newly_tagged += tagged as i64;
+            }
+            None => classifications.push(ProjectClassification {
+                project: rule.project,
+                rules: 1,
+                newly_tagged: tagged as i64,
+                conversations: 0,
+            }),
+        }
+    }
+    for classification in &mut classifications {
+        classification.conversations = conn
+            .query_row(
+                r#"
+                SELECT COUNT(*) FROM conversation_projects
+                WHERE project_id = (SELECT id FROM projects WHERE name = ? COLLATE NOCASE)
+                "#,
+                [&classification.project],
+                |row| row.get(0),
+            )
+            .context("Failed to count tagged conversations")?;
+    }
+    Ok(classifications)
+}
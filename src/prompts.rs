@@ -0,0 +1,215 @@
+//! MCP prompts: recall requests a user can pick from their client, arriving with the relevant
+//! history already fetched instead of relying on the model to call the search tools.
+
+use chrono::DateTime;
+use rmcp::{
+    handler::server::wrapper::{Json, Parameters},
+    model::{GetPromptResult, PromptMessage, PromptMessageRole},
+    prompt, prompt_router,
+};
+use std::fmt::Write;
+
+use crate::models::*;
+use crate::service::ConversationService;
+use crate::text;
+
+const MAX_MEMORIES: usize = 10;
+const MAX_TITLES: usize = 5;
+const MAX_MESSAGES: usize = 10;
+/// Trailing messages of the last conversation quoted by `resume_last_session`
+const RESUME_TAIL: usize = 6;
+const RESUME_PREVIEW_CHARS: usize = 500;
+
+#[prompt_router(vis = "pub(crate)")]
+impl ConversationService {
+    #[prompt(
+        name = "recall_topic",
+        description = "Recall everything known about a topic: stored memories, conversations with a matching title and matching messages, followed by instructions to summarize them"
+    )]
+    pub fn recall_topic_prompt(
+        &self,
+        Parameters(RecallTopicArgs { topic }): Parameters<RecallTopicArgs>,
+    ) -> GetPromptResult {
+        let keywords = text::tokenize(&topic);
+
+        let Json(memories) = self.search_memory(Parameters(SearchMemoryRequest {
+            keywords: keywords.clone(),
+            ..Default::default()
+        }));
+        let Json(titles) = self.search_conversation_titles(Parameters(SearchTitlesRequest {
+            query: topic.clone(),
+            sort: None,
+            collation: None,
+            similarity_threshold: None,
+            fields: None,
+        }));
+        let Json(messages) = self.search_conversations(Parameters(SearchConversationsRequest {
+            keywords,
+            phrase: None,
+            near: None,
+            prefix: false,
+            include_reasoning: false,
+            include_archive: false,
+            preview_chars: None,
+            include_full_content: false,
+            count_only: false,
+            max_per_conversation: None,
+            sort: None,
+            half_life_days: None,
+            min_score: None,
+            project: None,
+            tags: None,
+            language: None,
+            expand_synonyms: None,
+            fields: None,
+        }));
+
+        let mut body = format!(
+            "I want to pick up what we know about \"{}\". Below is what the conversation history server found. \
+             Use it, and call get_conversation or get_message where an excerpt isn't enough. Then summarize \
+             what we know, what was decided and what is still open.\n",
+            topic
+        );
+
+        body.push_str("\n## Stored memories\n");
+        let memories = memories.into_inner().items;
+        if memories.is_empty() {
+            body.push_str("None found.\n");
+        }
+        for memory in memories.iter().take(MAX_MEMORIES) {
+            let _ = writeln!(
+                body,
+                "- [memory {}{}] {}",
+                memory.id,
+                memory.category.as_deref().map(|c| format!(", {}", c)).unwrap_or_default(),
+                memory.content
+            );
+        }
+
+        body.push_str("\n## Conversations with a matching title\n");
+        let titles = titles.into_inner().items;
+        if titles.is_empty() {
+            body.push_str("None found.\n");
+        }
+        for conversation in titles.iter().take(MAX_TITLES) {
+            let _ = writeln!(
+                body,
+                "- \"{}\" (conversation {}, {}, {} messages)",
+                conversation.title,
+                conversation.id,
+                date(conversation.created_at),
+                conversation.message_count
+            );
+        }
+
+        body.push_str("\n## Matching messages\n");
+        let messages = messages.into_inner().items;
+        if messages.is_empty() {
+            body.push_str("None found.\n");
+        }
+        for message in messages.iter().take(MAX_MESSAGES) {
+            let _ = writeln!(
+                body,
+                "- conversation {}, message {} ({}, {}): {}",
+                message.conversation_id,
+                message.message_id,
+                message.role,
+                date(message.created_at),
+                one_line(&message.content_preview)
+            );
+        }
+
+        GetPromptResult {
+            description: Some(format!("Recall context about {}", topic)),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, body)],
+        }
+    }
+
+    #[prompt(
+        name = "resume_last_session",
+        description = "Continue the most recent conversation: its key messages and last exchanges, followed by instructions to recap where it stopped"
+    )]
+    pub fn resume_last_session_prompt(&self) -> GetPromptResult {
+        let Json(latest) = self.list_conversations(Parameters(ListConversationsRequest {
+            limit: Some(1),
+            offset: None,
+            sort: Some(ConversationSort::Recent),
+            collation: None,
+            tags: None,
+            pinned_only: None,
+            fields: None,
+        }));
+        let Some(summary) = latest.into_inner().items.into_iter().next() else {
+            return GetPromptResult {
+                description: Some("Resume the last session".to_string()),
+                messages: vec![PromptMessage::new_text(
+                    PromptMessageRole::User,
+                    "I wanted to resume our last session, but the conversation history is empty. Let's start fresh.",
+                )],
+            };
+        };
+
+        let Json(key_messages) = self.get_key_messages(Parameters(GetKeyMessagesRequest {
+            conversation_id: summary.id.clone(),
+            preview_chars: None,
+        }));
+        let Json(conversation) = self.get_conversation(Parameters(GetConversationRequest {
+            conversation_id: summary.id.clone(),
+            fields: None,
+        }));
+
+        let mut body = format!(
+            "Let's continue where we left off in \"{}\" (conversation {}, started {}, {} messages). \
+             Recap where we stopped and what the next step was, then continue from there. Call \
+             get_conversation if you need the full thread.\n",
+            summary.title,
+            summary.id,
+            date(summary.created_at),
+            summary.message_count
+        );
+
+        body.push_str("\n## Key messages\n");
+        if key_messages.items.is_empty() {
+            body.push_str("None detected.\n");
+        }
+        for message in &key_messages.items {
+            let _ = writeln!(
+                body,
+                "- message {} ({}, {}): {}",
+                message.id,
+                message.role,
+                date(message.created_at),
+                one_line(&message.content_preview)
+            );
+        }
+
+        body.push_str("\n## Last messages\n");
+        let messages = conversation.into_inner().messages;
+        let tail = messages
+            .iter()
+            .filter(|m| m.role == "user" || m.role == "assistant")
+            .rev()
+            .take(RESUME_TAIL)
+            .collect::<Vec<_>>();
+        for message in tail.into_iter().rev() {
+            let preview: String = message.content.chars().take(RESUME_PREVIEW_CHARS).collect();
+            let _ = writeln!(body, "- {} ({}): {}", message.role, date(message.created_at), one_line(&preview));
+        }
+
+        GetPromptResult {
+            description: Some(format!("Resume \"{}\"", summary.title)),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, body)],
+        }
+    }
+}
+
+fn date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| "unknown date".to_string())
+}
+
+/// Collapse whitespace so multi-line previews stay on one list line.
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
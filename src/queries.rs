@@ -0,0 +1,94 @@
+//! The store layer behind the tools: every query the service runs against conversations,
+//! messages and memories, with the row mapping they share. Statements go through SQLite's
+//! prepared statement cache, so a tool called again reuses its compiled queries. Failures come
+//! back as [`QueryError`], which tools turn into the `error` of their response.
+//!
+//! Features with tables of their own (journal, people, projects, ...) keep their queries in their
+//! modules.
+
+use std::fmt;
+
+pub mod conversations;
+pub mod memories;
+pub mod search;
+pub mod tool_calls;
+
+/// Prepared statements kept per connection. Dynamic queries (search filters, sort orders) vary
+/// their SQL, so this is well above the number of static ones.
+pub const STATEMENT_CACHE_CAPACITY: usize = 128;
+
+/// Why a query failed.
+#[derive(Debug)]
+pub enum QueryError {
+    /// No row has the id asked for; names what was looked up, e.g. "Conversation"
+    NotFound(&'static str),
+    /// The request can't be carried out as given
+    Invalid(String),
+    /// The database couldn't be opened
+    Unavailable(String),
+    Sqlite(rusqlite::Error),
+}
+
+pub type QueryResult<T> = Result<T, QueryError>;
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(what) => write!(f, "{} not found", what),
+            Self::Invalid(reason) => f.write_str(reason),
+            Self::Unavailable(reason) => write!(f, "Database unavailable: {}", reason),
+            Self::Sqlite(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl QueryError {
+    /// The error as a tool reports it: what is missing or unavailable as is, other failures
+    /// after `failed`, e.g. "Failed to update memory".
+    pub fn describe(&self, failed: &str) -> String {
+        match self {
+            Self::NotFound(_) | Self::Unavailable(_) => self.to_string(),
+            _ => format!("{}: {}", failed, self),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for QueryError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+/// `result` of a single-row lookup, with a missing row reported as [`QueryError::NotFound`].
+pub fn found<T>(result: rusqlite::Result<T>, what: &'static str) -> QueryResult<T> {
+    result.map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => QueryError::NotFound(what),
+        e => QueryError::Sqlite(e),
+    })
+}
+
+/// `[]`-style JSON array in column `index`, or empty.
+fn json_list(row: &rusqlite::Row, index: usize) -> Vec<String> {
+    row.get::<_, String>(index).ok().and_then(|list| serde_json::from_str(&list).ok()).unwrap_or_default()
+}
+
+/// Predicates joined with OR, parenthesized, with their parameters in order.
+pub fn any_condition(conditions: impl IntoIterator<Item = (String, Vec<String>)>) -> (String, Vec<String>) {
+    let (sql, params): (Vec<String>, Vec<Vec<String>>) = conditions.into_iter().unzip();
+    (format!("({})", sql.join(" OR ")), params.concat())
+}
+
+/// Predicates joined with AND, with their parameters in order.
+pub fn all_conditions(conditions: Vec<(String, Vec<String>)>) -> (String, Vec<String>) {
+    let (sql, params): (Vec<String>, Vec<Vec<String>>) = conditions.into_iter().unzip();
+    (sql.join(" AND "), params.concat())
+}
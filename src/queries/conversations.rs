@@ -0,0 +1,311 @@
+//! Conversations and their messages.
+
+use rusqlite::Connection;
+
+use super::{all_conditions, found, json_list, QueryResult};
+use crate::dates::{self, Timezone};
+use crate::db;
+use crate::fts;
+use crate::models::{Conversation, ConversationSort, ConversationSummary, Message, TitleCollation};
+use crate::tags;
+use crate::text;
+
+/// Columns read by [`message`], over alias `m`.
+const MESSAGE_COLUMNS: &str = "m.id, m.conversation_id, m.role, m.content, m.created_at, m.tool_calls, m.tool_call_id, \
+     m.tool_name, m.tool_status, m.tool_params_json, m.tool_result_json, m.reasoning_content";
+
+/// Message from a row of [`MESSAGE_COLUMNS`].
+fn message(row: &rusqlite::Row, timezone: Timezone) -> rusqlite::Result<Message> {
+    Ok(Message {
+        id: row.get(0).unwrap_or(0),
+        conversation_id: row.get(1).unwrap_or_default(),
+        role: row.get(2).unwrap_or_default(),
+        content: row.get(3).unwrap_or_default(),
+        created_at: row.get(4).unwrap_or(0),
+        created_at_iso: row.get(4).ok().and_then(|t| timezone.iso(t)),
+        tool_calls: row.get(5).ok(),
+        tool_call_id: row.get(6).ok(),
+        tool_name: row.get(7).ok(),
+        tool_status: row.get(8).ok(),
+        tool_params_json: row.get(9).ok(),
+        tool_result_json: row.get(10).ok(),
+        reasoning_content: row.get(11).ok(),
+        key: false,
+        estimated_tokens: 0,
+    })
+}
+
+/// Columns read by [`summary`], over `conversations c LEFT JOIN messages m`, grouped by
+/// [`SUMMARY_GROUP_BY`].
+const SUMMARY_COLUMNS: &str =
+    "c.id, c.title, c.created_at, c.title_generated, c.profile_name, COUNT(m.id) AS message_count";
+const SUMMARY_GROUP_BY: &str = "c.id, c.title, c.created_at, c.title_generated, c.profile_name";
+
+/// Conversation summary from a row starting with [`SUMMARY_COLUMNS`], without tags, pin or
+/// keywords.
+fn summary(row: &rusqlite::Row, timezone: Timezone) -> rusqlite::Result<ConversationSummary> {
+    Ok(ConversationSummary {
+        id: row.get(0).unwrap_or_default(),
+        title: row.get(1).unwrap_or_default(),
+        created_at: row.get(2).unwrap_or(0),
+        created_at_iso: row.get(2).ok().and_then(|t| timezone.iso(t)),
+        age: row.get(2).ok().map(dates::age),
+        title_generated: row.get(3).unwrap_or(0),
+        profile_name: row.get(4).ok(),
+        message_count: row.get(5).unwrap_or(0),
+        match_score: None,
+        tags: Vec::new(),
+        pinned: false,
+        keywords: Vec::new(),
+    })
+}
+
+/// The conversation with all its messages in order.
+pub fn get(db: &Connection, conversation_id: &str, timezone: Timezone) -> QueryResult<Conversation> {
+    let mut stmt =
+        db.prepare_cached("SELECT id, title, created_at, title_generated, profile_name FROM conversations WHERE id = ?")?;
+    let conversation = stmt.query_row([conversation_id], |row| {
+        Ok(Conversation {
+            id: row.get(0).unwrap_or_default(),
+            title: row.get(1).unwrap_or_default(),
+            created_at: row.get(2).unwrap_or(0),
+            title_generated: row.get(3).unwrap_or(0),
+            profile_name: row.get(4).ok(),
+            ..Default::default()
+        })
+    });
+    let mut conversation = found(conversation, "Conversation")?;
+    let mut stmt = db.prepare_cached(&format!(
+        "SELECT {} FROM messages m WHERE m.conversation_id = ? ORDER BY m.created_at ASC, m.id ASC",
+        MESSAGE_COLUMNS
+    ))?;
+    conversation.messages =
+        stmt.query_map([conversation_id], |row| message(row, timezone))?.collect::<rusqlite::Result<_>>()?;
+    Ok(conversation)
+}
+
+/// The message with id `message_id`.
+pub fn get_message(db: &Connection, message_id: i64, timezone: Timezone) -> QueryResult<Message> {
+    let mut stmt = db.prepare_cached(&format!("SELECT {} FROM messages m WHERE m.id = ?", MESSAGE_COLUMNS))?;
+    found(stmt.query_row([message_id], |row| message(row, timezone)), "Message")
+}
+
+/// ORDER BY clause (over alias `c`) for conversation summary listings.
+fn order_by(sort: Option<ConversationSort>, collation: Option<TitleCollation>) -> String {
+    match sort.unwrap_or_default() {
+        ConversationSort::Recent => "c.created_at DESC".to_string(),
+        ConversationSort::Oldest => "c.created_at ASC".to_string(),
+        ConversationSort::Title => {
+            let collation = match collation.unwrap_or_default() {
+                TitleCollation::Folded => db::FOLDED_COLLATION,
+                TitleCollation::Nocase => "NOCASE",
+                TitleCollation::Binary => "BINARY",
+            };
+            format!("c.title COLLATE {} ASC, c.created_at DESC", collation)
+        }
+    }
+}
+
+/// What `list_conversations` lists.
+#[derive(Debug, Default)]
+pub struct ListQuery<'a> {
+    /// Only conversations of this profile
+    pub profile: Option<&'a str>,
+    pub tags: &'a [String],
+    pub pinned_only: bool,
+    pub sort: Option<ConversationSort>,
+    pub collation: Option<TitleCollation>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// A page of conversation summaries with their tags, pin and top keywords.
+pub fn list(db: &Connection, query: &ListQuery, timezone: Timezone) -> QueryResult<Vec<ConversationSummary>> {
+    // Databases the server never wrote to have no conversation_tags, conversation_keywords or
+    // pinned_conversations table
+    let tag_list = if db::has_column(db, "conversation_tags", "tag") {
+        "(SELECT json_group_array(tag) FROM (SELECT tag FROM conversation_tags t WHERE t.conversation_id = c.id ORDER BY tag))"
+    } else {
+        "'[]'"
+    };
+    let keyword_list = if db::has_column(db, "conversation_keywords", "phrase") {
+        "(SELECT json_group_array(phrase) FROM (SELECT phrase FROM conversation_keywords k WHERE k.conversation_id = c.id ORDER BY score DESC LIMIT 5))"
+    } else {
+        "'[]'"
+    };
+    let pinned = if db::has_column(db, "pinned_conversations", "conversation_id") {
+        "c.id IN (SELECT conversation_id FROM pinned_conversations)"
+    } else {
+        "0"
+    };
+    let mut conditions: Vec<(String, Vec<String>)> = Vec::new();
+    if let Some(profile) = query.profile {
+        conditions.push(("c.profile_name = ?".to_string(), vec![profile.to_string()]));
+    }
+    conditions.extend(tags::condition("c.id", query.tags));
+    if query.pinned_only {
+        conditions.push((pinned.to_string(), Vec::new()));
+    }
+    let (filter, mut params) = if conditions.is_empty() {
+        (String::new(), Vec::new())
+    } else {
+        let (sql, params) = all_conditions(conditions);
+        (format!("WHERE {}", sql), params)
+    };
+    params.extend([query.limit.to_string(), query.offset.to_string()]);
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT {SUMMARY_COLUMNS}, {tag_list}, {pinned}, {keyword_list}
+        FROM conversations c
+        LEFT JOIN messages m ON c.id = m.conversation_id
+        {filter}
+        GROUP BY {SUMMARY_GROUP_BY}
+        ORDER BY {order}
+        LIMIT ? OFFSET ?
+        "#,
+        order = order_by(query.sort, query.collation)
+    ))?;
+    let summaries = stmt
+        .query_map(rusqlite::params_from_iter(&params), |row| {
+            let mut summary = summary(row, timezone)?;
+            summary.tags = json_list(row, 6);
+            summary.pinned = row.get(7).unwrap_or(false);
+            summary.keywords = json_list(row, 8);
+            Ok(summary)
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(summaries)
+}
+
+/// Up to 100 conversations whose title has every word of `query`, as a prefix, through the title
+/// index; best first unless `sort` says otherwise. Without the index, or for words too short for a
+/// trigram index, titles containing `query` as typed, in `sort` order.
+pub fn search_titles(
+    db: &Connection,
+    query: &str,
+    sort: Option<ConversationSort>,
+    collation: Option<TitleCollation>,
+    timezone: Timezone,
+) -> QueryResult<Vec<ConversationSummary>> {
+    // Every query word as a prefix, so "kube set" finds "Kubernetes setup" while typing
+    let words = text::tokenize(query);
+    let title_query = fts::all_of(words.iter().map(|word| fts::term(word, true)));
+    // A trigram index can't match words shorter than three characters; those take the LIKE path
+    let indexable = db::has_title_index(db)
+        && !(fts::uses_trigram(db, "titles_fts") && words.iter().any(|word| word.chars().count() < fts::TRIGRAM_MIN_CHARS));
+    let (sql, param) = match title_query.filter(|_| indexable) {
+        Some(title_query) => {
+            // Ranked by relevance unless another order is asked for. bm25() only works in the
+            // query that runs the MATCH, hence the materialized CTE
+            let order = match sort {
+                None => "h.score ASC, c.created_at DESC".to_string(),
+                Some(_) => order_by(sort, collation),
+            };
+            let sql = format!(
+                r#"
+                WITH hits AS MATERIALIZED (
+                    SELECT rowid, bm25(titles_fts) AS score FROM titles_fts WHERE titles_fts MATCH ?
+                )
+                SELECT {SUMMARY_COLUMNS}
+                FROM hits h
+                JOIN conversations c ON c.rowid = h.rowid
+                LEFT JOIN messages m ON c.id = m.conversation_id
+                GROUP BY {SUMMARY_GROUP_BY}, h.score
+                ORDER BY {order}
+                LIMIT 100
+                "#
+            );
+            (sql, title_query)
+        }
+        // No index (e.g. a read-only database the server never opened for writing), no words, or
+        // words too short for a trigram index
+        None => {
+            let sql = format!(
+                r#"
+                SELECT {SUMMARY_COLUMNS}
+                FROM conversations c
+                LEFT JOIN messages m ON c.id = m.conversation_id
+                WHERE c.title LIKE ? ESCAPE '\'
+                GROUP BY {SUMMARY_GROUP_BY}
+                ORDER BY {order}
+                LIMIT 100
+                "#,
+                order = order_by(sort, collation)
+            );
+            (sql, fts::like_pattern(query))
+        }
+    };
+    let mut stmt = db.prepare_cached(&sql)?;
+    let summaries = stmt
+        .query_map([&param], |row| Ok(ConversationSummary { match_score: Some(1.0), ..summary(row, timezone)? }))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(summaries)
+}
+
+/// Conversations with a keyphrase cached by `extract_keywords` that contains `query`, newest first.
+pub fn keyword_matches(db: &Connection, query: &str, timezone: Timezone) -> QueryResult<Vec<ConversationSummary>> {
+    // Databases the server never wrote to have no conversation_keywords table
+    if !db::has_column(db, "conversation_keywords", "phrase") {
+        return Ok(Vec::new());
+    }
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT {SUMMARY_COLUMNS}
+        FROM conversations c
+        LEFT JOIN messages m ON c.id = m.conversation_id
+        WHERE c.id IN (SELECT conversation_id FROM conversation_keywords WHERE phrase LIKE ? ESCAPE '\')
+        GROUP BY {SUMMARY_GROUP_BY}
+        ORDER BY c.created_at DESC
+        LIMIT 100
+        "#
+    ))?;
+    let summaries = stmt
+        .query_map([fts::like_pattern(query.trim())], |row| {
+            Ok(ConversationSummary { match_score: Some(1.0), ..summary(row, timezone)? })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(summaries)
+}
+
+/// Conversations whose title is similar to `query` by trigram containment, best first. Titles are
+/// folded first, so case and diacritics don't matter. Scans every title, which stays fast at tens of
+/// thousands of conversations and only runs when the plain `LIKE` search found nothing.
+pub fn fuzzy_title_matches(
+    db: &Connection,
+    query: &str,
+    threshold: f64,
+    timezone: Timezone,
+) -> QueryResult<Vec<ConversationSummary>> {
+    let query_grams = text::trigrams(&db::fold_for_sorting(query));
+    let mut stmt = db.prepare_cached("SELECT id, title FROM conversations")?;
+    let mut scored: Vec<(String, f64)> = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .filter_map(|row| row.ok())
+        .map(|(id, title)| {
+            let score = text::trigram_containment(&query_grams, &text::trigrams(&db::fold_for_sorting(&title)));
+            (id, score)
+        })
+        .filter(|(_, score)| *score >= threshold && *score > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(100);
+
+    let ids: Vec<&str> = scored.iter().map(|(id, _)| id.as_str()).collect();
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT {SUMMARY_COLUMNS}
+        FROM conversations c
+        LEFT JOIN messages m ON c.id = m.conversation_id
+        WHERE c.id IN (SELECT value FROM json_each(?))
+        GROUP BY {SUMMARY_GROUP_BY}
+        "#
+    ))?;
+    let mut summaries: Vec<ConversationSummary> = stmt
+        .query_map([serde_json::to_string(&ids).unwrap_or_default()], |row| summary(row, timezone))?
+        .collect::<rusqlite::Result<_>>()?;
+    for summary in &mut summaries {
+        summary.match_score = scored.iter().find(|(id, _)| *id == summary.id).map(|(_, score)| *score);
+    }
+    summaries.sort_by(|a, b| b.match_score.unwrap_or(0.0).total_cmp(&a.match_score.unwrap_or(0.0)));
+    Ok(summaries)
+}
@@ -0,0 +1,306 @@
+//! Long-term memory entries.
+
+use rusqlite::Connection;
+
+use super::{found, QueryError, QueryResult};
+use crate::dates::Timezone;
+use crate::db;
+use crate::fts;
+use crate::models::{MemoryCategoriesResponse, MemoryCategory, MemoryEntry, MemorySort, MemoryUsage, RenameCategoryPreview, RenameCategoryResponse, SimilarMemory};
+use crate::text;
+
+/// SQL predicate for memories that haven't expired.
+const UNEXPIRED_SQL: &str = "(expires_at IS NULL OR expires_at > unixepoch())";
+
+/// Columns read by [`entry`], over alias `m`.
+const COLUMNS: &str = "m.id, m.content, m.category, m.importance, m.created_at, m.expires_at, m.pinned, m.key";
+
+/// Age at which `search`'s weighted order gives a memory's recency half its weight.
+const HALF_LIFE_DAYS: f64 = 180.0;
+
+/// Memory entry from a row starting with [`COLUMNS`].
+fn entry(row: &rusqlite::Row, timezone: Timezone) -> rusqlite::Result<MemoryEntry> {
+    Ok(MemoryEntry {
+        id: row.get(0)?,
+        content: row.get(1).unwrap_or_default(),
+        category: row.get(2).ok().flatten(),
+        importance: row.get(3).unwrap_or(5),
+        created_at: row.get(4).unwrap_or(0),
+        created_at_iso: row.get(4).ok().and_then(|t| timezone.iso(t)),
+        score: None,
+        expires_at: row.get(5).ok().flatten(),
+        pinned: row.get(6).unwrap_or(false),
+        key: row.get(7).ok().flatten(),
+        attachments: Vec::new(),
+        conflicts: Vec::new(),
+    })
+}
+
+/// Store `memory`, ignoring its id, and return the id it got.
+pub fn insert(db: &Connection, memory: &MemoryEntry) -> QueryResult<i64> {
+    let mut stmt = db.prepare_cached(
+        "INSERT INTO memory (content, category, importance, created_at, expires_at, pinned, key) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    stmt.execute(rusqlite::params![
+        memory.content,
+        memory.category,
+        memory.importance,
+        memory.created_at,
+        memory.expires_at,
+        memory.pinned,
+        memory.key
+    ])?;
+    Ok(db.last_insert_rowid())
+}
+
+/// The memory with id `memory_id`, expired or not.
+pub fn get(db: &Connection, memory_id: i64, timezone: Timezone) -> QueryResult<MemoryEntry> {
+    let mut stmt = db.prepare_cached(&format!("SELECT {} FROM memory m WHERE m.id = ?", COLUMNS))?;
+    found(stmt.query_row([memory_id], |row| entry(row, timezone)), "Memory entry")
+}
+
+/// Unexpired pinned memories, most important first.
+pub fn pinned(db: &Connection, timezone: Timezone) -> QueryResult<Vec<MemoryEntry>> {
+    let mut stmt = db.prepare_cached(&format!(
+        "SELECT {} FROM memory m WHERE m.pinned AND {} ORDER BY m.importance DESC, m.created_at DESC",
+        COLUMNS, UNEXPIRED_SQL
+    ))?;
+    let entries = stmt.query_map([], |row| entry(row, timezone))?.collect::<rusqlite::Result<_>>()?;
+    Ok(entries)
+}
+
+/// Up to 50 unexpired memories filed under `category`, most important first.
+pub fn in_category(db: &Connection, category: &str, timezone: Timezone) -> QueryResult<Vec<MemoryEntry>> {
+    let mut stmt = db.prepare_cached(&format!(
+        "SELECT {} FROM memory m WHERE m.category = ?1 AND {} ORDER BY m.importance DESC, m.created_at DESC LIMIT 50",
+        COLUMNS, UNEXPIRED_SQL
+    ))?;
+    let entries = stmt.query_map([category], |row| entry(row, timezone))?.collect::<rusqlite::Result<_>>()?;
+    Ok(entries)
+}
+
+/// What `search_memory` looks for; every filter given must match.
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    /// FTS5 expression over `memory_fts`
+    pub fts_query: Option<String>,
+    pub min_importance: Option<i32>,
+    pub category: Option<String>,
+    pub after: Option<i64>,
+    pub before: Option<i64>,
+    pub project_id: Option<i64>,
+    pub sort: MemorySort,
+}
+
+impl SearchQuery {
+    /// Whether any filter is given; without one a search matches nothing.
+    pub fn is_empty(&self) -> bool {
+        self.fts_query.is_none()
+            && self.min_importance.is_none()
+            && self.category.is_none()
+            && self.after.is_none()
+            && self.before.is_none()
+            && self.project_id.is_none()
+    }
+}
+
+/// The 10 unexpired memories best matching `query`, with their scores under the score orders.
+pub fn search(db: &Connection, query: &SearchQuery, timezone: Timezone) -> QueryResult<Vec<MemoryEntry>> {
+    let mut conditions: Vec<&str> = vec![UNEXPIRED_SQL];
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(fts_query) = &query.fts_query {
+        conditions.push("memory_fts MATCH ?");
+        params.push(fts_query.clone().into());
+    }
+    if let Some(min_importance) = query.min_importance {
+        conditions.push("m.importance >= ?");
+        params.push(min_importance.into());
+    }
+    if let Some(category) = &query.category {
+        conditions.push("m.category = ?");
+        params.push(category.clone().into());
+    }
+    if let Some(after) = query.after {
+        conditions.push("m.created_at >= ?");
+        params.push(after.into());
+    }
+    if let Some(before) = query.before {
+        conditions.push("m.created_at < ?");
+        params.push(before.into());
+    }
+    if let Some(project_id) = query.project_id {
+        conditions.push("m.project_id = ?");
+        params.push(project_id.into());
+    }
+    let searches_text = query.fts_query.is_some();
+    let join = if searches_text { "JOIN memory_fts ON m.id = memory_fts.rowid" } else { "" };
+    let relevance = if searches_text { "-bm25(memory_fts)" } else { "1.0" };
+    let (score, order) = match query.sort {
+        // Importance 1-10 scales relevance by 0.6-1.5; age halves the recency part every
+        // HALF_LIFE_DAYS, so old memories keep at least half their weight
+        MemorySort::Weighted => (
+            format!(
+                "{} * (0.5 + m.importance / 10.0) * (0.5 + 0.5 * {}(unixepoch() - m.created_at, {}))",
+                relevance,
+                db::RECENCY_DECAY_FUNCTION,
+                HALF_LIFE_DAYS * 86400.0
+            ),
+            "score DESC, m.created_at DESC",
+        ),
+        MemorySort::Relevance if searches_text => (relevance.to_string(), "score DESC, m.created_at DESC"),
+        MemorySort::Relevance | MemorySort::Importance => ("NULL".to_string(), "m.importance DESC, m.created_at DESC"),
+        MemorySort::Recent => ("NULL".to_string(), "m.created_at DESC"),
+        MemorySort::Oldest => ("NULL".to_string(), "m.created_at ASC"),
+    };
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT {COLUMNS}, {score} AS score
+        FROM memory m
+        {join}
+        WHERE {conditions}
+        ORDER BY {order}
+        LIMIT 10
+        "#,
+        conditions = conditions.join(" AND ")
+    ))?;
+    let entries = stmt
+        .query_map(rusqlite::params_from_iter(&params), |row| {
+            let mut entry = entry(row, timezone)?;
+            entry.score = row.get(8).ok().flatten();
+            Ok(entry)
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(entries)
+}
+
+/// Pin or unpin a memory.
+pub fn set_pinned(db: &Connection, memory_id: i64, pinned: bool) -> QueryResult<()> {
+    let mut stmt = db.prepare_cached("UPDATE memory SET pinned = ?1 WHERE id = ?2")?;
+    match stmt.execute(rusqlite::params![pinned, memory_id])? {
+        0 => Err(QueryError::NotFound("Memory entry")),
+        _ => Ok(()),
+    }
+}
+
+pub fn delete(db: &Connection, memory_id: i64) -> QueryResult<()> {
+    let mut stmt = db.prepare_cached("DELETE FROM memory WHERE id = ?")?;
+    match stmt.execute([memory_id])? {
+        0 => Err(QueryError::NotFound("Memory entry")),
+        _ => Ok(()),
+    }
+}
+
+/// Count a retrieval of each returned memory; feeds the hygiene report. Best effort.
+pub fn record_access(db: &Connection, entries: &[MemoryEntry]) {
+    let Ok(mut stmt) =
+        db.prepare_cached("UPDATE memory SET access_count = access_count + 1, last_accessed_at = ?1 WHERE id = ?2")
+    else {
+        return;
+    };
+    let now = db::unix_now();
+    for entry in entries {
+        let _ = stmt.execute([now, entry.id]);
+    }
+}
+
+/// Move every memory filed under `old_name` to `new_name`, in one transaction. Fails when
+/// `new_name` is already in use, unless `merge`.
+pub fn rename_category(db: &Connection, old_name: &str, new_name: &str, merge: bool) -> QueryResult<RenameCategoryResponse> {
+    let tx = db.unchecked_transaction()?;
+    let RenameCategoryPreview { merged, .. } = preview_rename_category(&tx, old_name, new_name)?;
+    if merged && !merge {
+        return Err(QueryError::Invalid(format!(
+            "Category '{}' already exists; pass merge: true to fold '{}' into it",
+            new_name, old_name
+        )));
+    }
+    let renamed = tx.prepare_cached("UPDATE memory SET category = ?1 WHERE category = ?2")?.execute([new_name, old_name])?;
+    tx.commit()?;
+    Ok(RenameCategoryResponse { success: true, renamed: renamed as i64, merged, error: None })
+}
+
+/// How many memories renaming `old_name` to `new_name` would move, and whether it would merge
+/// them into an existing category.
+pub fn preview_rename_category(db: &Connection, old_name: &str, new_name: &str) -> QueryResult<RenameCategoryPreview> {
+    let count = |name: &str| -> rusqlite::Result<i64> {
+        db.prepare_cached("SELECT COUNT(*) FROM memory WHERE category = ?")?.query_row([name], |row| row.get(0))
+    };
+    let renamed = count(old_name)?;
+    if renamed == 0 {
+        return Err(QueryError::Invalid(format!("No memories in category '{}'", old_name)));
+    }
+    Ok(RenameCategoryPreview { renamed, merged: count(new_name)? > 0, ..Default::default() })
+}
+
+/// Categories of unexpired memories with their sizes, largest first, and the number without one.
+pub fn categories(db: &Connection, timezone: Timezone) -> QueryResult<MemoryCategoriesResponse> {
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT category, COUNT(*) AS count, MAX(created_at)
+        FROM memory
+        WHERE category IS NOT NULL AND category != '' AND {}
+        GROUP BY category
+        ORDER BY count DESC, category
+        "#,
+        UNEXPIRED_SQL
+    ))?;
+    let items = stmt
+        .query_map([], |row| {
+            Ok(MemoryCategory {
+                category: row.get(0)?,
+                count: row.get(1)?,
+                latest_created_at: row.get(2).unwrap_or(0),
+                latest_created_at_iso: row.get(2).ok().and_then(|t| timezone.iso(t)),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let uncategorized = db
+        .prepare_cached(&format!("SELECT COUNT(*) FROM memory WHERE (category IS NULL OR category = '') AND {}", UNEXPIRED_SQL))?
+        .query_row([], |row| row.get(0))?;
+    Ok(MemoryCategoriesResponse { items, uncategorized })
+}
+
+/// Every unexpired memory with its retrieval counts, oldest first.
+pub fn usage(db: &Connection, timezone: Timezone) -> QueryResult<Vec<MemoryUsage>> {
+    let mut stmt = db.prepare_cached(&format!(
+        "SELECT {}, m.access_count, m.last_accessed_at FROM memory m WHERE {} ORDER BY m.created_at ASC",
+        COLUMNS, UNEXPIRED_SQL
+    ))?;
+    let memories = stmt
+        .query_map([], |row| {
+            Ok(MemoryUsage { entry: entry(row, timezone)?, access_count: row.get(8).unwrap_or(0), last_accessed_at: row.get(9).ok() })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(memories)
+}
+
+/// Up to `limit` unexpired memories most similar to memory `memory_id` by word overlap (Jaccard
+/// similarity of their word sets), best first. Candidates share at least one word through
+/// `memory_fts`, or are all memories without the index.
+pub fn similar(db: &Connection, memory_id: i64, limit: usize, timezone: Timezone) -> QueryResult<Vec<SimilarMemory>> {
+    let memory = get(db, memory_id, timezone)?;
+    let tokens = text::token_set(&memory.content);
+    let words: Vec<String> = tokens.iter().cloned().collect();
+    let mut params: Vec<rusqlite::types::Value> = vec![memory_id.into()];
+    let filter = match fts::any_of(&words, false) {
+        Some(query) if db::has_index(db, "memory_fts") => {
+            params.push(query.into());
+            "AND m.id IN (SELECT rowid FROM memory_fts WHERE memory_fts MATCH ?)"
+        }
+        _ => "",
+    };
+    let mut stmt = db.prepare_cached(&format!(
+        "SELECT {} FROM memory m WHERE m.id != ? AND {} {}",
+        COLUMNS, UNEXPIRED_SQL, filter
+    ))?;
+    let mut similar: Vec<SimilarMemory> = stmt
+        .query_map(rusqlite::params_from_iter(&params), |row| entry(row, timezone))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .map(|entry| SimilarMemory { similarity: text::jaccard(&tokens, &text::token_set(&entry.content)), entry })
+        .filter(|similar| similar.similarity > 0.0)
+        .collect();
+    similar.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then_with(|| b.entry.id.cmp(&a.entry.id)));
+    similar.truncate(limit);
+    Ok(similar)
+}
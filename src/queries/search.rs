@@ -0,0 +1,549 @@
+//! Message searches: the SQL behind `search_conversations`, `advanced_search` and the lookups
+//! built on them. Searches are assembled from predicates over alias `m` (a row of `messages`),
+//! each with its parameters, which [`Ranking`] orders and scores.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use rusqlite::Connection;
+
+use super::{all_conditions, any_condition, QueryResult};
+use crate::dates::{self, Timezone};
+use crate::db;
+use crate::fts;
+use crate::models::{
+    KeywordHits, NearQuery, Project, SearchCounts, SearchDiagnostics, SearchExplanation, SearchResult, SearchSort, SearchStage,
+    SearchTermStrategy,
+};
+use crate::projects;
+use crate::synonyms;
+use crate::tokens;
+
+/// Default half-life of the recency boost in `recency_boosted` search ranking.
+const DEFAULT_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// A full-text index over a column of `messages` that `search_conversations` looks in.
+struct MessageIndex {
+    index: &'static str,
+    column: &'static str,
+    trigram: bool,
+}
+
+impl MessageIndex {
+    fn new(db: &Connection, index: &'static str, column: &'static str) -> Self {
+        Self { index, column, trigram: fts::uses_trigram(db, index) }
+    }
+
+    /// A trigram index matches inside words on its own; prefix queries don't apply to it.
+    fn prefix(&self, prefix: bool) -> bool {
+        prefix && !self.trigram
+    }
+
+    /// SQL predicate (over alias `m`) for messages whose column matches the FTS5 `expression`.
+    fn matching(&self, expression: String) -> (String, Vec<String>) {
+        (
+            format!("m.id IN (SELECT rowid FROM {0} WHERE {0} MATCH ?)", self.index),
+            vec![expression],
+        )
+    }
+
+    /// FTS5 expression matching `text`, or `None` when the index can't match it: a trigram index
+    /// can't match anything shorter than three characters (e.g. most Chinese words).
+    fn expression(&self, text: &str, prefix: bool) -> Option<String> {
+        let text = text.trim();
+        (!self.trigram || text.chars().count() >= fts::TRIGRAM_MIN_CHARS).then(|| fts::term(text, self.prefix(prefix)))
+    }
+
+    /// SQL predicate (over alias `m`) for messages whose column contains `text`. Terms the index
+    /// can't match fall back to a `LIKE` scan.
+    fn term(&self, text: &str, prefix: bool) -> (String, Vec<String>) {
+        match self.expression(text, prefix) {
+            Some(expression) => self.matching(expression),
+            None => (format!("m.{} LIKE ? ESCAPE '\\'", self.column), vec![fts::like_pattern(text.trim())]),
+        }
+    }
+
+    /// How [`term`](Self::term) matches `text`.
+    fn strategy(&self, text: &str, prefix: bool) -> SearchTermStrategy {
+        let (strategy, expression) = match self.expression(text, prefix) {
+            Some(expression) => ("fts", expression),
+            None => ("like", fts::like_pattern(text.trim())),
+        };
+        SearchTermStrategy {
+            term: text.to_string(),
+            index: self.index.to_string(),
+            trigram: self.trigram,
+            strategy: strategy.to_string(),
+            expression,
+        }
+    }
+}
+
+
+/// Search result from a row of `id, conversation_id, role, content_preview, created_at, content,
+/// reasoning_preview, search_score, content_chars`.
+fn search_result(row: &rusqlite::Row, timezone: Timezone) -> rusqlite::Result<SearchResult> {
+    Ok(SearchResult {
+        message_id: row.get(0).unwrap_or(0),
+        conversation_id: row.get(1).unwrap_or_default(),
+        role: row.get(2).unwrap_or_default(),
+        content_preview: row.get(3).unwrap_or_default(),
+        created_at: row.get(4).unwrap_or(0),
+        created_at_iso: row.get(4).ok().and_then(|t| timezone.iso(t)),
+        age: row.get(4).ok().map(dates::age),
+        content: row.get(5).ok().flatten(),
+        reasoning_preview: row.get(6).ok().flatten(),
+        score: row.get(7).ok().flatten(),
+        matched_keywords: Vec::new(),
+        estimated_tokens: row.get::<_, Option<i64>>(8).ok().flatten().map_or(0, |chars| tokens::from_chars(chars as u64)),
+        archived: false,
+    })
+}
+
+/// The text part of a message search: which indexes it looks in and how terms match there.
+pub struct TextSearch {
+    indexes: Vec<MessageIndex>,
+    prefix: bool,
+}
+
+impl TextSearch {
+    /// Message content, plus reasoning traces when asked for and indexed.
+    pub fn new(db: &Connection, include_reasoning: bool, prefix: bool) -> Self {
+        let mut indexes = vec![MessageIndex::new(db, "messages_fts", "content")];
+        if include_reasoning && db::has_index(db, "reasoning_fts") {
+            indexes.push(MessageIndex::new(db, "reasoning_fts", "reasoning_content"));
+        }
+        Self { indexes, prefix }
+    }
+
+    pub fn searches_reasoning(&self) -> bool {
+        self.indexes.len() > 1
+    }
+
+    /// SQL predicate (over alias `m`) for messages containing `text` in any searched column.
+    pub fn term(&self, text: &str) -> (String, Vec<String>) {
+        any_condition(self.indexes.iter().map(|index| index.term(text, self.prefix)))
+    }
+
+    /// How each of `terms` is matched in each searched index.
+    pub fn strategies(&self, terms: &[String]) -> Vec<SearchTermStrategy> {
+        terms.iter().flat_map(|term| self.indexes.iter().map(|index| index.strategy(term, self.prefix))).collect()
+    }
+
+    /// One predicate per part given: any of the keywords, the phrase, the near group.
+    pub fn conditions(&self, keywords: &[String], phrase: Option<&str>, near: Option<&NearQuery>) -> Vec<(String, Vec<String>)> {
+        let mut conditions = Vec::new();
+        if !keywords.is_empty() {
+            conditions.push(any_condition(keywords.iter().map(|k| self.term(k))));
+        }
+        if let Some(phrase) = phrase {
+            conditions.push(self.term(phrase));
+        }
+        if let Some(near) = near {
+            conditions.push(any_condition(self.indexes.iter().filter_map(|index| {
+                fts::near(&near.terms, near.distance, index.prefix(self.prefix)).map(|expression| index.matching(expression))
+            })));
+        }
+        conditions
+    }
+
+    /// FTS5 expression for bm25 relevance over the message content index, covering whichever
+    /// terms it can match; empty when it can match none.
+    pub fn relevance_query(&self, keywords: &[String], phrase: Option<&str>, near: Option<&NearQuery>) -> String {
+        let messages_index = &self.indexes[0];
+        keywords
+            .iter()
+            .map(String::as_str)
+            .chain(phrase)
+            .filter_map(|text| messages_index.expression(text, self.prefix))
+            .chain(near.and_then(|near| fts::near(&near.terms, near.distance, messages_index.prefix(self.prefix))))
+            .collect::<Vec<_>>()
+            .join(" OR ")
+    }
+}
+
+/// Ordering of a message search. `scores` is a CTE to put before the query, `score_join` joins it
+/// to `messages m`, and `score` is the expression selected as `search_score`, which `order` may
+/// sort by. `params` bind the CTE's placeholders and come before the query's own.
+pub struct Ranking {
+    pub scores: String,
+    score_join: &'static str,
+    score: String,
+    pub order: &'static str,
+    pub params: Vec<String>,
+}
+
+impl Ranking {
+    /// Ranking for `sort`; relevance orders fall back to newest first without a `relevance_query`.
+    /// `scored` computes relevance scores under the date orders too, for filtering by them.
+    pub fn new(sort: Option<SearchSort>, relevance_query: String, half_life_days: Option<f64>, scored: bool) -> Self {
+        const NEWEST: &str = "m.created_at DESC, m.id DESC";
+        const OLDEST: &str = "m.created_at ASC, m.id ASC";
+        const BEST: &str = "m.search_score DESC, m.created_at DESC, m.id DESC";
+        let sort = sort.unwrap_or_default();
+        let unranked = |order| Self { scores: String::new(), score_join: "", score: "NULL".to_string(), order, params: Vec::new() };
+        let relevance = "COALESCE(-s.score, 0)".to_string();
+        let (score, order) = match sort {
+            SearchSort::Oldest if !scored || relevance_query.is_empty() => return unranked(OLDEST),
+            SearchSort::Recent if !scored => return unranked(NEWEST),
+            _ if relevance_query.is_empty() => return unranked(NEWEST),
+            SearchSort::Oldest => (relevance, OLDEST),
+            SearchSort::Recent => (relevance, NEWEST),
+            SearchSort::Relevance => (relevance, BEST),
+            SearchSort::RecencyBoosted => {
+                let half_life_seconds = half_life_days.filter(|days| *days > 0.0).unwrap_or(DEFAULT_HALF_LIFE_DAYS) * 86400.0;
+                let boosted = format!(
+                    "COALESCE(-s.score, 0) * {}(unixepoch() - m.created_at, {})",
+                    db::RECENCY_DECAY_FUNCTION,
+                    half_life_seconds
+                );
+                (boosted, BEST)
+            }
+        };
+        Self {
+            // bm25() only works in the query that runs the MATCH, hence the materialized CTE
+            scores: "WITH scores AS MATERIALIZED (SELECT rowid, bm25(messages_fts) AS score FROM messages_fts WHERE messages_fts MATCH ?)".to_string(),
+            score_join: "LEFT JOIN scores s ON s.rowid = m.id",
+            score,
+            order,
+            params: vec![relevance_query],
+        }
+    }
+
+    /// Whether the ranking computes relevance scores.
+    pub fn scored(&self) -> bool {
+        !self.scores.is_empty()
+    }
+
+    /// Subquery (aliased `m`) of the messages matching the predicate `sql` with their
+    /// `search_score`, keeping only those scoring at least `min_score` when given. Unscored
+    /// rankings have no scores to pass `min_score`.
+    pub fn matches(&self, sql: &str, min_score: Option<f64>) -> String {
+        let matches = format!("SELECT m.*, {} AS search_score FROM messages m {} WHERE {}", self.score, self.score_join, sql);
+        match min_score {
+            Some(min) => format!("(SELECT * FROM ({}) WHERE search_score >= {}) m", matches, min),
+            None => format!("({}) m", matches),
+        }
+    }
+}
+
+/// Explain why a search for `keywords`, `phrase` and `near` under the predicate `sql` found
+/// nothing: which terms occur nowhere, how many messages the text alone matches, and whether the
+/// `filters` or `min_score` excluded them.
+#[allow(clippy::too_many_arguments)]
+pub fn diagnose(
+    db: &Connection,
+    text: &TextSearch,
+    keywords: &[String],
+    phrase: Option<&str>,
+    near: Option<&NearQuery>,
+    (sql, params): (&str, &[String]),
+    ranking: &Ranking,
+    mut filters: Vec<String>,
+    min_score: Option<f64>,
+) -> SearchDiagnostics {
+    let count = |condition| count(db, condition).unwrap_or(0);
+    let near_terms = near.map(|near| fts::clean_keywords(&near.terms)).unwrap_or_default();
+    let terms: Vec<&str> = keywords.iter().map(String::as_str).chain(phrase).chain(near_terms.iter().map(String::as_str)).collect();
+    let terms_without_matches: Vec<String> =
+        terms.iter().filter(|term| count(text.term(term)) == 0).map(|term| term.to_string()).collect();
+    let text_conditions = text.conditions(keywords, phrase, near);
+    let has_text = !text_conditions.is_empty();
+    let text_matches = if has_text { count(all_conditions(text_conditions)) } else { 0 };
+    let filtered_matches = if filters.is_empty() { text_matches } else { count((sql.to_string(), params.to_vec())) };
+    let best_score = min_score.filter(|_| filtered_matches > 0).and_then(|_| {
+        let mut query_params = ranking.params.clone();
+        query_params.extend(params.iter().cloned());
+        db.prepare_cached(&format!("{} SELECT MAX(m.search_score) FROM {}", ranking.scores, ranking.matches(sql, None)))
+            .and_then(|mut stmt| stmt.query_row(rusqlite::params_from_iter(&query_params), |row| row.get::<_, Option<f64>>(0)))
+            .ok()
+            .flatten()
+    });
+
+    let mut hints = Vec::new();
+    if !has_text {
+        if filtered_matches == 0 && !filters.is_empty() {
+            hints.push(format!("No message passes all of the filters ({}); relax or drop some", filters.join(", ")));
+        }
+    } else if text_matches == 0 {
+        if !terms.is_empty() && terms_without_matches.len() == terms.len() {
+            hints.push(
+                "No message contains any of the terms: check their spelling, try other words or synonyms (add_synonyms), or set prefix to match word beginnings".to_string(),
+            );
+        } else if !terms_without_matches.is_empty() {
+            hints.push(format!("Drop or respell the terms no message contains: {}", terms_without_matches.join(", ")));
+        }
+        if phrase.is_some() && terms_without_matches.iter().any(|term| Some(term.as_str()) == phrase) {
+            hints.push("The phrase doesn't occur word for word; search its words as keywords instead".to_string());
+        }
+        if [!keywords.is_empty(), phrase.is_some(), near.is_some()].iter().filter(|given| **given).count() > 1 {
+            hints.push("Keywords, phrase and near terms must all match the same message; try them separately".to_string());
+        }
+    } else if filtered_matches == 0 {
+        hints.push(format!(
+            "{} messages match the text but none pass the filters ({}); relax or drop them",
+            text_matches,
+            filters.join(", ")
+        ));
+    }
+    if let Some(min) = min_score {
+        filters.push("min_score".to_string());
+        if filtered_matches > 0 {
+            hints.push(match best_score {
+                Some(best) => {
+                    let best = if best >= 0.01 { format!("{:.2}", best) } else { format!("{:.2e}", best) };
+                    format!("min_score {} excluded all {} matches; the best scored {}", min, filtered_matches, best)
+                }
+                None => format!("min_score {} excluded all {} matches; none could be scored", min, filtered_matches),
+            });
+        }
+    }
+    SearchDiagnostics { terms_without_matches, text_matches, filters, best_score, hints }
+}
+
+
+/// SQL predicate (over alias `m`) for messages from conversations of `project`: ones whose title
+/// or any message mentions one of its terms, and ones its rules tagged.
+pub fn project_condition(db: &Connection, text: &TextSearch, project: &Project) -> (String, Vec<String>) {
+    let terms = projects::terms(project);
+    let (titles, mut params) = any_condition(
+        terms.iter().map(|term| ("title LIKE ? ESCAPE '\\'".to_string(), vec![fts::like_pattern(term)])),
+    );
+    let (messages, message_params) = any_condition(terms.iter().map(|term| text.term(term)));
+    params.extend(message_params);
+    // Databases last migrated before rules existed have no conversation_projects table
+    let tagged = if db::has_column(db, "conversation_projects", "project_id") {
+        params.push(project.id.to_string());
+        " UNION SELECT conversation_id FROM conversation_projects WHERE project_id = ?"
+    } else {
+        ""
+    };
+    (
+        format!(
+            "m.conversation_id IN (SELECT id FROM conversations WHERE {} UNION SELECT m.conversation_id FROM messages m WHERE {}{})",
+            titles, messages, tagged
+        ),
+        params,
+    )
+}
+
+/// Number of messages matching the predicate `condition`.
+pub fn count(db: &Connection, (sql, params): (String, Vec<String>)) -> QueryResult<i64> {
+    let mut stmt = db.prepare_cached(&format!("SELECT COUNT(*) FROM messages m WHERE {}", sql))?;
+    Ok(stmt.query_row(rusqlite::params_from_iter(&params), |row| row.get(0))?)
+}
+
+/// Number of messages in `matches`, from [`Ranking::matches`], bound by `params`.
+pub fn total(db: &Connection, ranking: &Ranking, matches: &str, params: &[String]) -> QueryResult<i64> {
+    let mut stmt = db.prepare_cached(&total_sql(ranking, matches))?;
+    Ok(stmt.query_row(rusqlite::params_from_iter(params), |row| row.get(0))?)
+}
+
+fn total_sql(ranking: &Ranking, matches: &str) -> String {
+    format!("{} SELECT COUNT(*) FROM {}", ranking.scores, matches)
+}
+
+/// Messages and conversations in `matches`, from [`Ranking::matches`], bound by `params`.
+pub fn counts(db: &Connection, ranking: &Ranking, matches: &str, params: &[String]) -> QueryResult<SearchCounts> {
+    let mut stmt =
+        db.prepare_cached(&format!("{} SELECT COUNT(*), COUNT(DISTINCT m.conversation_id) FROM {}", ranking.scores, matches))?;
+    Ok(stmt.query_row(rusqlite::params_from_iter(params), |row| {
+        Ok(SearchCounts { messages: row.get(0)?, conversations: row.get(1)? })
+    })?)
+}
+
+/// A page of search results and what each carries besides its preview.
+#[derive(Debug, Default)]
+pub struct Page {
+    pub preview_chars: u32,
+    pub full_content: bool,
+    /// Reasoning previews, when the search looks in reasoning
+    pub reasoning: bool,
+    /// Best `max_per_conversation` matches of each conversation only
+    pub max_per_conversation: Option<u32>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// The `page` of `matches`, from [`Ranking::matches`], bound by `params`, in the ranking's order.
+pub fn page(
+    db: &Connection,
+    ranking: &Ranking,
+    matches: String,
+    params: &[String],
+    page: &Page,
+    timezone: Timezone,
+) -> QueryResult<Vec<SearchResult>> {
+    let mut stmt = db.prepare_cached(&page_sql(ranking, matches, page))?;
+    let results = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| search_result(row, timezone))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(results)
+}
+
+fn page_sql(ranking: &Ranking, matches: String, page: &Page) -> String {
+    let Ranking { scores, order, .. } = ranking;
+    let source = match page.max_per_conversation {
+        // Best matches of each conversation first, so one long thread can't take every slot
+        Some(max) => format!(
+            "(SELECT m.*, ROW_NUMBER() OVER (PARTITION BY m.conversation_id ORDER BY {}) AS conversation_rank FROM {}) m WHERE m.conversation_rank <= {}",
+            order,
+            matches,
+            max.max(1)
+        ),
+        None => matches,
+    };
+    format!(
+        r#"
+        {scores}
+        SELECT
+            m.id,
+            m.conversation_id,
+            m.role,
+            substr(m.content, 1, {preview_chars}) as content_preview,
+            m.created_at,
+            {content},
+            {reasoning},
+            m.search_score,
+            length(m.content)
+        FROM {source}
+        ORDER BY {order}
+        LIMIT {limit} OFFSET {offset}
+        "#,
+        preview_chars = page.preview_chars,
+        content = if page.full_content { "m.content" } else { "NULL" },
+        reasoning = if page.reasoning {
+            format!("substr(m.reasoning_content, 1, {})", page.preview_chars)
+        } else {
+            "NULL".to_string()
+        },
+        limit = page.limit,
+        offset = page.offset,
+    )
+}
+
+/// Total hits of each of `keywords`, noting on `results` which of them each matched. One lookup per
+/// keyword.
+pub fn keyword_hits(db: &Connection, text: &TextSearch, keywords: &[String], results: &mut [SearchResult]) -> Vec<KeywordHits> {
+    let ids = serde_json::to_string(&results.iter().map(|r| r.message_id).collect::<Vec<_>>()).unwrap_or_default();
+    let mut keyword_hits = Vec::with_capacity(keywords.len());
+    for keyword in keywords {
+        let (sql, params) = text.term(keyword);
+        let hits = count(db, (sql.clone(), params.clone())).unwrap_or(0);
+        let matched: Vec<i64> = if results.is_empty() {
+            Vec::new()
+        } else {
+            db.prepare_cached(&format!("SELECT m.id FROM messages m WHERE {} AND m.id IN (SELECT value FROM json_each(?))", sql))
+                .and_then(|mut stmt| {
+                    stmt.query_map(rusqlite::params_from_iter(params.iter().chain([&ids])), |row| row.get(0))?.collect()
+                })
+                .unwrap_or_default()
+        };
+        keyword_hits.push(KeywordHits { keyword: keyword.clone(), hits });
+        for result in results.iter_mut().filter(|r| matched.contains(&r.message_id)) {
+            result.matched_keywords.push(keyword.clone());
+        }
+    }
+    keyword_hits
+}
+
+/// `EXPLAIN QUERY PLAN` of `sql` bound by `params`, one step per line, indented under its parent.
+pub fn query_plan(db: &Connection, sql: &str, params: &[String]) -> QueryResult<Vec<String>> {
+    let mut stmt = db.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+    let steps = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(3)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut depths: HashMap<i64, usize> = HashMap::new();
+    Ok(steps
+        .into_iter()
+        .map(|(id, parent, detail)| {
+            let depth = depths.get(&parent).map_or(0, |depth| depth + 1);
+            depths.insert(id, depth);
+            format!("{}{}", "  ".repeat(depth), detail)
+        })
+        .collect())
+}
+
+/// How `search_conversations` runs a search for `keywords`: how each term is matched, then the
+/// SQL, query plan and duration of each stage. The stages run for real, so their times are what
+/// the search would take.
+pub fn explain(
+    db: &Connection,
+    keywords: &[String],
+    include_reasoning: bool,
+    prefix: bool,
+    sort: Option<SearchSort>,
+    expand_synonyms: bool,
+    timezone: Timezone,
+) -> QueryResult<SearchExplanation> {
+    let started = Instant::now();
+    let elapsed_ms = |since: Instant| since.elapsed().as_micros() as f64 / 1000.0;
+    let searched = if expand_synonyms { synonyms::expand(db, keywords).0 } else { keywords.to_vec() };
+    let text = TextSearch::new(db, include_reasoning, prefix);
+    let (sql, params) = all_conditions(text.conditions(&searched, None, None));
+    let ranking = Ranking::new(sort, text.relevance_query(&searched, None, None), None, false);
+    let mut query_params = ranking.params.clone();
+    query_params.extend(params.iter().cloned());
+    let matches = ranking.matches(&sql, None);
+    let mut stages = vec![SearchStage { stage: "plan".to_string(), duration_ms: elapsed_ms(started), ..Default::default() }];
+
+    let mut stage = |name: &str, sql: String, run: &mut dyn FnMut() -> QueryResult<i64>| -> QueryResult<()> {
+        let stage_started = Instant::now();
+        let rows = run()?;
+        let duration_ms = elapsed_ms(stage_started);
+        stages.push(SearchStage {
+            stage: name.to_string(),
+            query_plan: query_plan(db, &sql, &query_params)?,
+            sql: Some(sql.split_whitespace().collect::<Vec<_>>().join(" ")),
+            rows,
+            duration_ms,
+        });
+        Ok(())
+    };
+    stage("count", total_sql(&ranking, &matches), &mut || total(db, &ranking, &matches, &query_params))?;
+    let first_page = Page { preview_chars: 200, limit: 50, ..Default::default() };
+    let mut results = Vec::new();
+    stage("page", page_sql(&ranking, matches.clone(), &first_page), &mut || {
+        results = page(db, &ranking, matches.clone(), &query_params, &first_page, timezone)?;
+        Ok(results.len() as i64)
+    })?;
+    let hits_started = Instant::now();
+    let hits = keyword_hits(db, &text, &searched, &mut results);
+    stages.push(SearchStage {
+        stage: "keyword_hits".to_string(),
+        rows: hits.iter().map(|hits| hits.hits).sum(),
+        duration_ms: elapsed_ms(hits_started),
+        ..Default::default()
+    });
+
+    Ok(SearchExplanation {
+        terms: text.strategies(&searched),
+        keywords: searched,
+        ranking: if ranking.scored() { "bm25" } else { "unscored" }.to_string(),
+        stages,
+        total_ms: elapsed_ms(started),
+        error: None,
+    })
+}
+
+/// The latest `limit` messages containing any of `names`, as search results.
+pub fn mentions(db: &Connection, names: &[String], limit: u32, timezone: Timezone) -> QueryResult<Vec<SearchResult>> {
+    let Some((sql, mut params)) = TextSearch::new(db, false, false).conditions(names, None, None).pop() else {
+        return Ok(Vec::new());
+    };
+    params.push(limit.to_string());
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT m.id, m.conversation_id, m.role, substr(m.content, 1, 200), m.created_at, NULL, NULL, NULL, length(m.content)
+        FROM messages m
+        WHERE {}
+        ORDER BY m.created_at DESC, m.id DESC
+        LIMIT ?
+        "#,
+        sql
+    ))?;
+    let mentions = stmt
+        .query_map(rusqlite::params_from_iter(&params), |row| search_result(row, timezone))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(mentions)
+}
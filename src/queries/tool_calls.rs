@@ -0,0 +1,268 @@
+//! Tool calls recorded in messages: what tools did, which failed and how often they ran.
+
+use rusqlite::Connection;
+
+use super::QueryResult;
+use crate::db;
+use crate::fts;
+use crate::models::{FailureResult, ToolActivityResult, ToolFailureStat, ToolStatusFilter, ToolUsage, ToolUsageConversation};
+
+/// SQL predicate (over alias `m`) matching tool messages whose status reports a failure.
+const FAILED_STATUS_SQL: &str = "lower(m.tool_status) IN ('error', 'failed', 'failure')";
+
+/// SQL predicate (over alias `m`) matching tool calls whose name, parameters or result contain
+/// `query` as a phrase, and its parameters. Without `tool_activity_fts`, or for a term too short
+/// for a trigram index, the columns are scanned instead.
+fn activity_condition(db: &Connection, query: &str) -> (String, Vec<String>) {
+    let indexed = db::has_index(db, "tool_activity_fts")
+        && !(fts::uses_trigram(db, "tool_activity_fts") && query.chars().count() < fts::TRIGRAM_MIN_CHARS);
+    if indexed {
+        (
+            "m.id IN (SELECT rowid FROM tool_activity_fts WHERE tool_activity_fts MATCH ?)".to_string(),
+            vec![fts::term(query, false)],
+        )
+    } else {
+        (
+            "(m.tool_name LIKE ? ESCAPE '\\' OR m.tool_params_json LIKE ? ESCAPE '\\' OR m.tool_result_json LIKE ? ESCAPE '\\')".to_string(),
+            vec![fts::like_pattern(query); 3],
+        )
+    }
+}
+
+/// Tool calls matching all `conditions` (over alias `m`), newest first.
+fn activity<P: rusqlite::ToSql>(
+    db: &Connection,
+    conditions: &[String],
+    params: &[P],
+    limit: i64,
+) -> QueryResult<Vec<ToolActivityResult>> {
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT
+            m.id,
+            m.conversation_id,
+            c.title,
+            m.tool_name,
+            m.tool_status,
+            substr(m.tool_params_json, 1, 200) as params_preview,
+            substr(m.tool_result_json, 1, 200) as result_preview,
+            m.created_at
+        FROM messages m
+        LEFT JOIN conversations c ON c.id = m.conversation_id
+        WHERE {}
+        ORDER BY m.created_at DESC, m.id DESC
+        LIMIT {}
+        "#,
+        conditions.join(" AND "),
+        limit
+    ))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            Ok(ToolActivityResult {
+                message_id: row.get(0).unwrap_or(0),
+                conversation_id: row.get(1).unwrap_or_default(),
+                conversation_title: row.get(2).ok(),
+                tool_name: row.get(3).ok(),
+                tool_status: row.get(4).ok(),
+                params_preview: row.get(5).ok(),
+                result_preview: row.get(6).ok(),
+                created_at: row.get(7).unwrap_or(0),
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(rows)
+}
+
+/// Up to `limit` tool calls whose name, parameters or result contain `query`, newest first.
+pub fn search(db: &Connection, query: &str, limit: i64) -> QueryResult<Vec<ToolActivityResult>> {
+    let (condition, params) = activity_condition(db, query);
+    activity(db, &[condition], &params, limit)
+}
+
+/// What `find_tool_calls` looks for; every filter given must match.
+#[derive(Debug, Default)]
+pub struct FindQuery<'a> {
+    pub tool_name: Option<&'a str>,
+    pub status: Option<ToolStatusFilter>,
+    /// Text in the tool name, parameters or result
+    pub query: Option<&'a str>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: i64,
+}
+
+/// Tool calls matching `query`, newest first.
+pub fn find(db: &Connection, query: &FindQuery) -> QueryResult<Vec<ToolActivityResult>> {
+    let mut conditions = vec!["m.tool_name IS NOT NULL".to_string()];
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(tool_name) = query.tool_name {
+        conditions.push("m.tool_name = ? COLLATE NOCASE".to_string());
+        params.push(tool_name.to_string().into());
+    }
+    match query.status {
+        Some(ToolStatusFilter::Error) => conditions.push(FAILED_STATUS_SQL.to_string()),
+        Some(ToolStatusFilter::Success) => conditions.push(format!("m.tool_status IS NOT NULL AND NOT {}", FAILED_STATUS_SQL)),
+        None => {}
+    }
+    if let Some(text) = query.query {
+        let (condition, text_params) = activity_condition(db, text);
+        conditions.push(condition);
+        params.extend(text_params.into_iter().map(Into::into));
+    }
+    if let Some(since) = query.since {
+        conditions.push("m.created_at >= ?".to_string());
+        params.push(since.into());
+    }
+    if let Some(until) = query.until {
+        conditions.push("m.created_at < ?".to_string());
+        params.push(until.into());
+    }
+    activity(db, &conditions, &params, query.limit)
+}
+
+/// Up to 50 failed tool calls whose message, tool name, parameters or result contain `query`,
+/// newest first, each with the start of the assistant message that followed.
+pub fn failures(db: &Connection, query: &str) -> QueryResult<Vec<FailureResult>> {
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT
+            m.id,
+            m.conversation_id,
+            m.tool_name,
+            m.tool_status,
+            substr(m.content, 1, 200) as content_preview,
+            m.created_at,
+            (
+                SELECT substr(n.content, 1, 200)
+                FROM messages n
+                WHERE n.conversation_id = m.conversation_id
+                  AND n.role = 'assistant'
+                  AND n.created_at >= m.created_at
+                  AND n.id != m.id
+                ORDER BY n.created_at ASC, n.id ASC
+                LIMIT 1
+            ) as follow_up_preview
+        FROM messages m
+        WHERE {}
+          AND (m.content LIKE ?1 ESCAPE '\' OR m.tool_name LIKE ?1 ESCAPE '\'
+               OR m.tool_params_json LIKE ?1 ESCAPE '\' OR m.tool_result_json LIKE ?1 ESCAPE '\')
+        ORDER BY m.created_at DESC
+        LIMIT 50
+        "#,
+        FAILED_STATUS_SQL
+    ))?;
+    let results = stmt
+        .query_map([fts::like_pattern(query.trim())], |row| {
+            Ok(FailureResult {
+                message_id: row.get(0).unwrap_or(0),
+                conversation_id: row.get(1).unwrap_or_default(),
+                tool_name: row.get(2).ok(),
+                tool_status: row.get(3).ok(),
+                content_preview: row.get(4).unwrap_or_default(),
+                created_at: row.get(5).unwrap_or(0),
+                follow_up_preview: row.get(6).ok(),
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(results)
+}
+
+/// Failure counts of the 50 most failing tools.
+pub fn failure_stats(db: &Connection) -> QueryResult<Vec<ToolFailureStat>> {
+    let mut stmt = db.prepare_cached(&format!(
+        r#"
+        SELECT
+            m.tool_name,
+            SUM(CASE WHEN {0} THEN 1 ELSE 0 END) as failure_count,
+            COUNT(*) as total_calls,
+            MAX(CASE WHEN {0} THEN m.created_at END) as last_failed_at
+        FROM messages m
+        WHERE m.tool_name IS NOT NULL AND m.tool_status IS NOT NULL
+        GROUP BY m.tool_name
+        HAVING failure_count > 0
+        ORDER BY failure_count DESC, last_failed_at DESC
+        LIMIT 50
+        "#,
+        FAILED_STATUS_SQL
+    ))?;
+    let stats = stmt
+        .query_map([], |row| {
+            let failure_count: i64 = row.get(1).unwrap_or(0);
+            let total_calls: i64 = row.get(2).unwrap_or(0);
+            Ok(ToolFailureStat {
+                tool_name: row.get(0).unwrap_or_default(),
+                failure_count,
+                total_calls,
+                failure_rate: if total_calls > 0 { failure_count as f64 / total_calls as f64 } else { 0.0 },
+                last_failed_at: row.get(3).unwrap_or(0),
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(stats)
+}
+
+/// Calls per tool since `since`, for the 100 most used tools, without their top conversations.
+pub fn usage(db: &Connection, since: i64) -> QueryResult<Vec<ToolUsage>> {
+    let mut stmt = db.prepare_cached(
+        r#"
+        SELECT
+            tool_name,
+            COUNT(*) as call_count,
+            COUNT(DISTINCT conversation_id) as conversation_count,
+            MAX(created_at) as last_used_at
+        FROM messages
+        WHERE tool_name IS NOT NULL AND created_at >= ?
+        GROUP BY tool_name
+        ORDER BY call_count DESC
+        LIMIT 100
+        "#,
+    )?;
+    let items = stmt
+        .query_map([since], |row| {
+            Ok(ToolUsage {
+                tool_name: row.get(0).unwrap_or_default(),
+                call_count: row.get(1).unwrap_or(0),
+                conversation_count: row.get(2).unwrap_or(0),
+                last_used_at: row.get(3).unwrap_or(0),
+                top_conversations: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(items)
+}
+
+/// The 3 conversations calling each tool most since `since`, with the tool's name.
+pub fn top_conversations(db: &Connection, since: i64) -> QueryResult<Vec<(String, ToolUsageConversation)>> {
+    let mut stmt = db.prepare_cached(
+        r#"
+        SELECT tool_name, conversation_id, title, call_count FROM (
+            SELECT
+                m.tool_name,
+                m.conversation_id,
+                c.title,
+                COUNT(*) as call_count,
+                ROW_NUMBER() OVER (PARTITION BY m.tool_name ORDER BY COUNT(*) DESC) as rank
+            FROM messages m
+            LEFT JOIN conversations c ON c.id = m.conversation_id
+            WHERE m.tool_name IS NOT NULL AND m.created_at >= ?
+            GROUP BY m.tool_name, m.conversation_id
+        )
+        WHERE rank <= 3
+        ORDER BY tool_name, rank
+        "#,
+    )?;
+    let rows = stmt
+        .query_map([since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                ToolUsageConversation {
+                    conversation_id: row.get(1).unwrap_or_default(),
+                    title: row.get(2).ok(),
+                    call_count: row.get(3).unwrap_or(0),
+                },
+            ))
+        })?
+        .flatten()
+        .collect();
+    Ok(rows)
+}
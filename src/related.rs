@@ -0,0 +1,115 @@
+//! Related conversations: the threads sharing the most distinctive terms of a given one, weighted
+//! TF-IDF style with document frequencies read from the `messages_fts` index.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::dates::Timezone;
+use crate::db;
+use crate::fts;
+use crate::models::RelatedConversation;
+use crate::text;
+
+/// Most frequent words of the conversation looked up in the index; bounds the work for long threads.
+const MAX_CANDIDATE_TERMS: usize = 300;
+/// Highest weighted terms the conversation is compared on.
+const TOP_TERMS: usize = 30;
+/// Shared terms listed per related conversation.
+const MAX_SHARED_TERMS: usize = 10;
+
+/// Up to `limit` other conversations most similar to `conversation_id`, best first. Each scores
+/// the share of the conversation's term weight (0.0 to 1.0) it shares; a term weighs more the more
+/// often the conversation uses it and the fewer conversations contain it at all.
+pub fn find(conn: &Connection, conversation_id: &str, limit: usize, timezone: Timezone) -> Result<Vec<RelatedConversation>> {
+    anyhow::ensure!(
+        db::has_index(conn, "messages_fts"),
+        "Finding related conversations needs the messages_fts full-text index"
+    );
+    let exists = conn
+        .query_row("SELECT 1 FROM conversations WHERE id = ?", [conversation_id], |_| Ok(()))
+        .optional()
+        .context("Failed to look up conversation")?
+        .is_some();
+    anyhow::ensure!(exists, "No conversation with ID '{}'", conversation_id);
+    let total: i64 = conn
+        .query_row("SELECT COUNT(*) FROM conversations", [], |row| row.get(0))
+        .context("Failed to count conversations")?;
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT content FROM messages WHERE conversation_id = ? AND role IN ('user', 'assistant')")
+        .context("Failed to read conversation")?;
+    for content in stmt.query_map([conversation_id], |row| row.get::<_, String>(0))? {
+        for term in text::terms(&content?) {
+            *counts.entry(term).or_default() += 1;
+        }
+    }
+    let mut candidates: Vec<(String, usize)> = counts.into_iter().collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    candidates.truncate(MAX_CANDIDATE_TERMS);
+
+    let mut stmt = conn
+        .prepare(
+            r#"
+            SELECT DISTINCT m.conversation_id
+            FROM messages_fts
+            JOIN messages m ON m.id = messages_fts.rowid
+            WHERE messages_fts MATCH ? AND m.role IN ('user', 'assistant')
+            "#,
+        )
+        .context("Failed to look up terms")?;
+    let mut terms: Vec<(String, f64, HashSet<String>)> = Vec::new();
+    for (term, frequency) in candidates {
+        let conversations = stmt
+            .query_map([fts::quote(&term)], |row| row.get(0))?
+            .collect::<rusqlite::Result<HashSet<String>>>()
+            .context("Failed to look up terms")?;
+        // A term no other conversation uses can't relate this one to anything
+        if conversations.iter().all(|id| id == conversation_id) {
+            continue;
+        }
+        let idf = (total as f64 / conversations.len() as f64).ln();
+        if idf > 0.0 {
+            terms.push((term, (1.0 + (frequency as f64).ln()) * idf, conversations));
+        }
+    }
+    terms.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    terms.truncate(TOP_TERMS);
+    let total_weight: f64 = terms.iter().map(|(_, weight, _)| weight).sum();
+
+    let mut scores: HashMap<&str, (f64, Vec<String>)> = HashMap::new();
+    for (term, weight, conversations) in &terms {
+        for id in conversations.iter().filter(|id| *id != conversation_id) {
+            let (score, shared) = scores.entry(id).or_default();
+            *score += weight / total_weight;
+            if shared.len() < MAX_SHARED_TERMS {
+                shared.push(term.clone());
+            }
+        }
+    }
+    let mut ranked: Vec<(&str, (f64, Vec<String>))> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| b.1 .0.total_cmp(&a.1 .0).then_with(|| a.0.cmp(b.0)));
+    ranked.truncate(limit);
+
+    let mut stmt = conn
+        .prepare("SELECT title, created_at FROM conversations WHERE id = ?")
+        .context("Failed to read conversations")?;
+    ranked
+        .into_iter()
+        .map(|(id, (score, shared_terms))| {
+            let (title, created_at): (String, i64) = stmt
+                .query_row([id], |row| Ok((row.get(0)?, row.get(1)?)))
+                .context("Failed to read conversations")?;
+            Ok(RelatedConversation {
+                conversation_id: id.to_string(),
+                title,
+                created_at,
+                created_at_iso: timezone.iso(created_at),
+                score,
+                shared_terms,
+            })
+        })
+        .collect()
+}
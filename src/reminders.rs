@@ -0,0 +1,71 @@
+//! Reminders: things to bring up at or after a due time, checked at the start of a session with
+//! `list_due_reminders`. Completed reminders are kept with their completion time.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::dates::{self, Timezone};
+use crate::db::unix_now;
+use crate::models::Reminder;
+
+const COLUMNS: &str = "id, content, due_at, created_at, completed_at";
+
+fn from_row(row: &Row, timezone: Timezone) -> rusqlite::Result<Reminder> {
+    let due_at: i64 = row.get(2)?;
+    Ok(Reminder {
+        id: row.get(0)?,
+        content: row.get(1)?,
+        due_at,
+        due_at_iso: timezone.iso(due_at),
+        due: Some(dates::age(due_at)),
+        created_at: row.get(3)?,
+        completed_at: row.get(4)?,
+        error: None,
+    })
+}
+
+/// Create a reminder of `content` due at `due_at` (unix seconds).
+pub fn create(conn: &Connection, content: &str, due_at: i64, timezone: Timezone) -> Result<Reminder> {
+    let content = content.trim();
+    anyhow::ensure!(!content.is_empty(), "Reminder content must not be empty");
+    conn.execute(
+        "INSERT INTO reminders (content, due_at, created_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![content, due_at, unix_now()],
+    )
+    .context("Failed to create reminder")?;
+    get(conn, conn.last_insert_rowid(), timezone)?.context("Reminder disappeared")
+}
+
+/// The reminder `id`, if any.
+pub fn get(conn: &Connection, id: i64, timezone: Timezone) -> Result<Option<Reminder>> {
+    conn.query_row(
+        &format!("SELECT {} FROM reminders WHERE id = ?", COLUMNS),
+        [id],
+        |row| from_row(row, timezone),
+    )
+    .optional()
+    .context("Failed to read reminder")
+}
+
+/// Open reminders due before `due_by` (unix seconds), earliest first.
+pub fn due(conn: &Connection, due_by: i64, limit: u32, timezone: Timezone) -> Result<Vec<Reminder>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM reminders WHERE completed_at IS NULL AND due_at < ?1 ORDER BY due_at, id LIMIT ?2",
+            COLUMNS
+        ))
+        .context("Failed to list due reminders")?;
+    let rows = stmt.query_map(rusqlite::params![due_by, limit], |row| from_row(row, timezone))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read reminders")
+}
+
+/// Mark the open reminder `id` completed. Returns false if there is no such open reminder.
+pub fn complete(conn: &Connection, id: i64) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE reminders SET completed_at = ?1 WHERE id = ?2 AND completed_at IS NULL",
+            rusqlite::params![unix_now(), id],
+        )
+        .context("Failed to complete reminder")?;
+    Ok(updated > 0)
+}
@@ -0,0 +1,176 @@
+//! Conversations as MCP resources (`conversation://{id}`), with update notifications for
+//! subscribed conversations, and as Markdown documents to attach (`conversation-export://{id}.md`).
+//! Files stored with `store_attachment` are resources too (`attachment://{id}`).
+//!
+//! A single watcher task polls the database while anything is subscribed. It compares
+//! `PRAGMA data_version`, which changes whenever another connection commits, and only then
+//! looks up the newest message id of each subscribed conversation against its watermark.
+
+use anyhow::Result;
+use rmcp::model::ResourceUpdatedNotificationParam;
+use rmcp::{Peer, RoleServer};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::db;
+
+pub const CONVERSATION_URI_PREFIX: &str = "conversation://";
+pub const CONVERSATION_URI_TEMPLATE: &str = "conversation://{id}";
+
+pub fn conversation_uri(conversation_id: &str) -> String {
+    format!("{}{}", CONVERSATION_URI_PREFIX, conversation_id)
+}
+
+pub const EXPORT_URI_PREFIX: &str = "conversation-export://";
+pub const EXPORT_URI_TEMPLATE: &str = "conversation-export://{id}.md";
+
+pub const ATTACHMENT_URI_PREFIX: &str = "attachment://";
+pub const ATTACHMENT_URI_TEMPLATE: &str = "attachment://{id}";
+
+pub fn attachment_uri(attachment_id: i64) -> String {
+    format!("{}{}", ATTACHMENT_URI_PREFIX, attachment_id)
+}
+
+/// Conversation id of a `conversation://{id}` URI.
+pub fn parse_conversation_uri(uri: &str) -> Option<&str> {
+    uri.strip_prefix(CONVERSATION_URI_PREFIX).filter(|id| !id.is_empty())
+}
+
+/// Conversation id of a `conversation-export://{id}.md` URI.
+pub fn parse_export_uri(uri: &str) -> Option<&str> {
+    uri.strip_prefix(EXPORT_URI_PREFIX)
+        .and_then(|rest| rest.strip_suffix(".md"))
+        .filter(|id| !id.is_empty())
+}
+
+/// Attachment id of an `attachment://{id}` URI.
+pub fn parse_attachment_uri(uri: &str) -> Option<i64> {
+    uri.strip_prefix(ATTACHMENT_URI_PREFIX).and_then(|id| id.parse().ok())
+}
+
+/// Newest message id in a conversation, 0 when it has none.
+pub fn latest_message_id(conn: &Connection, conversation_id: &str) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(id), 0) FROM messages WHERE conversation_id = ?",
+        [conversation_id],
+        |row| row.get(0),
+    )
+}
+
+/// Subscribed conversations of one client session and the newest message id already reported.
+#[derive(Default)]
+pub struct Subscriptions {
+    watermarks: Mutex<HashMap<String, i64>>,
+    peer: Mutex<Option<Peer<RoleServer>>>,
+    watching: AtomicBool,
+}
+
+impl Subscriptions {
+    /// Track `conversation_id` from `watermark` on. Returns true when the caller must start
+    /// [`watch`], i.e. on the session's first subscription.
+    pub fn subscribe(&self, conversation_id: &str, watermark: i64, peer: Peer<RoleServer>) -> bool {
+        self.lock_watermarks().insert(conversation_id.to_string(), watermark);
+        *self.peer.lock().unwrap_or_else(|e| e.into_inner()) = Some(peer);
+        !self.watching.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn unsubscribe(&self, conversation_id: &str) {
+        self.lock_watermarks().remove(conversation_id);
+    }
+
+    fn lock_watermarks(&self) -> std::sync::MutexGuard<'_, HashMap<String, i64>> {
+        self.watermarks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record newer watermarks and return the conversations that moved past theirs.
+    fn advance(&self, latest: HashMap<String, i64>) -> Vec<String> {
+        let mut watermarks = self.lock_watermarks();
+        let mut updated = Vec::new();
+        for (conversation_id, newest) in latest {
+            if let Some(watermark) = watermarks.get_mut(&conversation_id) {
+                if newest > *watermark {
+                    *watermark = newest;
+                    updated.push(conversation_id);
+                }
+            }
+        }
+        updated
+    }
+}
+
+/// Outcome of one poll: the connection to reuse, its `data_version`, and the newest message id
+/// per subscribed conversation when the version changed.
+type Poll = (Connection, i64, Option<HashMap<String, i64>>);
+
+/// Poll for new messages in subscribed conversations every `interval` and notify the client.
+/// Runs until the client goes away.
+pub async fn watch(db_path: String, subscriptions: Arc<Subscriptions>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    let mut conn: Option<Connection> = None;
+    let mut last_version: Option<i64> = None;
+    loop {
+        ticker.tick().await;
+        let ids: Vec<String> = subscriptions.lock_watermarks().keys().cloned().collect();
+        if ids.is_empty() {
+            continue;
+        }
+
+        let path = db_path.clone();
+        let taken = conn.take();
+        let polled = tokio::task::spawn_blocking(move || -> Result<Poll> {
+            let conn = match taken {
+                Some(conn) => conn,
+                None => db::open_read_only(&path)?,
+            };
+            let version: i64 = conn.query_row("PRAGMA data_version", [], |row| row.get(0))?;
+            let latest = if last_version == Some(version) {
+                None
+            } else {
+                Some(latest_message_ids(&conn, &ids)?)
+            };
+            Ok((conn, version, latest))
+        })
+        .await;
+
+        let latest = match polled {
+            Ok(Ok((polled_conn, version, latest))) => {
+                conn = Some(polled_conn);
+                last_version = Some(version);
+                latest
+            }
+            // Database briefly unavailable; reopen on the next tick
+            _ => None,
+        };
+        let Some(latest) = latest else {
+            continue;
+        };
+
+        let peer = subscriptions.peer.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let Some(peer) = peer else {
+            continue;
+        };
+        for conversation_id in subscriptions.advance(latest) {
+            let param = ResourceUpdatedNotificationParam {
+                uri: conversation_uri(&conversation_id),
+            };
+            if peer.notify_resource_updated(param).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn latest_message_ids(conn: &Connection, conversation_ids: &[String]) -> rusqlite::Result<HashMap<String, i64>> {
+    let placeholders = vec!["?"; conversation_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT conversation_id, MAX(id) FROM messages WHERE conversation_id IN ({}) GROUP BY conversation_id",
+        placeholders
+    ))?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(conversation_ids), |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    rows.collect()
+}
@@ -0,0 +1,79 @@
+//! Saved searches: `search_conversations` parameters stored under a name, so recurring searches
+//! ("project-luna status" every morning) are one call, with stats of the previous run.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::db::unix_now;
+use crate::models::{SavedSearch, SearchConversationsRequest};
+
+const COLUMNS: &str = "id, name, params_json, created_at, updated_at, run_count, last_run_at, last_result_count";
+
+fn from_row(row: &Row) -> rusqlite::Result<SavedSearch> {
+    let params: String = row.get(2)?;
+    Ok(SavedSearch {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        search: serde_json::from_str(&params).unwrap_or_default(),
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+        run_count: row.get(5)?,
+        last_run_at: row.get(6)?,
+        last_result_count: row.get(7)?,
+        error: None,
+    })
+}
+
+/// Save `search` under `name`, replacing the parameters of an existing search with that name but
+/// keeping its run stats. Field selection belongs to each run and is not saved.
+pub fn save(conn: &Connection, name: &str, mut search: SearchConversationsRequest) -> Result<SavedSearch> {
+    let name = name.trim();
+    anyhow::ensure!(!name.is_empty(), "Saved search name must not be empty");
+    search.fields = None;
+    let params = serde_json::to_string(&search).context("Failed to serialize search parameters")?;
+    let now = unix_now();
+    conn.execute(
+        r#"
+        INSERT INTO saved_searches (name, params_json, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)
+        ON CONFLICT(name) DO UPDATE SET params_json = excluded.params_json, updated_at = excluded.updated_at
+        "#,
+        rusqlite::params![name, params, now],
+    )
+    .context("Failed to save search")?;
+    get(conn, name)?.context("Saved search disappeared")
+}
+
+/// Every saved search, by name.
+pub fn list(conn: &Connection) -> Result<Vec<SavedSearch>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM saved_searches ORDER BY name", COLUMNS))
+        .context("Failed to list saved searches")?;
+    let rows = stmt.query_map([], from_row)?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read saved searches")
+}
+
+/// The search saved under `name`, if any.
+pub fn get(conn: &Connection, name: &str) -> Result<Option<SavedSearch>> {
+    conn.query_row(
+        &format!("SELECT {} FROM saved_searches WHERE name = ?", COLUMNS),
+        [name.trim()],
+        from_row,
+    )
+    .optional()
+    .context("Failed to read saved search")
+}
+
+/// Parameters of `saved` as a `search_conversations` request.
+pub fn request(saved: &SavedSearch) -> Result<SearchConversationsRequest> {
+    serde_json::from_value(saved.search.clone()).context("Saved search parameters are no longer valid")
+}
+
+/// Record a run of the search `id` that returned `result_count` results.
+pub fn record_run(conn: &Connection, id: i64, result_count: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE saved_searches SET run_count = run_count + 1, last_run_at = ?1, last_result_count = ?2 WHERE id = ?3",
+        rusqlite::params![unix_now(), result_count, id],
+    )
+    .context("Failed to record saved search run")?;
+    Ok(())
+}
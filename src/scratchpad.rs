@@ -0,0 +1,80 @@
+//! Scratchpads: working notes an agent keeps under a session or topic name, such as an unfinished
+//! plan, so they survive a restart without going into long-term memory. They expire on their own.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::db::unix_now;
+use crate::models::ScratchpadEntry;
+
+/// How long a scratchpad lives after its last write unless the write says otherwise.
+pub const DEFAULT_TTL_HOURS: f64 = 7.0 * 24.0;
+
+const COLUMNS: &str = "name, content, updated_at, expires_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<ScratchpadEntry> {
+    Ok(ScratchpadEntry {
+        name: row.get(0)?,
+        content: row.get(1)?,
+        updated_at: row.get(2)?,
+        expires_at: row.get(3)?,
+    })
+}
+
+/// Write `content` to the scratchpad `name`, replacing it or, with `append`, adding a line to it.
+/// Every write restarts the scratchpad's expiry.
+pub fn write(conn: &Connection, name: &str, content: &str, append: bool, ttl_hours: Option<f64>) -> Result<ScratchpadEntry> {
+    let name = name.trim();
+    anyhow::ensure!(!name.is_empty(), "Scratchpad name must not be empty");
+    let ttl_hours = ttl_hours.unwrap_or(DEFAULT_TTL_HOURS);
+    anyhow::ensure!(ttl_hours > 0.0, "ttl_hours must be positive");
+    purge_expired(conn)?;
+    let now = unix_now();
+    let expires_at = now + (ttl_hours * 60.0 * 60.0).ceil() as i64;
+    conn.execute(
+        r#"
+        INSERT INTO scratchpad (name, content, updated_at, expires_at) VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(name) DO UPDATE SET
+            content = CASE WHEN ?5 THEN scratchpad.content || char(10) || excluded.content ELSE excluded.content END,
+            updated_at = excluded.updated_at,
+            expires_at = excluded.expires_at
+        "#,
+        rusqlite::params![name, content, now, expires_at, append],
+    )
+    .context("Failed to write scratchpad")?;
+    read(conn, name)?.context("Scratchpad disappeared")
+}
+
+/// The unexpired scratchpad `name`, if any.
+pub fn read(conn: &Connection, name: &str) -> Result<Option<ScratchpadEntry>> {
+    conn.query_row(
+        &format!("SELECT {} FROM scratchpad WHERE name = ?1 AND expires_at > ?2", COLUMNS),
+        rusqlite::params![name.trim(), unix_now()],
+        from_row,
+    )
+    .optional()
+    .context("Failed to read scratchpad")
+}
+
+/// Every unexpired scratchpad, most recently written first.
+pub fn list(conn: &Connection) -> Result<Vec<ScratchpadEntry>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM scratchpad WHERE expires_at > ? ORDER BY updated_at DESC, name", COLUMNS))
+        .context("Failed to list scratchpads")?;
+    let rows = stmt.query_map([unix_now()], from_row)?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read scratchpads")
+}
+
+/// Delete the scratchpad `name`. Returns whether it existed.
+pub fn clear(conn: &Connection, name: &str) -> Result<bool> {
+    let deleted = conn
+        .execute("DELETE FROM scratchpad WHERE name = ?", [name.trim()])
+        .context("Failed to clear scratchpad")?;
+    Ok(deleted > 0)
+}
+
+/// Delete scratchpads whose expiry has passed. Reads skip them already; this drops the rows.
+pub fn purge_expired(conn: &Connection) -> Result<usize> {
+    conn.execute("DELETE FROM scratchpad WHERE expires_at <= ?", [unix_now()])
+        .context("Failed to purge expired scratchpads")
+}
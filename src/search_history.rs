@@ -0,0 +1,117 @@
+//! Search history: every query the search tools run, with how many results it found. Past
+//! queries that found something are offered as suggestions when a similar search comes up empty,
+//! so the model learns which phrasings actually match this history.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+use crate::db::unix_now;
+use crate::models::{SearchHistoryEntry, SearchSuggestion};
+use crate::text;
+
+/// Minimum trigram similarity for an earlier query to be suggested; pg_trgm's default threshold.
+pub const SUGGESTION_THRESHOLD: f64 = 0.3;
+
+/// How many of the latest successful searches are compared against a query for suggestions.
+const SUGGESTION_SCAN: i64 = 2000;
+
+/// Record that `tool` ran `query` and found `hit_count` results. Blank queries aren't recorded.
+pub fn record(conn: &Connection, tool: &str, query: &str, hit_count: usize) -> Result<()> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO search_history (tool, query, hit_count, searched_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![tool, query, hit_count as i64, unix_now()],
+    )
+    .context("Failed to record search")?;
+    Ok(())
+}
+
+/// Latest searches, newest first, optionally only those of `tool` or those that found something.
+pub fn recent(conn: &Connection, tool: Option<&str>, successful_only: bool, limit: usize) -> Result<Vec<SearchHistoryEntry>> {
+    let mut stmt = conn
+        .prepare(
+            r#"
+            SELECT id, tool, query, hit_count, searched_at
+            FROM search_history
+            WHERE (?1 IS NULL OR tool = ?1) AND (?2 = 0 OR hit_count > 0)
+            ORDER BY searched_at DESC, id DESC
+            LIMIT ?3
+            "#,
+        )
+        .context("Failed to read search history")?;
+    let rows = stmt.query_map(rusqlite::params![tool, successful_only, limit as i64], |row| {
+        Ok(SearchHistoryEntry {
+            id: row.get(0)?,
+            tool: row.get(1)?,
+            query: row.get(2)?,
+            hit_count: row.get(3)?,
+            searched_at: row.get(4)?,
+        })
+    })?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read search history")
+}
+
+/// Earlier queries that found results and resemble `query` by character trigrams, most similar
+/// first. `query` itself is never suggested.
+pub fn suggestions(conn: &Connection, query: &str, tool: Option<&str>, limit: usize) -> Result<Vec<SearchSuggestion>> {
+    let folded = query.trim().to_lowercase();
+    let grams = text::trigrams(&folded);
+    if grams.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn
+        .prepare(
+            r#"
+            SELECT tool, query, MAX(hit_count), MAX(searched_at)
+            FROM (
+                SELECT tool, query, hit_count, searched_at FROM search_history
+                WHERE hit_count > 0 AND (?1 IS NULL OR tool = ?1)
+                ORDER BY id DESC
+                LIMIT ?2
+            )
+            GROUP BY tool, lower(query)
+            "#,
+        )
+        .context("Failed to read search history")?;
+    let rows = stmt.query_map(rusqlite::params![tool, SUGGESTION_SCAN], |row| {
+        Ok(SearchSuggestion {
+            tool: row.get(0)?,
+            query: row.get(1)?,
+            hit_count: row.get(2)?,
+            last_searched_at: row.get(3)?,
+            similarity: 0.0,
+        })
+    })?;
+
+    // The same query may have found results in several tools; suggest its most successful run once
+    let mut best: HashMap<String, SearchSuggestion> = HashMap::new();
+    for mut suggestion in rows.collect::<rusqlite::Result<Vec<_>>>()? {
+        let candidate = suggestion.query.to_lowercase();
+        if candidate == folded {
+            continue;
+        }
+        suggestion.similarity = text::jaccard(&grams, &text::trigrams(&candidate));
+        if suggestion.similarity < SUGGESTION_THRESHOLD {
+            continue;
+        }
+        match best.get(&candidate) {
+            Some(seen) if seen.hit_count >= suggestion.hit_count => {}
+            _ => {
+                best.insert(candidate, suggestion);
+            }
+        }
+    }
+    let mut suggestions: Vec<SearchSuggestion> = best.into_values().collect();
+    suggestions.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then(b.hit_count.cmp(&a.hit_count))
+            .then(b.last_searched_at.cmp(&a.last_searched_at))
+    });
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
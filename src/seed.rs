@@ -0,0 +1,218 @@
+//! Synthetic conversation history for benchmarks: a scratch database filled with conversations
+//! on a handful of topics, mixing questions, longer answers, tool calls (some failed) and
+//! reasoning traces, so search and pagination changes can be timed on realistic volumes.
+//!
+//! The data only depends on the counts and the seed, timestamps included, so two databases seeded
+//! alike are identical and timings taken on them comparable.
+
+use anyhow::{ensure, Context, Result};
+use rusqlite::{params, Connection};
+
+use crate::db;
+
+/// Newest timestamp generated (2025-01-01T00:00:00Z); conversations spread over the two years before.
+const LATEST: i64 = 1_735_689_600;
+const SPAN_SECS: i64 = 2 * 365 * 86400;
+
+/// Topics with the words their conversations are made of.
+const TOPICS: &[(&str, &[&str])] = &[
+    ("Kubernetes", &["kubernetes", "kubectl", "pod", "deployment", "helm", "ingress", "namespace", "cluster", "node", "service", "container", "image", "rollout", "configmap", "secret"]),
+    ("Rust", &["rust", "cargo", "borrow", "lifetime", "trait", "async", "tokio", "crate", "clippy", "macro", "iterator", "closure", "enum", "compiler", "ownership"]),
+    ("Cooking", &["recipe", "pasta", "garlic", "oven", "sauce", "onion", "butter", "dough", "bake", "simmer", "pan", "spice", "tomato", "cheese", "dinner"]),
+    ("Travel", &["flight", "hotel", "train", "itinerary", "museum", "beach", "passport", "luggage", "booking", "airport", "city", "tour", "visa", "map", "weekend"]),
+    ("Finance", &["budget", "savings", "invoice", "tax", "mortgage", "interest", "portfolio", "expense", "salary", "pension", "loan", "account", "receipt", "stocks", "rent"]),
+    ("Linux", &["linux", "systemd", "kernel", "bash", "package", "driver", "wayland", "journal", "permission", "mount", "partition", "ssh", "firewall", "cron", "grub"]),
+    ("Fitness", &["workout", "running", "squat", "protein", "stretching", "marathon", "cardio", "weights", "recovery", "sleep", "heart", "pace", "injury", "gym", "yoga"]),
+    ("Databases", &["sqlite", "postgres", "index", "query", "migration", "schema", "transaction", "vacuum", "join", "table", "backup", "replica", "latency", "cache", "fts"]),
+];
+
+const FILLER: &[&str] = &[
+    "the", "a", "with", "for", "and", "when", "after", "before", "about", "because", "then", "still", "again", "maybe", "really",
+    "should", "could", "works", "fails", "looks", "seems", "better", "first", "next", "usually",
+];
+
+const QUESTIONS: &[&str] = &["How do I", "Why does", "What is the best way to handle", "Can you explain", "Is it normal that", "Help me with"];
+
+const TOOLS: &[&str] = &["shell", "read_file", "web_search", "write_file", "run_tests"];
+
+/// SplitMix64: small, fast and the same on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SeedReport {
+    pub conversations: i64,
+    pub messages: i64,
+    pub tool_calls: i64,
+}
+
+/// Fill `conn`, which must not hold any conversations yet, with `conversations` conversations
+/// sharing `messages` messages, in a single transaction. Thread lengths vary widely, as in real
+/// use: most are short and a few are very long.
+pub fn seed(conn: &mut Connection, conversations: usize, messages: usize, seed: u64) -> Result<SeedReport> {
+    ensure!(conversations > 0, "Seed at least one conversation");
+    ensure!(messages >= conversations, "Seed at least one message per conversation");
+    db::init_conversation_schema(conn)?;
+    let existing: i64 = conn.query_row("SELECT COUNT(*) FROM conversations", [], |row| row.get(0))?;
+    ensure!(existing == 0, "The database already holds {} conversations; seed a new scratch database instead", existing);
+    let sync_fts = !db::messages_fts_has_triggers(conn)?;
+
+    let mut rng = Rng(seed);
+    let lengths = thread_lengths(&mut rng, conversations, messages);
+    let tx = conn.transaction().context("Failed to start seed transaction")?;
+    let mut report = SeedReport::default();
+    {
+        let mut insert_conversation = tx.prepare(
+            "INSERT INTO conversations (id, title, created_at, title_generated, profile_name) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        let mut insert_message = tx.prepare(
+            "INSERT INTO messages (conversation_id, role, content, created_at, tool_name, tool_status, tool_params_json, tool_result_json, reasoning_content)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )?;
+        let mut index_message = tx.prepare("INSERT INTO messages_fts (rowid, content) VALUES (?1, ?2)")?;
+        for (n, length) in lengths.into_iter().enumerate() {
+            let (topic, words) = TOPICS[rng.below(TOPICS.len())];
+            let id = format!("seed-{:08}", n + 1);
+            let created_at = LATEST - SPAN_SECS + (rng.unit() * SPAN_SECS as f64) as i64;
+            let title = format!("{} {} {}", topic, rng.pick(words), rng.pick(words));
+            let profile = ["default", "work", "coding"][rng.below(3)];
+            insert_conversation.execute(params![id, title, created_at, rng.below(2) as i64, profile])?;
+            report.conversations += 1;
+
+            let mut at = created_at;
+            for position in 0..length {
+                at += 5 + rng.below(600) as i64;
+                let message = match position % 2 {
+                    0 => Message::question(&mut rng, words),
+                    _ if rng.below(12) == 0 => Message::tool_call(&mut rng, words),
+                    _ => Message::answer(&mut rng, words),
+                };
+                insert_message.execute(params![
+                    id,
+                    message.role,
+                    message.content,
+                    at,
+                    message.tool_name,
+                    message.tool_status,
+                    message.tool_params_json,
+                    message.tool_result_json,
+                    message.reasoning_content
+                ])?;
+                if sync_fts {
+                    index_message.execute(params![tx.last_insert_rowid(), message.content])?;
+                }
+                report.messages += 1;
+                report.tool_calls += message.tool_name.is_some() as i64;
+            }
+        }
+    }
+    tx.commit().context("Failed to commit seed data")?;
+    Ok(report)
+}
+
+/// Lengths of `conversations` threads adding up to `messages`, each at least 1, drawn from an
+/// exponential distribution.
+fn thread_lengths(rng: &mut Rng, conversations: usize, messages: usize) -> Vec<usize> {
+    let weights: Vec<f64> = (0..conversations).map(|_| -(1.0 - rng.unit()).ln()).collect();
+    let total: f64 = weights.iter().sum();
+    let spare = messages - conversations;
+    let mut lengths: Vec<usize> = weights.iter().map(|w| 1 + (w / total * spare as f64) as usize).collect();
+    let mut left = messages - lengths.iter().sum::<usize>();
+    while left > 0 {
+        lengths[rng.below(conversations)] += 1;
+        left -= 1;
+    }
+    lengths
+}
+
+#[derive(Default)]
+struct Message {
+    role: &'static str,
+    content: String,
+    tool_name: Option<&'static str>,
+    tool_status: Option<&'static str>,
+    tool_params_json: Option<String>,
+    tool_result_json: Option<String>,
+    reasoning_content: Option<String>,
+}
+
+impl Message {
+    fn question(rng: &mut Rng, words: &[&str]) -> Self {
+        let opening = rng.pick(QUESTIONS);
+        let length = 4 + rng.below(10);
+        let content = format!("{} {}?", opening, sentence(rng, words, length));
+        Self { role: "user", content, ..Default::default() }
+    }
+
+    fn answer(rng: &mut Rng, words: &[&str]) -> Self {
+        let count = 2 + rng.below(8);
+        let sentences: Vec<String> = (0..count)
+            .map(|_| {
+                let length = 6 + rng.below(14);
+                capitalized(sentence(rng, words, length))
+            })
+            .collect();
+        let reasoning = (rng.below(5) == 0).then(|| {
+            let length = 20 + rng.below(40);
+            sentence(rng, words, length)
+        });
+        Self { role: "assistant", content: sentences.join(". ") + ".", reasoning_content: reasoning, ..Default::default() }
+    }
+
+    fn tool_call(rng: &mut Rng, words: &[&str]) -> Self {
+        let tool = rng.pick(TOOLS);
+        let failed = rng.below(6) == 0;
+        let argument = rng.pick(words);
+        let result = if failed {
+            serde_json::json!({ "error": format!("{} not found", argument) })
+        } else {
+            let length = 8 + rng.below(20);
+            serde_json::json!({ "output": sentence(rng, words, length) })
+        };
+        Self {
+            role: "tool",
+            content: format!("{} {}", tool, argument),
+            tool_name: Some(tool),
+            tool_status: Some(if failed { "error" } else { "success" }),
+            tool_params_json: Some(serde_json::json!({ "query": argument }).to_string()),
+            tool_result_json: Some(result.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// `length` words, about one in three from the topic's `words` and the rest filler.
+fn sentence(rng: &mut Rng, words: &[&str], length: usize) -> String {
+    (0..length).map(|_| if rng.below(3) == 0 { rng.pick(words) } else { rng.pick(FILLER) }).collect::<Vec<_>>().join(" ")
+}
+
+fn capitalized(text: String) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    }
+}
@@ -1,666 +1,577 @@
 use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use rmcp::{
-    handler::server::{router::tool::ToolRouter, wrapper::{Json, Parameters}},
-    model::{ServerCapabilities, ServerInfo},
-    tool, tool_handler, tool_router, ServerHandler,
+    handler::server::{router::{prompt::PromptRouter, tool::ToolRouter}, tool::ToolCallContext, wrapper::{Json, Parameters}},
+    model::{
+        AnnotateAble, CallToolRequestParam, CallToolResult, Content, GetPromptRequestParam, GetPromptResult, ListPromptsResult,
+        ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, LoggingLevel, PaginatedRequestParam, RawResource,
+        RawResourceTemplate, ReadResourceRequestParam, ReadResourceResult, ResourceContents, ServerCapabilities, ServerInfo,
+        SetLevelRequestParam, SubscribeRequestParam, UnsubscribeRequestParam,
+    },
+    prompt_handler,
+    service::RequestContext,
+    ErrorData as McpError, RoleServer, ServerHandler,
 };
 use rusqlite::Connection;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-
+use std::time::Instant;
+
+use crate::archive;
+use crate::attachments;
+use crate::budget::Budget;
+use crate::cancel::Interrupter;
+use crate::compact;
+use crate::config::Config;
+use crate::confirm::ConfirmationTokens;
+use crate::conversation_cache::{self, ConversationCache};
 use crate::db;
+use crate::db::pool::ReadPool;
+use crate::erase;
+use crate::export;
+use crate::key_messages;
+use crate::logging::{self, ClientLog};
+use crate::maintenance;
+use crate::markdown;
 use crate::models::*;
+use crate::queries::{conversations, QueryError, QueryResult};
+use crate::redact::Masker;
+use crate::resources::{self, Subscriptions};
+use crate::search_history;
+use crate::tokens;
+use crate::tools;
+
+/// Most recent conversations listed by `resources/list`; older ones are reachable by URI.
+const RESOURCE_LIST_LIMIT: u32 = 50;
 
 pub struct ConversationService {
     /// DB path; connection is opened lazily after MCP handshake so Inspector gets a fast initialize response.
     db_path: String,
     db: Arc<Mutex<Option<Connection>>>,
+    read_pool: ReadPool,
+    pub(crate) config: Config,
+    /// Tokens issued by `preview_*` tools for this session
+    pub(crate) confirmations: ConfirmationTokens,
+    /// Calls and bytes this session has used against its limits
+    budget: Budget,
+    /// Conversations this session loaded recently
+    conversation_cache: ConversationCache,
     tool_router: ToolRouter<Self>,
+    prompt_router: PromptRouter<Self>,
+    /// Conversations this session subscribed to as resources
+    subscriptions: Arc<Subscriptions>,
+    /// Profile and working scratchpad this session set with `set_session_scope`
+    pub(crate) scope: Mutex<SessionScope>,
+    /// Level filter for log notifications sent to the client
+    client_log: ClientLog,
+    /// Interrupts the shared connection's query when its tool call is cancelled or runs out of time
+    interrupter: Arc<Interrupter>,
 }
 
-#[tool_router(router = tool_router)]
 impl ConversationService {
     /// Create service without opening the DB. DB is opened on first tool use so the MCP handshake
     /// (initialize → response → notifications/initialized) completes immediately for Inspector/stdio clients.
-    pub fn new(db_path: &str) -> Result<Self> {
+    pub fn new(db_path: &str, config: Config) -> Result<Self> {
+        let mut tool_router = Self::tool_router();
+        tools::select_tools(&mut tool_router, &config)?;
         Ok(Self {
             db_path: db_path.to_string(),
             db: Arc::new(Mutex::new(None)),
-            tool_router: Self::tool_router(),
+            read_pool: ReadPool::new(db_path, config.read_pool_size),
+            confirmations: ConfirmationTokens::default(),
+            budget: Budget::new(config.budget.clone()),
+            conversation_cache: ConversationCache::new(config.conversation_cache_size),
+            tool_router,
+            prompt_router: Self::prompt_router(),
+            subscriptions: Arc::new(Subscriptions::default()),
+            scope: Mutex::new(SessionScope::default()),
+            client_log: ClientLog::new(config.log_level.unwrap_or(logging::DEFAULT_LEVEL)),
+            interrupter: Arc::new(Interrupter::new(config.query_timeout)),
+            config,
         })
     }
 
+    /// Service over a private in-memory database holding the full schema: the Cosmic LLM
+    /// conversation tables and their full-text index, plus the memory module's tables. Meant for
+    /// tests and embedding; the database lives as long as the service.
+    pub fn new_in_memory() -> Result<Self> {
+        Self::new_in_memory_with(Config::default())
+    }
+
+    /// [`new_in_memory`](Self::new_in_memory) with `config`. The database is always writable, so
+    /// `read_only` only selects which tools are listed.
+    pub fn new_in_memory_with(config: Config) -> Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        // A named database in the memdb VFS, so the read pool and fixtures can open it too
+        let db_path = format!(
+            "file:/luna-memory-{}-{}?vfs=memdb",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let service = Self::new(&db_path, config)?;
+        // Conversation tables first, so the indexes `db::open` adds over messages get created
+        let schema = Connection::open(&db_path).context("Failed to create in-memory database")?;
+        db::init_conversation_schema(&schema)?;
+        let conn = db::open(&db_path, &service.config.fts_tokenizer)?;
+        service.interrupter.attach(&conn)?;
+        *service.db.lock().map_err(|_| anyhow::anyhow!("db lock poisoned"))? = Some(conn);
+        Ok(service)
+    }
+
+    /// Path or URI of the database the service opens; for an in-memory service, the URI other
+    /// connections open to reach the same data.
+    pub fn db_path(&self) -> &str {
+        &self.db_path
+    }
+
+    /// Database file old conversations are archived to.
+    pub(crate) fn archive_path(&self) -> PathBuf {
+        self.config.archive_path.clone().unwrap_or_else(|| archive::default_path(&self.db_path))
+    }
+
+    /// The archive with the live database attached behind it; `None` when nothing was archived
+    /// or it can't be opened.
+    pub(crate) fn open_archive(&self) -> Option<Connection> {
+        archive::open(&self.archive_path(), &self.db_path).ok().flatten()
+    }
+
+    /// Run two independent read queries, concurrently on pooled read-only connections unless the
+    /// pool is disabled.
+    pub(crate) fn with_db_parallel<A, B, RA, RB>(&self, a: A, b: B) -> Result<(RA, RB)>
+    where
+        A: FnOnce(&Connection) -> RA + Send,
+        B: FnOnce(&Connection) -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        if self.config.read_pool_size == 0 {
+            return self.with_db(|db| (a(db), b(db)));
+        }
+        // The main connection creates the memory schema; read-only connections can't
+        self.with_db(|_| ())?;
+        self.read_pool.join(&self.interrupter, a, b)
+    }
+
+    /// Check a destructive call's confirmation token. Tokens are required when
+    /// `MCP_LUNA_REQUIRE_CONFIRMATION` is set, and checked whenever one is passed.
+    pub(crate) fn confirm(&self, token: Option<&str>, action: &str, preview_tool: &str) -> std::result::Result<(), String> {
+        match token {
+            Some(token) if self.confirmations.redeem(token, action) => Ok(()),
+            Some(_) => Err(format!("Confirmation token is invalid, expired or for a different action; call {} again", preview_tool)),
+            None if self.config.require_confirmation => Err(format!("Confirmation required: call {} first and pass its confirmation_token", preview_tool)),
+            None => Ok(()),
+        }
+    }
+
+    /// The session's current scope.
+    pub(crate) fn scope(&self) -> SessionScope {
+        self.scope.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
     /// Get or open DB; opens and inits schema on first use. Ensures nothing blocks before serve() reads stdin.
-    fn with_db<F, R>(&self, f: F) -> Result<R>
+    pub(crate) fn with_db<F, R>(&self, f: F) -> Result<R>
     where
         F: FnOnce(&Connection) -> R,
     {
         let mut guard = self.db.lock().map_err(|_| anyhow::anyhow!("db lock poisoned"))?;
         if guard.is_none() {
-            let conn = Connection::open(&self.db_path)
-                .context("Failed to open database connection")?;
-            db::init_memory_schema(&conn)?;
+            let conn = if self.config.read_only {
+                db::open_read_only(&self.db_path)?
+            } else {
+                let conn = db::open(&self.db_path, &self.config.fts_tokenizer)?;
+                // Searches skip expired memories anyway; this only reclaims the rows
+                let _ = maintenance::purge_expired_memories(&conn);
+                // Conversations past their time in the trash are due to be gone for good
+                let _ = erase::purge_trash(&conn);
+                conn
+            };
+            self.interrupter.attach(&conn)?;
             *guard = Some(conn);
         }
+        let _running = self.interrupter.enter();
         Ok(f(guard.as_ref().unwrap()))
     }
 
-    #[tool(description = "Search across all past conversations with the user using full-text search. This tool searches through message content in all conversation history, allowing you to find relevant past discussions based on keywords or phrases.")]
-    pub fn search_conversations(
-        &self,
-        Parameters(SearchConversationsRequest { keywords }): Parameters<SearchConversationsRequest>,
-    ) -> Json<SearchResultsResponse> {
-        let fts_query = keywords.iter().filter(|s| !s.is_empty()).cloned().collect::<Vec<_>>().join(" OR ");
-        if fts_query.is_empty() {
-            return Json(SearchResultsResponse { items: Vec::new() });
-        }
+    /// Run store-layer queries on the shared connection; a database that can't be opened is
+    /// [`QueryError::Unavailable`].
+    pub(crate) fn query<F, T>(&self, f: F) -> QueryResult<T>
+    where
+        F: FnOnce(&Connection) -> QueryResult<T>,
+    {
+        self.with_db(f).unwrap_or_else(|e| Err(QueryError::Unavailable(format!("{:#}", e))))
+    }
 
-        match self.with_db(|db| {
-            let mut stmt = match db.prepare(
-            r#"
-            SELECT DISTINCT
-                m.id,
-                m.conversation_id,
-                m.role,
-                substr(m.content, 1, 200) as content_preview,
-                m.created_at
-            FROM messages m
-            JOIN messages_fts ON m.id = messages_fts.rowid
-            WHERE messages_fts MATCH ?
-            ORDER BY m.created_at DESC
-            LIMIT 50
-            "#
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => {
-                return Json(SearchResultsResponse { items: Vec::new() });
-            }
-        };
+    /// Record a search in the history; skipped in read-only mode, like other bookkeeping.
+    pub(crate) fn record_search(&self, db: &Connection, tool: &str, query: &str, hit_count: usize) {
+        if !self.config.read_only {
+            let _ = search_history::record(db, tool, query, hit_count);
+        }
+    }
 
-        let results: Vec<SearchResult> = match stmt.query_map([&fts_query], |row| {
-            Ok(SearchResult {
-                message_id: row.get(0).unwrap_or(0),
-                conversation_id: row.get(1).unwrap_or_default(),
-                role: row.get(2).unwrap_or_default(),
-                content_preview: row.get(3).unwrap_or_default(),
-                created_at: row.get(4).unwrap_or(0),
-            })
-        }) {
-            Ok(iter) => {
-                match iter.collect::<Result<Vec<_>, _>>() {
-                    Ok(results) => results,
-                    Err(_) => {
-                        Vec::new()
-                    }
-                }
+    /// The conversation with all its messages, or one titled `NOT_FOUND` or `ERROR`. Served from
+    /// the session's cache while the database is unchanged.
+    pub(crate) fn load_conversation(&self, conversation_id: &str) -> Conversation {
+        let loaded = self.query(|db| {
+            let version = conversation_cache::version(db)?;
+            if let Some(conversation) = self.conversation_cache.get(conversation_id, version) {
+                return Ok(conversation);
             }
-            Err(_) => {
-                Vec::new()
+            // Archived conversations read the same; the archive is only opened for them
+            let mut conversation = match conversations::get(db, conversation_id, self.config.timezone) {
+                Err(QueryError::NotFound(what)) => match self.open_archive() {
+                    Some(archive) => conversations::get(&archive, conversation_id, self.config.timezone)?,
+                    None => return Err(QueryError::NotFound(what)),
+                },
+                loaded => loaded?,
+            };
+            let key_reasons = key_messages::detect(&conversation.messages);
+            for (message, reasons) in conversation.messages.iter_mut().zip(key_reasons) {
+                message.key = !reasons.is_empty();
+                message.estimated_tokens = tokens::message(message);
             }
-        };
-
-            Json(SearchResultsResponse { items: results })
-        }) {
-            Ok(json) => json,
-            Err(_) => Json(SearchResultsResponse { items: Vec::new() }),
+            conversation.estimated_tokens = tokens::conversation(&conversation);
+            let conversation = Arc::new(conversation);
+            self.conversation_cache.insert(Arc::clone(&conversation), version);
+            Ok(conversation)
+        });
+        match loaded {
+            Ok(conversation) => Arc::unwrap_or_clone(conversation),
+            Err(e) => Conversation {
+                id: conversation_id.to_string(),
+                title: if matches!(e, QueryError::NotFound(_)) { "NOT_FOUND" } else { "ERROR" }.to_string(),
+                ..Default::default()
+            },
         }
     }
 
-    #[tool(description = "Retrieve a complete conversation thread from past conversations with the user. Returns the full conversation including all messages, tool calls, and responses in chronological order. Returns empty object if not found.")]
-    pub fn get_conversation(
-        &self,
-        Parameters(GetConversationRequest { conversation_id }): Parameters<GetConversationRequest>,
-    ) -> Json<Conversation> {
-        let empty_err = Json(Conversation {
-            id: conversation_id.clone(),
-            title: "ERROR".to_string(),
-            created_at: 0,
-            title_generated: 0,
-            profile_name: None,
-            messages: Vec::new(),
+    /// Resource contents of attachment `attachment_id`: text for text attachments, with secrets and
+    /// personal data masked as in `get_attachment` results, a blob otherwise.
+    fn read_attachment(&self, uri: String, attachment_id: i64) -> Result<ReadResourceResult, McpError> {
+        let read = self.with_db(|db| -> Result<_> {
+            let Some(attachment) = attachments::get(db, attachment_id, self.config.timezone)? else {
+                return Ok(None);
+            };
+            let (bytes, _) = attachments::content(db, &attachment, self.config.attachments_dir.as_deref())?;
+            Ok(Some((attachment, bytes)))
         });
-        match self.with_db(|db| {
-        // Get conversation metadata
-        let mut conv_stmt = match db.prepare(
-            "SELECT id, title, created_at, title_generated, profile_name FROM conversations WHERE id = ?"
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => {
-                return Json(Conversation {
-                    id: conversation_id.clone(),
-                    title: "ERROR".to_string(),
-                    created_at: 0,
-                    title_generated: 0,
-                    profile_name: None,
-                    messages: Vec::new(),
-                });
-            }
-        };
-
-        let mut conversation: Conversation = match conv_stmt
-            .query_row([conversation_id.as_str()], |row| {
-                Ok(Conversation {
-                    id: row.get(0).unwrap_or_default(),
-                    title: row.get(1).unwrap_or_default(),
-                    created_at: row.get(2).unwrap_or(0),
-                    title_generated: row.get(3).unwrap_or(0),
-                    profile_name: row.get(4).ok(),
-                    messages: Vec::new(),
-                })
-            }) {
-            Ok(conv) => conv,
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                // Return empty conversation with error indicator
-                return Json(Conversation {
-                    id: conversation_id.clone(),
-                    title: "NOT_FOUND".to_string(),
-                    created_at: 0,
-                    title_generated: 0,
-                    profile_name: None,
-                    messages: Vec::new(),
-                });
-            }
-            Err(_) => {
-                return Json(Conversation {
-                    id: conversation_id.clone(),
-                    title: "ERROR".to_string(),
-                    created_at: 0,
-                    title_generated: 0,
-                    profile_name: None,
-                    messages: Vec::new(),
-                });
-            }
+        let (attachment, bytes) = match read {
+            Ok(Ok(Some(read))) => read,
+            Ok(Ok(None)) => return Err(McpError::resource_not_found(format!("No attachment {}", attachment_id), None)),
+            Ok(Err(e)) => return Err(McpError::internal_error(format!("Failed to read attachment: {:#}", e), None)),
+            Err(e) => return Err(McpError::internal_error(format!("Database open/lock error: {:#}", e), None)),
         };
-
-        // Get all messages for this conversation
-        let mut msg_stmt = match db.prepare(
-            r#"
-            SELECT 
-                id, conversation_id, role, content, created_at,
-                tool_calls, tool_call_id, tool_name, tool_status,
-                tool_params_json, tool_result_json, reasoning_content
-            FROM messages
-            WHERE conversation_id = ?
-            ORDER BY created_at ASC
-            "#
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => {
-                return Json(conversation);
-            }
-        };
-
-        let messages: Vec<Message> = match msg_stmt.query_map([conversation_id.as_str()], |row| {
-            Ok(Message {
-                id: row.get(0).unwrap_or(0),
-                conversation_id: row.get(1).unwrap_or_default(),
-                role: row.get(2).unwrap_or_default(),
-                content: row.get(3).unwrap_or_default(),
-                created_at: row.get(4).unwrap_or(0),
-                tool_calls: row.get(5).ok(),
-                tool_call_id: row.get(6).ok(),
-                tool_name: row.get(7).ok(),
-                tool_status: row.get(8).ok(),
-                tool_params_json: row.get(9).ok(),
-                tool_result_json: row.get(10).ok(),
-                reasoning_content: row.get(11).ok(),
-            })
-        }) {
-            Ok(iter) => {
-                match iter.collect::<Result<Vec<_>, _>>() {
-                    Ok(messages) => messages,
-                    Err(_) => {
-                        Vec::new()
-                    }
+        let mime_type = Some(attachment.mime_type.clone());
+        let contents = match String::from_utf8(bytes) {
+            Ok(mut text) if attachments::is_text(&attachment.mime_type) => {
+                if self.config.redaction.enabled {
+                    text = self.config.redaction.redact_document(&text).0;
                 }
+                if self.config.pii.applies_to("get_attachment") {
+                    text = self.config.pii.redact_document(&text).0;
+                }
+                ResourceContents::TextResourceContents { uri, mime_type, text, meta: None }
             }
-            Err(_) => {
-                Vec::new()
-            }
+            Ok(text) => ResourceContents::BlobResourceContents { uri, mime_type, blob: BASE64.encode(text), meta: None },
+            Err(e) => ResourceContents::BlobResourceContents { uri, mime_type, blob: BASE64.encode(e.into_bytes()), meta: None },
         };
-
-        conversation.messages = messages;
-        Json(conversation)
-        }) {
-            Ok(json) => json,
-            Err(_) => empty_err,
-        }
+        Ok(ReadResourceResult { contents: vec![contents] })
     }
+}
 
-    #[tool(description = "Search conversation titles from past conversations with the user. This tool helps you quickly find conversations by their titles when you remember the topic but not the exact conversation ID.")]
-    pub fn search_conversation_titles(
-        &self,
-        Parameters(SearchTitlesRequest { query }): Parameters<SearchTitlesRequest>,
-    ) -> Json<ConversationSummariesResponse> {
-        let search_pattern = format!("%{}%", query);
-        match self.with_db(|db| {
-        let mut stmt = match db.prepare(
-            r#"
-            SELECT 
-                c.id,
-                c.title,
-                c.created_at,
-                c.title_generated,
-                c.profile_name,
-                COUNT(m.id) as message_count
-            FROM conversations c
-            LEFT JOIN messages m ON c.id = m.conversation_id
-            WHERE c.title LIKE ?
-            GROUP BY c.id, c.title, c.created_at, c.title_generated, c.profile_name
-            ORDER BY c.created_at DESC
-            LIMIT 100
-            "#
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => {
-                return Json(ConversationSummariesResponse { items: Vec::new() });
-            }
-        };
-
-        let results: Vec<ConversationSummary> = match stmt.query_map([&search_pattern], |row| {
-            Ok(ConversationSummary {
-                id: row.get(0).unwrap_or_default(),
-                title: row.get(1).unwrap_or_default(),
-                created_at: row.get(2).unwrap_or(0),
-                title_generated: row.get(3).unwrap_or(0),
-                profile_name: row.get(4).ok(),
-                message_count: row.get(5).unwrap_or(0),
-            })
-        }) {
-            Ok(iter) => {
-                match iter.collect::<Result<Vec<_>, _>>() {
-                    Ok(results) => results,
-                    Err(_) => {
-                        Vec::new()
-                    }
-                }
-            }
-            Err(_) => {
-                Vec::new()
-            }
-        };
 
-        Json(ConversationSummariesResponse { items: results })
-        }) {
-            Ok(json) => json,
-            Err(_) => Json(ConversationSummariesResponse { items: Vec::new() }),
+#[prompt_handler]
+impl ServerHandler for ConversationService {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            instructions: Some("MCP server for searching and retrieving past conversations with the user from Cosmic LLM history. Also provides memory persistence capabilities - use search_memory to check for user preferences, technical setups, or important facts stored in previous conversations before answering questions.".to_string()),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_prompts()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_logging()
+                .build(),
+            ..Default::default()
         }
     }
 
-    #[tool(description = "List past conversations with the user, ordered by most recent. Useful for browsing conversation history and finding conversations by recency.")]
-    pub fn list_conversations(
+    async fn list_tools(
         &self,
-        Parameters(ListConversationsRequest { limit, offset }): Parameters<ListConversationsRequest>,
-    ) -> Json<ConversationSummariesResponse> {
-        let limit = limit.unwrap_or(50).min(200) as i64;
-        let offset = offset.unwrap_or(0) as i64;
-
-        match self.with_db(|db| {
-        let mut stmt = match db.prepare(
-            r#"
-            SELECT 
-                c.id,
-                c.title,
-                c.created_at,
-                c.title_generated,
-                c.profile_name,
-                COUNT(m.id) as message_count
-            FROM conversations c
-            LEFT JOIN messages m ON c.id = m.conversation_id
-            GROUP BY c.id, c.title, c.created_at, c.title_generated, c.profile_name
-            ORDER BY c.created_at DESC
-            LIMIT ? OFFSET ?
-            "#
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => {
-                return Json(ConversationSummariesResponse { items: Vec::new() });
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let mut tools = self.tool_router.list_all();
+        for tool in &mut tools {
+            let format = if markdown::TOOLS.contains(&tool.name.as_ref()) {
+                json!({
+                    "type": "string",
+                    "enum": ["json", "compact", "markdown"],
+                    "description": "json (default); compact for JSON without nulls and with short keys, listed in _keys; or markdown for a rendering that costs far fewer tokens to read",
+                })
+            } else {
+                json!({
+                    "type": "string",
+                    "enum": ["json", "compact"],
+                    "description": "json (default), or compact for JSON without nulls and with short keys, listed in _keys",
+                })
+            };
+            let mut schema = (*tool.input_schema).clone();
+            if let serde_json::Value::Object(properties) = schema.entry("properties").or_insert_with(|| json!({})) {
+                properties.insert("format".to_string(), format);
             }
-        };
-
-        let results: Vec<ConversationSummary> = match stmt.query_map([limit, offset], |row| {
-            Ok(ConversationSummary {
-                id: row.get(0).unwrap_or_default(),
-                title: row.get(1).unwrap_or_default(),
-                created_at: row.get(2).unwrap_or(0),
-                title_generated: row.get(3).unwrap_or(0),
-                profile_name: row.get(4).ok(),
-                message_count: row.get(5).unwrap_or(0),
-            })
-        }) {
-            Ok(iter) => {
-                match iter.collect::<Result<Vec<_>, _>>() {
-                    Ok(results) => results,
-                    Err(_) => {
-                        Vec::new()
-                    }
+            tool.input_schema = Arc::new(schema);
+        }
+        if self.config.redaction.enabled {
+            for tool in &mut tools {
+                let mut schema = (*tool.input_schema).clone();
+                if let serde_json::Value::Object(properties) = schema.entry("properties").or_insert_with(|| json!({})) {
+                    properties.insert(
+                        "allow_secrets".to_string(),
+                        json!({
+                            "type": "boolean",
+                            "description": "Return API keys, tokens and passwords unmasked; only when the user asked for a secret from their history",
+                        }),
+                    );
                 }
+                tool.input_schema = Arc::new(schema);
             }
-            Err(_) => {
-                Vec::new()
-            }
-        };
-
-        Json(ConversationSummariesResponse { items: results })
-        }) {
-            Ok(json) => json,
-            Err(_) => Json(ConversationSummariesResponse { items: Vec::new() }),
         }
+        Ok(ListToolsResult::with_all_items(tools))
     }
 
-    #[tool(description = "Retrieve a specific message from past conversations with the user by its message ID. Returns the complete message including content, role, tool calls, and any associated metadata. Returns empty message if not found.")]
-    pub fn get_message(
+    /// Dispatch to the tool router, reporting each call to the client log: tool name, duration,
+    /// rows returned and any error. A cancelled call has its running query interrupted; one whose
+    /// queries run past the query timeout is interrupted too and gets a timeout error. Secrets in
+    /// the result are masked unless the call passes `allow_secrets`, and personal data too when
+    /// PII masking covers the tool. Calls over the session's budget get an error instead. When
+    /// `format` asks, the text content is compact JSON, or Markdown for the tools that have a
+    /// rendering; the structured content stays as the output schema describes it.
+    async fn call_tool(
         &self,
-        Parameters(GetMessageRequest { message_id }): Parameters<GetMessageRequest>,
-    ) -> Json<Message> {
-        let lock_err_msg = Json(Message {
-            id: message_id,
-            conversation_id: "ERROR".to_string(),
-            role: "error".to_string(),
-            content: "Database lock error".to_string(),
-            created_at: 0,
-            tool_calls: None,
-            tool_call_id: None,
-            tool_name: None,
-            tool_status: None,
-            tool_params_json: None,
-            tool_result_json: None,
-            reasoning_content: None,
-        });
-        match self.with_db(|db| {
-        let mut stmt = match db.prepare(
-            r#"
-            SELECT 
-                id, conversation_id, role, content, created_at,
-                tool_calls, tool_call_id, tool_name, tool_status,
-                tool_params_json, tool_result_json, reasoning_content
-            FROM messages
-            WHERE id = ?
-            "#
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => {
-                return Json(Message {
-                    id: message_id,
-                    conversation_id: "ERROR".to_string(),
-                    role: "error".to_string(),
-                    content: "Database error".to_string(),
-                    created_at: 0,
-                    tool_calls: None,
-                    tool_call_id: None,
-                    tool_name: None,
-                    tool_status: None,
-                    tool_params_json: None,
-                    tool_result_json: None,
-                    reasoning_content: None,
-                });
+        mut request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let tool = request.name.to_string();
+        let allow_secrets = request
+            .arguments
+            .as_mut()
+            .and_then(|arguments| arguments.remove("allow_secrets"))
+            .and_then(|allow| allow.as_bool())
+            .unwrap_or(false);
+        let format = request.arguments.as_mut().and_then(|arguments| arguments.remove("format"));
+        let markdown = markdown::TOOLS.contains(&tool.as_str()) && format.as_ref().is_some_and(|f| f == "markdown");
+        let compact = format.as_ref().is_some_and(|f| f == "compact");
+        let peer = context.peer.clone();
+        let started = Instant::now();
+        let ct = context.ct.clone();
+        let mut result = match self.budget.admit() {
+            Ok(()) => {
+                let call = self.tool_router.call(ToolCallContext::new(self, request, context));
+                match self.interrupter.run(async move { ct.cancelled().await }, call).await {
+                    Ok(result) => result,
+                    Err(timeout) => Ok(timeout.into_result()),
+                }
             }
+            Err(exceeded) => Ok(exceeded.into_result()),
         };
-
-        match stmt.query_row([message_id], |row| {
-            Ok(Message {
-                id: row.get(0).unwrap_or(0),
-                conversation_id: row.get(1).unwrap_or_default(),
-                role: row.get(2).unwrap_or_default(),
-                content: row.get(3).unwrap_or_default(),
-                created_at: row.get(4).unwrap_or(0),
-                tool_calls: row.get(5).ok(),
-                tool_call_id: row.get(6).ok(),
-                tool_name: row.get(7).ok(),
-                tool_status: row.get(8).ok(),
-                tool_params_json: row.get(9).ok(),
-                tool_result_json: row.get(10).ok(),
-                reasoning_content: row.get(11).ok(),
-            })
-        }) {
-            Ok(msg) => Json(msg),
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                // Return empty message with error indicator
-                Json(Message {
-                    id: message_id,
-                    conversation_id: "NOT_FOUND".to_string(),
-                    role: "error".to_string(),
-                    content: "Message not found".to_string(),
-                    created_at: 0,
-                    tool_calls: None,
-                    tool_call_id: None,
-                    tool_name: None,
-                    tool_status: None,
-                    tool_params_json: None,
-                    tool_result_json: None,
-                    reasoning_content: None,
-                })
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let redacted = match &mut result {
+            Ok(output) if self.config.redaction.enabled && !allow_secrets => self.config.redaction.redact_result(output),
+            _ => 0,
+        };
+        let pii_masked = match &mut result {
+            Ok(output) if self.config.pii.applies_to(&tool) => self.config.pii.redact_result(output),
+            _ => 0,
+        };
+        if let (Ok(output), true) = (&mut result, markdown) {
+            let rendered = output.structured_content.as_ref().and_then(|value| markdown::render(&tool, value, self.config.timezone));
+            if let Some(rendered) = rendered {
+                output.content = vec![Content::text(rendered)];
             }
-            Err(_) => {
-                Json(Message {
-                    id: message_id,
-                    conversation_id: "ERROR".to_string(),
-                    role: "error".to_string(),
-                    content: "Database error".to_string(),
-                    created_at: 0,
-                    tool_calls: None,
-                    tool_call_id: None,
-                    tool_name: None,
-                    tool_status: None,
-                    tool_params_json: None,
-                    tool_result_json: None,
-                    reasoning_content: None,
-                })
+        }
+        if let (Ok(output), true) = (&mut result, compact) {
+            if let Some(value) = &output.structured_content {
+                output.content = vec![Content::text(compact::to_string(value))];
             }
         }
-        }) {
-            Ok(json) => json,
-            Err(_) => lock_err_msg,
+        if let Ok(output) = &mut result {
+            if let Err(exceeded) = self.budget.charge(output) {
+                *output = exceeded.into_result();
+            }
         }
+
+        let (level, data) = match &result {
+            Ok(output) => match logging::result_error(output) {
+                Some(error) => (
+                    LoggingLevel::Warning,
+                    json!({ "event": "tool_call", "tool": tool, "duration_ms": duration_ms, "error": error }),
+                ),
+                None => (
+                    LoggingLevel::Info,
+                    json!({ "event": "tool_call", "tool": tool, "duration_ms": duration_ms, "rows": logging::result_rows(output), "redacted": redacted, "pii_masked": pii_masked }),
+                ),
+            },
+            Err(e) => (
+                LoggingLevel::Error,
+                json!({ "event": "tool_call", "tool": tool, "duration_ms": duration_ms, "error": e.message }),
+            ),
+        };
+        self.client_log.log(&peer, level, data).await;
+        result
     }
 
-    #[tool(description = "THIS IS A TOOL TO REMEMBER, OR TO UPDATE(Delete and then create) THE MEMORY.USE IT OFTEN TO REMEMBER IMPORTANT STUFF! Store important facts, preferences, or relevant information in long-term memory.")]
-    pub fn store_memory(
+    async fn set_level(
         &self,
-        Parameters(StoreMemoryRequest {
-            content,
-            category,
-            importance,
-        }): Parameters<StoreMemoryRequest>,
-    ) -> Json<MemoryEntry> {
-        let lock_err = Json(MemoryEntry {
-            id: 0,
-            content: "Database lock error".to_string(),
-            category: None,
-            importance: 0,
-            created_at: 0,
-        });
-        match self.with_db(|db| {
-        let importance_value = importance.unwrap_or(5);
-        let created_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs() as i64;
-
-        match db.execute(
-            "INSERT INTO memory (content, category, importance, created_at) VALUES (?1, ?2, ?3, ?4)",
-            rusqlite::params![content, category, importance_value, created_at],
-        ) {
-            Ok(_) => {
-                let id = db.last_insert_rowid();
-                Json(MemoryEntry {
-                    id,
-                    content,
-                    category,
-                    importance: importance_value,
-                    created_at,
-                })
-            }
-            Err(e) => {
-                Json(MemoryEntry {
-                    id: 0,
-                    content: format!("Failed to store memory: {}", e),
-                    category: None,
-                    importance: 0,
-                    created_at: 0,
-                })
-            }
-        }
-        }) {
-            Ok(json) => json,
-            Err(_) => lock_err,
-        }
+        SetLevelRequestParam { level }: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.client_log.set_level(level);
+        Ok(())
     }
 
-    #[tool(description = "THISI IS A TOOL TO REMIND/GET CONTEXT FROM MEMORY. USE IT OFTEN! USE IT TOGETHER WITH CHAT HISTORY IF NEEDED (for details) ! Search long-term memory using full-text search. This tool finds relevant stored knowledge based on keywords or phrases. Results are ranked by relevance.")]
-    pub fn search_memory(
+    async fn list_resources(
         &self,
-        Parameters(SearchMemoryRequest { keywords }): Parameters<SearchMemoryRequest>,
-    ) -> Json<MemorySearchResponse> {
-        let fts_query = keywords.iter().filter(|s| !s.is_empty()).cloned().collect::<Vec<_>>().join(" OR ");
-        if fts_query.is_empty() {
-            return Json(MemorySearchResponse { items: Vec::new() });
-        }
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let Json(recent) = self.list_conversations(Parameters(ListConversationsRequest {
+            limit: Some(RESOURCE_LIST_LIMIT),
+            offset: None,
+            sort: Some(ConversationSort::Recent),
+            collation: None,
+            tags: None,
+            pinned_only: None,
+            fields: None,
+        }));
+        let mut resources: Vec<_> = recent
+            .into_inner()
+            .items
+            .into_iter()
+            .map(|conversation| {
+                let mut resource = RawResource::new(resources::conversation_uri(&conversation.id), conversation.title);
+                resource.description = Some(format!("{} messages", conversation.message_count));
+                resource.mime_type = Some("application/json".to_string());
+                resource.no_annotation()
+            })
+            .collect();
+        let stored = self.with_db(|db| attachments::recent(db, RESOURCE_LIST_LIMIT, self.config.timezone));
+        resources.extend(stored.ok().and_then(Result::ok).unwrap_or_default().into_iter().map(|attachment| {
+            let mut resource = RawResource::new(attachment.uri, attachment.name);
+            resource.mime_type = Some(attachment.mime_type);
+            resource.size = u32::try_from(attachment.size_bytes).ok();
+            resource.no_annotation()
+        }));
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
 
-        match self.with_db(|db| {
-        let mut stmt = match db.prepare(
-            r#"
-            SELECT 
-                m.id,
-                m.content,
-                m.category,
-                m.importance,
-                m.created_at
-            FROM memory m
-            JOIN memory_fts ON m.id = memory_fts.rowid
-            WHERE memory_fts MATCH ?
-            ORDER BY bm25(memory_fts) ASC
-            LIMIT 10
-            "#
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => {
-                return Json(MemorySearchResponse { items: Vec::new() });
-            }
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        let conversation = RawResourceTemplate {
+            uri_template: resources::CONVERSATION_URI_TEMPLATE.to_string(),
+            name: "conversation".to_string(),
+            title: Some("Past conversation".to_string()),
+            description: Some("A conversation with all its messages, as returned by get_conversation. Subscribe to be notified of new messages.".to_string()),
+            mime_type: Some("application/json".to_string()),
+            icons: None,
         };
-
-        let results: Vec<MemoryEntry> = match stmt.query_map([&fts_query], |row| {
-            Ok(MemoryEntry {
-                id: row.get(0).unwrap_or(0),
-                content: row.get(1).unwrap_or_default(),
-                category: row.get(2).ok(),
-                importance: row.get(3).unwrap_or(5),
-                created_at: row.get(4).unwrap_or(0),
-            })
-        }) {
-            Ok(iter) => {
-                match iter.collect::<Result<Vec<_>, _>>() {
-                    Ok(results) => results,
-                    Err(_) => {
-                        Vec::new()
-                    }
-                }
-            }
-            Err(_) => {
-                Vec::new()
-            }
+        let export = RawResourceTemplate {
+            uri_template: resources::EXPORT_URI_TEMPLATE.to_string(),
+            name: "conversation-export".to_string(),
+            title: Some("Past conversation as Markdown".to_string()),
+            description: Some("A whole conversation rendered as a Markdown document, for attaching a past thread to a new chat.".to_string()),
+            mime_type: Some("text/markdown".to_string()),
+            icons: None,
         };
-
-        Json(MemorySearchResponse { items: results })
-        }) {
-            Ok(json) => json,
-            Err(_) => Json(MemorySearchResponse { items: Vec::new() }),
-        }
+        let attachment = RawResourceTemplate {
+            uri_template: resources::ATTACHMENT_URI_TEMPLATE.to_string(),
+            name: "attachment".to_string(),
+            title: Some("Attachment".to_string()),
+            description: Some("A file stored with long-term memory by store_attachment, such as a diagram or a config file.".to_string()),
+            mime_type: None,
+            icons: None,
+        };
+        Ok(ListResourceTemplatesResult::with_all_items(vec![
+            conversation.no_annotation(),
+            export.no_annotation(),
+            attachment.no_annotation(),
+        ]))
     }
 
-    #[tool(description = "Search long-term memory by category. Returns all memory entries in the given category (e.g. 'moltbook', 'work', 'personal', 'security').")]
-    pub fn search_memory_by_category(
+    async fn read_resource(
         &self,
-        Parameters(SearchMemoryByCategoryRequest { category }): Parameters<SearchMemoryByCategoryRequest>,
-    ) -> Json<MemorySearchResponse> {
-        if category.is_empty() {
-            return Json(MemorySearchResponse { items: Vec::new() });
+        ReadResourceRequestParam { uri }: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        if let Some(attachment_id) = resources::parse_attachment_uri(&uri) {
+            return self.read_attachment(uri, attachment_id);
         }
-
-        match self.with_db(|db| {
-        let mut stmt = match db.prepare(
-            r#"
-            SELECT id, content, category, importance, created_at
-            FROM memory
-            WHERE category = ?
-            ORDER BY importance DESC, created_at DESC
-            LIMIT 50
-            "#
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => {
-                return Json(MemorySearchResponse { items: Vec::new() });
-            }
+        let (conversation_id, markdown) = match resources::parse_export_uri(&uri) {
+            Some(conversation_id) => (conversation_id, true),
+            None => resources::parse_conversation_uri(&uri)
+                .map(|conversation_id| (conversation_id, false))
+                .ok_or_else(|| McpError::invalid_params(format!("Unsupported resource URI: {}", uri), None))?,
         };
-
-        let results: Vec<MemoryEntry> = match stmt.query_map([&category], |row| {
-            Ok(MemoryEntry {
-                id: row.get(0).unwrap_or(0),
-                content: row.get(1).unwrap_or_default(),
-                category: row.get(2).ok(),
-                importance: row.get(3).unwrap_or(5),
-                created_at: row.get(4).unwrap_or(0),
-            })
-        }) {
-            Ok(iter) => {
-                match iter.collect::<Result<Vec<_>, _>>() {
-                    Ok(results) => results,
-                    Err(_) => Vec::new(),
-                }
-            }
-            Err(_) => Vec::new(),
+        let conversation = self.load_conversation(conversation_id);
+        if conversation.title == "NOT_FOUND" {
+            return Err(McpError::resource_not_found(format!("No conversation {}", conversation_id), None));
+        }
+        let (mime_type, mut text) = if markdown {
+            ("text/markdown", export::conversation_markdown(&conversation, self.config.timezone))
+        } else {
+            let json = serde_json::to_string(&conversation).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            ("application/json", json)
         };
-
-        Json(MemorySearchResponse { items: results })
-        }) {
-            Ok(json) => json,
-            Err(_) => Json(MemorySearchResponse { items: Vec::new() }),
+        if self.config.redaction.enabled {
+            text = self.config.redaction.redact_document(&text).0;
+        }
+        if markdown && self.config.pii.applies_to("conversation-export") {
+            text = self.config.pii.redact_document(&text).0;
         }
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri,
+                mime_type: Some(mime_type.to_string()),
+                text,
+                meta: None,
+            }],
+        })
     }
 
-    #[tool(description = "THIS IS A TOOL TO FORGET, OR TO UPDATE(Delete and then create) THE MEMORY USE IT TO CORRECT YOUR MEMORIES. Delete a memory entry by its ID. Use this to remove outdated or incorrect information from long-term memory.")]
-    pub fn delete_memory(
+    async fn subscribe(
         &self,
-        Parameters(DeleteMemoryRequest { memory_id }): Parameters<DeleteMemoryRequest>,
-    ) -> Json<DeleteMemoryResponse> {
-        match self.with_db(|db| {
-        match db.execute("DELETE FROM memory WHERE id = ?", [memory_id]) {
-            Ok(rows_affected) => {
-                if rows_affected > 0 {
-                    Json(DeleteMemoryResponse {
-                        success: true,
-                        error: None,
-                    })
-                } else {
-                    Json(DeleteMemoryResponse {
-                        success: false,
-                        error: Some("Memory entry not found".to_string()),
-                    })
-                }
-            }
-            Err(e) => {
-                Json(DeleteMemoryResponse {
-                    success: false,
-                    error: Some(format!("Failed to delete memory: {}", e)),
-                })
-            }
-        }
-        }) {
-            Ok(json) => json,
-            Err(_) => Json(DeleteMemoryResponse {
-                success: false,
-                error: Some("Database open/lock error".to_string()),
-            }),
+        SubscribeRequestParam { uri }: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let conversation_id = resources::parse_conversation_uri(&uri)
+            .ok_or_else(|| McpError::invalid_params(format!("Only conversation:// resources can be subscribed to, not {}", uri), None))?;
+        let watermark = match self.with_db(|db| resources::latest_message_id(db, conversation_id)) {
+            Ok(Ok(watermark)) => watermark,
+            Ok(Err(e)) => return Err(McpError::internal_error(format!("Failed to read conversation: {}", e), None)),
+            Err(e) => return Err(McpError::internal_error(format!("Database open/lock error: {:#}", e), None)),
+        };
+        if self.subscriptions.subscribe(conversation_id, watermark, context.peer) {
+            tokio::spawn(resources::watch(
+                self.db_path.clone(),
+                self.subscriptions.clone(),
+                self.config.watch_interval,
+            ));
         }
+        Ok(())
     }
-}
 
-#[tool_handler]
-impl ServerHandler for ConversationService {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            instructions: Some("MCP server for searching and retrieving past conversations with the user from Cosmic LLM history. Also provides memory persistence capabilities - use search_memory to check for user preferences, technical setups, or important facts stored in previous conversations before answering questions.".to_string()),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
-            ..Default::default()
+    async fn unsubscribe(
+        &self,
+        UnsubscribeRequestParam { uri }: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        if let Some(conversation_id) = resources::parse_conversation_uri(&uri) {
+            self.subscriptions.unsubscribe(conversation_id);
         }
+        Ok(())
     }
 }
@@ -0,0 +1,84 @@
+//! Response shaping applied after a tool has built its typed response.
+//!
+//! Tools keep returning their usual models; wrapping them in [`Shaped`] lets callers trim the
+//! serialized output (e.g. to just ids and previews) without every tool growing its own variant.
+
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// Keys of wrapper collections and paging handles that are kept even when not listed in `fields`.
+const WRAPPER_KEYS: &[&str] = &["items", "chunk_token", "total_messages"];
+
+/// A response serialized through `T`, then trimmed to the requested fields.
+#[derive(Debug)]
+pub struct Shaped<T> {
+    inner: T,
+    fields: Option<HashSet<String>>,
+}
+
+impl<T> Shaped<T> {
+    /// `fields` selects object keys to keep at every nesting level; `None` or empty keeps everything.
+    pub fn new(inner: T, fields: Option<Vec<String>>) -> Self {
+        let fields = fields
+            .map(|f| f.into_iter().collect::<HashSet<_>>())
+            .filter(|f| !f.is_empty());
+        Self { inner, fields }
+    }
+
+    /// The unshaped response, for callers inside the server that need every field.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Serialize> Serialize for Shaped<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(fields) = &self.fields else {
+            return self.inner.serialize(serializer);
+        };
+        let mut value = serde_json::to_value(&self.inner).map_err(serde::ser::Error::custom)?;
+        select_fields(&mut value, fields);
+        value.serialize(serializer)
+    }
+}
+
+fn select_fields(value: &mut Value, fields: &HashSet<String>) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| WRAPPER_KEYS.contains(&key.as_str()) || fields.contains(key));
+            map.values_mut().for_each(|v| select_fields(v, fields));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| select_fields(v, fields)),
+        _ => {}
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Shaped<T> {
+    fn schema_name() -> Cow<'static, str> {
+        format!("Shaped{}", T::schema_name()).into()
+    }
+
+    /// Same shape as `T`, but no property is required since any of them may be trimmed.
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let mut schema = T::json_schema(generator).to_value();
+        drop_required(&mut schema);
+        for definition in generator.definitions_mut().values_mut() {
+            drop_required(definition);
+        }
+        Schema::try_from(schema).unwrap_or_default()
+    }
+}
+
+fn drop_required(schema: &mut Value) {
+    if let Value::Object(map) = schema {
+        if map.contains_key("properties") && map.get("required").is_some_and(Value::is_array) {
+            map.remove("required");
+        }
+        map.values_mut().for_each(drop_required);
+    } else if let Value::Array(items) = schema {
+        items.iter_mut().for_each(drop_required);
+    }
+}
@@ -0,0 +1,164 @@
+//! Code snippets: commands and code the assistant gave before, with the language and a
+//! description, so they can be found again instead of asked for anew.
+//!
+//! `snippets_fts` keeps its own copy of the text plus an `identifiers` column holding the words
+//! of every compound identifier in the code: `getUserName`, `get_user_name` and `get-user-name`
+//! are all indexed as "get user name", so searching "user name" finds any of them.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, Row};
+
+use crate::dates::Timezone;
+use crate::db::unix_now;
+use crate::fts;
+use crate::models::SnippetEntry;
+
+const COLUMNS: &str = "s.id, s.language, s.description, s.code, s.created_at";
+
+/// Common short names of languages, stored and searched under the full name.
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("bash", "shell"),
+    ("c++", "cpp"),
+    ("c#", "csharp"),
+    ("golang", "go"),
+    ("js", "javascript"),
+    ("py", "python"),
+    ("rs", "rust"),
+    ("sh", "shell"),
+    ("ts", "typescript"),
+    ("yml", "yaml"),
+    ("zsh", "shell"),
+];
+
+fn from_row(row: &Row, timezone: Timezone) -> rusqlite::Result<SnippetEntry> {
+    let created_at = row.get(4)?;
+    Ok(SnippetEntry {
+        id: row.get(0)?,
+        language: row.get(1)?,
+        description: row.get(2)?,
+        code: row.get(3)?,
+        created_at,
+        created_at_iso: timezone.iso(created_at),
+    })
+}
+
+/// `language` lowercased, under its full name when it is a known alias ("py" is "python").
+pub fn normalize_language(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == language)
+        .map_or(language, |(_, name)| name.to_string())
+}
+
+/// Store a snippet and index it.
+pub fn store(conn: &Connection, code: &str, language: &str, description: &str, timezone: Timezone) -> Result<SnippetEntry> {
+    anyhow::ensure!(!code.trim().is_empty(), "Snippet code must not be empty");
+    let language = normalize_language(language);
+    anyhow::ensure!(!language.is_empty(), "Snippet language must not be empty");
+    let description = description.trim();
+    let tx = conn.unchecked_transaction().context("Failed to start transaction")?;
+    tx.execute(
+        "INSERT INTO snippets (language, description, code, created_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![language, description, code, unix_now()],
+    )
+    .context("Failed to store snippet")?;
+    let id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO snippets_fts (rowid, description, code, language, identifiers) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![id, description, code, language, identifier_words(code).join(" ")],
+    )
+    .context("Failed to index snippet")?;
+    tx.commit().context("Failed to commit snippet")?;
+    get(conn, id, timezone)
+}
+
+/// The snippet with id `id`.
+pub fn get(conn: &Connection, id: i64, timezone: Timezone) -> Result<SnippetEntry> {
+    conn.query_row(&format!("SELECT {} FROM snippets s WHERE s.id = ?", COLUMNS), [id], |row| from_row(row, timezone))
+        .context("Failed to read snippet")
+}
+
+/// Up to `limit` snippets in `language` matching `query`, best match first; the newest first
+/// without a query.
+pub fn search(conn: &Connection, query: &str, language: Option<&str>, limit: u32, timezone: Timezone) -> Result<Vec<SnippetEntry>> {
+    let fts_query = match_query(query);
+    let mut conditions = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    if let Some(query) = &fts_query {
+        conditions.push("snippets_fts MATCH ?");
+        params.push(query.clone());
+    }
+    if let Some(language) = language.map(normalize_language).filter(|l| !l.is_empty()) {
+        conditions.push("s.language = ?");
+        params.push(language);
+    }
+    let (join, order) = match fts_query {
+        Some(_) => ("JOIN snippets_fts ON s.id = snippets_fts.rowid", "snippets_fts.rank"),
+        None => ("", "s.created_at DESC, s.id DESC"),
+    };
+    let filter = if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) };
+    params.push(limit.to_string());
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM snippets s {} {} ORDER BY {} LIMIT ?", COLUMNS, join, filter, order))
+        .context("Failed to search snippets")?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| from_row(row, timezone))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read snippets")
+}
+
+/// FTS5 query matching any word of `query`, where a compound identifier also matches code
+/// holding all of its words: `getUserName` becomes `"getUserName"* OR ("get"* AND "user"* AND "name"*)`.
+fn match_query(query: &str) -> Option<String> {
+    let words = fts::clean_keywords(&query.split_whitespace().map(str::to_string).collect::<Vec<_>>());
+    let alternatives: Vec<String> = words
+        .iter()
+        .map(|word| {
+            let parts = identifier_parts(word);
+            match fts::all_of(parts.iter().map(|part| fts::term(part, true))) {
+                Some(all_parts) if parts.len() > 1 => format!("{} OR ({})", fts::term(word, true), all_parts),
+                _ => fts::term(word, true),
+            }
+        })
+        .collect();
+    (!alternatives.is_empty()).then(|| alternatives.join(" OR "))
+}
+
+/// Lowercased words of the compound identifiers in `text` (`parseHTTPRequest` gives "parse",
+/// "http" and "request"), each once. Plain words add nothing; the tokenizer already has them.
+pub fn identifier_words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for identifier in text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-')) {
+        let parts = identifier_parts(identifier);
+        if parts.len() < 2 {
+            continue;
+        }
+        for part in parts {
+            if !words.contains(&part) {
+                words.push(part);
+            }
+        }
+    }
+    words
+}
+
+/// `identifier` split at `_`, `-`, lower-to-upper case changes and the end of an acronym.
+fn identifier_parts(identifier: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for piece in identifier.split(['_', '-']).filter(|p| !p.is_empty()) {
+        let chars: Vec<char> = piece.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let lower_to_upper = chars[i - 1].is_lowercase() && chars[i].is_uppercase();
+            // The last capital of "HTTPRequest" starts the next word
+            let acronym_end = chars[i - 1].is_uppercase()
+                && chars[i].is_uppercase()
+                && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if lower_to_upper || acronym_end {
+                parts.push(chars[start..i].iter().collect::<String>().to_lowercase());
+                start = i;
+            }
+        }
+        parts.push(chars[start..].iter().collect::<String>().to_lowercase());
+    }
+    parts
+}
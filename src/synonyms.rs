@@ -0,0 +1,118 @@
+//! Synonyms for query expansion: a search for "k8s" also tries "kubernetes" once the two are
+//! linked. Links work both ways and compare ignoring case; `source` records whether the user or a
+//! sampled model suggestion added them.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+use crate::db::unix_now;
+use crate::models::{SynonymExpansion, SynonymSet};
+
+/// Terms trimmed and lowercased, without blanks, repeats and `term` itself.
+fn clean(term: &str, synonyms: &[String]) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for synonym in synonyms.iter().map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()) {
+        if synonym != term && !cleaned.contains(&synonym) {
+            cleaned.push(synonym);
+        }
+    }
+    cleaned
+}
+
+/// Link `term` with each of `synonyms`. Returns all of the term's synonyms.
+pub fn add(conn: &Connection, term: &str, synonyms: &[String], source: &str) -> Result<Vec<String>> {
+    let term = term.trim().to_lowercase();
+    anyhow::ensure!(!term.is_empty(), "No term given");
+    let synonyms = clean(&term, synonyms);
+    anyhow::ensure!(!synonyms.is_empty(), "No synonyms given");
+    let now = unix_now();
+    for synonym in &synonyms {
+        conn.execute(
+            "INSERT OR IGNORE INTO synonyms (term, synonym, source, created_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![term, synonym, source, now],
+        )
+        .context("Failed to add synonyms")?;
+    }
+    of(conn, &term)
+}
+
+/// Unlink `term` from each of `synonyms`, or from all of them when none are given. Returns the
+/// synonyms it has left.
+pub fn remove(conn: &Connection, term: &str, synonyms: &[String]) -> Result<Vec<String>> {
+    let term = term.trim().to_lowercase();
+    if synonyms.is_empty() {
+        conn.execute("DELETE FROM synonyms WHERE term = ?1 OR synonym = ?1", [&term])
+            .context("Failed to remove synonyms")?;
+    }
+    for synonym in clean(&term, synonyms) {
+        conn.execute(
+            "DELETE FROM synonyms WHERE (term = ?1 AND synonym = ?2) OR (term = ?2 AND synonym = ?1)",
+            rusqlite::params![term, synonym],
+        )
+        .context("Failed to remove synonyms")?;
+    }
+    of(conn, &term)
+}
+
+/// Synonyms of `term`, alphabetically.
+pub fn of(conn: &Connection, term: &str) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT synonym FROM synonyms WHERE term = ?1 UNION SELECT term FROM synonyms WHERE synonym = ?1 ORDER BY 1",
+        )
+        .context("Failed to read synonyms")?;
+    let rows = stmt.query_map([term.trim().to_lowercase()], |row| row.get(0))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read synonyms")
+}
+
+/// Every term with its synonyms, alphabetically. Each link is listed under the term it was added
+/// for.
+pub fn list(conn: &Connection) -> Result<Vec<SynonymSet>> {
+    let mut stmt = conn
+        .prepare("SELECT term, synonym FROM synonyms ORDER BY term, synonym")
+        .context("Failed to list synonyms")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read synonyms")?;
+    let mut sets: Vec<SynonymSet> = Vec::new();
+    for (term, synonym) in rows {
+        match sets.last_mut() {
+            Some(set) if set.term == term => set.synonyms.push(synonym),
+            _ => sets.push(SynonymSet { term, synonyms: vec![synonym] }),
+        }
+    }
+    Ok(sets)
+}
+
+/// `keywords` followed by the synonyms of each that aren't keywords already, and which keyword
+/// brought in which synonyms. Databases without synonyms expand nothing.
+pub fn expand(conn: &Connection, keywords: &[String]) -> (Vec<String>, Vec<SynonymExpansion>) {
+    let mut searched = keywords.to_vec();
+    let mut expansions = Vec::new();
+    for keyword in keywords {
+        let added: Vec<String> = of(conn, keyword)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|synonym| !searched.iter().any(|s| s.to_lowercase() == *synonym))
+            .collect();
+        if !added.is_empty() {
+            searched.extend(added.iter().cloned());
+            expansions.push(SynonymExpansion { keyword: keyword.clone(), synonyms: added });
+        }
+    }
+    (searched, expansions)
+}
+
+/// Synonyms in a sampled model reply: one per line or comma-separated, with list markers and
+/// quotes stripped and `term` itself left out.
+pub fn parse_suggestions(term: &str, reply: &str) -> Vec<String> {
+    let candidates: Vec<String> = reply
+        .split(['\n', ','])
+        .map(|s| s.trim().trim_start_matches(['-', '*', '•']).trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ')'))
+        .map(|s| s.trim().trim_matches(['"', '\'', '`']).trim())
+        .filter(|s| !s.is_empty() && s.split_whitespace().count() <= 3)
+        .map(String::from)
+        .collect();
+    clean(&term.trim().to_lowercase(), &candidates)
+}
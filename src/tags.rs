@@ -0,0 +1,96 @@
+//! Conversation tags: free-form labels ("bug-hunt", "travel", "cosmic-applet") for organizing
+//! history more finely than titles allow. Tags compare ignoring case.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db::unix_now;
+use crate::models::TagCount;
+
+/// Tags with surrounding whitespace, blanks and repeats (ignoring case) removed.
+fn clean(tags: &[String]) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !cleaned.iter().any(|seen| seen.to_lowercase() == tag.to_lowercase()) {
+            cleaned.push(tag.to_string());
+        }
+    }
+    cleaned
+}
+
+/// Add `tags` to the conversation `conversation_id`. Returns all of its tags.
+pub fn tag(conn: &Connection, conversation_id: &str, tags: &[String]) -> Result<Vec<String>> {
+    let tags = clean(tags);
+    anyhow::ensure!(!tags.is_empty(), "No tags given");
+    let exists = conn
+        .query_row("SELECT 1 FROM conversations WHERE id = ?", [conversation_id], |_| Ok(()))
+        .optional()
+        .context("Failed to look up conversation")?
+        .is_some();
+    anyhow::ensure!(exists, "No conversation with ID '{}'", conversation_id);
+    let now = unix_now();
+    for tag in &tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag, created_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![conversation_id, tag, now],
+        )
+        .context("Failed to tag conversation")?;
+    }
+    of(conn, conversation_id)
+}
+
+/// Remove `tags` from the conversation `conversation_id`. Returns the tags it has left.
+pub fn untag(conn: &Connection, conversation_id: &str, tags: &[String]) -> Result<Vec<String>> {
+    for tag in clean(tags) {
+        conn.execute(
+            "DELETE FROM conversation_tags WHERE conversation_id = ?1 AND tag = ?2",
+            rusqlite::params![conversation_id, tag],
+        )
+        .context("Failed to untag conversation")?;
+    }
+    of(conn, conversation_id)
+}
+
+/// Tags of the conversation `conversation_id`, alphabetically.
+pub fn of(conn: &Connection, conversation_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare("SELECT tag FROM conversation_tags WHERE conversation_id = ? ORDER BY tag")
+        .context("Failed to read tags")?;
+    let rows = stmt.query_map([conversation_id], |row| row.get(0))?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read tags")
+}
+
+/// Every tag in use with its number of conversations, most used first.
+pub fn list(conn: &Connection) -> Result<Vec<TagCount>> {
+    let mut stmt = conn
+        .prepare(
+            r#"
+            SELECT tag, COUNT(*) AS conversations, MAX(created_at)
+            FROM conversation_tags
+            GROUP BY tag
+            ORDER BY conversations DESC, tag
+            "#,
+        )
+        .context("Failed to list tags")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TagCount { tag: row.get(0)?, conversations: row.get(1)?, last_used_at: row.get(2)? })
+    })?;
+    rows.collect::<rusqlite::Result<_>>().context("Failed to read tags")
+}
+
+/// SQL predicate for conversation IDs in `column` whose conversation has every one of `tags`, and
+/// its parameters; `None` without tags.
+pub fn condition(column: &str, tags: &[String]) -> Option<(String, Vec<String>)> {
+    let tags = clean(tags);
+    (!tags.is_empty()).then(|| {
+        (
+            format!(
+                "{} IN (SELECT conversation_id FROM conversation_tags WHERE tag IN ({}) GROUP BY conversation_id HAVING COUNT(*) = {})",
+                column,
+                vec!["?"; tags.len()].join(", "),
+                tags.len()
+            ),
+            tags,
+        )
+    })
+}
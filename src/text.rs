@@ -0,0 +1,57 @@
+//! Small text utilities shared by similarity and keyword features.
+
+use std::collections::HashSet;
+
+/// Lowercased alphanumeric word tokens; everything else separates tokens.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Shorter words are too ambiguous to relate texts by ("api", "fix").
+const MIN_TERM_CHARS: usize = 4;
+
+/// Tokens distinctive enough to compare texts on: at least four characters and not only digits.
+pub fn terms(text: &str) -> impl Iterator<Item = String> {
+    tokenize(text)
+        .into_iter()
+        .filter(|t| t.chars().count() >= MIN_TERM_CHARS && !t.chars().all(char::is_numeric))
+}
+
+/// Distinct tokens of `text`.
+pub fn token_set(text: &str) -> HashSet<String> {
+    tokenize(text).into_iter().collect()
+}
+
+/// Jaccard similarity of two token sets (0.0 when both are empty).
+pub fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Character trigrams of each word of `text`, with words padded the way pg_trgm does
+/// ("  k", " ku", "kub", ..., "es ") so word starts and ends count too.
+pub fn trigrams(text: &str) -> HashSet<String> {
+    let mut grams = HashSet::new();
+    for word in tokenize(text) {
+        let padded: Vec<char> = format!("  {} ", word).chars().collect();
+        for window in padded.windows(3) {
+            grams.insert(window.iter().collect());
+        }
+    }
+    grams
+}
+
+/// Share of `query`'s trigrams found in `text` (0.0 to 1.0). Unlike Jaccard it doesn't penalize a
+/// long `text`, so "kubernets" scores high against "Kubernetes setup for the home cluster".
+pub fn trigram_containment(query: &HashSet<String>, text: &HashSet<String>) -> f64 {
+    if query.is_empty() {
+        return 0.0;
+    }
+    query.intersection(text).count() as f64 / query.len() as f64
+}
@@ -0,0 +1,81 @@
+//! Better titles for conversations whose auto-generated title says nothing ("New Conversation"):
+//! extractive suggestions from the conversation's own text, and renaming.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::keywords;
+
+/// Titles longer than this are cut at a word boundary.
+const MAX_TITLE_CHARS: usize = 60;
+
+/// `text` cut to at most [`MAX_TITLE_CHARS`] at a word boundary, first letter capitalized.
+fn shorten(text: &str) -> String {
+    let mut title = String::new();
+    for word in text.split_whitespace() {
+        if title.chars().count() + word.chars().count() + 1 > MAX_TITLE_CHARS {
+            break;
+        }
+        if !title.is_empty() {
+            title.push(' ');
+        }
+        title.push_str(word);
+    }
+    let mut chars = title.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+fn current_title(conn: &Connection, conversation_id: &str) -> Result<String> {
+    conn.query_row("SELECT title FROM conversations WHERE id = ?", [conversation_id], |row| row.get(0))
+        .optional()
+        .context("Failed to look up conversation")?
+        .with_context(|| format!("No conversation with ID '{}'", conversation_id))
+}
+
+/// The current title of conversation `conversation_id` and title suggestions for it, best first:
+/// the opening sentence of the first user message, then its top keyphrases.
+pub fn suggest(conn: &Connection, conversation_id: &str) -> Result<(String, Vec<String>)> {
+    let current = current_title(conn, conversation_id)?;
+    let first_message: Option<String> = conn
+        .query_row(
+            "SELECT content FROM messages WHERE conversation_id = ? AND role = 'user' AND trim(content) != '' ORDER BY id LIMIT 1",
+            [conversation_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to read conversation")?;
+
+    let mut suggestions = Vec::new();
+    if let Some(message) = first_message {
+        let sentence = message
+            .split_inclusive(['.', '?', '!', '\n'])
+            .map(|s| s.trim().trim_end_matches(['.', '!', '\n']))
+            .find(|s| !s.is_empty())
+            .unwrap_or_default();
+        suggestions.push(shorten(sentence));
+    }
+    let keyphrases = keywords::extract(conn, conversation_id, 3)?;
+    if let [first, second, ..] = keyphrases.as_slice() {
+        suggestions.push(shorten(&format!("{} – {}", first.phrase, second.phrase)));
+    }
+    if let Some(first) = keyphrases.first() {
+        suggestions.push(shorten(&first.phrase));
+    }
+    suggestions.retain(|s| !s.is_empty() && *s != current);
+    suggestions.dedup();
+    Ok((current, suggestions))
+}
+
+/// Rename conversation `conversation_id` to `title`, marking it as titled so the app doesn't
+/// generate a title over it. Returns the previous title.
+pub fn update(conn: &Connection, conversation_id: &str, title: &str) -> Result<String> {
+    let title = title.trim();
+    anyhow::ensure!(!title.is_empty(), "The title is empty");
+    let previous = current_title(conn, conversation_id)?;
+    conn.execute(
+        "UPDATE conversations SET title = ?1, title_generated = 1 WHERE id = ?2",
+        rusqlite::params![title, conversation_id],
+    )
+    .context("Failed to update title")?;
+    Ok(previous)
+}
@@ -0,0 +1,32 @@
+//! Token estimates, so a model can tell what fits its context before fetching it. Four characters
+//! per token is the usual rule of thumb for English text and code under BPE tokenizers; it runs
+//! high for prose and low for CJK text, but never needs the model's own tokenizer.
+
+use crate::models::{Conversation, Message};
+
+const CHARS_PER_TOKEN: u64 = 4;
+
+/// Tokens of a text `chars` characters long.
+pub fn from_chars(chars: u64) -> u64 {
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Tokens of `text`.
+pub fn estimate(text: &str) -> u64 {
+    from_chars(text.chars().count() as u64)
+}
+
+/// Tokens of a message's text: its content, reasoning, tool calls, parameters and result.
+pub fn message(message: &Message) -> u64 {
+    estimate(&message.content)
+        + [&message.reasoning_content, &message.tool_calls, &message.tool_params_json, &message.tool_result_json]
+            .into_iter()
+            .flatten()
+            .map(|text| estimate(text))
+            .sum::<u64>()
+}
+
+/// Tokens of a conversation's title and all its messages, whose own estimates must be set.
+pub fn conversation(conversation: &Conversation) -> u64 {
+    estimate(&conversation.title) + conversation.messages.iter().map(|m| m.estimated_tokens).sum::<u64>()
+}
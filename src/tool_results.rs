@@ -0,0 +1,166 @@
+//! Pruning bulky tool results: old `tool_result_json` payloads above a size are replaced with a
+//! short summary – the error, if any, and the start of their text – plus their original length.
+//! The original can be kept zlib-compressed in `pruned_tool_results` and read back on demand.
+
+use std::io::{Read, Write};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+
+use crate::db::unix_now;
+use crate::models::PruneToolResultsReport;
+
+pub const DEFAULT_MIN_KB: u32 = 16;
+pub const DEFAULT_OLDER_THAN_DAYS: u32 = 90;
+
+/// Characters of text a summary keeps.
+const SUMMARY_CHARS: usize = 300;
+
+/// Messages read per batch, so a large history isn't loaded at once.
+const BATCH: usize = 200;
+
+/// What to prune.
+#[derive(Debug, Clone)]
+pub struct PruneOptions {
+    pub min_bytes: u64,
+    pub older_than_days: u32,
+    pub keep_original: bool,
+    pub dry_run: bool,
+}
+
+impl Default for PruneOptions {
+    fn default() -> Self {
+        Self {
+            min_bytes: u64::from(DEFAULT_MIN_KB) * 1024,
+            older_than_days: DEFAULT_OLDER_THAN_DAYS,
+            keep_original: true,
+            dry_run: false,
+        }
+    }
+}
+
+/// Replace the tool results `options` selects with summaries, in a single transaction. The
+/// tool activity index follows through its update trigger.
+pub fn prune(conn: &Connection, options: &PruneOptions) -> Result<PruneToolResultsReport> {
+    let started = Instant::now();
+    let cutoff = unix_now() - i64::from(options.older_than_days) * 86400;
+    let tx = conn.unchecked_transaction().context("Failed to start pruning")?;
+    let mut report = PruneToolResultsReport { dry_run: options.dry_run, ..Default::default() };
+    let mut after_id = 0;
+    loop {
+        let batch: Vec<(i64, String)> = {
+            let mut stmt = tx.prepare_cached(
+                "SELECT id, tool_result_json FROM messages
+                 WHERE id > ?1 AND created_at < ?2 AND length(CAST(tool_result_json AS BLOB)) > ?3
+                   AND id NOT IN (SELECT message_id FROM pruned_tool_results)
+                 ORDER BY id LIMIT ?4",
+            )?;
+            let rows = stmt
+                .query_map(params![after_id, cutoff, options.min_bytes as i64, BATCH as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()
+                .context("Failed to read tool results")?;
+            rows
+        };
+        let Some((last_id, _)) = batch.last() else {
+            break;
+        };
+        after_id = *last_id;
+        for (message_id, original) in batch {
+            let stand_in = stand_in(&original, options.keep_original);
+            report.messages_pruned += 1;
+            report.bytes_before += original.len() as u64;
+            report.bytes_after += stand_in.len() as u64;
+            let kept = options.keep_original.then(|| compress(&original)).transpose()?;
+            report.bytes_kept += kept.as_ref().map_or(0, |kept| kept.len() as u64);
+            if options.dry_run {
+                continue;
+            }
+            tx.execute("UPDATE messages SET tool_result_json = ?1 WHERE id = ?2", params![stand_in, message_id])
+                .context("Failed to prune tool result")?;
+            tx.execute(
+                "INSERT OR REPLACE INTO pruned_tool_results (message_id, original_bytes, original, pruned_at) VALUES (?1, ?2, ?3, ?4)",
+                params![message_id, original.len() as i64, kept, unix_now()],
+            )
+            .context("Failed to keep the original tool result")?;
+        }
+    }
+    tx.commit().context("Failed to commit pruning")?;
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    Ok(report)
+}
+
+/// Tool result of message `message_id` as it was before pruning; `None` when it wasn't pruned or
+/// the original wasn't kept.
+pub fn original(conn: &Connection, message_id: i64) -> Result<Option<String>> {
+    let kept: Option<Vec<u8>> = conn
+        .query_row("SELECT original FROM pruned_tool_results WHERE message_id = ?", [message_id], |row| row.get(0))
+        .optional()
+        .context("Failed to look up pruned tool result")?
+        .flatten();
+    kept.map(|kept| decompress(&kept)).transpose()
+}
+
+/// JSON replacing a pruned tool result: its summary and error, and what happened to the rest.
+fn stand_in(original: &str, kept: bool) -> String {
+    let parsed = serde_json::from_str::<Value>(original).ok();
+    let error = parsed.as_ref().and_then(|value| value.get("error")).map(|error| match error {
+        Value::String(error) => shortened(error),
+        other => shortened(&other.to_string()),
+    });
+    let mut text = String::new();
+    match &parsed {
+        Some(value) => collect_text(value, &mut text),
+        None => text.push_str(original),
+    }
+    let mut stand_in = json!({
+        "summary": shortened(&text),
+        "pruned": true,
+        "original_bytes": original.len(),
+        "original_kept": kept,
+    });
+    if let Some(error) = error {
+        stand_in["error"] = Value::String(error);
+    }
+    stand_in.to_string()
+}
+
+/// The strings in `value`, in document order, separated by spaces.
+fn collect_text(value: &Value, text: &mut String) {
+    match value {
+        Value::String(s) => {
+            text.push_str(s);
+            text.push(' ');
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_text(item, text)),
+        Value::Object(fields) => fields.values().for_each(|field| collect_text(field, text)),
+        _ => {}
+    }
+}
+
+/// `text` with its whitespace collapsed, cut to [`SUMMARY_CHARS`] at a word boundary.
+fn shortened(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= SUMMARY_CHARS {
+        return collapsed;
+    }
+    let cut: String = collapsed.chars().take(SUMMARY_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(words, _)| words);
+    format!("{}…", cut)
+}
+
+fn compress(text: &str) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    encoder.finish().context("Failed to compress tool result")
+}
+
+fn decompress(bytes: &[u8]) -> Result<String> {
+    let mut text = String::new();
+    ZlibDecoder::new(bytes).read_to_string(&mut text).context("Failed to decompress tool result")?;
+    Ok(text)
+}
@@ -0,0 +1,268 @@
+//! The MCP tools, one module per domain, each adding a `#[tool_router]` block to
+//! [`ConversationService`], and the configuration deciding which of them a session lists.
+
+mod admin;
+mod conversations;
+mod memory;
+
+use anyhow::Result;
+use rmcp::handler::server::router::tool::ToolRouter;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Config;
+use crate::dates::{DateInput, Timezone};
+use crate::service::ConversationService;
+
+/// Tools by group, for `MCP_LUNA_TOOL_GROUPS`. Every tool must be listed in exactly one group.
+const TOOL_GROUPS: &[(&str, &[&str])] = &[
+    (
+        "conversations",
+        &[
+            "search_conversations",
+            "advanced_search",
+            "save_search",
+            "list_saved_searches",
+            "run_saved_search",
+            "get_search_history",
+            "get_conversation",
+            "get_conversation_chunk",
+            "get_key_messages",
+            "search_conversation_titles",
+            "list_conversations",
+            "find_related_conversations",
+            "extract_keywords",
+            "suggest_title",
+            "update_title",
+            "preview_delete_conversation",
+            "delete_conversation",
+            "restore_conversation",
+            "preview_redact_message",
+            "redact_message",
+            "get_message",
+            "search_failures",
+            "search_tool_activity",
+            "find_tool_calls",
+            "get_failure_stats",
+            "get_tool_usage_report",
+            "tag_conversation",
+            "untag_conversation",
+            "list_tags",
+            "pin_conversation",
+            "unpin_conversation",
+            "cluster_topics",
+            "add_synonyms",
+            "remove_synonyms",
+            "list_synonyms",
+            "suggest_synonyms",
+        ],
+    ),
+    (
+        "memory",
+        &[
+            "store_memory",
+            "search_memory",
+            "search_memory_by_category",
+            "get_pinned_memories",
+            "pin_memory",
+            "store_attachment",
+            "get_attachment",
+            "set_preference",
+            "get_preference",
+            "list_preferences",
+            "scratchpad_write",
+            "scratchpad_read",
+            "preview_scratchpad_clear",
+            "scratchpad_clear",
+            "set_session_scope",
+            "create_reminder",
+            "list_due_reminders",
+            "complete_reminder",
+            "append_journal",
+            "get_journal",
+            "save_bookmark",
+            "search_bookmarks",
+            "store_snippet",
+            "search_snippets",
+            "assert_fact",
+            "query_facts",
+            "list_conflicts",
+            "preview_resolve_conflict",
+            "resolve_conflict",
+            "remember_person",
+            "get_person",
+            "search_people",
+            "create_project",
+            "list_projects",
+            "assign_memory_to_project",
+            "add_project_rule",
+            "list_project_rules",
+            "classify_conversations",
+            "list_memory_categories",
+            "preview_rename_category",
+            "rename_category",
+            "preview_delete_memory",
+            "delete_memory",
+            "get_memory_hygiene_report",
+            "find_similar_memories",
+            "get_memory_history",
+        ],
+    ),
+    ("export", &["export_all", "create_backup"]),
+    ("admin", &["health", "get_database_info", "archive_conversations", "run_maintenance", "prune_tool_results", "reindex", "explain_search"]),
+];
+
+/// Tools hidden unless `MCP_LUNA_ADMIN_TOOLS` is enabled: slow or disruptive operations an agent
+/// shouldn't run on its own initiative.
+const ADMIN_TOOLS: &[&str] = &["run_maintenance", "prune_tool_results", "reindex", "explain_search"];
+
+/// Tools hidden unless `MCP_LUNA_HISTORY_WRITES` is enabled: they change the conversation history
+/// the app owns rather than the server's own tables.
+const HISTORY_WRITE_TOOLS: &[&str] = &[
+    "update_title",
+    "preview_delete_conversation",
+    "delete_conversation",
+    "restore_conversation",
+    "preview_redact_message",
+    "redact_message",
+    "archive_conversations",
+    "prune_tool_results",
+];
+
+/// Tools not registered in read-only mode (`MCP_LUNA_READONLY`): everything that writes to the
+/// database or to files on the server machine.
+const MUTATING_TOOLS: &[&str] = &[
+    "save_search",
+    "tag_conversation",
+    "untag_conversation",
+    "pin_conversation",
+    "unpin_conversation",
+    "cluster_topics",
+    "update_title",
+    "preview_delete_conversation",
+    "delete_conversation",
+    "restore_conversation",
+    "preview_redact_message",
+    "redact_message",
+    "archive_conversations",
+    "prune_tool_results",
+    "add_synonyms",
+    "remove_synonyms",
+    "store_memory",
+    "pin_memory",
+    "store_attachment",
+    "set_preference",
+    "scratchpad_write",
+    "preview_scratchpad_clear",
+    "scratchpad_clear",
+    "create_reminder",
+    "complete_reminder",
+    "append_journal",
+    "save_bookmark",
+    "store_snippet",
+    "assert_fact",
+    "preview_resolve_conflict",
+    "resolve_conflict",
+    "remember_person",
+    "create_project",
+    "assign_memory_to_project",
+    "add_project_rule",
+    "classify_conversations",
+    "preview_rename_category",
+    "rename_category",
+    "preview_delete_memory",
+    "delete_memory",
+    "export_all",
+    "create_backup",
+    "run_maintenance",
+    "reindex",
+];
+
+impl ConversationService {
+    /// Every tool, before the configuration selects some.
+    pub(crate) fn tool_router() -> ToolRouter<Self> {
+        Self::conversation_tools() + Self::memory_tools() + Self::admin_tools()
+    }
+}
+
+/// Drop the tools the configuration leaves out: those outside the enabled groups and individual
+/// tools, explicitly disabled ones, admin tools without `MCP_LUNA_ADMIN_TOOLS`, history-changing
+/// tools without `MCP_LUNA_HISTORY_WRITES`, and writing tools in read-only mode. Unknown group or tool names are an error so typos don't go unnoticed.
+pub(crate) fn select_tools(router: &mut ToolRouter<ConversationService>, config: &Config) -> Result<()> {
+    let known_tool = |name: &str| TOOL_GROUPS.iter().any(|(_, tools)| tools.contains(&name));
+    for name in config.enabled_tools.iter().chain(&config.disabled_tools) {
+        anyhow::ensure!(known_tool(name), "Unknown tool '{}'", name);
+    }
+    if let Some(groups) = &config.tool_groups {
+        for group in groups {
+            anyhow::ensure!(
+                TOOL_GROUPS.iter().any(|(name, _)| name == group),
+                "Unknown tool group '{}' (expected one of: {})",
+                group,
+                TOOL_GROUPS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+            );
+        }
+    }
+
+    let mut selected: HashSet<&str> = config.enabled_tools.iter().map(String::as_str).collect();
+    for (group, tools) in TOOL_GROUPS {
+        let enabled = match &config.tool_groups {
+            Some(groups) => groups.iter().any(|g| g == group),
+            None => true,
+        };
+        if enabled {
+            selected.extend(tools.iter().copied());
+        }
+    }
+    for name in &config.disabled_tools {
+        selected.remove(name.as_str());
+    }
+    if !config.admin_tools {
+        for tool in ADMIN_TOOLS {
+            selected.remove(tool);
+        }
+    }
+    if !config.history_writes {
+        for tool in HISTORY_WRITE_TOOLS {
+            selected.remove(tool);
+        }
+    }
+    if config.read_only {
+        for tool in MUTATING_TOOLS {
+            selected.remove(tool);
+        }
+    }
+
+    for tool in router.list_all() {
+        if !selected.contains(tool.name.as_ref()) {
+            router.remove_route(&tool.name);
+        }
+    }
+    Ok(())
+}
+
+/// Where a tool reads or writes files on the server machine: `subdir`, passed as `param`, of the
+/// directory configured in `variable`, which it may not leave. The CLI subcommands take any path
+/// instead.
+fn confined_dir(base: Option<&Path>, subdir: Option<&str>, param: &str, variable: &str) -> Result<PathBuf> {
+    let Some(base) = base else {
+        anyhow::bail!("{} is not set", variable);
+    };
+    let subdir = Path::new(subdir.unwrap_or_default());
+    if !subdir.components().all(|component| matches!(component, Component::Normal(_))) {
+        anyhow::bail!("{} must be a relative path inside {}", param, variable);
+    }
+    Ok(base.join(subdir))
+}
+
+/// `since` and `until` filters as unix seconds: the start of `since`'s span and the end of `until`'s.
+fn date_range(
+    timezone: Timezone,
+    since: Option<&DateInput>,
+    until: Option<&DateInput>,
+) -> Result<(Option<i64>, Option<i64>)> {
+    Ok((
+        since.map(|since| timezone.since(since)).transpose()?,
+        until.map(|until| timezone.until(until)).transpose()?,
+    ))
+}
@@ -0,0 +1,282 @@
+//! Tools over the database as a whole: health and size reports, exports and backups, and the
+//! admin-only maintenance tools.
+
+use rmcp::{
+    handler::server::wrapper::{Json, Parameters},
+    service::RequestContext,
+    tool, tool_router, RoleServer,
+};
+use std::path::Path;
+
+use crate::archive;
+use crate::backup;
+use crate::confirm;
+use crate::export;
+use crate::fts;
+use crate::health;
+use crate::info;
+use crate::maintenance;
+use crate::models::*;
+use crate::queries::search;
+use crate::service::ConversationService;
+use crate::tool_results::{self, PruneOptions};
+
+use super::confined_dir;
+
+fn prune_action(options: &PruneOptions) -> String {
+    format!("prune_tool_results:{}:{}:{}", options.min_bytes, options.older_than_days, options.keep_original)
+}
+
+#[tool_router(router = admin_tools, vis = "pub(crate)")]
+impl ConversationService {
+    #[tool(
+        description = "Quick health check: the database opens on a fresh connection, the tables the server needs exist and a query on them succeeds. Returns a status and the result of each check. Cheap enough to poll; use get_database_info for details.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn health(&self) -> Json<HealthReport> {
+        // The service connection migrates the schema on first use; check after it has
+        let connected = self.with_db(|_| ());
+        let mut report = health::check(self.db_path(), self.config.read_only);
+        if let Err(e) = connected {
+            report.checks.push(HealthCheck {
+                name: "connection".to_string(),
+                ok: false,
+                detail: Some(format!("Service connection failed: {:#}", e)),
+            });
+            report.healthy = false;
+            report.status = "unhealthy".to_string();
+        }
+        Json(report)
+    }
+
+    #[tool(
+        description = "Report which database file the server uses and its health: size, page count, schema version, row counts per table, and whether the full-text indexes exist and cover every row. Check this first when searches unexpectedly return nothing.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_database_info(&self) -> Json<DatabaseInfo> {
+        let failed = |error: String| {
+            Json(DatabaseInfo {
+                path: self.db_path().to_string(),
+                error: Some(error),
+                ..Default::default()
+            })
+        };
+        // Counting rows scans every table; do the tables and the FTS indexes in parallel
+        let (tables, fts_indexes) = match self.with_db_parallel(info::table_counts, info::fts_status) {
+            Ok((Ok(tables), Ok(fts_indexes))) => (tables, fts_indexes),
+            Ok((Err(e), _)) | Ok((_, Err(e))) => return failed(format!("Failed to inspect database: {:#}", e)),
+            Err(e) => return failed(format!("Database open/lock error: {:#}", e)),
+        };
+        match self.with_db(|db| info::summarize(db, Path::new(self.db_path()), tables, fts_indexes)) {
+            Ok(Ok(info)) => Json(info),
+            Ok(Err(e)) => failed(format!("Failed to inspect database: {:#}", e)),
+            Err(e) => failed(format!("Database open/lock error: {:#}", e)),
+        }
+    }
+
+    #[tool(
+        description = "Export all past conversations (with messages) and all memory entries to JSONL files in the server's export directory, for backup or analysis. Writes conversations.jsonl, memories.jsonl and a manifest.json carrying the export schema version.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn export_all(
+        &self,
+        Parameters(ExportAllRequest { output_dir, mask_pii }): Parameters<ExportAllRequest>,
+    ) -> Json<ExportReport> {
+        let failed = |error: String| {
+            Json(ExportReport {
+                schema_version: export::EXPORT_SCHEMA_VERSION,
+                output_dir: output_dir.clone().unwrap_or_default(),
+                conversations: 0,
+                messages: 0,
+                memories: 0,
+                files: Vec::new(),
+                estimated_tokens: 0,
+                pii_masked: false,
+                error: Some(error),
+            })
+        };
+        let dir = match confined_dir(self.config.export_dir.as_deref(), output_dir.as_deref(), "output_dir", "MCP_LUNA_EXPORT_DIR") {
+            Ok(dir) => dir,
+            Err(e) => return failed(format!("{:#}", e)),
+        };
+        let pii = mask_pii.unwrap_or(self.config.pii.applies_to("export_all")).then_some(&self.config.pii);
+        match self.with_db(|db| export::export_all(db, &dir, pii)) {
+            Ok(Ok(report)) => Json(report),
+            Ok(Err(e)) => failed(format!("Export failed: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Snapshot the whole database (conversations and memory) to a backup file using SQLite's online backup API, then delete older snapshots outside the retention policy (newest per day for the last days, newest per week for the last weeks).",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn create_backup(
+        &self,
+        Parameters(CreateBackupRequest { output_dir }): Parameters<CreateBackupRequest>,
+    ) -> Json<BackupReport> {
+        let failed = |error: String| {
+            Json(BackupReport {
+                path: String::new(),
+                size_bytes: 0,
+                created_at: 0,
+                removed: Vec::new(),
+                error: Some(error),
+            })
+        };
+        let dir = match confined_dir(self.config.backup.dir.as_deref(), output_dir.as_deref(), "output_dir", "MCP_LUNA_BACKUP_DIR") {
+            Ok(dir) => dir,
+            Err(e) => return failed(format!("{:#}", e)),
+        };
+        let retention = self.config.backup.retention;
+        match self.with_db(|db| backup::create_backup(db, &dir, retention)) {
+            Ok(Ok(report)) => Json(report),
+            Ok(Err(e)) => failed(format!("Backup failed: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Move conversations whose last message is older than a date out of the live history into a separate archive database file, keeping the live one small and fast to search. Nothing is lost: search_conversations with include_archive still finds them and get_conversation still reads them, but the app no longer lists them. Pinned conversations stay.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn archive_conversations(
+        &self,
+        Parameters(ArchiveConversationsRequest { before_date }): Parameters<ArchiveConversationsRequest>,
+    ) -> Json<ArchiveConversationsResponse> {
+        let archive_path = self.archive_path();
+        let failed = |error: String| {
+            Json(ArchiveConversationsResponse {
+                archive_path: archive_path.display().to_string(),
+                error: Some(error),
+                ..Default::default()
+            })
+        };
+        let before = match self.config.timezone.since(&before_date) {
+            Ok(before) => before,
+            Err(e) => return failed(e.to_string()),
+        };
+        match self.with_db(|db| archive::archive_conversations(db, &archive_path, before)) {
+            Ok(Ok(report)) => Json(report),
+            Ok(Err(e)) => failed(format!("Archiving failed: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Database upkeep: run an integrity check, optimize the full-text indexes, delete expired memories, refresh query planner statistics (ANALYZE) and optionally VACUUM. Use when searches get slow or after large imports and deletions. Only available when MCP_LUNA_ADMIN_TOOLS is enabled.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn run_maintenance(
+        &self,
+        Parameters(RunMaintenanceRequest { vacuum }): Parameters<RunMaintenanceRequest>,
+    ) -> Json<MaintenanceReport> {
+        let failed = |error: String| {
+            Json(MaintenanceReport {
+                error: Some(error),
+                ..Default::default()
+            })
+        };
+        match self.with_db(|db| maintenance::run_maintenance(db, vacuum.unwrap_or(false))) {
+            Ok(Ok(report)) => Json(report),
+            Ok(Err(e)) => failed(format!("Maintenance failed: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Shrink the database by replacing bulky tool results (tool_result_json) of old tool calls with a short summary – the error, if any, and the start of their text – plus their original size. Originals are kept compressed unless keep_original is false; get_message with original_tool_result returns them. Run it with dry_run first to see what would be saved and get a confirmation token for pruning it; clients that support elicitation ask the user before pruning. Only available when MCP_LUNA_ADMIN_TOOLS and MCP_LUNA_HISTORY_WRITES are enabled.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub async fn prune_tool_results(
+        &self,
+        Parameters(PruneToolResultsRequest {
+            min_kb,
+            older_than_days,
+            keep_original,
+            dry_run,
+            confirmation_token,
+        }): Parameters<PruneToolResultsRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Json<PruneToolResultsReport> {
+        let options = PruneOptions {
+            min_bytes: u64::from(min_kb.unwrap_or(tool_results::DEFAULT_MIN_KB)) * 1024,
+            older_than_days: older_than_days.unwrap_or(tool_results::DEFAULT_OLDER_THAN_DAYS),
+            keep_original: keep_original.unwrap_or(true),
+            dry_run,
+        };
+        let failed = |error: String| {
+            Json(PruneToolResultsReport {
+                dry_run,
+                error: Some(error),
+                ..Default::default()
+            })
+        };
+        let action = prune_action(&options);
+        if !dry_run {
+            if let Err(error) = self.confirm(confirmation_token.as_deref(), &action, "prune_tool_results with dry_run") {
+                return failed(error);
+            }
+            let question = format!(
+                "Replace tool results over {} KB older than {} days with short summaries{}?",
+                options.min_bytes / 1024,
+                options.older_than_days,
+                if options.keep_original { "" } else { ", discarding the originals" }
+            );
+            if let Err(error) = confirm::ask_user(&context.peer, &question, self.config.require_elicitation).await {
+                return failed(error);
+            }
+        }
+        match self.with_db(|db| tool_results::prune(db, &options)) {
+            Ok(Ok(report)) if dry_run => {
+                let (token, expires_at) = self.confirmations.issue(&action);
+                Json(PruneToolResultsReport { confirmation_token: Some(token), expires_at: Some(expires_at), ..report })
+            }
+            Ok(Ok(report)) => Json(report),
+            Ok(Err(e)) => failed(format!("Pruning failed: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Rebuild full-text indexes with the configured tokenizer (MCP_LUNA_FTS_KEEP_DIACRITICS, MCP_LUNA_FTS_PORTER), e.g. after enabling diacritics-insensitive or stemmed search. Indexes already using it are skipped. Rebuilding messages_fts re-reads every message and can take a while. Only available when MCP_LUNA_ADMIN_TOOLS is enabled.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn reindex(
+        &self,
+        Parameters(ReindexRequest { tables }): Parameters<ReindexRequest>,
+    ) -> Json<ReindexReport> {
+        let failed = |error: String| {
+            Json(ReindexReport {
+                error: Some(error),
+                ..Default::default()
+            })
+        };
+        match self.with_db(|db| maintenance::reindex(db, tables.as_deref(), &self.config.fts_tokenizer)) {
+            Ok(Ok(report)) => Json(report),
+            Ok(Err(e)) => failed(format!("Reindex failed: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Explain how search_conversations runs a keyword search, for tuning slow searches: whether each keyword is looked up in a full-text index or scanned with LIKE, the ranking, and the SQL, EXPLAIN QUERY PLAN output and duration of each stage (count, first page, keyword hits). Runs the search for real. Only available when MCP_LUNA_ADMIN_TOOLS is enabled.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn explain_search(
+        &self,
+        Parameters(ExplainSearchRequest { query, prefix, include_reasoning, sort, expand_synonyms }): Parameters<ExplainSearchRequest>,
+    ) -> Json<SearchExplanation> {
+        let keywords = fts::clean_keywords(&query.split_whitespace().map(str::to_string).collect::<Vec<_>>());
+        if keywords.is_empty() {
+            return Json(SearchExplanation { error: Some("query has no keywords".to_string()), ..Default::default() });
+        }
+        let explained = self.query(|db| {
+            search::explain(db, &keywords, include_reasoning, prefix, sort, expand_synonyms.unwrap_or(true), self.config.timezone)
+        });
+        Json(match explained {
+            Ok(explanation) => explanation,
+            Err(e) => SearchExplanation { error: Some(e.describe("Search failed")), ..Default::default() },
+        })
+    }
+}
@@ -0,0 +1,1128 @@
+//! Tools over the conversation history: searching it, reading conversations and messages,
+//! tool call activity, and the server's own annotations on conversations (tags, pins, synonyms,
+//! saved searches).
+
+use rmcp::{
+    handler::server::wrapper::{Json, Parameters},
+    model::{Content, CreateMessageRequestParam, Role, SamplingMessage},
+    service::RequestContext,
+    tool, tool_router, RoleServer,
+};
+use rusqlite::Connection;
+
+use crate::chunks;
+use crate::confirm;
+use crate::conversation_pins;
+use crate::db;
+use crate::erase;
+use crate::fts;
+use crate::languages;
+use crate::keywords;
+use crate::key_messages;
+use crate::models::*;
+use crate::projects;
+use crate::queries::{self, conversations, search, tool_calls, QueryError, QueryResult};
+use crate::related;
+use crate::saved_searches;
+use crate::search_history;
+use crate::service::ConversationService;
+use crate::synonyms;
+use crate::tags;
+use crate::shape::Shaped;
+use crate::titles;
+use crate::tool_results;
+use crate::tokens;
+use crate::topics;
+
+use super::date_range;
+
+/// Default and maximum length of message previews in search results, in characters.
+const DEFAULT_PREVIEW_CHARS: u32 = 200;
+const MAX_PREVIEW_CHARS: u32 = 2000;
+
+/// Most suggestions offered with an empty search result or asked for by `get_search_history`.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Default minimum `match_score` of fuzzy title matches.
+const DEFAULT_TITLE_SIMILARITY: f64 = 0.5;
+
+fn delete_conversation_action(conversation_id: &str) -> String {
+    format!("delete_conversation:{:?}", conversation_id)
+}
+
+fn redact_message_action(message_id: i64, text: Option<&str>) -> String {
+    format!("redact_message:{}:{:?}", message_id, text)
+}
+
+/// `search_conversations` terms as one line for the search history: keywords, then the phrase
+/// in quotes, then the near terms.
+fn search_text(keywords: &[String], phrase: Option<&str>, near: Option<&NearQuery>) -> String {
+    let mut parts: Vec<String> = keywords.to_vec();
+    parts.extend(phrase.map(|phrase| format!("\"{}\"", phrase.trim())));
+    if let Some(near) = near {
+        parts.extend(fts::clean_keywords(&near.terms));
+    }
+    parts.join(" ")
+}
+
+/// Start of the reporting window for `period`, in unix seconds; `None` means no lower bound.
+fn period_start(period: UsagePeriod) -> Option<i64> {
+    let days = match period {
+        UsagePeriod::Day => 1,
+        UsagePeriod::Week => 7,
+        UsagePeriod::Month => 30,
+        UsagePeriod::Year => 365,
+        UsagePeriod::All => return None,
+    };
+    Some(db::unix_now() - days * 24 * 60 * 60)
+}
+
+#[tool_router(router = conversation_tools, vis = "pub(crate)")]
+impl ConversationService {
+    /// Predicate for messages in language `code`. Detects the language of messages added since the
+    /// last filtered search first, except in read-only mode, where only earlier detections count.
+    fn language_condition(&self, db: &Connection, code: &str) -> (String, Vec<String>) {
+        if !self.config.read_only {
+            let _ = languages::index(db, &languages::detector(&self.config.languages));
+        }
+        languages::condition(code)
+    }
+
+    #[tool(
+        description = "Search across all past conversations with the user using full-text search. This tool searches through message content in all conversation history, allowing you to find relevant past discussions based on keywords or phrases.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_conversations(
+        &self,
+        Parameters(SearchConversationsRequest {
+            keywords,
+            phrase,
+            near,
+            prefix,
+            include_reasoning,
+            include_archive,
+            preview_chars,
+            include_full_content,
+            count_only,
+            max_per_conversation,
+            sort,
+            half_life_days,
+            min_score,
+            project,
+            tags,
+            language,
+            expand_synonyms,
+            fields,
+        }): Parameters<SearchConversationsRequest>,
+    ) -> Json<Shaped<SearchResultsResponse>> {
+        let preview_chars = preview_chars.unwrap_or(DEFAULT_PREVIEW_CHARS).min(MAX_PREVIEW_CHARS);
+        // Keywords, phrase and proximity are each optional; every one given must match
+        let keywords = fts::clean_keywords(&keywords);
+        let phrase = phrase.filter(|p| !p.trim().is_empty());
+        let near = near.filter(|near| near.terms.iter().any(|t| !t.trim().is_empty()));
+        if keywords.is_empty() && phrase.is_none() && near.is_none() {
+            return Json(Shaped::new(SearchResultsResponse::default(), None));
+        }
+
+        let Json(response) = match self.with_db(|db| {
+            // Synonyms widen the keywords' OR; the search history keeps the query as typed
+            let (searched, expansions) = if expand_synonyms.unwrap_or(true) {
+                synonyms::expand(db, &keywords)
+            } else {
+                (keywords.clone(), Vec::new())
+            };
+            let text = search::TextSearch::new(db, include_reasoning, prefix);
+            let mut conditions = text.conditions(&searched, phrase.as_deref(), near.as_ref());
+            let mut filters = Vec::new();
+            // A filter that can't match anything explains itself instead of returning a bare empty list
+            let unmatchable = |hint: String| {
+                let diagnostics = SearchDiagnostics { hints: vec![hint], ..Default::default() };
+                Json(SearchResultsResponse { diagnostics: Some(diagnostics), ..Default::default() })
+            };
+            if let Some(name) = &project {
+                match projects::get(db, name).ok().flatten() {
+                    Some(project) => conditions.push(search::project_condition(db, &text, &project)),
+                    None => return unmatchable(format!("No project named '{}'; see list_projects", name)),
+                }
+                filters.push("project".to_string());
+            }
+            if let Some(condition) = tags::condition("m.conversation_id", tags.as_deref().unwrap_or_default()) {
+                conditions.push(condition);
+                filters.push("tags".to_string());
+            }
+            if let Some(profile) = self.scope().profile {
+                conditions.push(("m.conversation_id IN (SELECT id FROM conversations WHERE profile_name = ?)".to_string(), vec![profile]));
+                filters.push("session profile".to_string());
+            }
+            if let Some(language) = &language {
+                match languages::resolve(language) {
+                    Some(code) => conditions.push(self.language_condition(db, code)),
+                    None => return unmatchable(format!("Unknown language '{}'; use an ISO 639-3 code such as 'eng'", language)),
+                }
+                filters.push("language".to_string());
+            }
+            let (sql, params) = queries::all_conditions(conditions);
+            let ranking = search::Ranking::new(
+                sort,
+                text.relevance_query(&searched, phrase.as_deref(), near.as_ref()),
+                half_life_days,
+                min_score.is_some(),
+            );
+            let mut query_params = ranking.params.clone();
+            query_params.extend(params.iter().cloned());
+            let matches = ranking.matches(&sql, min_score);
+
+            // Counting alone skips listing and previewing messages
+            let mut counts = count_only.then(|| search::counts(db, &ranking, &matches, &query_params).unwrap_or_default());
+            let page = search::Page {
+                preview_chars,
+                full_content: include_full_content,
+                reasoning: text.searches_reasoning(),
+                max_per_conversation,
+                limit: 50,
+                offset: 0,
+            };
+            let mut results = if count_only {
+                Vec::new()
+            } else {
+                search::page(db, &ranking, matches.clone(), &query_params, &page, self.config.timezone).unwrap_or_default()
+            };
+            let mut keyword_hits = search::keyword_hits(db, &text, &searched, &mut results);
+            // The same query over the archive; its matches fill the page after the live ones
+            if let Some(archive) = include_archive.then(|| self.open_archive()).flatten() {
+                let mut archived = Vec::new();
+                if let Some(counts) = counts.as_mut() {
+                    let more = search::counts(&archive, &ranking, &matches, &query_params).unwrap_or_default();
+                    counts.messages += more.messages;
+                    counts.conversations += more.conversations;
+                } else if results.len() < page.limit as usize {
+                    let page = search::Page { limit: page.limit - results.len() as u32, ..page };
+                    archived = search::page(&archive, &ranking, matches, &query_params, &page, self.config.timezone).unwrap_or_default();
+                }
+                let more_hits = search::keyword_hits(&archive, &text, &searched, &mut archived);
+                for (hits, more) in keyword_hits.iter_mut().zip(more_hits) {
+                    hits.hits += more.hits;
+                }
+                for result in &mut archived {
+                    result.archived = true;
+                }
+                results.extend(archived);
+            }
+
+            let query = search_text(&keywords, phrase.as_deref(), near.as_ref());
+            let hits = counts.as_ref().map_or(results.len(), |counts| counts.messages as usize);
+            self.record_search(db, "search_conversations", &query, hits);
+            // Nothing found: offer earlier phrasings that did find something
+            let suggestions = if hits == 0 {
+                search_history::suggestions(db, &query, None, MAX_SUGGESTIONS).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            let diagnostics = (hits == 0).then(|| {
+                search::diagnose(
+                    db,
+                    &text,
+                    &searched,
+                    phrase.as_deref(),
+                    near.as_ref(),
+                    (&sql, &params),
+                    &ranking,
+                    filters,
+                    min_score,
+                )
+            });
+
+            Json(SearchResultsResponse { items: results, keyword_hits, counts, suggestions, expansions, diagnostics })
+        }) {
+            Ok(json) => json,
+            Err(_) => Json(SearchResultsResponse::default()),
+        };
+        Json(Shaped::new(response, fields))
+    }
+
+    #[tool(
+        description = "Search messages from past conversations with every filter in one call: keywords and phrase, role, profile, conversation, tool name and date range, with sort order and pagination. Text is optional, so the filters alone list matching messages. Returns the page of matches and the total count.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn advanced_search(
+        &self,
+        Parameters(AdvancedSearchRequest {
+            keywords,
+            phrase,
+            prefix,
+            role,
+            profile_name,
+            conversation_id,
+            tool_name,
+            project,
+            tags,
+            language,
+            expand_synonyms,
+            since,
+            until,
+            sort,
+            half_life_days,
+            min_score,
+            limit,
+            offset,
+            preview_chars,
+            fields,
+        }): Parameters<AdvancedSearchRequest>,
+    ) -> Json<Shaped<AdvancedSearchResponse>> {
+        let keywords = fts::clean_keywords(&keywords);
+        let phrase = phrase.filter(|p| !p.trim().is_empty());
+        let limit = limit.unwrap_or(50).min(200);
+        let offset = offset.unwrap_or(0);
+        let preview_chars = preview_chars.unwrap_or(DEFAULT_PREVIEW_CHARS).min(MAX_PREVIEW_CHARS);
+        let filter = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let (since, until) = match date_range(self.config.timezone, since.as_ref(), until.as_ref()) {
+            Ok(range) => range,
+            Err(e) => {
+                let response = AdvancedSearchResponse { error: Some(e.to_string()), ..Default::default() };
+                return Json(Shaped::new(response, fields));
+            }
+        };
+        if min_score.is_some() && keywords.is_empty() && phrase.is_none() {
+            let response = AdvancedSearchResponse { error: Some("min_score needs keywords or a phrase to score".to_string()), ..Default::default() };
+            return Json(Shaped::new(response, fields));
+        }
+
+        let response = self.query(|db| -> QueryResult<AdvancedSearchResponse> {
+            // Synonyms widen the keywords' OR; the search history keeps the query as typed
+            let (searched, expansions) = if expand_synonyms.unwrap_or(true) {
+                synonyms::expand(db, &keywords)
+            } else {
+                (keywords.clone(), Vec::new())
+            };
+            let text = search::TextSearch::new(db, false, prefix);
+            let mut conditions = text.conditions(&searched, phrase.as_deref(), None);
+            let mut filters = Vec::new();
+            for (name, condition, value) in [
+                ("role", "m.role = ?", filter(role)),
+                ("profile_name", "m.conversation_id IN (SELECT id FROM conversations WHERE profile_name = ?)", filter(profile_name).or(self.scope().profile)),
+                ("conversation_id", "m.conversation_id = ?", filter(conversation_id)),
+                ("tool_name", "m.tool_name = ? COLLATE NOCASE", filter(tool_name)),
+                ("since", "m.created_at >= ?", since.map(|t| t.to_string())),
+                ("until", "m.created_at < ?", until.map(|t| t.to_string())),
+            ] {
+                if let Some(value) = value {
+                    conditions.push((condition.to_string(), vec![value]));
+                    filters.push(name.to_string());
+                }
+            }
+            if let Some(name) = filter(project) {
+                match projects::get(db, &name).ok().flatten() {
+                    Some(project) => conditions.push(search::project_condition(db, &text, &project)),
+                    None => {
+                        return Ok(AdvancedSearchResponse { error: Some(format!("No project named '{}'", name)), ..Default::default() })
+                    }
+                }
+                filters.push("project".to_string());
+            }
+            if let Some(condition) = tags::condition("m.conversation_id", tags.as_deref().unwrap_or_default()) {
+                conditions.push(condition);
+                filters.push("tags".to_string());
+            }
+            if let Some(language) = filter(language) {
+                match languages::resolve(&language) {
+                    Some(code) => conditions.push(self.language_condition(db, code)),
+                    None => {
+                        return Ok(AdvancedSearchResponse { error: Some(format!("Unknown language '{}'", language)), ..Default::default() })
+                    }
+                }
+                filters.push("language".to_string());
+            }
+            if conditions.is_empty() {
+                conditions.push(("1".to_string(), Vec::new()));
+            }
+            let (sql, params) = queries::all_conditions(conditions);
+            let ranking = search::Ranking::new(
+                sort,
+                text.relevance_query(&searched, phrase.as_deref(), None),
+                half_life_days,
+                min_score.is_some(),
+            );
+            let mut query_params = ranking.params.clone();
+            query_params.extend(params.iter().cloned());
+            let matches = ranking.matches(&sql, min_score);
+
+            let total = search::total(db, &ranking, &matches, &query_params)?;
+            let page = search::Page { preview_chars, limit, offset, ..Default::default() };
+            let items = search::page(db, &ranking, matches, &query_params, &page, self.config.timezone)?;
+            // Filter-only listings have no query worth suggesting later
+            let query = search_text(&keywords, phrase.as_deref(), None);
+            if !query.is_empty() {
+                self.record_search(db, "advanced_search", &query, total as usize);
+            }
+            let diagnostics = (total == 0).then(|| {
+                search::diagnose(db, &text, &searched, phrase.as_deref(), None, (&sql, &params), &ranking, filters, min_score)
+            });
+            Ok(AdvancedSearchResponse {
+                has_more: (offset as i64) + (items.len() as i64) < total,
+                items,
+                total,
+                offset,
+                limit,
+                expansions,
+                diagnostics,
+                error: None,
+            })
+        });
+        let response = match response {
+            Ok(response) => response,
+            Err(e @ QueryError::Unavailable(_)) => AdvancedSearchResponse { error: Some(e.to_string()), ..Default::default() },
+            Err(e) => AdvancedSearchResponse { error: Some(format!("Search failed: {}", e)), ..Default::default() },
+        };
+        Json(Shaped::new(response, fields))
+    }
+
+    #[tool(
+        description = "Save search_conversations parameters under a name, so a recurring search can be rerun with run_saved_search. Saving under an existing name replaces its parameters.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn save_search(
+        &self,
+        Parameters(SaveSearchRequest { name, search }): Parameters<SaveSearchRequest>,
+    ) -> Json<SavedSearch> {
+        let saved = self
+            .with_db(|db| saved_searches::save(db, &name, search))
+            .and_then(|saved| saved);
+        Json(saved.unwrap_or_else(|e| SavedSearch {
+            name,
+            error: Some(format!("Failed to save search: {:#}", e)),
+            ..Default::default()
+        }))
+    }
+
+    #[tool(
+        description = "List saved searches with their parameters, how often they ran and how many results the last run returned.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_saved_searches(&self) -> Json<SavedSearchesResponse> {
+        // Databases the server never wrote to have no saved_searches table
+        let items = self
+            .with_db(saved_searches::list)
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(SavedSearchesResponse { items })
+    }
+
+    #[tool(
+        description = "Run a saved search by name. Returns the same results as search_conversations, how many of them are new since the previous run, and that run's time and result count.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn run_saved_search(
+        &self,
+        Parameters(RunSavedSearchRequest { name, fields }): Parameters<RunSavedSearchRequest>,
+    ) -> Json<Shaped<SavedSearchRun>> {
+        let saved = self
+            .with_db(|db| saved_searches::get(db, &name))
+            .and_then(|saved| saved)
+            .ok()
+            .flatten();
+        let Some(saved) = saved else {
+            return Json(Shaped::new(
+                SavedSearchRun {
+                    name: name.clone(),
+                    error: Some(format!("No saved search named '{}'", name)),
+                    ..Default::default()
+                },
+                None,
+            ));
+        };
+        let request = match saved_searches::request(&saved) {
+            Ok(request) => request,
+            Err(e) => {
+                return Json(Shaped::new(
+                    SavedSearchRun { name, error: Some(format!("{:#}", e)), ..Default::default() },
+                    None,
+                ));
+            }
+        };
+
+        let Json(results) = self.search_conversations(Parameters(request));
+        let results = results.into_inner();
+        let new_since_last_run = results
+            .items
+            .iter()
+            .filter(|r| saved.last_run_at.is_none_or(|last_run| r.created_at > last_run))
+            .count() as i64;
+        // Run stats are bookkeeping, like memory retrieval counts: skipped in read-only mode
+        if !self.config.read_only {
+            let _ = self.with_db(|db| saved_searches::record_run(db, saved.id, results.items.len() as i64));
+        }
+        Json(Shaped::new(
+            SavedSearchRun {
+                name: saved.name,
+                previous_run_at: saved.last_run_at,
+                previous_result_count: saved.last_result_count,
+                new_since_last_run,
+                items: results.items,
+                keyword_hits: results.keyword_hits,
+                counts: results.counts,
+                error: None,
+            },
+            fields,
+        ))
+    }
+
+    #[tool(
+        description = "Show earlier searches and how many results each found, newest first, to learn which phrasings find things in this history. With similar_to, suggests earlier successful queries resembling that one instead.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_search_history(
+        &self,
+        Parameters(GetSearchHistoryRequest { tool, successful_only, similar_to, limit }): Parameters<GetSearchHistoryRequest>,
+    ) -> Json<SearchHistoryResponse> {
+        let tool = tool.as_deref().map(str::trim).filter(|t| !t.is_empty());
+        // Databases the server never wrote to have no search history
+        let response = self
+            .with_db(|db| match similar_to.as_deref() {
+                Some(query) => SearchHistoryResponse {
+                    items: Vec::new(),
+                    suggestions: search_history::suggestions(db, query, tool, limit.map_or(MAX_SUGGESTIONS, |l| l.min(500) as usize))
+                        .unwrap_or_default(),
+                },
+                None => SearchHistoryResponse {
+                    items: search_history::recent(db, tool, successful_only, limit.unwrap_or(50).min(500) as usize)
+                        .unwrap_or_default(),
+                    suggestions: Vec::new(),
+                },
+            })
+            .unwrap_or_default();
+        Json(response)
+    }
+
+    #[tool(
+        description = "Retrieve a complete conversation thread from past conversations with the user. Returns the full conversation including all messages, tool calls, and responses in chronological order. Very large conversations come back as their first chunk of messages with a chunk_token; pass it to get_conversation_chunk for the rest. Returns empty object if not found.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_conversation(
+        &self,
+        Parameters(GetConversationRequest { conversation_id, fields }): Parameters<GetConversationRequest>,
+    ) -> Json<Shaped<Conversation>> {
+        let mut conversation = self.load_conversation(&conversation_id);
+        let wants_messages = fields.as_ref().is_none_or(|fields| fields.is_empty() || fields.iter().any(|f| f == "messages"));
+        let chunk_bytes = self.config.chunk_bytes;
+        if wants_messages && chunks::too_large(&conversation.messages, chunk_bytes) {
+            let end = chunks::chunk_end(&conversation.messages, 0, chunk_bytes);
+            conversation.total_messages = Some(conversation.messages.len() as i64);
+            conversation.chunk_token = Some(chunks::token(&conversation.id, end));
+            conversation.messages.truncate(end);
+        }
+        Json(Shaped::new(conversation, fields))
+    }
+
+    #[tool(
+        description = "Continue reading a conversation too large for get_conversation: pass the chunk_token it returned to get the next chunk of messages, then each chunk's next_chunk_token until it is null.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_conversation_chunk(
+        &self,
+        Parameters(GetConversationChunkRequest { chunk_token }): Parameters<GetConversationChunkRequest>,
+    ) -> Json<ConversationChunk> {
+        let Some((conversation_id, start)) = chunks::parse_token(&chunk_token) else {
+            return Json(ConversationChunk { error: Some("Invalid chunk_token".to_string()), ..Default::default() });
+        };
+        let conversation = self.load_conversation(conversation_id);
+        let failed = |error: &str| ConversationChunk {
+            conversation_id: conversation_id.to_string(),
+            error: Some(error.to_string()),
+            ..Default::default()
+        };
+        Json(match conversation.title.as_str() {
+            "NOT_FOUND" => failed("Conversation not found"),
+            "ERROR" => failed("Failed to read conversation"),
+            _ if start > conversation.messages.len() => failed("chunk_token points past the end of the conversation"),
+            _ => {
+                let end = chunks::chunk_end(&conversation.messages, start, self.config.chunk_bytes.max(1));
+                let total_messages = conversation.messages.len();
+                ConversationChunk {
+                    conversation_id: conversation.id,
+                    first_message: start as i64,
+                    total_messages: total_messages as i64,
+                    next_chunk_token: (end < total_messages).then(|| chunks::token(conversation_id, end)),
+                    items: conversation.messages.into_iter().skip(start).take(end - start).collect(),
+                    error: None,
+                }
+            }
+        })
+    }
+
+    #[tool(
+        description = "Skim a long conversation: return only its key messages (longest assistant answers, answers to user questions, and messages stating decisions or conclusions) as short previews with the reasons they were picked. Use get_message or get_conversation for full content.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_key_messages(
+        &self,
+        Parameters(GetKeyMessagesRequest { conversation_id, preview_chars }): Parameters<GetKeyMessagesRequest>,
+    ) -> Json<KeyMessagesResponse> {
+        let preview_chars = preview_chars.unwrap_or(300) as usize;
+        let failed = |error: String| {
+            Json(KeyMessagesResponse {
+                conversation_id: conversation_id.clone(),
+                title: String::new(),
+                total_messages: 0,
+                items: Vec::new(),
+                error: Some(error),
+            })
+        };
+        let (title, messages) = match self.query(|db| conversations::get(db, &conversation_id, self.config.timezone)) {
+            Ok(conversation) => (conversation.title, conversation.messages),
+            Err(e) => return failed(e.describe("Failed to read conversation")),
+        };
+        let reasons = key_messages::detect(&messages);
+        let total_messages = messages.len() as i64;
+        let items = messages
+            .into_iter()
+            .zip(reasons)
+            .filter(|(_, reasons)| !reasons.is_empty())
+            .map(|(message, reasons)| KeyMessage {
+                id: message.id,
+                role: message.role,
+                created_at: message.created_at,
+                content_preview: message.content.chars().take(preview_chars).collect(),
+                reasons,
+            })
+            .collect();
+        Json(KeyMessagesResponse {
+            conversation_id: conversation_id.clone(),
+            title,
+            total_messages,
+            items,
+            error: None,
+        })
+    }
+
+    #[tool(
+        description = "Search conversation titles from past conversations with the user. This tool helps you quickly find conversations by their titles when you remember the topic but not the exact conversation ID.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_conversation_titles(
+        &self,
+        Parameters(SearchTitlesRequest { query, sort, collation, similarity_threshold, fields }): Parameters<SearchTitlesRequest>,
+    ) -> Json<Shaped<ConversationSummariesResponse>> {
+        let threshold = similarity_threshold.unwrap_or(DEFAULT_TITLE_SIMILARITY).clamp(0.0, 1.0);
+        let items = self
+            .query(|db| {
+                let mut results = conversations::search_titles(db, &query, sort, collation, self.config.timezone)?;
+                // No title matches the query's words: try cached keyphrases, then, e.g. for a typo,
+                // trigram similarity
+                if results.is_empty() && !query.trim().is_empty() {
+                    results = conversations::keyword_matches(db, &query, self.config.timezone)?;
+                    if results.is_empty() {
+                        results = conversations::fuzzy_title_matches(db, &query, threshold, self.config.timezone)?;
+                    }
+                }
+                self.record_search(db, "search_conversation_titles", &query, results.len());
+                Ok(results)
+            })
+            .unwrap_or_default();
+        Json(Shaped::new(ConversationSummariesResponse { items }, fields))
+    }
+
+    #[tool(
+        description = "List past conversations with the user, ordered by most recent (or oldest first, or alphabetically by title). Useful for browsing conversation history and finding conversations by recency.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_conversations(
+        &self,
+        Parameters(ListConversationsRequest { limit, offset, sort, collation, tags, pinned_only, fields }): Parameters<ListConversationsRequest>,
+    ) -> Json<Shaped<ConversationSummariesResponse>> {
+        let profile = self.scope().profile;
+        let query = conversations::ListQuery {
+            profile: profile.as_deref(),
+            tags: tags.as_deref().unwrap_or_default(),
+            pinned_only: pinned_only.unwrap_or(false),
+            sort,
+            collation,
+            limit: limit.unwrap_or(50).min(200),
+            offset: offset.unwrap_or(0),
+        };
+        let items = self.query(|db| conversations::list(db, &query, self.config.timezone)).unwrap_or_default();
+        Json(Shaped::new(ConversationSummariesResponse { items }, fields))
+    }
+
+    #[tool(
+        description = "Retrieve a specific message from past conversations with the user by its message ID. Returns the complete message including content, role, tool calls, and any associated metadata. Returns empty message if not found.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_message(
+        &self,
+        Parameters(GetMessageRequest { message_id, original_tool_result, fields }): Parameters<GetMessageRequest>,
+    ) -> Json<Shaped<Message>> {
+        let failed = |conversation_id: &str, content: String| Message {
+            id: message_id,
+            conversation_id: conversation_id.to_string(),
+            role: "error".to_string(),
+            content,
+            ..Default::default()
+        };
+        let message = match self.query(|db| {
+            let mut message = conversations::get_message(db, message_id, self.config.timezone)?;
+            if original_tool_result {
+                let original = tool_results::original(db, message_id).map_err(|e| QueryError::Invalid(format!("{:#}", e)))?;
+                if let Some(original) = original {
+                    message.tool_result_json = Some(original);
+                }
+            }
+            Ok(message)
+        }) {
+            Ok(mut message) => {
+                message.estimated_tokens = tokens::message(&message);
+                message
+            }
+            Err(e @ QueryError::NotFound(_)) => failed("NOT_FOUND", e.to_string()),
+            Err(e) => failed("ERROR", e.to_string()),
+        };
+        Json(Shaped::new(message, fields))
+    }
+
+    #[tool(
+        description = "Search failed tool calls from past conversations with the user. Matches tool messages whose status indicates an error, filtered by text in the message, tool name, tool parameters or tool result. Each hit includes a preview of the assistant message that followed, which often explains how the failure was fixed.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_failures(
+        &self,
+        Parameters(SearchFailuresRequest { query, fields }): Parameters<SearchFailuresRequest>,
+    ) -> Json<Shaped<FailureSearchResponse>> {
+        let items = self.query(|db| tool_calls::failures(db, &query)).unwrap_or_default();
+        Json(Shaped::new(FailureSearchResponse { items }, fields))
+    }
+
+    #[tool(
+        description = "Search what tools did in past conversations with the user: tool names, parameters and results. Finds which conversation ran a given command or touched a given file path. The query is matched as a phrase. Returns up to 50 tool calls, newest first.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_tool_activity(
+        &self,
+        Parameters(SearchToolActivityRequest { query, fields }): Parameters<SearchToolActivityRequest>,
+    ) -> Json<Shaped<ToolActivityResponse>> {
+        let query = query.trim().to_string();
+        if query.is_empty() {
+            return Json(Shaped::new(ToolActivityResponse::default(), fields));
+        }
+        let items = self
+            .query(|db| {
+                let items = tool_calls::search(db, &query, 50)?;
+                self.record_search(db, "search_tool_activity", &query, items.len());
+                Ok(items)
+            })
+            .unwrap_or_default();
+        Json(Shaped::new(ToolActivityResponse { items, error: None }, fields))
+    }
+
+    #[tool(
+        description = "Find tool calls in past conversations with the user by tool name, outcome (success or error), text in their parameters or result, and date range. Answers questions like \"every time terraform apply failed\". Returns tool calls newest first.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn find_tool_calls(
+        &self,
+        Parameters(FindToolCallsRequest { tool_name, tool_status, query, since, until, limit, fields }): Parameters<FindToolCallsRequest>,
+    ) -> Json<Shaped<ToolActivityResponse>> {
+        let limit = limit.unwrap_or(50).min(200) as i64;
+        let (since, until) = match date_range(self.config.timezone, since.as_ref(), until.as_ref()) {
+            Ok(range) => range,
+            Err(e) => {
+                return Json(Shaped::new(ToolActivityResponse { items: Vec::new(), error: Some(e.to_string()) }, fields))
+            }
+        };
+        let find = tool_calls::FindQuery {
+            tool_name: tool_name.as_deref().map(str::trim).filter(|t| !t.is_empty()),
+            status: tool_status,
+            query: query.as_deref().map(str::trim).filter(|q| !q.is_empty()),
+            since,
+            until,
+            limit,
+        };
+        let items = self.query(|db| tool_calls::find(db, &find)).unwrap_or_default();
+        Json(Shaped::new(ToolActivityResponse { items, error: None }, fields))
+    }
+
+    #[tool(
+        description = "Summarize which tools fail most often across past conversations with the user. Returns per-tool failure counts, total calls, failure rate and the time of the last failure, most failing tools first.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_failure_stats(&self) -> Json<FailureStatsResponse> {
+        Json(FailureStatsResponse { items: self.query(tool_calls::failure_stats).unwrap_or_default() })
+    }
+
+    #[tool(
+        description = "Report which tools the assistant invoked and how often across past conversations with the user, over a period ('day', 'week', 'month', 'year' or 'all'). For each tool returns call count, number of conversations, last use and the conversations that used it most.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_tool_usage_report(
+        &self,
+        Parameters(ToolUsageReportRequest { period }): Parameters<ToolUsageReportRequest>,
+    ) -> Json<ToolUsageReport> {
+        let period = period.unwrap_or_default();
+        let since = period_start(period);
+        let empty = || Json(ToolUsageReport { period, since, total_calls: 0, items: Vec::new() });
+        let since_value = since.unwrap_or(i64::MIN);
+
+        // Per-tool totals and top conversations are separate scans; run them side by side
+        let usage = |db: &Connection| tool_calls::usage(db, since_value);
+        let top = |db: &Connection| tool_calls::top_conversations(db, since_value);
+        let (mut items, top) = match self.with_db_parallel(usage, top) {
+            Ok((Ok(items), top)) => (items, top.unwrap_or_default()),
+            _ => return empty(),
+        };
+        for (tool_name, conversation) in top {
+            if let Some(usage) = items.iter_mut().find(|t| t.tool_name == tool_name) {
+                usage.top_conversations.push(conversation);
+            }
+        }
+
+        let total_calls = items.iter().map(|t| t.call_count).sum();
+        Json(ToolUsageReport { period, since, total_calls, items })
+    }
+
+    #[tool(
+        description = "Tag a conversation with labels such as 'bug-hunt' or 'travel', for organizing history more finely than titles allow. The tags filter of list_conversations, search_conversations and advanced_search then finds it.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn tag_conversation(
+        &self,
+        Parameters(TagConversationRequest { conversation_id, tags }): Parameters<TagConversationRequest>,
+    ) -> Json<ConversationTagsResponse> {
+        Json(match self.with_db(|db| tags::tag(db, &conversation_id, &tags)).and_then(|tags| tags) {
+            Ok(tags) => ConversationTagsResponse { success: true, tags, error: None },
+            Err(e) => ConversationTagsResponse { error: Some(format!("{:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "Remove tags from a conversation.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn untag_conversation(
+        &self,
+        Parameters(UntagConversationRequest { conversation_id, tags }): Parameters<UntagConversationRequest>,
+    ) -> Json<ConversationTagsResponse> {
+        Json(match self.with_db(|db| tags::untag(db, &conversation_id, &tags)).and_then(|tags| tags) {
+            Ok(tags) => ConversationTagsResponse { success: true, tags, error: None },
+            Err(e) => ConversationTagsResponse { error: Some(format!("{:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "List the conversation tags in use with how many conversations carry each, most used first. Check it before tag_conversation to reuse existing tags.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_tags(&self) -> Json<TagsResponse> {
+        // Databases the server never wrote to have no conversation_tags table
+        let items = self
+            .with_db(tags::list)
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(TagsResponse { items })
+    }
+
+    #[tool(
+        description = "Link a search term with synonyms, e.g. 'k8s' with 'kubernetes' or 'deployment' with 'rollout', so keyword searches for either also find the other. Links work both ways. Returns all of the term's synonyms.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn add_synonyms(
+        &self,
+        Parameters(AddSynonymsRequest { term, synonyms }): Parameters<AddSynonymsRequest>,
+    ) -> Json<SynonymsResponse> {
+        Json(match self.with_db(|db| synonyms::add(db, &term, &synonyms, "user")).and_then(|synonyms| synonyms) {
+            Ok(synonyms) => SynonymsResponse { success: true, term, synonyms, error: None },
+            Err(e) => SynonymsResponse { term, error: Some(format!("{:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "Unlink synonyms from a search term, or all of its synonyms when none are given.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn remove_synonyms(
+        &self,
+        Parameters(RemoveSynonymsRequest { term, synonyms }): Parameters<RemoveSynonymsRequest>,
+    ) -> Json<SynonymsResponse> {
+        Json(match self.with_db(|db| synonyms::remove(db, &term, &synonyms)).and_then(|synonyms| synonyms) {
+            Ok(synonyms) => SynonymsResponse { success: true, term, synonyms, error: None },
+            Err(e) => SynonymsResponse { term, error: Some(format!("{:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "List the search terms that have synonyms, with their synonyms.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_synonyms(&self) -> Json<SynonymSetsResponse> {
+        // Databases the server never wrote to have no synonyms table
+        let items = self
+            .with_db(synonyms::list)
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(SynonymSetsResponse { items })
+    }
+
+    #[tool(
+        description = "Ask the client's model (MCP sampling) for synonyms, abbreviations and closely related terms of a search term. Only suggests; add the useful ones with add_synonyms. Needs a client that supports sampling.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub async fn suggest_synonyms(
+        &self,
+        Parameters(SuggestSynonymsRequest { term }): Parameters<SuggestSynonymsRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Json<SynonymSuggestions> {
+        let term = term.trim().to_lowercase();
+        let existing = self.with_db(|db| synonyms::of(db, &term)).ok().and_then(|synonyms| synonyms.ok()).unwrap_or_default();
+        let failed = |error: String| SynonymSuggestions { term: term.clone(), existing: existing.clone(), error: Some(error), ..Default::default() };
+        if term.is_empty() {
+            return Json(failed("No term given".to_string()));
+        }
+        let supports_sampling = context.peer.peer_info().is_some_and(|info| info.capabilities.sampling.is_some());
+        if !supports_sampling {
+            return Json(failed("The client doesn't support sampling".to_string()));
+        }
+        let request = CreateMessageRequestParam {
+            messages: vec![SamplingMessage {
+                role: Role::User,
+                content: Content::text(format!(
+                    "List up to 8 synonyms, abbreviations or closely related terms someone might have used instead of \"{}\" when discussing it in a chat, e.g. \"k8s\" for \"kubernetes\". Reply with one term per line and nothing else.",
+                    term
+                )),
+            }],
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: Some(0.2),
+            max_tokens: 200,
+            stop_sequences: None,
+            metadata: None,
+        };
+        Json(match context.peer.create_message(request).await {
+            Ok(result) => {
+                let reply = result.message.content.as_text().map(|text| text.text.clone()).unwrap_or_default();
+                let suggestions = synonyms::parse_suggestions(&term, &reply)
+                    .into_iter()
+                    .filter(|suggestion| !existing.contains(suggestion))
+                    .collect();
+                SynonymSuggestions { term, suggestions, existing, model: Some(result.model), error: None }
+            }
+            Err(e) => failed(format!("Sampling failed: {}", e)),
+        })
+    }
+
+    #[tool(
+        description = "Find the past conversations most similar to a given one, by the distinctive terms they share (terms used often in it but rare across the history). Use it for \"we solved something like this before – where?\". Each result has a similarity score from 0.0 to 1.0 and the shared terms.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn find_related_conversations(
+        &self,
+        Parameters(FindRelatedConversationsRequest { conversation_id, limit }): Parameters<FindRelatedConversationsRequest>,
+    ) -> Json<RelatedConversationsResponse> {
+        let limit = limit.unwrap_or(10).min(50) as usize;
+        let timezone = self.config.timezone;
+        Json(match self.with_db(|db| related::find(db, &conversation_id, limit, timezone)).and_then(|items| items) {
+            Ok(items) => RelatedConversationsResponse { items, error: None },
+            Err(e) => RelatedConversationsResponse { items: Vec::new(), error: Some(format!("{:#}", e)) },
+        })
+    }
+
+    #[tool(
+        description = "Extract the characteristic keyphrases of a conversation (RAKE), e.g. to tell what a vaguely titled thread was about. The result is cached: list_conversations then shows the best ones as keywords, and search_conversation_titles finds the conversation by them when no title matches.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn extract_keywords(
+        &self,
+        Parameters(ExtractKeywordsRequest { conversation_id, limit }): Parameters<ExtractKeywordsRequest>,
+    ) -> Json<KeywordsResponse> {
+        let limit = limit.unwrap_or(10).min(50) as usize;
+        let extracted = self.with_db(|db| {
+            let keyphrases = keywords::extract(db, &conversation_id, limit)?;
+            // Caching is bookkeeping, like retrieval counts: skipped in read-only mode, best effort otherwise
+            let cached = !self.config.read_only && keywords::save(db, &conversation_id, &keyphrases).is_ok();
+            Ok::<_, anyhow::Error>((keyphrases, cached))
+        });
+        Json(match extracted.and_then(|extracted| extracted) {
+            Ok((keyphrases, cached)) => KeywordsResponse { conversation_id, keyphrases, cached, error: None },
+            Err(e) => KeywordsResponse { conversation_id, error: Some(format!("{:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "Suggest better titles for a conversation from its own text – the opening of the first user message and its top keyphrases – for conversations still called something like 'New Conversation'. Only suggests; update_title applies one.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn suggest_title(
+        &self,
+        Parameters(SuggestTitleRequest { conversation_id }): Parameters<SuggestTitleRequest>,
+    ) -> Json<TitleSuggestions> {
+        Json(match self.with_db(|db| titles::suggest(db, &conversation_id)).and_then(|suggested| suggested) {
+            Ok((current_title, suggestions)) => TitleSuggestions { conversation_id, current_title, suggestions, error: None },
+            Err(e) => TitleSuggestions { conversation_id, error: Some(format!("{:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "Rename a conversation, e.g. to a title from suggest_title. Changes the title shown in the app. Only available when MCP_LUNA_HISTORY_WRITES is enabled.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn update_title(
+        &self,
+        Parameters(UpdateTitleRequest { conversation_id, title }): Parameters<UpdateTitleRequest>,
+    ) -> Json<UpdateTitleResponse> {
+        Json(match self.with_db(|db| titles::update(db, &conversation_id, &title)).and_then(|previous| previous) {
+            Ok(previous_title) => UpdateTitleResponse { success: true, previous_title: Some(previous_title), error: None },
+            Err(e) => UpdateTitleResponse { error: Some(format!("{:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "Show the conversation delete_conversation with permanent would remove for good, from the history or the trash, and issue a single-use confirmation token for it (valid for 5 minutes). Pass the token to delete_conversation; it is required for permanent deletes when the server enforces confirmations. Only available when MCP_LUNA_HISTORY_WRITES is enabled.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn preview_delete_conversation(
+        &self,
+        Parameters(PreviewDeleteConversationRequest { conversation_id }): Parameters<PreviewDeleteConversationRequest>,
+    ) -> Json<DeleteConversationPreview> {
+        match self.with_db(|db| erase::preview_delete(db, &conversation_id)).and_then(|preview| preview) {
+            Ok(preview) => {
+                let (token, expires_at) = self.confirmations.issue(&delete_conversation_action(&conversation_id));
+                Json(DeleteConversationPreview { confirmation_token: Some(token), expires_at: Some(expires_at), ..preview })
+            }
+            Err(e) => Json(DeleteConversationPreview { conversation_id, error: Some(format!("{:#}", e)), ..Default::default() }),
+        }
+    }
+
+    #[tool(
+        description = "Delete a conversation from the history, e.g. one holding something the user pasted by mistake. It moves to a trash and restore_conversation can bring it back for 30 days; with permanent it is gone for good: call preview_delete_conversation first to check it and get a confirmation token, and clients that support elicitation ask the user first. Only available when MCP_LUNA_HISTORY_WRITES is enabled.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub async fn delete_conversation(
+        &self,
+        Parameters(DeleteConversationRequest { conversation_id, permanent, confirmation_token }): Parameters<DeleteConversationRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Json<DeleteConversationResponse> {
+        let failed = |error: String| {
+            Json(DeleteConversationResponse { conversation_id: conversation_id.clone(), permanent, error: Some(error), ..Default::default() })
+        };
+        // The trash keeps a conversation restorable, so only deleting it for good is confirmed
+        if permanent {
+            let action = delete_conversation_action(&conversation_id);
+            if let Err(error) = self.confirm(confirmation_token.as_deref(), &action, "preview_delete_conversation") {
+                return failed(error);
+            }
+            let question = format!("Delete conversation {} for good? It can't be restored afterwards.", conversation_id);
+            if let Err(error) = confirm::ask_user(&context.peer, &question, self.config.require_elicitation).await {
+                return failed(error);
+            }
+        }
+        let deleted = self.with_db(|db| {
+            if permanent {
+                erase::delete_permanently(db, &conversation_id)
+            } else {
+                erase::trash(db, &conversation_id)
+            }
+        });
+        match deleted.and_then(|deleted| deleted) {
+            Ok(response) => Json(response),
+            Err(e) => failed(format!("{:#}", e)),
+        }
+    }
+
+    #[tool(
+        description = "Bring a conversation deleted with delete_conversation back from the trash, with all its messages. Only available when MCP_LUNA_HISTORY_WRITES is enabled.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn restore_conversation(
+        &self,
+        Parameters(RestoreConversationRequest { conversation_id }): Parameters<RestoreConversationRequest>,
+    ) -> Json<RestoreConversationResponse> {
+        match self.with_db(|db| erase::restore(db, &conversation_id)).and_then(|restored| restored) {
+            Ok(response) => Json(response),
+            Err(e) => Json(RestoreConversationResponse { conversation_id, error: Some(format!("{:#}", e)), ..Default::default() }),
+        }
+    }
+
+    #[tool(
+        description = "Show which fields of a message redact_message would change and how its content would read afterwards, and issue a single-use confirmation token for that redaction (valid for 5 minutes). Pass the token to redact_message; it is required when the server enforces confirmations. Only available when MCP_LUNA_HISTORY_WRITES is enabled.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn preview_redact_message(
+        &self,
+        Parameters(PreviewRedactMessageRequest { message_id, text }): Parameters<PreviewRedactMessageRequest>,
+    ) -> Json<RedactMessagePreview> {
+        match self.with_db(|db| erase::preview_redact(db, message_id, text.as_deref())).and_then(|preview| preview) {
+            Ok(preview) => {
+                let (token, expires_at) = self.confirmations.issue(&redact_message_action(message_id, text.as_deref()));
+                Json(RedactMessagePreview { confirmation_token: Some(token), expires_at: Some(expires_at), ..preview })
+            }
+            Err(e) => Json(RedactMessagePreview { message_id, error: Some(format!("{:#}", e)), ..Default::default() }),
+        }
+    }
+
+    #[tool(
+        description = "Remove sensitive content from a message for good, e.g. a password the user pasted. With text, only that text is replaced by [redacted], in the message, its tool parameters and result and its reasoning; without, the whole message is blanked. Search stops finding the removed text. Call preview_redact_message first to check the change and get a confirmation token. Clients that support elicitation ask the user first. Only available when MCP_LUNA_HISTORY_WRITES is enabled.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub async fn redact_message(
+        &self,
+        Parameters(RedactMessageRequest { message_id, text, confirmation_token }): Parameters<RedactMessageRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Json<RedactMessageResponse> {
+        let failed = |error: String| Json(RedactMessageResponse { message_id, error: Some(error), ..Default::default() });
+        let action = redact_message_action(message_id, text.as_deref());
+        if let Err(error) = self.confirm(confirmation_token.as_deref(), &action, "preview_redact_message") {
+            return failed(error);
+        }
+        let question = match text {
+            Some(_) => format!("Redact the given text from message {} for good?", message_id),
+            None => format!("Blank message {} for good?", message_id),
+        };
+        if let Err(error) = confirm::ask_user(&context.peer, &question, self.config.require_elicitation).await {
+            return failed(error);
+        }
+        match self.with_db(|db| erase::redact(db, message_id, text.as_deref())).and_then(|redacted| redacted) {
+            Ok(response) => Json(response),
+            Err(e) => failed(format!("{:#}", e)),
+        }
+    }
+
+    #[tool(
+        description = "Pin a conversation the user keeps coming back to, such as a reference thread. list_conversations marks it as pinned and its pinned_only filter lists just the pinned ones.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn pin_conversation(
+        &self,
+        Parameters(PinConversationRequest { conversation_id }): Parameters<PinConversationRequest>,
+    ) -> Json<PinConversationResponse> {
+        Json(match self.with_db(|db| conversation_pins::pin(db, &conversation_id)).and_then(|pinned| pinned) {
+            Ok(()) => PinConversationResponse { success: true, error: None },
+            Err(e) => PinConversationResponse { success: false, error: Some(format!("{:#}", e)) },
+        })
+    }
+
+    #[tool(
+        description = "Unpin a conversation pinned with pin_conversation.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn unpin_conversation(
+        &self,
+        Parameters(UnpinConversationRequest { conversation_id }): Parameters<UnpinConversationRequest>,
+    ) -> Json<PinConversationResponse> {
+        Json(match self.with_db(|db| conversation_pins::unpin(db, &conversation_id)).and_then(|unpinned| unpinned) {
+            Ok(()) => PinConversationResponse { success: true, error: None },
+            Err(e) => PinConversationResponse { success: false, error: Some(format!("{:#}", e)) },
+        })
+    }
+
+    #[tool(
+        description = "Group all past conversations into topics by their vocabulary, for a bird's-eye map of the history: each topic gets a label from its most central terms, its size, the dates it spans and its most representative conversations. Replaces the previous clustering. Takes a few seconds on a large history.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn cluster_topics(
+        &self,
+        Parameters(ClusterTopicsRequest { topics: count }): Parameters<ClusterTopicsRequest>,
+    ) -> Json<TopicClusteringReport> {
+        let timezone = self.config.timezone;
+        Json(match self.with_db(|db| topics::cluster(db, count.map(|c| c as usize), timezone)).and_then(|report| report) {
+            Ok(report) => report,
+            Err(e) => TopicClusteringReport { error: Some(format!("Clustering failed: {:#}", e)), ..Default::default() },
+        })
+    }
+}
@@ -0,0 +1,1254 @@
+//! Tools over the server's long-term memory: memories, preferences, scratchpads, reminders, the
+//! journal, people and projects.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rmcp::{
+    handler::server::wrapper::{Json, Parameters},
+    service::RequestContext,
+    tool, tool_router, RoleServer,
+};
+use std::collections::HashSet;
+
+use crate::attachments::{self, Content, NewAttachment};
+use crate::bookmarks::{self, BookmarkUpdate};
+use crate::confirm;
+use crate::conflicts;
+use crate::db;
+use crate::facts::{self, FactQuery, NewFact};
+use crate::fts;
+use crate::journal;
+use crate::memory_history::{self, HistoryQuery};
+use crate::models::*;
+use crate::people::{self, PersonUpdate};
+use crate::preferences;
+use crate::projects;
+use crate::queries::{memories, search};
+use crate::reminders;
+use crate::scratchpad;
+use crate::service::ConversationService;
+use crate::shape::Shaped;
+use crate::snippets;
+use crate::text;
+
+use super::{confined_dir, date_range};
+
+/// Cap on how many memories the near-duplicate scan compares pairwise.
+const MAX_DUPLICATE_SCAN: usize = 2000;
+
+fn delete_memory_action(memory_id: i64, forget_history: bool) -> String {
+    format!("delete_memory:{}:{}", memory_id, forget_history)
+}
+
+fn scratchpad_clear_action(name: &str) -> String {
+    format!("scratchpad_clear:{:?}", name.trim())
+}
+
+fn rename_category_action(old_name: &str, new_name: &str) -> String {
+    format!("rename_category:{:?}:{:?}", old_name, new_name)
+}
+
+/// Bound to the side deleted, so a token for keeping one side can't delete the other.
+fn resolve_conflict_action(conflict_id: i64, deleted_id: i64) -> String {
+    format!("resolve_conflict:{}:{}", conflict_id, deleted_id)
+}
+
+#[tool_router(router = memory_tools, vis = "pub(crate)")]
+impl ConversationService {
+    #[tool(
+        description = "THIS IS A TOOL TO REMEMBER, OR TO UPDATE(Delete and then create) THE MEMORY.USE IT OFTEN TO REMEMBER IMPORTANT STUFF! Store important facts, preferences, or relevant information in long-term memory.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn store_memory(
+        &self,
+        Parameters(StoreMemoryRequest {
+            content,
+            category,
+            importance,
+            ttl_days,
+            pinned,
+            key,
+        }): Parameters<StoreMemoryRequest>,
+    ) -> Json<MemoryEntry> {
+        let created_at = db::unix_now();
+        let mut memory = MemoryEntry {
+            content,
+            category,
+            importance: importance.unwrap_or(5),
+            created_at,
+            created_at_iso: self.config.timezone.iso(created_at),
+            expires_at: ttl_days.filter(|days| *days > 0.0).map(|days| created_at + (days * 86400.0) as i64),
+            pinned,
+            key: key.as_deref().map(facts::key).filter(|k| !k.is_empty()),
+            ..Default::default()
+        };
+        match self.query(|db| memories::insert(db, &memory)) {
+            Ok(id) => memory.id = id,
+            Err(e) => return Json(MemoryEntry { content: e.describe("Failed to store memory"), ..Default::default() }),
+        }
+        // The memory is stored either way; a failed check only leaves its conflicts unreported
+        memory.conflicts = self
+            .with_db(|db| conflicts::for_memory(db, &memory, self.config.timezone))
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        Json(memory)
+    }
+
+    #[tool(
+        description = "THISI IS A TOOL TO REMIND/GET CONTEXT FROM MEMORY. USE IT OFTEN! USE IT TOGETHER WITH CHAT HISTORY IF NEEDED (for details) ! Search long-term memory using full-text search. This tool finds relevant stored knowledge based on keywords or phrases. Results are ranked by relevance.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_memory(
+        &self,
+        Parameters(SearchMemoryRequest {
+            keywords,
+            min_importance,
+            category,
+            after,
+            before,
+            sort_by,
+            include_pinned,
+            project,
+            fields,
+        }): Parameters<SearchMemoryRequest>,
+    ) -> Json<Shaped<MemorySearchResponse>> {
+        let keywords = fts::clean_keywords(&keywords);
+        let fts_query = fts::any_of(&keywords, false);
+        let category = category.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+        let (after, before) = match date_range(self.config.timezone, after.as_ref(), before.as_ref()) {
+            Ok(range) => range,
+            Err(e) => {
+                return Json(Shaped::new(MemorySearchResponse { items: Vec::new(), error: Some(e.to_string()) }, fields))
+            }
+        };
+
+        let mut search = memories::SearchQuery {
+            fts_query,
+            min_importance,
+            category,
+            after,
+            before,
+            sort: sort_by.unwrap_or_default(),
+            ..Default::default()
+        };
+        if let Some(name) = project.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            match self.with_db(|db| projects::get(db, name)).ok().and_then(Result::ok).flatten() {
+                Some(project) => search.project_id = Some(project.id),
+                None => {
+                    let response = MemorySearchResponse { items: Vec::new(), error: Some(format!("No project named '{}'", name)) };
+                    return Json(Shaped::new(response, fields));
+                }
+            }
+        }
+        if search.is_empty() {
+            return Json(Shaped::new(MemorySearchResponse { items: Vec::new(), error: None }, None));
+        }
+
+        let items = self
+            .query(|db| {
+                let matches = memories::search(db, &search, self.config.timezone)?;
+                if !keywords.is_empty() {
+                    self.record_search(db, "search_memory", &keywords.join(" "), matches.len());
+                }
+                let mut results =
+                    if include_pinned.unwrap_or(true) { memories::pinned(db, self.config.timezone)? } else { Vec::new() };
+                let pinned_ids: HashSet<i64> = results.iter().map(|entry| entry.id).collect();
+                results.extend(matches.into_iter().filter(|entry| !pinned_ids.contains(&entry.id)));
+                if !self.config.read_only {
+                    memories::record_access(db, &results);
+                }
+                attachments::link(db, &mut results, self.config.timezone)?;
+                Ok(results)
+            })
+            .unwrap_or_default();
+        Json(Shaped::new(MemorySearchResponse { items, error: None }, fields))
+    }
+
+    #[tool(
+        description = "Search long-term memory by category. Returns all memory entries in the given category (e.g. 'moltbook', 'work', 'personal', 'security').",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_memory_by_category(
+        &self,
+        Parameters(SearchMemoryByCategoryRequest { category, fields }): Parameters<SearchMemoryByCategoryRequest>,
+    ) -> Json<Shaped<MemorySearchResponse>> {
+        if category.is_empty() {
+            return Json(Shaped::new(MemorySearchResponse { items: Vec::new(), error: None }, None));
+        }
+
+        let items = self
+            .query(|db| {
+                let mut entries = memories::in_category(db, &category, self.config.timezone)?;
+                if !self.config.read_only {
+                    memories::record_access(db, &entries);
+                }
+                attachments::link(db, &mut entries, self.config.timezone)?;
+                Ok(entries)
+            })
+            .unwrap_or_default();
+        Json(Shaped::new(MemorySearchResponse { items, error: None }, fields))
+    }
+
+    #[tool(
+        description = "Get the pinned memories: standing facts about the user such as their name, timezone or coding style, most important first. Cheap enough to call at the start of every session.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_pinned_memories(&self) -> Json<MemorySearchResponse> {
+        let items = self
+            .query(|db| {
+                let mut pinned = memories::pinned(db, self.config.timezone)?;
+                if !self.config.read_only {
+                    memories::record_access(db, &pinned);
+                }
+                attachments::link(db, &mut pinned, self.config.timezone)?;
+                Ok(pinned)
+            })
+            .unwrap_or_default();
+        Json(MemorySearchResponse { items, error: None })
+    }
+
+    #[tool(
+        description = "Pin a memory so it tops every search_memory result and get_pinned_memories returns it, or unpin it. Pin standing facts the user shouldn't have to search for, such as their name, timezone or coding style.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn pin_memory(
+        &self,
+        Parameters(PinMemoryRequest { memory_id, pinned }): Parameters<PinMemoryRequest>,
+    ) -> Json<PinMemoryResponse> {
+        match self.query(|db| memories::set_pinned(db, memory_id, pinned.unwrap_or(true))) {
+            Ok(()) => Json(PinMemoryResponse { success: true, error: None }),
+            Err(e) => Json(PinMemoryResponse { success: false, error: Some(e.describe("Failed to update memory")) }),
+        }
+    }
+
+    #[tool(
+        description = "Store a file with long-term memory, such as a diagram, a screenshot or a config file, optionally attached to a memory entry so searches returning the memory list it. Pass text, base64-encoded bytes, or the path of a file in the server's attachments directory to reference instead. Identical content is stored once. Read it back with get_attachment.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn store_attachment(
+        &self,
+        Parameters(StoreAttachmentRequest {
+            name,
+            text,
+            base64,
+            file_path,
+            mime_type,
+            memory_id,
+            message_id,
+        }): Parameters<StoreAttachmentRequest>,
+    ) -> Json<StoreAttachmentResponse> {
+        let failed = |error: String| Json(StoreAttachmentResponse { success: false, attachment: None, error: Some(error) });
+        let content = match (text, base64, file_path) {
+            (Some(text), None, None) => Content::Stored(text.into_bytes()),
+            (None, Some(encoded), None) => match BASE64.decode(encoded.trim()) {
+                Ok(bytes) => Content::Stored(bytes),
+                Err(e) => return failed(format!("base64 is not valid base64: {}", e)),
+            },
+            (None, None, Some(path)) => {
+                match confined_dir(self.config.attachments_dir.as_deref(), Some(&path), "file_path", "MCP_LUNA_ATTACHMENTS_DIR") {
+                    Ok(path) => Content::File(path),
+                    Err(e) => return failed(format!("{:#}", e)),
+                }
+            }
+            _ => return failed("Pass exactly one of text, base64 or file_path".to_string()),
+        };
+        let attachment = NewAttachment { name, mime_type, content, memory_id, message_id };
+        let files = self.config.attachments_dir.as_deref();
+        match self.with_db(|db| attachments::store(db, attachment, files, self.config.timezone)) {
+            Ok(Ok(attachment)) => Json(StoreAttachmentResponse { success: true, attachment: Some(attachment), error: None }),
+            Ok(Err(e)) => failed(format!("Failed to store attachment: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Read an attachment stored with store_attachment by its ID, as listed in a memory's attachments: text content as text, anything else base64-encoded.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_attachment(
+        &self,
+        Parameters(GetAttachmentRequest { attachment_id }): Parameters<GetAttachmentRequest>,
+    ) -> Json<GetAttachmentResponse> {
+        let failed = |error: String| Json(GetAttachmentResponse { error: Some(error), ..Default::default() });
+        let read = self.with_db(|db| -> Result<_> {
+            let attachment =
+                attachments::get(db, attachment_id, self.config.timezone)?.context("Attachment not found")?;
+            let (bytes, file_changed) = attachments::content(db, &attachment, self.config.attachments_dir.as_deref())?;
+            Ok((attachment, bytes, file_changed))
+        });
+        let (attachment, bytes, file_changed) = match read {
+            Ok(Ok(read)) => read,
+            Ok(Err(e)) => return failed(format!("Failed to read attachment: {:#}", e)),
+            Err(_) => return failed("Database open/lock error".to_string()),
+        };
+        let mut response = GetAttachmentResponse { file_changed, ..Default::default() };
+        match String::from_utf8(bytes) {
+            Ok(text) if attachments::is_text(&attachment.mime_type) => response.text = Some(text),
+            Ok(text) => response.base64 = Some(BASE64.encode(text)),
+            Err(e) => response.base64 = Some(BASE64.encode(e.into_bytes())),
+        }
+        response.attachment = Some(attachment);
+        Json(response)
+    }
+
+    #[tool(
+        description = "Set a user preference: an exact key-value setting such as editor=helix or timezone=Europe/Warsaw, read back by key with get_preference. Use it instead of store_memory for settings with a single current value; setting a key again replaces its value.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn set_preference(
+        &self,
+        Parameters(SetPreferenceRequest { key, value }): Parameters<SetPreferenceRequest>,
+    ) -> Json<Preference> {
+        let preference = self
+            .with_db(|db| preferences::set(db, &key, &value))
+            .and_then(|preference| preference);
+        Json(preference.unwrap_or_else(|e| Preference {
+            key,
+            value,
+            error: Some(format!("Failed to set preference: {:#}", e)),
+            ..Default::default()
+        }))
+    }
+
+    #[tool(
+        description = "Get the value of a user preference by its exact key (e.g. 'editor'). Call list_preferences to see which keys are set.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_preference(
+        &self,
+        Parameters(GetPreferenceRequest { key }): Parameters<GetPreferenceRequest>,
+    ) -> Json<Preference> {
+        // Databases the server never wrote to have no preferences table
+        let preference = self
+            .with_db(|db| preferences::get(db, &key))
+            .and_then(|preference| preference)
+            .ok()
+            .flatten();
+        Json(preference.unwrap_or_else(|| Preference {
+            error: Some(format!("No preference named '{}'", key.trim())),
+            key,
+            ..Default::default()
+        }))
+    }
+
+    #[tool(
+        description = "List every user preference (exact key-value settings such as editor=helix), by key.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_preferences(&self) -> Json<PreferencesResponse> {
+        let items = self
+            .with_db(preferences::list)
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(PreferencesResponse { items })
+    }
+
+    #[tool(
+        description = "Write working notes, such as an unfinished plan, to a scratchpad named after the session or topic, so they survive a restart without going into long-term memory. Replaces the notes unless append is set. Scratchpads expire a week after their last write by default.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn scratchpad_write(
+        &self,
+        Parameters(ScratchpadWriteRequest { name, content, append, ttl_hours }): Parameters<ScratchpadWriteRequest>,
+    ) -> Json<ScratchpadResponse> {
+        let Some(name) = name.or(self.scope().scratchpad) else {
+            let error = "Pass a name, or set a working scratchpad with set_session_scope".to_string();
+            return Json(ScratchpadResponse { items: Vec::new(), error: Some(error) });
+        };
+        let written = self
+            .with_db(|db| scratchpad::write(db, &name, &content, append, ttl_hours))
+            .and_then(|entry| entry);
+        Json(match written {
+            Ok(entry) => ScratchpadResponse { items: vec![entry], error: None },
+            Err(e) => ScratchpadResponse { items: Vec::new(), error: Some(format!("Failed to write scratchpad: {:#}", e)) },
+        })
+    }
+
+    #[tool(
+        description = "Read a scratchpad written by scratchpad_write, e.g. to pick up a plan after a restart. Without a name, lists every scratchpad, most recently written first.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn scratchpad_read(
+        &self,
+        Parameters(ScratchpadReadRequest { name }): Parameters<ScratchpadReadRequest>,
+    ) -> Json<ScratchpadResponse> {
+        // Databases the server never wrote to have no scratchpad table
+        let Some(name) = name.or(self.scope().scratchpad) else {
+            let items = self
+                .with_db(scratchpad::list)
+                .and_then(|items| items)
+                .unwrap_or_default();
+            return Json(ScratchpadResponse { items, error: None });
+        };
+        let entry = self
+            .with_db(|db| scratchpad::read(db, &name))
+            .and_then(|entry| entry)
+            .ok()
+            .flatten();
+        Json(match entry {
+            Some(entry) => ScratchpadResponse { items: vec![entry], error: None },
+            None => ScratchpadResponse { items: Vec::new(), error: Some(format!("No scratchpad named '{}'", name.trim())) },
+        })
+    }
+
+    #[tool(
+        description = "Set defaults for the rest of this session: a profile that conversation listings and searches stay within, and a working scratchpad for scratchpad_write and scratchpad_read without a name. Other sessions are unaffected. Returns the scope now in effect; call it without arguments to check it.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn set_session_scope(
+        &self,
+        Parameters(SetSessionScopeRequest { profile, scratchpad }): Parameters<SetSessionScopeRequest>,
+    ) -> Json<SessionScope> {
+        let mut scope = self.scope.lock().unwrap_or_else(|e| e.into_inner());
+        // Given values replace the current ones; blank ones clear them
+        let set = |current: &mut Option<String>, value: Option<String>| {
+            if let Some(value) = value {
+                *current = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+            }
+        };
+        set(&mut scope.profile, profile);
+        set(&mut scope.scratchpad, scratchpad);
+        Json(scope.clone())
+    }
+
+    #[tool(
+        description = "Show the scratchpad scratchpad_clear would delete and issue a single-use confirmation token for deleting it (valid for 5 minutes). Pass the token to scratchpad_clear; it is required when the server enforces confirmations.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn preview_scratchpad_clear(
+        &self,
+        Parameters(PreviewScratchpadClearRequest { name }): Parameters<PreviewScratchpadClearRequest>,
+    ) -> Json<ScratchpadClearPreview> {
+        let failed = |error: String| Json(ScratchpadClearPreview { error: Some(error), ..Default::default() });
+        match self.with_db(|db| scratchpad::read(db, &name)) {
+            Ok(Ok(Some(entry))) => {
+                let (token, expires_at) = self.confirmations.issue(&scratchpad_clear_action(&name));
+                Json(ScratchpadClearPreview {
+                    scratchpad: Some(entry),
+                    confirmation_token: Some(token),
+                    expires_at: Some(expires_at),
+                    error: None,
+                })
+            }
+            Ok(Ok(None)) => failed(format!("No scratchpad named '{}'", name.trim())),
+            Ok(Err(e)) => failed(format!("{:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Delete a scratchpad once its notes are no longer needed, e.g. when the plan is done. Call preview_scratchpad_clear first to check it and get a confirmation token. Clients that support elicitation ask the user to confirm first.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub async fn scratchpad_clear(
+        &self,
+        Parameters(ScratchpadClearRequest { name, confirmation_token }): Parameters<ScratchpadClearRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Json<ScratchpadClearResponse> {
+        let failed = |error: String| Json(ScratchpadClearResponse { success: false, error: Some(error) });
+        if let Err(error) = self.confirm(confirmation_token.as_deref(), &scratchpad_clear_action(&name), "preview_scratchpad_clear") {
+            return failed(error);
+        }
+        let entry = match self.with_db(|db| scratchpad::read(db, &name)) {
+            Ok(Ok(Some(entry))) => entry,
+            Ok(Ok(None)) => return failed(format!("No scratchpad named '{}'", name.trim())),
+            Ok(Err(e)) => return failed(format!("{:#}", e)),
+            Err(_) => return failed("Database open/lock error".to_string()),
+        };
+        let question = format!("Delete the scratchpad '{}' ({} characters of notes)?", entry.name, entry.content.chars().count());
+        if let Err(error) = confirm::ask_user(&context.peer, &question, self.config.require_elicitation).await {
+            return failed(error);
+        }
+        match self.with_db(|db| scratchpad::clear(db, &name)) {
+            Ok(Ok(true)) => Json(ScratchpadClearResponse { success: true, error: None }),
+            Ok(Ok(false)) => failed(format!("No scratchpad named '{}'", name.trim())),
+            Ok(Err(e)) => failed(format!("{:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Create a reminder for the user, due at a time such as 'tomorrow', 'in 3 days' or 'next friday'. list_due_reminders brings it up once it is due.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn create_reminder(
+        &self,
+        Parameters(CreateReminderRequest { content, due_at }): Parameters<CreateReminderRequest>,
+    ) -> Json<Reminder> {
+        let timezone = self.config.timezone;
+        let created = timezone.since(&due_at).and_then(|due_at| {
+            self.with_db(|db| reminders::create(db, &content, due_at, timezone))
+                .and_then(|reminder| reminder)
+        });
+        Json(created.unwrap_or_else(|e| Reminder {
+            content,
+            error: Some(format!("Failed to create reminder: {:#}", e)),
+            ..Default::default()
+        }))
+    }
+
+    #[tool(
+        description = "List open reminders that are due, earliest first, with how overdue each one is. Call it at the start of a session to bring up what the user wanted to be reminded of, and complete_reminder once it's dealt with.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_due_reminders(
+        &self,
+        Parameters(ListDueRemindersRequest { now, limit }): Parameters<ListDueRemindersRequest>,
+    ) -> Json<RemindersResponse> {
+        let due_by = match now.map(|now| self.config.timezone.until(&now)).transpose() {
+            Ok(due_by) => due_by.unwrap_or_else(db::unix_now),
+            Err(e) => return Json(RemindersResponse { items: Vec::new(), error: Some(format!("{:#}", e)) }),
+        };
+        let limit = limit.unwrap_or(50).min(500);
+        // Databases the server never wrote to have no reminders table
+        let items = self
+            .with_db(|db| reminders::due(db, due_by, limit, self.config.timezone))
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(RemindersResponse { items, error: None })
+    }
+
+    #[tool(
+        description = "Mark a reminder done so list_due_reminders stops returning it.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn complete_reminder(
+        &self,
+        Parameters(CompleteReminderRequest { reminder_id }): Parameters<CompleteReminderRequest>,
+    ) -> Json<CompleteReminderResponse> {
+        let failed = |error: String| Json(CompleteReminderResponse { success: false, error: Some(error) });
+        match self.with_db(|db| reminders::complete(db, reminder_id)) {
+            Ok(Ok(true)) => Json(CompleteReminderResponse { success: true, error: None }),
+            Ok(Ok(false)) => failed("Reminder not found or already completed".to_string()),
+            Ok(Err(e)) => failed(format!("{:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Add an entry to the daily journal: a few sentences on what was worked on, decided or left open, in your own words. Keeps a running log separate from the verbatim conversations; read it back with get_journal.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn append_journal(
+        &self,
+        Parameters(AppendJournalRequest { date, text }): Parameters<AppendJournalRequest>,
+    ) -> Json<JournalEntry> {
+        let timezone = self.config.timezone;
+        let day = date
+            .map(|date| timezone.since(&date))
+            .transpose()
+            .map(|start| timezone.date(start.unwrap_or_else(db::unix_now)).unwrap_or_default());
+        let appended = day.and_then(|day| {
+            self.with_db(|db| journal::append(db, &day, &text, timezone))
+                .and_then(|entry| entry)
+        });
+        Json(appended.unwrap_or_else(|e| JournalEntry {
+            text,
+            error: Some(format!("Failed to append to journal: {:#}", e)),
+            ..Default::default()
+        }))
+    }
+
+    #[tool(
+        description = "Read the daily journal written with append_journal for a range of days, e.g. since 'last week', optionally only entries mentioning some keywords. Use it to recall what was worked on recently.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_journal(
+        &self,
+        Parameters(GetJournalRequest { since, until, keywords, limit }): Parameters<GetJournalRequest>,
+    ) -> Json<JournalResponse> {
+        let timezone = self.config.timezone;
+        let (since, until) = match date_range(timezone, since.as_ref(), until.as_ref()) {
+            // until is the end of its span; the day it closes is the one before
+            Ok((since, until)) => (since.and_then(|t| timezone.date(t)), until.and_then(|t| timezone.date(t - 1))),
+            Err(e) => return Json(JournalResponse { items: Vec::new(), error: Some(format!("{:#}", e)) }),
+        };
+        let limit = limit.unwrap_or(100).min(1000);
+        // Databases the server never wrote to have no journal table
+        let items = self
+            .with_db(|db| {
+                journal::entries(db, since.as_deref(), until.as_deref(), keywords.as_deref().unwrap_or_default(), limit, timezone)
+            })
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(JournalResponse { items, error: None })
+    }
+
+    #[tool(
+        description = "Bookmark a link worth keeping, such as one shared in the conversation, with a note on why it matters and tags. Fetches the page's title and description unless fetch_metadata is false. Saving a URL again updates its bookmark. Find bookmarks again with search_bookmarks.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = true)
+    )]
+    pub async fn save_bookmark(
+        &self,
+        Parameters(SaveBookmarkRequest { url, note, tags, fetch_metadata }): Parameters<SaveBookmarkRequest>,
+    ) -> Json<SaveBookmarkResponse> {
+        let failed = |error: String| Json(SaveBookmarkResponse { error: Some(error), ..Default::default() });
+        let url = match bookmarks::normalize_url(&url) {
+            Ok(url) => url,
+            Err(e) => return failed(format!("{:#}", e)),
+        };
+        let (metadata, fetch_error) = if fetch_metadata.unwrap_or(true) {
+            let (page, allow_private) = (url.clone(), self.config.fetch_private_urls);
+            match tokio::task::spawn_blocking(move || bookmarks::fetch_metadata(&page, allow_private)).await {
+                Ok(Ok(metadata)) => (Some(metadata), None),
+                Ok(Err(e)) => (None, Some(format!("{:#}", e))),
+                Err(e) => (None, Some(e.to_string())),
+            }
+        } else {
+            (None, None)
+        };
+        let update = BookmarkUpdate { url, note, tags: tags.unwrap_or_default(), metadata };
+        match self.with_db(|db| bookmarks::save(db, update, self.config.timezone)) {
+            Ok(Ok(bookmark)) => Json(SaveBookmarkResponse { success: true, bookmark: Some(bookmark), fetch_error, error: None }),
+            Ok(Err(e)) => failed(format!("Failed to save bookmark: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Search the links saved with save_bookmark by keywords in their URL, page title, description, note or tags, optionally only those with some tags. Without keywords, lists the most recently saved bookmarks.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_bookmarks(
+        &self,
+        Parameters(SearchBookmarksRequest { keywords, tags, limit }): Parameters<SearchBookmarksRequest>,
+    ) -> Json<BookmarksResponse> {
+        let limit = limit.unwrap_or(20).clamp(1, 200);
+        let search = |db: &rusqlite::Connection| {
+            let keywords = keywords.as_deref().unwrap_or_default();
+            let bookmarks = bookmarks::search(db, keywords, tags.as_deref().unwrap_or_default(), limit, self.config.timezone)?;
+            if !keywords.is_empty() {
+                self.record_search(db, "search_bookmarks", &keywords.join(" "), bookmarks.len());
+            }
+            Ok::<_, anyhow::Error>(bookmarks)
+        };
+        // Databases the server never wrote to have no bookmarks table
+        let items = self.with_db(search).and_then(|items| items).unwrap_or_default();
+        Json(BookmarksResponse { items, error: None })
+    }
+
+    #[tool(
+        description = "Save a code snippet or command you gave the user, with its language and a description of what it does, so it can be found with search_snippets instead of being written again.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn store_snippet(
+        &self,
+        Parameters(StoreSnippetRequest { code, language, description }): Parameters<StoreSnippetRequest>,
+    ) -> Json<StoreSnippetResponse> {
+        let failed = |error: String| Json(StoreSnippetResponse { success: false, snippet: None, error: Some(error) });
+        match self.with_db(|db| snippets::store(db, &code, &language, &description, self.config.timezone)) {
+            Ok(Ok(snippet)) => Json(StoreSnippetResponse { success: true, snippet: Some(snippet), error: None }),
+            Ok(Err(e)) => failed(format!("Failed to store snippet: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Find code snippets and commands saved with store_snippet by words or identifiers in their description or code, optionally in one language. Identifiers match by their parts: 'user name' finds getUserName and get_user_name. Check it before writing a command or snippet the user may have been given before.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_snippets(
+        &self,
+        Parameters(SearchSnippetsRequest { query, language, limit }): Parameters<SearchSnippetsRequest>,
+    ) -> Json<SnippetsResponse> {
+        let query = query.unwrap_or_default();
+        let limit = limit.unwrap_or(10).clamp(1, 100);
+        let search = |db: &rusqlite::Connection| {
+            let snippets = snippets::search(db, &query, language.as_deref(), limit, self.config.timezone)?;
+            if !query.trim().is_empty() {
+                self.record_search(db, "search_snippets", query.trim(), snippets.len());
+            }
+            Ok::<_, anyhow::Error>(snippets)
+        };
+        // Databases the server never wrote to have no snippets table
+        let items = self.with_db(search).and_then(|items| items).unwrap_or_default();
+        Json(SnippetsResponse { items, error: None })
+    }
+
+    #[tool(
+        description = "Record a structured fact as a subject–predicate–object triple, e.g. (\"user's dog\", 'name', 'Rex') or ('user', 'lives in', 'Kraków'), with how confident it is and its source. Use it for single-valued details the user may ask about exactly; query_facts finds them. Asserting a known triple again updates its confidence.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn assert_fact(
+        &self,
+        Parameters(AssertFactRequest { subject, predicate, object, confidence, source }): Parameters<AssertFactRequest>,
+    ) -> Json<AssertFactResponse> {
+        let failed = |error: String| Json(AssertFactResponse { error: Some(error), ..Default::default() });
+        let fact = NewFact { subject, predicate, object, confidence: confidence.unwrap_or(1.0), source };
+        let asserted = self.with_db(|db| {
+            let (fact, already_known) = facts::assert(db, fact, self.config.timezone)?;
+            let conflicts = conflicts::for_fact(db, &fact, self.config.timezone)?;
+            Ok::<_, anyhow::Error>((fact, already_known, conflicts))
+        });
+        match asserted {
+            Ok(Ok((fact, already_known, conflicts))) => {
+                Json(AssertFactResponse { success: true, fact: Some(fact), already_known, conflicts, error: None })
+            }
+            Ok(Err(e)) => failed(format!("Failed to assert fact: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Look up facts recorded with assert_fact by any of subject, predicate and object, matched exactly ignoring case and spacing. E.g. subject \"user's dog\" and predicate 'name' answers 'what is my dog called'; only an object answers 'who or what is in Kraków'. Most confident first.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn query_facts(
+        &self,
+        Parameters(QueryFactsRequest { subject, predicate, object, min_confidence, limit }): Parameters<QueryFactsRequest>,
+    ) -> Json<FactsResponse> {
+        let query = FactQuery {
+            subject: subject.as_deref(),
+            predicate: predicate.as_deref(),
+            object: object.as_deref(),
+            min_confidence,
+        };
+        let limit = limit.unwrap_or(50).clamp(1, 500);
+        // Databases the server never wrote to have no facts table
+        let items = self
+            .with_db(|db| facts::query(db, &query, limit, self.config.timezone))
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(FactsResponse { items, error: None })
+    }
+
+    #[tool(
+        description = "List conflicts: memories stored with the same key, or facts with the same subject and predicate, whose values differ, e.g. 'user editor: vim' then 'user editor: helix'. Open ones first, newest first. Settle each with resolve_conflict so the superseded value stops being recalled.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_conflicts(
+        &self,
+        Parameters(ListConflictsRequest { include_resolved, limit }): Parameters<ListConflictsRequest>,
+    ) -> Json<ConflictsResponse> {
+        let limit = limit.unwrap_or(50).clamp(1, 500);
+        // Databases the server never wrote to have no conflicts table
+        let items = self
+            .with_db(|db| conflicts::list(db, include_resolved, limit, self.config.timezone))
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(ConflictsResponse { items, error: None })
+    }
+
+    #[tool(
+        description = "Show the memory or fact resolve_conflict would delete when keeping a side, and issue a single-use confirmation token for it (valid for 5 minutes). Pass the token to resolve_conflict; it is required when the server enforces confirmations.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn preview_resolve_conflict(
+        &self,
+        Parameters(PreviewResolveConflictRequest { conflict_id, keep }): Parameters<PreviewResolveConflictRequest>,
+    ) -> Json<ResolveConflictPreview> {
+        let conflict = match self.with_db(|db| conflicts::get_open(db, conflict_id, self.config.timezone)) {
+            Ok(Ok(conflict)) => conflict,
+            Ok(Err(e)) => return Json(ResolveConflictPreview { error: Some(format!("{:#}", e)), ..Default::default() }),
+            Err(_) => return Json(ResolveConflictPreview { error: Some("Database open/lock error".to_string()), ..Default::default() }),
+        };
+        let retired = conflicts::retired(&conflict, keep).map(|(id, value)| (id, value.to_string()));
+        let (token, expires_at) = match &retired {
+            Some((id, _)) => {
+                let (token, expires_at) = self.confirmations.issue(&resolve_conflict_action(conflict_id, *id));
+                (Some(token), Some(expires_at))
+            }
+            None => (None, None),
+        };
+        Json(ResolveConflictPreview {
+            conflict: Some(conflict),
+            deleted_id: retired.as_ref().map(|(id, _)| *id),
+            deleted_value: retired.map(|(_, value)| value),
+            confirmation_token: token,
+            expires_at,
+            error: None,
+        })
+    }
+
+    #[tool(
+        description = "Resolve a conflict from list_conflicts, store_memory or assert_fact: keep 'new' to delete the earlier memory or fact, 'existing' to delete the newer one, or 'both' when both hold. Other open conflicts over a deleted side are settled with it. Call preview_resolve_conflict first to check what would be deleted and get a confirmation token. Clients that support elicitation ask the user to confirm a deletion first.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub async fn resolve_conflict(
+        &self,
+        Parameters(ResolveConflictRequest { conflict_id, keep, confirmation_token }): Parameters<ResolveConflictRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Json<ResolveConflictResponse> {
+        let failed = |error: String| Json(ResolveConflictResponse { error: Some(error), ..Default::default() });
+        let conflict = match self.with_db(|db| conflicts::get_open(db, conflict_id, self.config.timezone)) {
+            Ok(Ok(conflict)) => conflict,
+            Ok(Err(e)) => return failed(format!("Failed to resolve conflict: {:#}", e)),
+            Err(_) => return failed("Database open/lock error".to_string()),
+        };
+        // Keeping both deletes nothing, so there is nothing to confirm
+        if let Some((deleted_id, value)) = conflicts::retired(&conflict, keep) {
+            let action = resolve_conflict_action(conflict_id, deleted_id);
+            if let Err(error) = self.confirm(confirmation_token.as_deref(), &action, "preview_resolve_conflict") {
+                return failed(error);
+            }
+            let question = format!("Resolve the conflict over \"{}\" by deleting this for good?\n\n{}", conflict.key, value);
+            if let Err(error) = confirm::ask_user(&context.peer, &question, self.config.require_elicitation).await {
+                return failed(error);
+            }
+        }
+        match self.with_db(|db| conflicts::resolve(db, conflict_id, keep, self.config.timezone)) {
+            Ok(Ok((conflict, deleted_id))) => {
+                Json(ResolveConflictResponse { success: true, conflict: Some(conflict), deleted_id, error: None })
+            }
+            Ok(Err(e)) => failed(format!("Failed to resolve conflict: {:#}", e)),
+            Err(_) => failed("Database open/lock error".to_string()),
+        }
+    }
+
+    #[tool(
+        description = "Remember a person the user mentions: their aliases, relationship to the user and notes about them. Updates the person if they're already known by that name or alias, adding aliases and appending notes. Prefer it over store_memory for facts about people.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn remember_person(
+        &self,
+        Parameters(RememberPersonRequest { name, aliases, relationship, notes, message_id }): Parameters<RememberPersonRequest>,
+    ) -> Json<PersonResponse> {
+        let update = PersonUpdate {
+            aliases: aliases.as_deref().unwrap_or_default(),
+            relationship: relationship.as_deref(),
+            notes: notes.as_deref(),
+            message_id,
+        };
+        Json(match self.with_db(|db| people::remember(db, &name, update)).and_then(|person| person) {
+            Ok(person) => PersonResponse { person: Some(person), ..Default::default() },
+            Err(e) => PersonResponse { error: Some(format!("Failed to remember person: {:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "Look up a person by name or alias: who they are to the user, notes about them, and the latest messages mentioning them. Answers 'who is Marek and what did we discuss about him'.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_person(
+        &self,
+        Parameters(GetPersonRequest { name, mentions_limit }): Parameters<GetPersonRequest>,
+    ) -> Json<PersonResponse> {
+        let mentions_limit = mentions_limit.unwrap_or(10).min(50);
+        let response = self.with_db(|db| -> Result<PersonResponse> {
+            // Databases the server never wrote to have no people table
+            let Some(person) = people::get(db, &name).ok().flatten() else {
+                return Ok(PersonResponse { error: Some(format!("No person named '{}'", name.trim())), ..Default::default() });
+            };
+            let names: Vec<String> = std::iter::once(person.name.clone()).chain(person.aliases.iter().cloned()).collect();
+            let mentions = if mentions_limit > 0 {
+                search::mentions(db, &names, mentions_limit, self.config.timezone)?
+            } else {
+                Vec::new()
+            };
+            Ok(PersonResponse { person: Some(person), mentions, error: None })
+        });
+        Json(match response.and_then(|response| response) {
+            Ok(response) => response,
+            Err(e) => PersonResponse { error: Some(format!("Lookup failed: {:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "Find people remembered with remember_person whose name, aliases, relationship or notes contain some text, e.g. 'coworker' or 'Kraków'. Without a query, lists everyone.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn search_people(
+        &self,
+        Parameters(SearchPeopleRequest { query, limit }): Parameters<SearchPeopleRequest>,
+    ) -> Json<PeopleResponse> {
+        let limit = limit.unwrap_or(50).min(500);
+        let items = self
+            .with_db(|db| people::search(db, &query, limit))
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(PeopleResponse { items })
+    }
+
+    #[tool(
+        description = "Create a project to scope recall to one codebase or effort. Its keywords (default: its name) pick out its conversations for the project filter of search_conversations and advanced_search; memories join it through assign_memory_to_project.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn create_project(
+        &self,
+        Parameters(CreateProjectRequest { name, description, keywords }): Parameters<CreateProjectRequest>,
+    ) -> Json<Project> {
+        let created = self
+            .with_db(|db| projects::create(db, &name, description.as_deref(), keywords.as_deref().unwrap_or_default()))
+            .and_then(|project| project);
+        Json(created.unwrap_or_else(|e| Project {
+            name,
+            error: Some(format!("Failed to create project: {:#}", e)),
+            ..Default::default()
+        }))
+    }
+
+    #[tool(
+        description = "List projects with their keywords and how many memories each holds, by name.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_projects(&self) -> Json<ProjectsResponse> {
+        // Databases the server never wrote to have no projects table
+        let items = self
+            .with_db(projects::list)
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(ProjectsResponse { items })
+    }
+
+    #[tool(
+        description = "Assign a memory to a project so search_memory with that project finds it, or omit the project to take the memory out of its project.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn assign_memory_to_project(
+        &self,
+        Parameters(AssignMemoryToProjectRequest { memory_id, project }): Parameters<AssignMemoryToProjectRequest>,
+    ) -> Json<AssignMemoryToProjectResponse> {
+        let failed = |error: String| Json(AssignMemoryToProjectResponse { success: false, error: Some(error) });
+        let assigned = self.with_db(|db| -> Result<std::result::Result<(), String>> {
+            let project = match project.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+                Some(name) => match projects::get(db, name)? {
+                    Some(project) => Some(project),
+                    None => return Ok(Err(format!("No project named '{}'", name))),
+                },
+                None => None,
+            };
+            if !projects::assign_memory(db, memory_id, project.as_ref())? {
+                return Ok(Err("Memory entry not found".to_string()));
+            }
+            Ok(Ok(()))
+        });
+        match assigned.and_then(|assigned| assigned) {
+            Ok(Ok(())) => Json(AssignMemoryToProjectResponse { success: true, error: None }),
+            Ok(Err(error)) => failed(error),
+            Err(e) => failed(format!("{:#}", e)),
+        }
+    }
+
+    #[tool(
+        description = "Add a rule tagging conversations that mention a pattern, in their title or messages, with a project. Rules take effect when classify_conversations runs; tagged conversations then match the project filter of search_conversations and advanced_search.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = false, open_world_hint = false)
+    )]
+    pub fn add_project_rule(
+        &self,
+        Parameters(AddProjectRuleRequest { project, pattern, scope }): Parameters<AddProjectRuleRequest>,
+    ) -> Json<ProjectRule> {
+        let added = self.with_db(|db| -> Result<ProjectRule> {
+            let found = projects::get(db, &project)?
+                .with_context(|| format!("No project named '{}'", project.trim()))?;
+            projects::add_rule(db, &found, &pattern, scope)
+        });
+        Json(added.and_then(|rule| rule).unwrap_or_else(|e| ProjectRule {
+            project,
+            pattern,
+            scope,
+            error: Some(format!("Failed to add rule: {:#}", e)),
+            ..Default::default()
+        }))
+    }
+
+    #[tool(
+        description = "List the rules classify_conversations uses to tag conversations with projects, optionally for one project.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_project_rules(
+        &self,
+        Parameters(ListProjectRulesRequest { project }): Parameters<ListProjectRulesRequest>,
+    ) -> Json<ProjectRulesResponse> {
+        // Databases the server never wrote to have no project tables
+        let listed = self.with_db(|db| -> Result<ProjectRulesResponse> {
+            let project = match project.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+                Some(name) => match projects::get(db, name)? {
+                    Some(project) => Some(project),
+                    None => return Ok(ProjectRulesResponse { error: Some(format!("No project named '{}'", name)), ..Default::default() }),
+                },
+                None => None,
+            };
+            Ok(ProjectRulesResponse { items: projects::rules(db, project.as_ref())?, error: None })
+        });
+        Json(listed.and_then(|listed| listed).unwrap_or_default())
+    }
+
+    #[tool(
+        description = "Tag past conversations with projects by applying the project rules (see add_project_rule) to every conversation's title and messages. Conversations already tagged stay tagged. Run it after adding rules, and now and then to tag new conversations; dry_run only reports the counts.",
+        annotations(read_only_hint = false, destructive_hint = false, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub fn classify_conversations(
+        &self,
+        Parameters(ClassifyConversationsRequest { project, dry_run }): Parameters<ClassifyConversationsRequest>,
+    ) -> Json<ClassifyConversationsReport> {
+        let classified = self.with_db(|db| -> Result<Vec<ProjectClassification>> {
+            let project = match project.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+                Some(name) => Some(projects::get(db, name)?.with_context(|| format!("No project named '{}'", name))?),
+                None => None,
+            };
+            let text = search::TextSearch::new(db, false, false);
+            let tx = db.unchecked_transaction()?;
+            let classified = projects::classify(&tx, project.as_ref(), |pattern| text.term(pattern))?;
+            // A dry run rolls back when the transaction drops
+            if !dry_run {
+                tx.commit()?;
+            }
+            Ok(classified)
+        });
+        Json(match classified.and_then(|classified| classified) {
+            Ok(projects) => ClassifyConversationsReport { projects, dry_run, error: None },
+            Err(e) => ClassifyConversationsReport { dry_run, error: Some(format!("Classification failed: {:#}", e)), ..Default::default() },
+        })
+    }
+
+    #[tool(
+        description = "List the categories long-term memory is filed under, with how many memories each holds and when the newest was stored, largest first. Check it before store_memory to reuse an existing category instead of inventing a new name for the same thing.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn list_memory_categories(&self) -> Json<MemoryCategoriesResponse> {
+        Json(self.query(|db| memories::categories(db, self.config.timezone)).unwrap_or_default())
+    }
+
+    #[tool(
+        description = "Show how many memories rename_category would move and whether it would merge them into an existing category, and for a merge issue a single-use confirmation token (valid for 5 minutes). Pass the token to rename_category; it is required for merges when the server enforces confirmations.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn preview_rename_category(
+        &self,
+        Parameters(PreviewRenameCategoryRequest { old_name, new_name }): Parameters<PreviewRenameCategoryRequest>,
+    ) -> Json<RenameCategoryPreview> {
+        let (old_name, new_name) = (old_name.trim(), new_name.trim());
+        match self.query(|db| memories::preview_rename_category(db, old_name, new_name)) {
+            Ok(preview) if preview.merged => {
+                let (token, expires_at) = self.confirmations.issue(&rename_category_action(old_name, new_name));
+                Json(RenameCategoryPreview { confirmation_token: Some(token), expires_at: Some(expires_at), ..preview })
+            }
+            Ok(preview) => Json(preview),
+            Err(e) => Json(RenameCategoryPreview { error: Some(e.describe("Preview failed")), ..Default::default() }),
+        }
+    }
+
+    #[tool(
+        description = "Rename a long-term memory category, moving all of its memories at once. With merge, folds it into a category that already exists, e.g. 'prefs' into 'preferences'; call preview_rename_category first to check it and get a confirmation token, and clients that support elicitation ask the user first. Check list_memory_categories for the names in use.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub async fn rename_category(
+        &self,
+        Parameters(RenameCategoryRequest { old_name, new_name, merge, confirmation_token }): Parameters<RenameCategoryRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Json<RenameCategoryResponse> {
+        let failed = |error: String| Json(RenameCategoryResponse { error: Some(error), ..Default::default() });
+        let (old_name, new_name) = (old_name.trim(), new_name.trim());
+        if old_name.is_empty() || new_name.is_empty() {
+            return failed("old_name and new_name must not be empty".to_string());
+        }
+        if old_name == new_name {
+            return failed("old_name and new_name are the same".to_string());
+        }
+        // A plain rename can be renamed back; a merge can't be told apart afterwards
+        if merge {
+            let preview = match self.query(|db| memories::preview_rename_category(db, old_name, new_name)) {
+                Ok(preview) => preview,
+                Err(e) => return failed(e.describe("Rename failed")),
+            };
+            if preview.merged {
+                let action = rename_category_action(old_name, new_name);
+                if let Err(error) = self.confirm(confirmation_token.as_deref(), &action, "preview_rename_category") {
+                    return failed(error);
+                }
+                let question = format!("Merge the {} memories in '{}' into '{}' for good?", preview.renamed, old_name, new_name);
+                if let Err(error) = confirm::ask_user(&context.peer, &question, self.config.require_elicitation).await {
+                    return failed(error);
+                }
+            }
+        }
+        match self.query(|db| memories::rename_category(db, old_name, new_name, merge)) {
+            Ok(response) => Json(response),
+            Err(e) => failed(e.describe("Rename failed")),
+        }
+    }
+
+    #[tool(
+        description = "Build a curation checklist for long-term memory: memories never returned by a search, old memories not retrieved recently, memories without a category, and near-duplicate pairs. Use it periodically to suggest deleting or merging memories with the user.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_memory_hygiene_report(
+        &self,
+        Parameters(MemoryHygieneReportRequest {
+            stale_after_days,
+            similarity_threshold,
+            limit,
+        }): Parameters<MemoryHygieneReportRequest>,
+    ) -> Json<MemoryHygieneReport> {
+        let stale_before = db::unix_now() - stale_after_days.unwrap_or(180) as i64 * 24 * 60 * 60;
+        let threshold = similarity_threshold.unwrap_or(0.8).clamp(0.0, 1.0);
+        let limit = limit.unwrap_or(50).min(500) as usize;
+        let Ok(memories) = self.query(|db| memories::usage(db, self.config.timezone)) else {
+            return Json(MemoryHygieneReport {
+                total_memories: 0,
+                never_retrieved: Vec::new(),
+                stale: Vec::new(),
+                uncategorized: Vec::new(),
+                near_duplicates: Vec::new(),
+            });
+        };
+
+        // Pairwise comparison is quadratic; only look at the most recent memories
+        let tokens: Vec<_> = memories
+            .iter()
+            .rev()
+            .take(MAX_DUPLICATE_SCAN)
+            .map(|m| (m, text::token_set(&m.entry.content)))
+            .collect();
+        let mut near_duplicates = Vec::new();
+        for (i, (first, first_tokens)) in tokens.iter().enumerate() {
+            for (second, second_tokens) in &tokens[i + 1..] {
+                let similarity = text::jaccard(first_tokens, second_tokens);
+                if similarity >= threshold {
+                    near_duplicates.push(NearDuplicateMemories {
+                        first: first.entry.clone(),
+                        second: second.entry.clone(),
+                        similarity,
+                    });
+                }
+            }
+        }
+        near_duplicates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        near_duplicates.truncate(limit);
+
+        let total_memories = memories.len() as i64;
+        let mut never_retrieved = Vec::new();
+        let mut stale = Vec::new();
+        let mut uncategorized = Vec::new();
+        for memory in memories {
+            let last_seen = memory.last_accessed_at.unwrap_or(memory.entry.created_at);
+            if memory.entry.category.as_deref().is_none_or(|c| c.trim().is_empty()) && uncategorized.len() < limit {
+                uncategorized.push(memory.clone());
+            }
+            if last_seen < stale_before && stale.len() < limit {
+                stale.push(memory.clone());
+            }
+            if memory.access_count == 0 && never_retrieved.len() < limit {
+                never_retrieved.push(memory);
+            }
+        }
+
+        Json(MemoryHygieneReport {
+            total_memories,
+            never_retrieved,
+            stale,
+            uncategorized,
+            near_duplicates,
+        })
+    }
+
+    #[tool(
+        description = "Find the memories most similar to a given one by word overlap, with a similarity score from 0.0 to 1.0 (the share of distinct words they have in common). Use it to spot duplicates before merging or deleting memories with the user.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn find_similar_memories(
+        &self,
+        Parameters(FindSimilarMemoriesRequest { memory_id, limit }): Parameters<FindSimilarMemoriesRequest>,
+    ) -> Json<SimilarMemoriesResponse> {
+        let limit = limit.unwrap_or(10).min(100) as usize;
+        Json(match self.query(|db| memories::similar(db, memory_id, limit, self.config.timezone)) {
+            Ok(items) => SimilarMemoriesResponse { items, error: None },
+            Err(e) => SimilarMemoriesResponse { items: Vec::new(), error: Some(e.to_string()) },
+        })
+    }
+
+    #[tool(
+        description = "Show how a memory changed: every version of it as created, after each update and as it was when deleted, newest first. Pass key instead to follow a value replaced by deleting and storing anew, such as a changed preference, or as_of to see memories as they were at a past time.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn get_memory_history(
+        &self,
+        Parameters(GetMemoryHistoryRequest { memory_id, key, as_of, limit }): Parameters<GetMemoryHistoryRequest>,
+    ) -> Json<MemoryHistoryResponse> {
+        let failed = |error: String| Json(MemoryHistoryResponse { items: Vec::new(), error: Some(error) });
+        let key = key.map(|k| k.trim().to_string()).filter(|k| !k.is_empty());
+        if memory_id.is_none() && key.is_none() && as_of.is_none() {
+            return failed("Give memory_id, key or as_of".to_string());
+        }
+        let as_of = match as_of.map(|as_of| self.config.timezone.until(&as_of)).transpose() {
+            Ok(as_of) => as_of,
+            Err(e) => return failed(e.to_string()),
+        };
+        let query = HistoryQuery { memory_id, key: key.as_deref(), as_of };
+        let limit = limit.unwrap_or(50).clamp(1, 500);
+        // Databases the server never wrote to have no memory_history table
+        let items = self
+            .with_db(|db| memory_history::versions(db, &query, limit, self.config.timezone))
+            .and_then(|items| items)
+            .unwrap_or_default();
+        Json(MemoryHistoryResponse { items, error: None })
+    }
+
+    #[tool(
+        description = "Show the memory entry delete_memory would remove and issue a single-use confirmation token for deleting it, with or without its history (valid for 5 minutes). Pass the token to delete_memory with the same forget_history; it is required when the server enforces confirmations.",
+        annotations(read_only_hint = true, open_world_hint = false)
+    )]
+    pub fn preview_delete_memory(
+        &self,
+        Parameters(PreviewDeleteMemoryRequest { memory_id, forget_history }): Parameters<PreviewDeleteMemoryRequest>,
+    ) -> Json<DeletePreview> {
+        let failed = |error: String| {
+            Json(DeletePreview {
+                memory: None,
+                confirmation_token: None,
+                expires_at: None,
+                error: Some(error),
+            })
+        };
+        match self.query(|db| memories::get(db, memory_id, self.config.timezone)) {
+            Ok(memory) => {
+                let action = delete_memory_action(memory_id, forget_history.unwrap_or(false));
+                let (token, expires_at) = self.confirmations.issue(&action);
+                Json(DeletePreview {
+                    memory: Some(memory),
+                    confirmation_token: Some(token),
+                    expires_at: Some(expires_at),
+                    error: None,
+                })
+            }
+            Err(e) => failed(e.describe("Failed to load memory")),
+        }
+    }
+
+    #[tool(
+        description = "THIS IS A TOOL TO FORGET, OR TO UPDATE(Delete and then create) THE MEMORY USE IT TO CORRECT YOUR MEMORIES. Delete a memory entry by its ID. Use this to remove outdated or incorrect information from long-term memory. get_memory_history keeps what the memory said unless forget_history is set, which erases its versions too; use that when the user wants it forgotten entirely. Call preview_delete_memory first to check the entry and get a confirmation token. Clients that support elicitation ask the user to confirm first.",
+        annotations(read_only_hint = false, destructive_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    pub async fn delete_memory(
+        &self,
+        Parameters(DeleteMemoryRequest { memory_id, forget_history, confirmation_token }): Parameters<DeleteMemoryRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Json<DeleteMemoryResponse> {
+        let forget_history = forget_history.unwrap_or(false);
+        let action = delete_memory_action(memory_id, forget_history);
+        if let Err(error) = self.confirm(confirmation_token.as_deref(), &action, "preview_delete_memory") {
+            return Json(DeleteMemoryResponse {
+                success: false,
+                error: Some(error),
+            });
+        }
+        let failed = |error: String| Json(DeleteMemoryResponse { success: false, error: Some(error) });
+        let content = match self.query(|db| memories::get(db, memory_id, self.config.timezone)) {
+            Ok(memory) => memory.content,
+            Err(e) => return failed(e.describe("Failed to load memory")),
+        };
+        let question = if forget_history {
+            format!("Delete this memory and erase its history for good?\n\n{}", content)
+        } else {
+            format!("Delete this memory? Its history keeps what it said.\n\n{}", content)
+        };
+        if let Err(error) = confirm::ask_user(&context.peer, &question, self.config.require_elicitation).await {
+            return failed(error);
+        }
+        let deleted = self.query(|db| {
+            let tx = db.unchecked_transaction()?;
+            memories::delete(&tx, memory_id)?;
+            attachments::delete_for_memory(&tx, memory_id)?;
+            if forget_history {
+                memory_history::forget(&tx, memory_id)?;
+            }
+            tx.commit()?;
+            Ok(())
+        });
+        match deleted {
+            Ok(()) => Json(DeleteMemoryResponse { success: true, error: None }),
+            Err(e) => failed(e.describe("Failed to delete memory")),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Topic clustering: a bird's-eye map of the history. Conversations become TF-IDF vectors over
+//! their user and assistant messages and are grouped by spherical k-means (cosine similarity).
+//! The latest clustering is kept in `topics` and `conversation_topics`.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+use crate::dates::Timezone;
+use crate::db::unix_now;
+use crate::models::{Topic, TopicClusteringReport, TopicConversation};
+use crate::text;
+
+/// Terms found in more than this share of conversations say nothing about a topic ("would", "thanks").
+const MAX_DOCUMENT_SHARE: f64 = 0.5;
+/// Vocabulary size cap; the terms found in the most conversations are kept.
+const MAX_VOCABULARY: usize = 5000;
+/// k-means stops after this many rounds even if assignments still change.
+const MAX_ITERATIONS: usize = 50;
+/// Terms listed per topic, most central first.
+const TOPIC_TERMS: usize = 10;
+/// Leading terms joined into a topic's label.
+const LABEL_TERMS: usize = 3;
+/// Conversations listed per topic, closest to its center first.
+const REPRESENTATIVES: usize = 3;
+/// Most topics one clustering may produce.
+pub const MAX_TOPICS: usize = 100;
+
+/// Sparse TF-IDF vector of unit length: (term index, weight) pairs.
+type Vector = Vec<(usize, f64)>;
+
+struct Document {
+    id: String,
+    title: String,
+    created_at: i64,
+    vector: Vector,
+}
+
+/// Number of topics when none is given: the rule of thumb sqrt(n / 2) for `n` conversations,
+/// between 2 and 30.
+pub fn default_count(conversations: usize) -> usize {
+    ((conversations as f64 / 2.0).sqrt().round() as usize).clamp(2, 30)
+}
+
+fn dot(vector: &Vector, centroid: &[f64]) -> f64 {
+    vector.iter().map(|(term, weight)| weight * centroid[*term]).sum()
+}
+
+fn normalize(values: &mut [f64]) {
+    let norm = values.iter().map(|v| v * v).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        values.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+/// TF-IDF vectors of every conversation, and the vocabulary their term indexes refer to.
+fn documents(conn: &Connection) -> Result<(Vec<Document>, Vec<String>)> {
+    let mut stmt = conn
+        .prepare(
+            r#"
+            SELECT c.id, c.title, c.created_at, m.content
+            FROM conversations c
+            LEFT JOIN messages m ON m.conversation_id = c.id AND m.role IN ('user', 'assistant')
+            ORDER BY c.created_at, c.id, m.id
+            "#,
+        )
+        .context("Failed to read conversations")?;
+    let mut rows = stmt.query([])?;
+    let mut conversations: Vec<(String, String, i64, HashMap<String, usize>)> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: String = row.get(0)?;
+        if conversations.last().is_none_or(|(last, ..)| *last != id) {
+            let title: String = row.get(1)?;
+            let counts = text::terms(&title).fold(HashMap::new(), |mut counts, term| {
+                *counts.entry(term).or_default() += 1;
+                counts
+            });
+            conversations.push((id, title, row.get(2)?, counts));
+        }
+        if let Some(content) = row.get::<_, Option<String>>(3)? {
+            let counts = &mut conversations.last_mut().expect("pushed above").3;
+            for term in text::terms(&content) {
+                *counts.entry(term).or_default() += 1;
+            }
+        }
+    }
+
+    let total = conversations.len();
+    let mut frequencies: HashMap<&str, usize> = HashMap::new();
+    for (.., counts) in &conversations {
+        for term in counts.keys() {
+            *frequencies.entry(term).or_default() += 1;
+        }
+    }
+    let max_frequency = (MAX_DOCUMENT_SHARE * total as f64).max(2.0);
+    let mut vocabulary: Vec<(&str, usize)> = frequencies
+        .into_iter()
+        .filter(|(_, frequency)| *frequency >= 2 && *frequency < total && *frequency as f64 <= max_frequency)
+        .collect();
+    vocabulary.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    vocabulary.truncate(MAX_VOCABULARY);
+    let index: HashMap<&str, (usize, f64)> = vocabulary
+        .iter()
+        .enumerate()
+        .map(|(i, (term, frequency))| (*term, (i, (total as f64 / *frequency as f64).ln())))
+        .collect();
+
+    let documents = conversations
+        .iter()
+        .map(|(id, title, created_at, counts)| {
+            let mut vector: Vector = counts
+                .iter()
+                .filter_map(|(term, count)| {
+                    index.get(term.as_str()).map(|(i, idf)| (*i, (1.0 + (*count as f64).ln()) * idf))
+                })
+                .collect();
+            vector.sort_by_key(|(i, _)| *i);
+            let norm = vector.iter().map(|(_, w)| w * w).sum::<f64>().sqrt();
+            vector.iter_mut().for_each(|(_, w)| *w /= norm);
+            Document { id: id.clone(), title: title.clone(), created_at: *created_at, vector }
+        })
+        .collect();
+    Ok((documents, vocabulary.into_iter().map(|(term, _)| term.to_string()).collect()))
+}
+
+/// Cluster all conversations into `count` topics (default: [`default_count`]), replacing the
+/// stored clustering. Conversations without distinctive terms stay unclustered.
+pub fn cluster(conn: &Connection, count: Option<usize>, timezone: Timezone) -> Result<TopicClusteringReport> {
+    let started = Instant::now();
+    let (documents, vocabulary) = documents(conn)?;
+    let total = documents.len();
+    let documents: Vec<Document> = documents.into_iter().filter(|d| !d.vector.is_empty()).collect();
+    anyhow::ensure!(documents.len() >= 2, "Clustering needs at least two conversations with text in common");
+    let count = count.unwrap_or_else(|| default_count(documents.len())).clamp(1, MAX_TOPICS).min(documents.len());
+
+    // Deterministic farthest-first seeding: start from the richest conversation, then repeatedly
+    // add the one least similar to every seed so far
+    let dense = |vector: &Vector| {
+        let mut centroid = vec![0.0; vocabulary.len()];
+        vector.iter().for_each(|(term, weight)| centroid[*term] = *weight);
+        centroid
+    };
+    let first = (0..documents.len()).max_by_key(|i| (documents[*i].vector.len(), std::cmp::Reverse(*i))).unwrap_or(0);
+    let mut centroids = vec![dense(&documents[first].vector)];
+    while centroids.len() < count {
+        let next = (0..documents.len())
+            .map(|i| (i, centroids.iter().map(|c| dot(&documents[i].vector, c)).fold(f64::MIN, f64::max)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        centroids.push(dense(&documents[next].vector));
+    }
+
+    let mut assignments = vec![usize::MAX; documents.len()];
+    let mut iterations = 0;
+    while iterations < MAX_ITERATIONS {
+        iterations += 1;
+        let mut changed = false;
+        for (document, assignment) in documents.iter().zip(assignments.iter_mut()) {
+            let best = (0..centroids.len())
+                .max_by(|a, b| dot(&document.vector, &centroids[*a]).total_cmp(&dot(&document.vector, &centroids[*b])).then(b.cmp(a)))
+                .unwrap_or(0);
+            changed |= *assignment != best;
+            *assignment = best;
+        }
+        if !changed {
+            break;
+        }
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let mut sum = vec![0.0; vocabulary.len()];
+            let mut members = 0;
+            for document in documents.iter().zip(&assignments).filter(|(_, a)| **a == cluster).map(|(d, _)| d) {
+                document.vector.iter().for_each(|(term, weight)| sum[*term] += weight);
+                members += 1;
+            }
+            // An emptied cluster keeps its old center and may win conversations back
+            if members > 0 {
+                normalize(&mut sum);
+                *centroid = sum;
+            }
+        }
+    }
+
+    let mut topics: Vec<(Topic, Vec<(String, f64)>)> = Vec::new();
+    for (cluster, centroid) in centroids.iter().enumerate() {
+        let mut members: Vec<(&Document, f64)> = documents
+            .iter()
+            .zip(&assignments)
+            .filter(|(_, a)| **a == cluster)
+            .map(|(d, _)| (d, dot(&d.vector, centroid)))
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        let mut terms: Vec<(usize, f64)> = centroid.iter().copied().enumerate().filter(|(_, w)| *w > 0.0).collect();
+        terms.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        let terms: Vec<String> = terms.into_iter().take(TOPIC_TERMS).map(|(i, _)| vocabulary[i].clone()).collect();
+        let first_at = members.iter().map(|(d, _)| d.created_at).min();
+        let last_at = members.iter().map(|(d, _)| d.created_at).max();
+        members.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.id.cmp(&b.0.id)));
+        let topic = Topic {
+            id: 0,
+            label: terms.iter().take(LABEL_TERMS).cloned().collect::<Vec<_>>().join(", "),
+            terms,
+            size: members.len(),
+            first_date: first_at.and_then(|t| timezone.date(t)),
+            last_date: last_at.and_then(|t| timezone.date(t)),
+            representatives: members
+                .iter()
+                .take(REPRESENTATIVES)
+                .map(|(d, similarity)| TopicConversation {
+                    conversation_id: d.id.clone(),
+                    title: d.title.clone(),
+                    created_at: d.created_at,
+                    created_at_iso: timezone.iso(d.created_at),
+                    similarity: *similarity,
+                })
+                .collect(),
+        };
+        topics.push((topic, members.into_iter().map(|(d, similarity)| (d.id.clone(), similarity)).collect()));
+    }
+    topics.sort_by(|a, b| b.0.size.cmp(&a.0.size).then_with(|| a.0.label.cmp(&b.0.label)));
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM conversation_topics", []).context("Failed to clear topics")?;
+    tx.execute("DELETE FROM topics", []).context("Failed to clear topics")?;
+    let now = unix_now();
+    for (i, (topic, members)) in topics.iter_mut().enumerate() {
+        topic.id = i as i64 + 1;
+        tx.execute(
+            "INSERT INTO topics (id, label, terms, size, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![topic.id, topic.label, serde_json::to_string(&topic.terms)?, topic.size as i64, now],
+        )
+        .context("Failed to store topic")?;
+        for (conversation_id, similarity) in members.iter() {
+            tx.execute(
+                "INSERT INTO conversation_topics (conversation_id, topic_id, similarity) VALUES (?1, ?2, ?3)",
+                rusqlite::params![conversation_id, topic.id, similarity],
+            )
+            .context("Failed to store topic")?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(TopicClusteringReport {
+        conversations: total,
+        unclustered: total - documents.len(),
+        iterations,
+        topics: topics.into_iter().map(|(topic, _)| topic).collect(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        error: None,
+    })
+}
@@ -0,0 +1,184 @@
+//! `merge-db` run against two database files: ID collisions, deduplication, memory columns and
+//! the trash.
+
+use std::path::{Path, PathBuf};
+
+use mcp_luna_history::config::Config;
+use mcp_luna_history::merge::{merge_database, MergeReport};
+use mcp_luna_history::{db, erase};
+use rusqlite::{params, Connection};
+
+/// 2025-01-01T00:00:00Z
+const JAN_2025: i64 = 1_735_689_600;
+
+/// Fresh directory holding the target and source databases of one test.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("luna-merge-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Database at `path` with Luna's conversation tables and this server's own.
+fn open(path: &Path) -> Connection {
+    db::init_conversation_schema(&Connection::open(path).unwrap()).unwrap();
+    db::open(path.to_str().unwrap(), &Config::default().fts_tokenizer).unwrap()
+}
+
+fn add_conversation(conn: &Connection, id: &str, title: &str, created_at: i64, messages: &[&str]) {
+    conn.execute(
+        "INSERT INTO conversations (id, title, created_at) VALUES (?1, ?2, ?3)",
+        params![id, title, created_at],
+    )
+    .unwrap();
+    for (n, content) in messages.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO messages (conversation_id, role, content, created_at) VALUES (?1, 'user', ?2, ?3)",
+            params![id, content, created_at + n as i64 * 60],
+        )
+        .unwrap();
+    }
+}
+
+fn messages(conn: &Connection, conversation_id: &str) -> Vec<String> {
+    let mut stmt = conn.prepare("SELECT content FROM messages WHERE conversation_id = ? ORDER BY id").unwrap();
+    stmt.query_map([conversation_id], |row| row.get(0)).unwrap().map(Result::unwrap).collect()
+}
+
+fn conversation_ids(conn: &Connection) -> Vec<String> {
+    let mut stmt = conn.prepare("SELECT id FROM conversations ORDER BY id").unwrap();
+    stmt.query_map([], |row| row.get(0)).unwrap().map(Result::unwrap).collect()
+}
+
+/// Target and source databases of one test, the source already closed when merging.
+struct Pair {
+    target: Connection,
+    source: PathBuf,
+}
+
+impl Pair {
+    fn new(name: &str, target: impl FnOnce(&Connection), source: impl FnOnce(&Connection)) -> Self {
+        let dir = scratch_dir(name);
+        let source_path = dir.join("source.db");
+        source(&open(&source_path));
+        let conn = open(&dir.join("target.db"));
+        target(&conn);
+        Self { target: conn, source: source_path }
+    }
+
+    fn merge(&mut self) -> MergeReport {
+        merge_database(&mut self.target, &self.source).unwrap()
+    }
+}
+
+#[test]
+fn colliding_conversation_ids_are_renamed_and_remerging_adds_nothing() {
+    let mut pair = Pair::new(
+        "collision",
+        |target| add_conversation(target, "c1", "Target", JAN_2025, &["kept"]),
+        |source| {
+            add_conversation(source, "c1", "Source", JAN_2025 + 3600, &["copied", "aside"]);
+            add_conversation(source, "c2", "New", JAN_2025, &["new"]);
+        },
+    );
+
+    let report = pair.merge();
+    assert_eq!(report.conversations_added, 2);
+    assert_eq!(report.conversations_renamed, 1);
+    assert_eq!(report.messages_added, 3);
+    assert_eq!(conversation_ids(&pair.target), ["c1", "c1-merged-1", "c2"]);
+    assert_eq!(messages(&pair.target, "c1"), ["kept"]);
+    assert_eq!(messages(&pair.target, "c1-merged-1"), ["copied", "aside"]);
+
+    let again = pair.merge();
+    assert_eq!(again.conversations_added, 0);
+    assert_eq!(again.conversations_renamed, 0);
+    assert_eq!(again.conversations_skipped, 2);
+    assert_eq!(again.messages_added, 0);
+    assert_eq!(conversation_ids(&pair.target), ["c1", "c1-merged-1", "c2"]);
+}
+
+#[test]
+fn shared_conversations_get_only_missing_messages() {
+    let mut pair = Pair::new(
+        "dedup",
+        |target| add_conversation(target, "c1", "Shared", JAN_2025, &["one"]),
+        |source| add_conversation(source, "c1", "Shared", JAN_2025, &["one", "two"]),
+    );
+
+    let report = pair.merge();
+    assert_eq!(report.conversations_updated, 1);
+    assert_eq!(report.messages_added, 1);
+    assert_eq!(messages(&pair.target, "c1"), ["one", "two"]);
+}
+
+#[test]
+fn memories_keep_their_columns_and_duplicates_are_skipped() {
+    let mut pair = Pair::new(
+        "memories",
+        |target| {
+            target
+                .execute("INSERT INTO memory (content, category, created_at) VALUES ('Likes tea', 'preference', ?1)", [JAN_2025])
+                .unwrap();
+        },
+        |source| {
+            source
+                .execute("INSERT INTO projects (name, keywords, created_at) VALUES ('Garden', '[\"roses\"]', ?1)", [JAN_2025])
+                .unwrap();
+            source
+                .execute("INSERT INTO memory (content, category, created_at) VALUES ('Likes tea', 'preference', ?1)", [JAN_2025])
+                .unwrap();
+            source
+                .execute(
+                    "INSERT INTO memory (content, category, importance, created_at, expires_at, pinned, key, project_id)
+                     VALUES ('Roses go south', 'fact', 8, ?1, ?2, 1, 'roses', 1)",
+                    params![JAN_2025, JAN_2025 * 2],
+                )
+                .unwrap();
+        },
+    );
+
+    let report = pair.merge();
+    assert_eq!(report.memories_added, 1);
+    assert_eq!(report.memories_skipped, 1);
+    let copied: (i64, i64, Option<i64>, bool, String, String, String) = pair
+        .target
+        .query_row(
+            "SELECT m.importance, m.created_at, m.expires_at, m.pinned, m.key, p.name, p.keywords
+             FROM memory m JOIN projects p ON p.id = m.project_id WHERE m.content = 'Roses go south'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?)),
+        )
+        .unwrap();
+    assert_eq!(copied, (8, JAN_2025, Some(JAN_2025 * 2), true, "roses".into(), "Garden".into(), "[\"roses\"]".into()));
+
+    let again = pair.merge();
+    assert_eq!(again.memories_added, 0);
+    assert_eq!(again.memories_skipped, 2);
+}
+
+#[test]
+fn trashed_conversations_stay_deleted() {
+    let mut pair = Pair::new(
+        "trash",
+        |target| {
+            add_conversation(target, "gone", "Deleted here", JAN_2025, &["old"]);
+            erase::trash(target, "gone").unwrap();
+            add_conversation(target, "there", "Deleted there", JAN_2025, &["old"]);
+        },
+        |source| {
+            add_conversation(source, "gone", "Deleted here", JAN_2025, &["old", "newer"]);
+            add_conversation(source, "there", "Deleted there", JAN_2025, &["old"]);
+            erase::trash(source, "there").unwrap();
+        },
+    );
+
+    let report = pair.merge();
+    assert_eq!(report.conversations_in_trash, 1);
+    assert_eq!(report.conversations_trashed, 1);
+    assert!(conversation_ids(&pair.target).is_empty());
+    let trash: i64 = pair.target.query_row("SELECT COUNT(*) FROM conversation_trash", [], |row| row.get(0)).unwrap();
+    assert_eq!(trash, 2);
+    assert!(erase::restore(&pair.target, "there").is_ok());
+    assert_eq!(messages(&pair.target, "there"), ["old"]);
+}
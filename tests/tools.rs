@@ -0,0 +1,853 @@
+//! Every tool called the way a client calls it: through the rmcp handler, over an in-process
+//! transport, against an in-memory database holding a couple of conversations.
+
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+use mcp_luna_history::backup::BackupConfig;
+use mcp_luna_history::config::Config;
+use mcp_luna_history::db;
+use mcp_luna_history::service::ConversationService;
+use rmcp::model::{CallToolRequestParam, CallToolResult, ReadResourceRequestParam, ResourceContents};
+use rmcp::service::RunningService;
+use rmcp::{RoleClient, ServiceExt};
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+
+/// 2025-01-01T00:00:00Z
+const JAN_2025: i64 = 1_735_689_600;
+
+/// Server started over an in-memory database, and the client connected to it.
+struct Harness {
+    client: RunningService<RoleClient, ()>,
+}
+
+impl Harness {
+    /// Every tool listed, history writes and admin tools included.
+    async fn start() -> Self {
+        Self::start_with(Config { admin_tools: true, history_writes: true, ..Config::default() }).await
+    }
+
+    async fn start_with(config: Config) -> Self {
+        let service = ConversationService::new_in_memory_with(config).expect("in-memory service");
+        insert_conversations(&Connection::open(service.db_path()).expect("fixture connection"));
+        Self::serve(service).await
+    }
+
+    /// Server over a database file at `path`, for tools that need one on disk.
+    async fn start_on_file(path: &Path, config: Config) -> Self {
+        let db = Connection::open(path).expect("fixture database");
+        db::init_conversation_schema(&db).expect("conversation schema");
+        insert_conversations(&db);
+        Self::serve(ConversationService::new(&path.to_string_lossy(), config).expect("service")).await
+    }
+
+    async fn serve(service: ConversationService) -> Self {
+        let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let server = service.serve(server_io).await?;
+            server.waiting().await?;
+            anyhow::Ok(())
+        });
+        let client = ().serve(client_io).await.expect("client handshake");
+        Self { client }
+    }
+
+    async fn tool_names(&self) -> BTreeSet<String> {
+        let tools = self.client.list_all_tools().await.expect("tools/list");
+        tools.into_iter().map(|tool| tool.name.to_string()).collect()
+    }
+
+    async fn call_raw(&self, name: &str, arguments: Value) -> CallToolResult {
+        let arguments = match arguments {
+            Value::Object(map) => Some(map),
+            _ => None,
+        };
+        self.client
+            .call_tool(CallToolRequestParam { name: name.to_string().into(), arguments, task: None })
+            .await
+            .unwrap_or_else(|e| panic!("{} failed at the protocol level: {}", name, e))
+    }
+
+    /// Structured response of a call that must succeed.
+    async fn call(&self, name: &str, arguments: Value) -> Value {
+        let result = self.call_raw(name, arguments).await;
+        assert_ne!(result.is_error, Some(true), "{} returned an error result: {:?}", name, result.content);
+        let response = result
+            .structured_content
+            .unwrap_or_else(|| panic!("{} returned no structured content", name));
+        assert!(response.get("error").is_none_or(Value::is_null), "{} reported an error: {}", name, response);
+        response
+    }
+}
+
+/// Three conversations: a Kubernetes troubleshooting thread with a failed tool call and reasoning,
+/// a follow-up on the same deployment, and a short cooking one with accented text. The deployment
+/// ones are under the "work" profile, the cooking one under "home".
+fn insert_conversations(db: &Connection) {
+    let conversations = [
+        ("c1", "Kubernetes deployment stuck", JAN_2025 - 7200, "work"),
+        ("c2", "Café recipes", JAN_2025 - 3600, "home"),
+        ("c3", "Helm chart for the deployment", JAN_2025 - 1800, "work"),
+    ];
+    for (id, title, created_at, profile) in conversations {
+        db.execute(
+            "INSERT INTO conversations (id, title, created_at, profile_name) VALUES (?1, ?2, ?3, ?4)",
+            params![id, title, created_at, profile],
+        )
+        .unwrap();
+    }
+    let messages = [
+        ("c1", "user", "My kubernetes deployment is stuck in a rollout, how do I debug it?", None, None, None),
+        ("c1", "assistant", "Start with kubectl describe on the deployment and check the pod events.", None, None, Some("The rollout probably waits on a failing readiness probe")),
+        ("c1", "tool", "kubectl rollout status", Some("shell"), Some("error"), None),
+        ("c1", "assistant", "The readiness probe fails, so the new pods never become ready.", None, None, None),
+        ("c1", "user", "Thanks, fixing the probe solved it. Remember that I deploy with helm.", None, None, None),
+        ("c2", "user", "Give me a café au lait recipe with a crème topping", None, None, None),
+        ("c2", "assistant", "Heat the milk, brew strong coffee and top it with whipped crème.", None, None, None),
+        ("c3", "user", "Package my kubernetes deployment as a helm chart", None, None, None),
+        ("c3", "assistant", "Move the deployment manifest into templates and the image tag into values.", None, None, None),
+    ];
+    for (n, (conversation_id, role, content, tool_name, tool_status, reasoning)) in messages.into_iter().enumerate() {
+        let (params_json, result_json) = match tool_name {
+            Some(_) => (Some(r#"{"command":"kubectl rollout status deploy/web"}"#), Some(r#"{"error":"timed out waiting for rollout"}"#)),
+            None => (None, None),
+        };
+        db.execute(
+            "INSERT INTO messages (conversation_id, role, content, created_at, tool_name, tool_status, tool_params_json, tool_result_json, reasoning_content)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![conversation_id, role, content, JAN_2025 - 7000 + n as i64 * 60, tool_name, tool_status, params_json, result_json, reasoning],
+        )
+        .unwrap();
+    }
+}
+
+/// Fresh directory for tools writing files.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("luna-tools-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Tools whose sample call fails in the response rather than as a failed call: the chunk token
+/// is unknown, the test client can't sample, and an in-memory database can't attach an archive.
+const ANSWERED_WITH_ERROR: &[&str] = &["get_conversation_chunk", "suggest_synonyms", "archive_conversations"];
+
+/// One call per tool, ordered so calls find what earlier ones created (memory 1, reminder 1, ...).
+fn sample_calls() -> Vec<(&'static str, Value)> {
+    vec![
+        ("health", json!({})),
+        ("get_database_info", json!({})),
+        ("search_conversations", json!({ "keywords": ["kubernetes"] })),
+        ("advanced_search", json!({ "keywords": ["readiness"] })),
+        ("search_conversation_titles", json!({ "query": "cafe" })),
+        ("list_conversations", json!({})),
+        ("get_conversation", json!({ "conversation_id": "c1" })),
+        ("get_conversation_chunk", json!({ "chunk_token": "unknown" })),
+        ("get_message", json!({ "message_id": 1 })),
+        ("get_key_messages", json!({ "conversation_id": "c1" })),
+        ("find_related_conversations", json!({ "conversation_id": "c1" })),
+        ("extract_keywords", json!({ "conversation_id": "c1" })),
+        ("suggest_title", json!({ "conversation_id": "c1" })),
+        ("update_title", json!({ "conversation_id": "c2", "title": "Coffee recipes" })),
+        ("preview_redact_message", json!({ "message_id": 2, "text": "kubectl" })),
+        ("redact_message", json!({ "message_id": 2, "text": "kubectl" })),
+        ("preview_delete_conversation", json!({ "conversation_id": "c3" })),
+        ("delete_conversation", json!({ "conversation_id": "c3" })),
+        ("restore_conversation", json!({ "conversation_id": "c3" })),
+        ("cluster_topics", json!({})),
+        ("tag_conversation", json!({ "conversation_id": "c1", "tags": ["work"] })),
+        ("list_tags", json!({})),
+        ("untag_conversation", json!({ "conversation_id": "c1", "tags": ["work"] })),
+        ("pin_conversation", json!({ "conversation_id": "c1" })),
+        ("unpin_conversation", json!({ "conversation_id": "c1" })),
+        ("search_tool_activity", json!({ "query": "rollout" })),
+        ("find_tool_calls", json!({ "tool_name": "shell" })),
+        ("search_failures", json!({ "query": "kubectl" })),
+        ("get_failure_stats", json!({})),
+        ("get_tool_usage_report", json!({})),
+        ("store_memory", json!({ "content": "User deploys Kubernetes apps with helm", "category": "preferences", "importance": 8 })),
+        ("search_memory", json!({ "keywords": ["helm"] })),
+        ("search_memory_by_category", json!({ "category": "preferences" })),
+        ("list_memory_categories", json!({})),
+        ("pin_memory", json!({ "memory_id": 1, "pinned": true })),
+        ("store_attachment", json!({ "name": "values.yaml", "text": "replicas: 2", "memory_id": 1 })),
+        ("get_attachment", json!({ "attachment_id": 1 })),
+        ("get_pinned_memories", json!({})),
+        ("find_similar_memories", json!({ "memory_id": 1 })),
+        ("get_memory_history", json!({ "memory_id": 1 })),
+        ("create_project", json!({ "name": "infra", "keywords": ["kubernetes"] })),
+        ("list_projects", json!({})),
+        ("assign_memory_to_project", json!({ "memory_id": 1, "project": "infra" })),
+        ("add_project_rule", json!({ "project": "infra", "pattern": "kubernetes" })),
+        ("list_project_rules", json!({})),
+        ("classify_conversations", json!({ "dry_run": true })),
+        ("preview_rename_category", json!({ "old_name": "preferences", "new_name": "tooling" })),
+        ("rename_category", json!({ "old_name": "preferences", "new_name": "tooling" })),
+        ("get_memory_hygiene_report", json!({})),
+        ("append_journal", json!({ "text": "Fixed the readiness probe" })),
+        ("get_journal", json!({})),
+        ("save_bookmark", json!({ "url": "https://helm.sh/docs/", "tags": ["helm"], "fetch_metadata": false })),
+        ("search_bookmarks", json!({ "keywords": ["helm"] })),
+        ("store_snippet", json!({ "code": "kubectl rollout undo deploy/web", "language": "sh", "description": "Roll back a deployment" })),
+        ("search_snippets", json!({ "query": "roll back", "language": "shell" })),
+        ("assert_fact", json!({ "subject": "user's dog", "predicate": "name", "object": "Rex" })),
+        ("query_facts", json!({ "subject": "user's dog" })),
+        ("assert_fact", json!({ "subject": "user's dog", "predicate": "name", "object": "Max" })),
+        ("list_conflicts", json!({})),
+        ("preview_resolve_conflict", json!({ "conflict_id": 1, "keep": "new" })),
+        ("resolve_conflict", json!({ "conflict_id": 1, "keep": "new" })),
+        ("remember_person", json!({ "name": "Alice", "relationship": "colleague" })),
+        ("get_person", json!({ "name": "Alice" })),
+        ("search_people", json!({ "query": "ali" })),
+        ("set_preference", json!({ "key": "editor", "value": "helix" })),
+        ("get_preference", json!({ "key": "editor" })),
+        ("list_preferences", json!({})),
+        ("add_synonyms", json!({ "term": "k8s", "synonyms": ["kubernetes"] })),
+        ("list_synonyms", json!({})),
+        ("suggest_synonyms", json!({ "term": "kubernetes" })),
+        ("remove_synonyms", json!({ "term": "k8s" })),
+        ("save_search", json!({ "name": "k8s", "search": { "keywords": ["kubernetes"] } })),
+        ("list_saved_searches", json!({})),
+        ("run_saved_search", json!({ "name": "k8s" })),
+        ("get_search_history", json!({})),
+        ("scratchpad_write", json!({ "name": "plan", "content": "check the probe" })),
+        ("scratchpad_read", json!({ "name": "plan" })),
+        ("preview_scratchpad_clear", json!({ "name": "plan" })),
+        ("scratchpad_clear", json!({ "name": "plan" })),
+        ("set_session_scope", json!({ "scratchpad": "" })),
+        ("create_reminder", json!({ "content": "Renew the cluster certificate", "due_at": "2025-01-01" })),
+        ("list_due_reminders", json!({})),
+        ("complete_reminder", json!({ "reminder_id": 1 })),
+        ("explain_search", json!({ "query": "kubernetes" })),
+        ("reindex", json!({})),
+        ("run_maintenance", json!({})),
+        ("prune_tool_results", json!({ "dry_run": true })),
+        ("export_all", json!({ "output_dir": "full" })),
+        ("create_backup", json!({})),
+        ("preview_delete_memory", json!({ "memory_id": 1 })),
+        ("delete_memory", json!({ "memory_id": 1 })),
+        ("archive_conversations", json!({ "before_date": JAN_2025 - 6600 })),
+    ]
+}
+
+#[tokio::test]
+async fn every_listed_tool_answers() {
+    let export_dir = scratch_dir("export");
+    let backup_dir = scratch_dir("backup");
+    let harness = Harness::start_with(Config {
+        admin_tools: true,
+        history_writes: true,
+        export_dir: Some(export_dir.clone()),
+        backup: BackupConfig { dir: Some(backup_dir.clone()), ..BackupConfig::default() },
+        ..Config::default()
+    })
+    .await;
+    let calls = sample_calls();
+
+    let sampled: BTreeSet<String> = calls.iter().map(|(name, _)| name.to_string()).collect();
+    assert_eq!(sampled, harness.tool_names().await, "sample calls and listed tools differ");
+
+    for (name, arguments) in calls {
+        if ANSWERED_WITH_ERROR.contains(&name) {
+            let result = harness.call_raw(name, arguments).await;
+            assert_ne!(result.is_error, Some(true));
+            continue;
+        }
+        harness.call(name, arguments).await;
+    }
+    assert!(export_dir.join("full").join("manifest.json").exists());
+    let _ = std::fs::remove_dir_all(export_dir);
+    let _ = std::fs::remove_dir_all(backup_dir);
+}
+
+#[tokio::test]
+async fn exports_stay_in_the_export_directory() {
+    let export_dir = scratch_dir("confined-export");
+    let outside = scratch_dir("outside-export");
+    let harness = Harness::start_with(Config { export_dir: Some(export_dir.clone()), ..Config::default() }).await;
+    for output_dir in [outside.to_string_lossy().into_owned(), "../outside".to_string()] {
+        let refused = harness.call_raw("export_all", json!({ "output_dir": output_dir })).await;
+        let response = refused.structured_content.expect("structured content");
+        assert!(response["error"].as_str().is_some_and(|e| e.contains("MCP_LUNA_EXPORT_DIR")), "{}", response);
+    }
+    assert!(!outside.exists());
+
+    harness.call("export_all", json!({})).await;
+    assert!(export_dir.join("manifest.json").exists());
+    let _ = std::fs::remove_dir_all(export_dir);
+}
+
+#[tokio::test]
+async fn search_finds_messages_by_keyword() {
+    let harness = Harness::start().await;
+    let response = harness.call("search_conversations", json!({ "keywords": ["readiness"] })).await;
+    let results = response["items"].as_array().expect("results");
+    assert!(!results.is_empty());
+    assert!(results.iter().all(|r| r["conversation_id"] == "c1"), "{}", response);
+
+    let response = harness.call("search_conversations", json!({ "keywords": ["cafe"] })).await;
+    let results = response["items"].as_array().expect("results");
+    assert!(results.iter().any(|r| r["conversation_id"] == "c2"), "accents are folded: {}", response);
+}
+
+#[tokio::test]
+async fn failure_search_matches_text_literally() {
+    let harness = Harness::start().await;
+    let found = harness.call("search_failures", json!({ "query": "rollout status" })).await;
+    assert_eq!(found["items"].as_array().map(Vec::len), Some(1), "{}", found);
+    let underscore = harness.call("search_failures", json!({ "query": "rollout_status" })).await;
+    assert_eq!(underscore["items"].as_array().map(Vec::len), Some(0), "'_' is not a wildcard: {}", underscore);
+}
+
+#[tokio::test]
+async fn get_conversation_returns_messages_in_order() {
+    let harness = Harness::start().await;
+    let response = harness.call("get_conversation", json!({ "conversation_id": "c1" })).await;
+    let messages = response["messages"].as_array().expect("messages");
+    assert_eq!(messages.len(), 5);
+    assert_eq!(messages[0]["role"], "user");
+    assert_eq!(messages[2]["tool_name"], "shell");
+
+    let missing = harness.call("get_conversation", json!({ "conversation_id": "nope" })).await;
+    assert_eq!(missing["title"], "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn formatted_results_keep_structured_content() {
+    let harness = Harness::start().await;
+    let rendered = harness.call_raw("get_conversation", json!({ "conversation_id": "c1", "format": "markdown" })).await;
+    let text = rendered.content[0].as_text().expect("markdown text");
+    assert!(text.text.starts_with("# Kubernetes deployment stuck"), "{}", text.text);
+    let response = rendered.structured_content.expect("structured content");
+    assert_eq!(response["messages"].as_array().map(Vec::len), Some(5));
+
+    let compact = harness.call_raw("get_conversation", json!({ "conversation_id": "c1", "format": "compact" })).await;
+    let text = compact.content[0].as_text().expect("compact text");
+    assert!(text.text.contains("\"_keys\""), "{}", text.text);
+    let response = compact.structured_content.expect("structured content");
+    assert_eq!(response["id"], "c1");
+}
+
+#[tokio::test]
+async fn memories_round_trip() {
+    let harness = Harness::start().await;
+    let stored = harness
+        .call("store_memory", json!({ "content": "User prefers dark themes", "category": "preferences" }))
+        .await;
+    let id = stored["id"].as_i64().expect("memory id");
+
+    let found = harness.call("search_memory", json!({ "keywords": ["dark"] })).await;
+    let memories = found["items"].as_array().expect("memories");
+    assert!(memories.iter().any(|m| m["id"] == id), "{}", found);
+
+    harness.call("delete_memory", json!({ "memory_id": id })).await;
+    let found = harness.call("search_memory", json!({ "keywords": ["dark"] })).await;
+    assert!(found["items"].as_array().is_some_and(Vec::is_empty), "{}", found);
+}
+
+#[tokio::test]
+async fn confirmation_tokens_gate_deletes_when_required() {
+    let harness = Harness::start_with(Config { require_confirmation: true, admin_tools: true, history_writes: true, ..Config::default() }).await;
+    let stored = harness.call("store_memory", json!({ "content": "Temporary note" })).await;
+    let id = stored["id"].as_i64().expect("memory id");
+
+    let refused = harness.call_raw("delete_memory", json!({ "memory_id": id })).await;
+    let response = refused.structured_content.expect("structured content");
+    assert!(response["error"].as_str().is_some_and(|e| e.contains("Confirmation required")), "{}", response);
+
+    let preview = harness.call("preview_delete_memory", json!({ "memory_id": id })).await;
+    let token = preview["confirmation_token"].as_str().expect("confirmation token");
+    let widened = harness.call_raw("delete_memory", json!({ "memory_id": id, "forget_history": true, "confirmation_token": token })).await;
+    assert!(widened.structured_content.is_some_and(|r| r["error"].is_string()), "a plain delete's token doesn't erase history");
+    let preview = harness.call("preview_delete_memory", json!({ "memory_id": id })).await;
+    let token = preview["confirmation_token"].as_str().expect("confirmation token");
+    harness.call("delete_memory", json!({ "memory_id": id, "confirmation_token": token })).await;
+
+    harness.call("store_memory", json!({ "content": "Lives in Oslo", "key": "user city" })).await;
+    let bergen = harness.call("store_memory", json!({ "content": "Lives in Bergen", "key": "user city" })).await;
+    let conflict_id = &bergen["conflicts"][0]["id"];
+    let refused = harness.call_raw("resolve_conflict", json!({ "conflict_id": conflict_id, "keep": "new" })).await;
+    let response = refused.structured_content.expect("structured content");
+    assert!(response["error"].as_str().is_some_and(|e| e.contains("preview_resolve_conflict")), "{}", response);
+
+    let preview = harness.call("preview_resolve_conflict", json!({ "conflict_id": conflict_id, "keep": "existing" })).await;
+    assert_eq!(preview["deleted_value"], "Lives in Bergen");
+    let token = preview["confirmation_token"].as_str().expect("confirmation token");
+    let mismatched = harness.call_raw("resolve_conflict", json!({ "conflict_id": conflict_id, "keep": "new", "confirmation_token": token })).await;
+    assert!(mismatched.structured_content.is_some_and(|r| r["error"].is_string()), "a token deletes only the side it previewed");
+    let resolved = harness.call("resolve_conflict", json!({ "conflict_id": conflict_id, "keep": "existing", "confirmation_token": token })).await;
+    assert_eq!(resolved["deleted_id"], bergen["id"]);
+
+    harness.call("delete_conversation", json!({ "conversation_id": "c3" })).await;
+    let refused = harness.call_raw("delete_conversation", json!({ "conversation_id": "c3", "permanent": true })).await;
+    let response = refused.structured_content.expect("structured content");
+    assert!(response["error"].as_str().is_some_and(|e| e.contains("preview_delete_conversation")), "{}", response);
+    let preview = harness.call("preview_delete_conversation", json!({ "conversation_id": "c3" })).await;
+    assert_eq!(preview["in_trash"], true);
+    let token = preview["confirmation_token"].as_str().expect("confirmation token");
+    harness.call("delete_conversation", json!({ "conversation_id": "c3", "permanent": true, "confirmation_token": token })).await;
+
+    let preview = harness.call("preview_redact_message", json!({ "message_id": 3, "text": "deploy/web" })).await;
+    assert_eq!(preview["fields_redacted"], json!(["tool_params_json"]));
+    let token = preview["confirmation_token"].as_str().expect("confirmation token");
+    let mismatched = harness.call_raw("redact_message", json!({ "message_id": 3, "confirmation_token": token })).await;
+    assert!(mismatched.structured_content.is_some_and(|r| r["error"].is_string()), "a token redacts only the text it previewed");
+    harness.call("redact_message", json!({ "message_id": 3, "text": "deploy/web", "confirmation_token": token })).await;
+
+    let pruned = harness.call("prune_tool_results", json!({ "older_than_days": 0, "dry_run": true })).await;
+    let token = pruned["confirmation_token"].as_str().expect("confirmation token");
+    harness.call("prune_tool_results", json!({ "older_than_days": 0, "confirmation_token": token })).await;
+}
+
+#[tokio::test]
+async fn session_scope_narrows_listings_and_names_the_scratchpad() {
+    let harness = Harness::start().await;
+    let ids = |response: &Value| -> Vec<String> {
+        response["items"].as_array().expect("items").iter().map(|c| c["id"].as_str().unwrap_or_default().to_string()).collect()
+    };
+    let scope = harness.call("set_session_scope", json!({ "profile": "home", "scratchpad": "plan" })).await;
+    assert_eq!(scope, json!({ "profile": "home", "scratchpad": "plan" }));
+    assert_eq!(ids(&harness.call("list_conversations", json!({})).await), ["c2"]);
+    let found = harness.call("search_conversations", json!({ "keywords": ["helm"] })).await;
+    assert!(found["items"].as_array().is_some_and(Vec::is_empty), "{}", found);
+    let found = harness.call("advanced_search", json!({ "keywords": ["helm"], "profile_name": "work" })).await;
+    assert_eq!(found["total"], 2, "an explicit profile_name overrides the session's");
+
+    harness.call("scratchpad_write", json!({ "content": "check the probe" })).await;
+    let read = harness.call("scratchpad_read", json!({})).await;
+    assert_eq!(read["items"][0]["name"], "plan");
+    assert_eq!(read["items"][0]["content"], "check the probe");
+
+    let scope = harness.call("set_session_scope", json!({ "profile": "" })).await;
+    assert_eq!(scope, json!({ "profile": null, "scratchpad": "plan" }));
+    assert_eq!(ids(&harness.call("list_conversations", json!({})).await).len(), 3);
+}
+
+#[tokio::test]
+async fn deleted_conversations_leave_search_until_restored() {
+    let harness = Harness::start().await;
+    let hits = |response: &Value| response["items"].as_array().map_or(0, Vec::len);
+    let search = json!({ "keywords": ["helm"] });
+    assert_eq!(hits(&harness.call("search_conversations", search.clone()).await), 2);
+
+    let deleted = harness.call("delete_conversation", json!({ "conversation_id": "c3" })).await;
+    assert_eq!(deleted["messages_deleted"], 2);
+    assert!(deleted["restorable_until"].is_i64());
+    assert_eq!(hits(&harness.call("search_conversations", search.clone()).await), 1);
+
+    let restored = harness.call("restore_conversation", json!({ "conversation_id": "c3" })).await;
+    assert_eq!(restored["messages_restored"], 2);
+    assert_eq!(hits(&harness.call("search_conversations", search.clone()).await), 2);
+
+    harness.call("delete_conversation", json!({ "conversation_id": "c3", "permanent": true })).await;
+    let restored = harness.call_raw("restore_conversation", json!({ "conversation_id": "c3" })).await;
+    assert!(restored.structured_content.is_some_and(|r| r["error"].is_string()), "permanent deletes empty the trash");
+}
+
+#[tokio::test]
+async fn redacted_text_is_gone_from_messages_and_search() {
+    let harness = Harness::start().await;
+    let redacted = harness.call("redact_message", json!({ "message_id": 3, "text": "deploy/web" })).await;
+    assert_eq!(redacted["fields_redacted"], json!(["tool_params_json"]));
+    let message = harness.call("get_message", json!({ "message_id": 3 })).await;
+    assert!(!message.to_string().contains("deploy/web"), "{}", message);
+
+    harness.call("redact_message", json!({ "message_id": 4 })).await;
+    let found = harness.call("search_conversations", json!({ "keywords": ["pods"] })).await;
+    assert!(found["items"].as_array().is_some_and(Vec::is_empty), "{}", found);
+    let message = harness.call("get_message", json!({ "message_id": 4 })).await;
+    assert_eq!(message["content"], "[redacted]");
+}
+
+#[tokio::test]
+async fn erased_messages_take_their_attachments() {
+    let dir = scratch_dir("erase-attachments");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("history.db");
+    let harness = &Harness::start_on_file(&path, Config { history_writes: true, ..Config::default() }).await;
+    let attach = |message_id: i64, text: &str| {
+        harness.call("store_attachment", json!({ "name": "notes.txt", "text": text, "message_id": message_id }))
+    };
+    let chart = attach(8, "chart: web").await["attachment"]["id"].clone();
+    let pods = attach(4, "pods: 3").await["attachment"]["id"].clone();
+    let kept = attach(1, "rollout: stuck").await["attachment"]["id"].clone();
+    let readable = |id: Value| async move {
+        let read = harness.call_raw("get_attachment", json!({ "attachment_id": id })).await;
+        read.structured_content.is_some_and(|r| r["error"].is_null())
+    };
+
+    harness.call("delete_conversation", json!({ "conversation_id": "c3" })).await;
+    assert!(readable(chart.clone()).await, "trashed conversations keep their attachments until deleted for good");
+    harness.call("delete_conversation", json!({ "conversation_id": "c3", "permanent": true })).await;
+    assert!(!readable(chart).await);
+    harness.call("redact_message", json!({ "message_id": 4 })).await;
+    assert!(!readable(pods).await);
+    assert!(readable(kept).await);
+
+    let db = Connection::open(&path).unwrap();
+    let blobs: i64 = db.query_row("SELECT COUNT(*) FROM attachment_blobs", [], |row| row.get(0)).unwrap();
+    assert_eq!(blobs, 1, "content of deleted attachments is removed");
+}
+
+#[tokio::test]
+async fn archived_conversations_stay_searchable_and_readable() {
+    let dir = scratch_dir("archive");
+    std::fs::create_dir_all(&dir).unwrap();
+    let harness = Harness::start_on_file(
+        &dir.join("history.db"),
+        Config { history_writes: true, archive_path: Some(dir.join("archive.db")), ..Config::default() },
+    )
+    .await;
+    let search = json!({ "keywords": ["readiness"] });
+    let unarchived = harness.call("search_conversations", search.clone()).await;
+
+    // Only c1 ended before then
+    let archived = harness.call("archive_conversations", json!({ "before_date": JAN_2025 - 6700 })).await;
+    assert_eq!(archived["conversations_archived"], 1);
+    assert_eq!(archived["messages_archived"], 5);
+    let found = harness.call("search_conversations", search.clone()).await;
+    assert!(found["items"].as_array().is_some_and(Vec::is_empty), "{}", found);
+
+    let found = harness.call("search_conversations", json!({ "keywords": ["readiness"], "include_archive": true })).await;
+    assert_eq!(found["items"].as_array().map(Vec::len), unarchived["items"].as_array().map(Vec::len));
+    assert!(found["items"].as_array().unwrap().iter().all(|r| r["archived"] == true), "{}", found);
+    let conversation = harness.call("get_conversation", json!({ "conversation_id": "c1" })).await;
+    assert_eq!(conversation["messages"].as_array().map(Vec::len), Some(5));
+
+    harness.call("pin_conversation", json!({ "conversation_id": "c3" })).await;
+    let archived = harness.call("archive_conversations", json!({ "before_date": JAN_2025 })).await;
+    assert_eq!(archived["conversations_archived"], 1);
+    assert_eq!(archived["pinned_skipped"], 1);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn pruned_tool_results_keep_their_error_and_original() {
+    let harness = Harness::start().await;
+    let prune = json!({ "min_kb": 0, "older_than_days": 0 });
+    let pruned = harness.call("prune_tool_results", prune.clone()).await;
+    assert_eq!(pruned["messages_pruned"], 1);
+
+    let message = harness.call("get_message", json!({ "message_id": 3 })).await;
+    let stand_in: Value = serde_json::from_str(message["tool_result_json"].as_str().expect("tool result")).unwrap();
+    assert_eq!(stand_in["pruned"], true);
+    assert_eq!(stand_in["error"], "timed out waiting for rollout");
+    let original = harness.call("get_message", json!({ "message_id": 3, "original_tool_result": true })).await;
+    assert_eq!(original["tool_result_json"], r#"{"error":"timed out waiting for rollout"}"#);
+
+    let again = harness.call("prune_tool_results", prune).await;
+    assert_eq!(again["messages_pruned"], 0, "pruned results aren't pruned twice");
+}
+
+#[tokio::test]
+async fn attachments_follow_their_memory() {
+    let harness = Harness::start().await;
+    let memory = harness.call("store_memory", json!({ "content": "The web chart sets two replicas" })).await;
+    let memory_id = memory["id"].as_i64().expect("memory id");
+    let config = json!({ "name": "values.yaml", "text": "replicas: 2", "memory_id": memory_id });
+    let stored = harness.call("store_attachment", config.clone()).await;
+    let attachment = &stored["attachment"];
+    assert_eq!(attachment["mime_type"], "application/yaml");
+    let copy = harness.call("store_attachment", config).await;
+    assert_eq!(copy["attachment"]["sha256"], attachment["sha256"]);
+    let image = harness.call("store_attachment", json!({ "name": "diagram.png", "base64": "iVBORw0KGgo=" })).await;
+
+    let found = harness.call("search_memory", json!({ "keywords": ["replicas"] })).await;
+    assert_eq!(found["items"][0]["attachments"].as_array().map(Vec::len), Some(2), "{}", found);
+    let read = harness.call("get_attachment", json!({ "attachment_id": attachment["id"] })).await;
+    assert_eq!(read["text"], "replicas: 2");
+    let read = harness.call("get_attachment", json!({ "attachment_id": image["attachment"]["id"] })).await;
+    assert_eq!(read["base64"], "iVBORw0KGgo=");
+
+    let uri = attachment["uri"].as_str().expect("uri").to_string();
+    let resource = harness
+        .client
+        .read_resource(ReadResourceRequestParam { uri: uri.clone() })
+        .await
+        .expect("resources/read");
+    assert!(matches!(&resource.contents[0], ResourceContents::TextResourceContents { text, .. } if text == "replicas: 2"));
+
+    harness.call("delete_memory", json!({ "memory_id": memory_id })).await;
+    let gone = harness.call_raw("get_attachment", json!({ "attachment_id": attachment["id"] })).await;
+    assert!(gone.structured_content.is_some_and(|r| r["error"].is_string()));
+    assert!(harness.client.read_resource(ReadResourceRequestParam { uri }).await.is_err());
+}
+
+#[tokio::test]
+async fn expired_memories_take_their_attachments() {
+    let dir = scratch_dir("expired-attachments");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("history.db");
+    let harness = Harness::start_on_file(&path, Config { admin_tools: true, ..Config::default() }).await;
+    let memory = harness.call("store_memory", json!({ "content": "Staging runs one replica", "ttl_days": 1 })).await;
+    let attachment = harness
+        .call("store_attachment", json!({ "name": "staging.yaml", "text": "replicas: 1", "memory_id": memory["id"] }))
+        .await;
+
+    let db = Connection::open(&path).unwrap();
+    db.execute("UPDATE memory SET expires_at = 1 WHERE id = ?", [memory["id"].as_i64()]).unwrap();
+    let report = harness.call("run_maintenance", json!({})).await;
+    assert_eq!(report["expired_memories_purged"], 1);
+
+    let gone = harness.call_raw("get_attachment", json!({ "attachment_id": attachment["attachment"]["id"] })).await;
+    assert!(gone.structured_content.is_some_and(|r| r["error"].is_string()));
+    let blobs: i64 = db.query_row("SELECT COUNT(*) FROM attachment_blobs", [], |row| row.get(0)).unwrap();
+    assert_eq!(blobs, 0);
+}
+
+#[tokio::test]
+async fn text_attachments_are_masked_like_other_results() {
+    let mut config = Config::default();
+    config.pii.enabled = true;
+    config.pii.tools = vec!["get_attachment".to_string()];
+    let harness = Harness::start_with(config).await;
+    let env = "OPENAI_API_KEY=sk-proj-4fT9qLm2ZxR8vN3wKp7YbJ6d\nOWNER=ops@example.com\n";
+    let stored = harness.call("store_attachment", json!({ "name": "deploy.env", "text": env })).await;
+    let attachment_id = &stored["attachment"]["id"];
+
+    let uri = stored["attachment"]["uri"].as_str().expect("uri").to_string();
+    let resource = harness.client.read_resource(ReadResourceRequestParam { uri }).await.expect("resources/read");
+    let ResourceContents::TextResourceContents { text, .. } = &resource.contents[0] else { panic!("not text: {:?}", resource) };
+    assert!(!text.contains("sk-proj-") && !text.contains("ops@example.com"), "{}", text);
+    assert!(text.contains("[REDACTED:"), "{}", text);
+
+    let read = harness.call("get_attachment", json!({ "attachment_id": attachment_id })).await;
+    assert!(!read["text"].as_str().unwrap_or_default().contains("sk-proj-"), "{}", read);
+    let read = harness.call("get_attachment", json!({ "attachment_id": attachment_id, "allow_secrets": true })).await;
+    assert!(read["text"].as_str().unwrap_or_default().contains("sk-proj-4fT9"), "{}", read);
+}
+
+#[tokio::test]
+async fn attachment_files_stay_inside_the_attachments_directory() {
+    let root = scratch_dir("attachments");
+    let dir = root.join("files");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("values.yaml"), "replicas: 2").unwrap();
+    std::fs::write(root.join("secret.txt"), "hunter2").unwrap();
+    let harness = Harness::start_with(Config { attachments_dir: Some(dir.clone()), ..Config::default() }).await;
+
+    let stored = harness.call("store_attachment", json!({ "name": "values.yaml", "file_path": "values.yaml" })).await;
+    assert_eq!(stored["success"], true, "{}", stored);
+    let read = harness.call("get_attachment", json!({ "attachment_id": stored["attachment"]["id"] })).await;
+    assert_eq!(read["text"], "replicas: 2");
+
+    let refusal = |result: CallToolResult| result.structured_content.and_then(|r| r["error"].as_str().map(str::to_string));
+    let outside = root.join("secret.txt").to_string_lossy().into_owned();
+    for file_path in ["../secret.txt", "files/../../secret.txt", outside.as_str(), "/etc/passwd"] {
+        let refused = harness.call_raw("store_attachment", json!({ "name": "secret.txt", "file_path": file_path })).await;
+        assert!(refusal(refused).is_some(), "{} was attached", file_path);
+    }
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(root.join("secret.txt"), dir.join("link.txt")).unwrap();
+        let refused = harness.call_raw("store_attachment", json!({ "name": "link.txt", "file_path": "link.txt" })).await;
+        assert!(refusal(refused).is_some_and(|e| e.contains("outside")));
+    }
+
+    let unset = Harness::start().await;
+    let refused = unset.call_raw("store_attachment", json!({ "name": "values.yaml", "file_path": "values.yaml" })).await;
+    assert!(refusal(refused).is_some_and(|e| e.contains("MCP_LUNA_ATTACHMENTS_DIR")));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[tokio::test]
+async fn bookmarks_keep_page_metadata_and_are_searchable() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/rollbacks", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = stream.read(&mut [0; 4096]);
+        let page = r#"<html><head><title>Ignored</title><meta property="og:title" content="Helm rollbacks &amp; history">
+            <meta name="description" content="How to undo a bad release"></head></html>"#;
+        let response = format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{}", page.len(), page);
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    let guarded = Harness::start().await;
+    let saved = guarded.call("save_bookmark", json!({ "url": url })).await;
+    assert!(saved["fetch_error"].as_str().is_some_and(|e| e.contains("non-public address")), "{}", saved);
+    assert!(saved["bookmark"]["title"].is_null(), "a page on a private address is not fetched");
+
+    let harness = Harness::start_with(Config { fetch_private_urls: true, ..Config::default() }).await;
+    let saved = harness.call("save_bookmark", json!({ "url": url, "note": "fix for the stuck release", "tags": ["helm"] })).await;
+    assert_eq!(saved["bookmark"]["title"], "Helm rollbacks & history", "{}", saved);
+    assert_eq!(saved["bookmark"]["description"], "How to undo a bad release");
+
+    let again = harness.call("save_bookmark", json!({ "url": url, "tags": ["k8s"], "fetch_metadata": false })).await;
+    assert_eq!(again["bookmark"]["id"], saved["bookmark"]["id"]);
+    assert_eq!(again["bookmark"]["tags"], json!(["helm", "k8s"]));
+    assert_eq!(again["bookmark"]["note"], "fix for the stuck release", "a later save keeps the note");
+
+    for keywords in [json!(["rollback"]), json!(["undo"]), json!(["stuck"])] {
+        let found = harness.call("search_bookmarks", json!({ "keywords": keywords })).await;
+        assert_eq!(found["items"].as_array().map(Vec::len), Some(1), "{}", found);
+    }
+    let found = harness.call("search_bookmarks", json!({ "tags": ["K8S"] })).await;
+    assert_eq!(found["items"].as_array().map(Vec::len), Some(1), "{}", found);
+    let refused = harness.call_raw("save_bookmark", json!({ "url": "file:///etc/passwd" })).await;
+    assert!(refused.structured_content.is_some_and(|r| r["error"].is_string()));
+}
+
+#[tokio::test]
+async fn snippets_match_identifiers_by_their_words() {
+    let harness = Harness::start().await;
+    let snippets = [
+        ("function getUserName(user) { return user.name; }", "js", "Read a user's display name"),
+        ("def get_user_name(user):\n    return user.name", "py", "Display name of a user"),
+        ("kubectl get pods --field-selector=status.phase=Pending", "sh", "List pods stuck in Pending"),
+    ];
+    for (code, language, description) in snippets {
+        harness.call("store_snippet", json!({ "code": code, "language": language, "description": description })).await;
+    }
+    let languages = |response: &Value| -> Vec<String> {
+        let items = response["items"].as_array().expect("snippets");
+        items.iter().map(|s| s["language"].as_str().unwrap_or_default().to_string()).collect()
+    };
+
+    let found = harness.call("search_snippets", json!({ "query": "getUserName" })).await;
+    assert_eq!(languages(&found).len(), 2, "camelCase finds snake_case too: {}", found);
+    let found = harness.call("search_snippets", json!({ "query": "user name", "language": "python" })).await;
+    assert_eq!(languages(&found), ["python"], "{}", found);
+    let found = harness.call("search_snippets", json!({ "query": "field selector" })).await;
+    assert_eq!(languages(&found), ["shell"], "{}", found);
+}
+
+#[tokio::test]
+async fn facts_answer_exact_questions() {
+    let harness = Harness::start().await;
+    let facts = [("user's dog", "name", "Rex", 1.0), ("user", "lives in", "Kraków", 0.9), ("Marek", "lives in", "Kraków", 0.6)];
+    for (subject, predicate, object, confidence) in facts {
+        let asserted = harness
+            .call("assert_fact", json!({ "subject": subject, "predicate": predicate, "object": object, "confidence": confidence }))
+            .await;
+        assert_eq!(asserted["already_known"], Value::Null);
+    }
+    let again = harness.call("assert_fact", json!({ "subject": "User's  Dog", "predicate": "Name", "object": "rex", "source": "c1" })).await;
+    assert_eq!(again["already_known"], true, "{}", again);
+
+    let dog = harness.call("query_facts", json!({ "subject": "user's dog", "predicate": "name" })).await;
+    assert_eq!(dog["items"][0]["object"], "Rex");
+    assert_eq!(dog["items"][0]["source"], "c1");
+    let krakow = harness.call("query_facts", json!({ "object": "kraków" })).await;
+    let subjects: Vec<&str> = krakow["items"].as_array().unwrap().iter().filter_map(|f| f["subject"].as_str()).collect();
+    assert_eq!(subjects, ["user", "Marek"], "most confident first");
+    let confident = harness.call("query_facts", json!({ "predicate": "lives in", "min_confidence": 0.8 })).await;
+    assert_eq!(confident["items"].as_array().map(Vec::len), Some(1));
+
+    let invalid = harness.call_raw("assert_fact", json!({ "subject": "user", "predicate": "age", "object": "40", "confidence": 2 })).await;
+    assert!(invalid.structured_content.is_some_and(|r| r["error"].is_string()));
+}
+
+#[tokio::test]
+async fn conflicting_values_are_flagged_until_resolved() {
+    let harness = Harness::start().await;
+    let vim = harness.call("store_memory", json!({ "content": "Uses vim", "key": "user editor" })).await;
+    let same = harness.call("store_memory", json!({ "content": "uses  VIM", "key": "User Editor" })).await;
+    assert_eq!(same["conflicts"], Value::Null, "same value, no conflict");
+    harness.call("delete_memory", json!({ "memory_id": same["id"] })).await;
+    let helix = harness.call("store_memory", json!({ "content": "Uses helix", "key": "user editor" })).await;
+    assert_eq!(helix["conflicts"][0]["existing_id"], vim["id"], "{}", helix);
+    assert_eq!(helix["conflicts"][0]["existing_value"], "Uses vim");
+
+    harness.call("assert_fact", json!({ "subject": "user's dog", "predicate": "name", "object": "Rex" })).await;
+    let max = harness.call("assert_fact", json!({ "subject": "user's dog", "predicate": "name", "object": "Max" })).await;
+    assert_eq!(max["conflicts"][0]["kind"], "fact");
+    assert_eq!(max["conflicts"][0]["existing_value"], "Rex");
+    let open = harness.call("list_conflicts", json!({})).await;
+    assert_eq!(open["items"].as_array().map(Vec::len), Some(2));
+
+    let resolved = harness.call("resolve_conflict", json!({ "conflict_id": helix["conflicts"][0]["id"], "keep": "new" })).await;
+    assert_eq!(resolved["deleted_id"], vim["id"], "{}", resolved);
+    assert_eq!(resolved["conflict"]["kept"], "new");
+    let editor = harness.call("search_memory", json!({ "keywords": ["uses"], "include_pinned": false })).await;
+    let contents: Vec<&str> = editor["items"].as_array().unwrap().iter().filter_map(|m| m["content"].as_str()).collect();
+    assert_eq!(contents, ["Uses helix"], "the superseded memory is gone");
+    let again = harness.call_raw("resolve_conflict", json!({ "conflict_id": helix["conflicts"][0]["id"], "keep": "new" })).await;
+    assert!(again.structured_content.is_some_and(|r| r["error"].is_string()));
+
+    let both = harness.call("resolve_conflict", json!({ "conflict_id": max["conflicts"][0]["id"], "keep": "both" })).await;
+    assert_eq!(both["deleted_id"], Value::Null);
+    let dogs = harness.call("query_facts", json!({ "subject": "user's dog" })).await;
+    assert_eq!(dogs["items"].as_array().map(Vec::len), Some(2));
+    let open = harness.call("list_conflicts", json!({})).await;
+    assert_eq!(open["items"].as_array().map(Vec::len), Some(0));
+    let all = harness.call("list_conflicts", json!({ "include_resolved": true })).await;
+    assert_eq!(all["items"].as_array().map(Vec::len), Some(2));
+}
+
+#[tokio::test]
+async fn memory_history_keeps_every_version() {
+    let harness = Harness::start().await;
+    let tabs = harness.call("store_memory", json!({ "content": "Prefers tabs", "key": "indentation" })).await;
+    harness.call("pin_memory", json!({ "memory_id": tabs["id"] })).await;
+    harness.call("delete_memory", json!({ "memory_id": tabs["id"] })).await;
+    let spaces = harness.call("store_memory", json!({ "content": "Prefers spaces", "key": "indentation" })).await;
+
+    let history = harness.call("get_memory_history", json!({ "memory_id": tabs["id"] })).await;
+    let changes: Vec<&str> = history["items"].as_array().unwrap().iter().filter_map(|v| v["change"].as_str()).collect();
+    assert_eq!(changes, ["deleted", "updated", "created"], "{}", history);
+    assert_eq!(history["items"][0]["content"], "Prefers tabs", "a deleted version keeps what the memory said");
+    assert_eq!(history["items"][1]["pinned"], true);
+
+    let by_key = harness.call("get_memory_history", json!({ "key": "Indentation" })).await;
+    assert_eq!(by_key["items"].as_array().map(Vec::len), Some(4));
+    assert_eq!(by_key["items"][0]["content"], "Prefers spaces");
+
+    let stored_at = spaces["created_at"].as_i64().unwrap();
+    let now = harness.call("get_memory_history", json!({ "key": "indentation", "as_of": stored_at })).await;
+    let contents: Vec<&str> = now["items"].as_array().unwrap().iter().filter_map(|v| v["content"].as_str()).collect();
+    assert_eq!(contents, ["Prefers spaces"], "deleted memories are gone as of now");
+    let before = harness.call("get_memory_history", json!({ "key": "indentation", "as_of": stored_at - 1 })).await;
+    assert_eq!(before["items"].as_array().map(Vec::len), Some(0));
+
+    let unfiltered = harness.call_raw("get_memory_history", json!({})).await;
+    assert!(unfiltered.structured_content.is_some_and(|r| r["error"].is_string()));
+}
+
+#[tokio::test]
+async fn forgotten_and_expired_memories_leave_no_history() {
+    let dir = scratch_dir("forget-history");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("history.db");
+    let harness = Harness::start_on_file(&path, Config { admin_tools: true, ..Config::default() }).await;
+    let versions = |memory_id: Value| {
+        let harness = &harness;
+        async move {
+            let history = harness.call("get_memory_history", json!({ "memory_id": memory_id })).await;
+            history["items"].as_array().map_or(0, Vec::len)
+        }
+    };
+
+    let password = harness.call("store_memory", json!({ "content": "Wifi password is swordfish" })).await;
+    harness.call("delete_memory", json!({ "memory_id": password["id"], "forget_history": true })).await;
+    assert_eq!(versions(password["id"].clone()).await, 0);
+
+    let trip = harness.call("store_memory", json!({ "content": "Traveling to Lisbon next week", "ttl_days": 7 })).await;
+    let db = Connection::open(&path).unwrap();
+    db.execute("UPDATE memory SET expires_at = 1 WHERE id = ?", [trip["id"].as_i64()]).unwrap();
+    assert_eq!(versions(trip["id"].clone()).await, 2, "expiry is a change like any other until the purge");
+    harness.call("run_maintenance", json!({})).await;
+    assert_eq!(versions(trip["id"].clone()).await, 0);
+
+    let editor = harness.call("store_memory", json!({ "content": "Uses helix" })).await;
+    harness.call("delete_memory", json!({ "memory_id": editor["id"] })).await;
+    assert_eq!(versions(editor["id"].clone()).await, 2, "a plain delete keeps the history");
+}
+
+#[tokio::test]
+async fn tool_selection_follows_config() {
+    let harness = Harness::start_with(Config::default()).await;
+    let tools = harness.tool_names().await;
+    assert!(tools.contains("search_conversations"));
+    assert!(!tools.contains("run_maintenance"), "admin tools are opt-in");
+    assert!(!tools.contains("update_title"), "history writes are opt-in");
+    assert!(!tools.contains("delete_conversation"), "history writes are opt-in");
+
+    let harness = Harness::start_with(Config { read_only: true, ..Config::default() }).await;
+    let tools = harness.tool_names().await;
+    assert!(tools.contains("search_memory"));
+    assert!(!tools.contains("store_memory"), "read-only mode lists no writing tools");
+}